@@ -0,0 +1,48 @@
+/// Remove ANSI escape sequences and carriage returns from terminal output
+///
+/// Handles CSI sequences (`ESC [ ... final`), OSC sequences (`ESC ] ... BEL`
+/// or `ESC ] ... ESC \`) and two-byte escapes, which covers what LLM CLIs
+/// emit for colours, cursor movement and window titles.
+pub fn strip_ansi(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters and intermediates until a final byte in @..~
+                Some('[') => {
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            break;
+                        }
+                    }
+                },
+                // OSC: terminated by BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                },
+                // Other two-byte escapes
+                _ => {},
+            },
+            '\r' => {},
+            _ => output.push(c),
+        }
+    }
+    
+    output
+}
+
+/// Get the last line of terminal output with escape sequences removed
+pub fn last_line(input: &str) -> String {
+    let stripped = strip_ansi(input);
+    stripped.rsplit('\n').next().unwrap_or("").to_string()
+}
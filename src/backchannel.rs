@@ -0,0 +1,98 @@
+use anyhow::{Result, Context};
+use serde::{Serialize, Deserialize};
+
+/// Start of a backchannel sequence: `ESC ] 7777 ; grill:` followed by JSON
+pub const PREFIX: &str = "\x1b]7777;grill:";
+
+/// Longest backchannel sequence accepted; anything longer is shown as output
+const MAX_SEQUENCE: usize = 64 * 1024;
+
+/// Something a program running inside the CLI asks grill to do
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// Switch to another task
+    SwitchTask { task: String },
+    /// Add text to the end of the current task's state.md
+    AppendState { text: String },
+    /// Show a notification
+    Notify {
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+}
+
+/// Parse the JSON payload of a backchannel sequence
+pub fn parse(payload: &str) -> Result<Action> {
+    serde_json::from_str(payload).context(format!("Invalid grill backchannel request: {}", payload))
+}
+
+/// Takes backchannel sequences out of the CLI's output
+///
+/// Sequences end with BEL or ST and may be split across chunks, so the
+/// start of one is held back until the rest arrives.
+#[derive(Debug, Default)]
+pub struct Backchannel {
+    pending: String,
+}
+
+impl Backchannel {
+    /// Remove backchannel sequences from a chunk of output, returning what is left and the requests found
+    pub fn process(&mut self, output: &str) -> (String, Vec<Result<Action>>) {
+        let mut text = std::mem::take(&mut self.pending);
+        text.push_str(output);
+        
+        let mut shown = String::with_capacity(text.len());
+        let mut actions = Vec::new();
+        let mut rest = text.as_str();
+        loop {
+            let Some(start) = find_prefix_start(rest) else {
+                shown.push_str(rest);
+                break;
+            };
+            shown.push_str(&rest[..start]);
+            let sequence = &rest[start..];
+            
+            if sequence.len() < PREFIX.len() {
+                // Possibly the start of the prefix; wait for more
+                self.pending = sequence.to_string();
+                break;
+            }
+            let payload = &sequence[PREFIX.len()..];
+            let end = payload.char_indices()
+                .find(|(i, c)| *c == '\x07' || (*c == '\x1b' && payload[i + 1..].starts_with('\\')))
+                .map(|(i, c)| (i, if c == '\x07' { 1 } else { 2 }));
+            match end {
+                Some((end, terminator)) => {
+                    actions.push(parse(&payload[..end]));
+                    rest = &payload[end + terminator..];
+                },
+                None if sequence.len() <= MAX_SEQUENCE => {
+                    self.pending = sequence.to_string();
+                    break;
+                },
+                None => {
+                    // Too long to be a request; show it as it is
+                    shown.push_str(sequence);
+                    break;
+                },
+            }
+        }
+        (shown, actions)
+    }
+}
+
+/// Find where a backchannel sequence, or the start of one at the end of the text, begins
+fn find_prefix_start(text: &str) -> Option<usize> {
+    let mut search = 0;
+    while let Some(offset) = text[search..].find('\x1b') {
+        let start = search + offset;
+        let candidate = &text[start..];
+        if candidate.starts_with(PREFIX) || PREFIX.starts_with(candidate) {
+            return Some(start);
+        }
+        search = start + 1;
+    }
+    None
+}
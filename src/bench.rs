@@ -0,0 +1,172 @@
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::config::{CliProfile, Config, ResourceLimits};
+use crate::events::{format_millis, Latency};
+use crate::runner::{Runner, DEFAULT_RESPONSE_TIMEOUT};
+
+/// Prompt sent to time round trips, which any CLI can answer at once
+pub const DEFAULT_PROMPT: &str = "Reply with just the word OK.";
+
+/// Bytes of context pasted in by default
+pub const DEFAULT_CONTEXT_BYTES: usize = 64 * 1024;
+
+/// What `grill bench` measures, and how hard it pushes
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Times the prompt is sent
+    pub rounds: usize,
+    /// Bytes of context pasted in; 0 skips it
+    pub context_bytes: usize,
+    pub prompt: String,
+    /// How long to wait for the CLI each time
+    pub timeout: Duration,
+}
+
+impl Default for BenchOptions {
+    fn default() -> Self {
+        Self {
+            rounds: 3,
+            context_bytes: DEFAULT_CONTEXT_BYTES,
+            prompt: DEFAULT_PROMPT.to_string(),
+            timeout: DEFAULT_RESPONSE_TIMEOUT,
+        }
+    }
+}
+
+/// How quickly a CLI started, answered and took in context
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchResult {
+    /// The CLI's name under `[clis]`, or its command
+    pub cli: String,
+    pub handler: String,
+    /// Whether the handler recognized the CLI's prompt; if not, every time
+    /// includes waiting for the CLI to go quiet
+    pub prompt_recognized: bool,
+    /// From spawning the CLI to its first prompt
+    pub spawn_to_prompt_ms: u64,
+    /// From sending the prompt to the CLI's next prompt
+    pub round_trip: Latency,
+    /// Bytes of context sent, after wrapping it as a paste
+    pub context_bytes: usize,
+    /// Writing the context into the CLI's terminal
+    pub context_write_ms: u64,
+    /// From the context being written to the CLI's prompt
+    pub context_answer_ms: u64,
+}
+
+impl BenchResult {
+    /// Get the bytes of context written per second
+    pub fn context_throughput(&self) -> f64 {
+        self.context_bytes as f64 * 1000.0 / self.context_write_ms.max(1) as f64
+    }
+    
+    /// Format a human-readable report
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        report.push_str(&format!("CLI:              {} (handler: {})\n", self.cli, self.handler));
+        report.push_str(&format!("Spawn to prompt:  {}\n", format_millis(self.spawn_to_prompt_ms)));
+        if self.round_trip.count > 0 {
+            report.push_str(&format!(
+                "Round trip:       {} median, {} 95th percentile, {} max over {} rounds\n",
+                format_millis(self.round_trip.median_ms),
+                format_millis(self.round_trip.p95_ms),
+                format_millis(self.round_trip.max_ms),
+                self.round_trip.count
+            ));
+        }
+        if self.context_bytes > 0 {
+            report.push_str(&format!(
+                "Context:          {} bytes written in {} ({:.1} KiB/s), answered {} later\n",
+                self.context_bytes,
+                format_millis(self.context_write_ms),
+                self.context_throughput() / 1024.0,
+                format_millis(self.context_answer_ms)
+            ));
+        }
+        if !self.prompt_recognized {
+            report.push_str("The CLI's prompt wasn't recognized, so each time includes waiting for it to go quiet.\n");
+        }
+        report
+    }
+}
+
+/// Build about `bytes` of context to paste, asking the CLI only to acknowledge it
+pub fn context_text(bytes: usize) -> String {
+    let mut text = format!("{} Everything after this line is filler.\n", DEFAULT_PROMPT);
+    let header = text.len();
+    let mut line = 0;
+    while text.len() < bytes {
+        line += 1;
+        text.push_str(&format!("{:05} The quick brown fox jumps over the lazy dog.\n", line));
+    }
+    text.truncate(bytes.max(header));
+    text
+}
+
+/// Benchmark a CLI: time its start, round trips of the prompt, and taking in context
+pub async fn run(name: &str, cli: &CliProfile, working_dir: Option<PathBuf>, config: &Config, options: &BenchOptions) -> Result<BenchResult> {
+    let started = Instant::now();
+    let mut runner = Runner::start_in(cli, working_dir, ResourceLimits::default(), None, config)?;
+    let result = measure(&mut runner, name, started, options).await;
+    runner.stop()?;
+    result
+}
+
+async fn measure(runner: &mut Runner, name: &str, started: Instant, options: &BenchOptions) -> Result<BenchResult> {
+    let output = runner.wait_until_ready(options.timeout).await?;
+    let spawn_to_prompt = started.elapsed();
+    let prompt_recognized = runner.handler().is_prompt(&output);
+    ensure_running(runner, "before its first prompt")?;
+    
+    let mut round_trips = Vec::new();
+    for _ in 0..options.rounds {
+        let exchange = runner.exchange(name, &options.prompt, options.timeout).await;
+        if let Some(error) = exchange.error {
+            return Err(anyhow!(error));
+        }
+        ensure_running(runner, "while answering the prompt")?;
+        round_trips.push(exchange.duration_ms);
+    }
+    
+    let (context_bytes, write, answer) = if options.context_bytes > 0 {
+        // The context is written once every byte of it has gone through to the CLI
+        let traffic = runner.traffic();
+        let before = traffic.bytes_in();
+        let start = Instant::now();
+        let sent = runner.paste(&context_text(options.context_bytes)).await?;
+        while traffic.bytes_in() < before + sent as u64 {
+            if start.elapsed() > options.timeout {
+                return Err(anyhow!("Timed out after {}s writing context to the CLI", options.timeout.as_secs()));
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        let write = start.elapsed();
+        runner.wait_for_prompt(options.timeout).await?;
+        ensure_running(runner, "while taking in the context")?;
+        (sent, write, start.elapsed() - write)
+    } else {
+        (0, Duration::ZERO, Duration::ZERO)
+    };
+    
+    Ok(BenchResult {
+        cli: name.to_string(),
+        handler: runner.handler().name(),
+        prompt_recognized,
+        spawn_to_prompt_ms: spawn_to_prompt.as_millis() as u64,
+        round_trip: Latency::from_samples(round_trips),
+        context_bytes,
+        context_write_ms: write.as_millis() as u64,
+        context_answer_ms: answer.as_millis() as u64,
+    })
+}
+
+/// Fail if the CLI has exited, since nothing it did after that can be timed
+fn ensure_running(runner: &mut Runner, when: &str) -> Result<()> {
+    match runner.try_wait()? {
+        Some(status) => Err(anyhow!("The CLI exited with status {} {}", status.exit_code(), when)),
+        None => Ok(()),
+    }
+}
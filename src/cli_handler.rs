@@ -1,18 +1,39 @@
 use anyhow::{Result, Context};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
 use tokio::sync::mpsc;
 use crate::io::Command;
+use crate::plugin::{self, PluginCliHandler};
+use crate::config::{CliProfile, TaskConfig};
+use crate::environment::Environment;
+use crate::task::Task;
+use crate::template;
 
 /// Concrete CLI handler type
 #[derive(Clone)]
 pub enum CliHandler {
     Q(QCliHandler),
+    /// External `grill-handler-<name>` plugin discovered on PATH
+    Plugin(PluginCliHandler),
     // Add more variants here for other CLI types
 }
 
 impl CliHandler {
+    /// Name the handler for reports such as `/version`
+    pub fn name(&self) -> String {
+        match self {
+            CliHandler::Q(handler) if handler.native_commands().is_some() => "q".to_string(),
+            CliHandler::Q(_) => "default".to_string(),
+            CliHandler::Plugin(handler) => format!("grill-handler-{}", handler.name()),
+        }
+    }
+    
     pub fn get_command(&self) -> &str {
         match self {
             CliHandler::Q(handler) => handler.get_command(),
+            CliHandler::Plugin(handler) => handler.get_command(),
         }
     }
     
@@ -24,12 +45,14 @@ impl CliHandler {
     ) -> Result<bool> {
         match self {
             CliHandler::Q(handler) => handler.process_command(command, output_tx, current_task),
+            CliHandler::Plugin(handler) => handler.process_command(command, output_tx, current_task),
         }
     }
     
     pub fn get_help_text(&self) -> String {
         match self {
             CliHandler::Q(handler) => handler.get_help_text(),
+            CliHandler::Plugin(handler) => handler.get_help_text(),
         }
     }
     
@@ -40,44 +63,423 @@ impl CliHandler {
     ) -> Result<()> {
         match self {
             CliHandler::Q(handler) => handler.on_start(task_name, output_tx),
+            CliHandler::Plugin(handler) => handler.on_start(task_name, output_tx),
         }
     }
     
     pub fn intercept_input(&self, input: String) -> Result<Option<String>> {
         match self {
             CliHandler::Q(handler) => handler.intercept_input(input),
+            CliHandler::Plugin(handler) => handler.intercept_input(input),
         }
     }
     
     pub fn intercept_output(&self, output: String) -> Result<Option<String>> {
         match self {
             CliHandler::Q(handler) => handler.intercept_output(output),
+            CliHandler::Plugin(handler) => handler.intercept_output(output),
         }
     }
     
-    /// Clear the CLI's context and prepare for new task
+    /// Clear the CLI's context, unless `clear` is false, and prepare for new task
+    ///
+    /// Q is sent the same context as when the session starts, from
+    /// [`task_context_messages`](Self::task_context_messages).
     pub async fn clear_context_and_switch_task(
         &self,
+        environment: &Environment,
         new_task_name: &str,
         task_dir: &std::path::Path,
+        clear: bool,
         process_input_tx: &mpsc::Sender<String>,
         output_tx: &mpsc::Sender<String>,
     ) -> Result<()> {
         match self {
             CliHandler::Q(handler) => {
-                handler.clear_context_and_switch_task(new_task_name, task_dir, process_input_tx, output_tx).await
+                let messages = self.task_context_messages(environment, new_task_name, task_dir);
+                handler.clear_context_and_switch_task(new_task_name, &messages, clear, process_input_tx, output_tx).await
+            },
+            CliHandler::Plugin(handler) => {
+                handler.clear_context_and_switch_task(new_task_name, task_dir, clear, process_input_tx, output_tx).await
             },
         }
     }
     
+    /// Build the messages that give the CLI a task's context, as (label, message) pairs
+    ///
+    /// The task's persona comes first, its `context_files` follow the
+    /// handler's own messages as the CLI's [`ContextStrategy`] has them, and
+    /// placeholders such as `{{task_name}}` are expanded in every message.
+    pub fn task_context_messages(&self, environment: &Environment, task_name: &str, task_dir: &std::path::Path) -> Vec<(String, String)> {
+        let mut messages = match self {
+            CliHandler::Q(handler) => handler.task_context_messages(task_name, task_dir),
+            CliHandler::Plugin(handler) => handler.task_context_messages(task_name, task_dir),
+        };
+        
+        match TaskConfig::load_for_task(task_dir) {
+            Ok(config) => {
+                if let Some(persona) = persona_message(environment, task_name, &config) {
+                    messages.insert(0, persona);
+                }
+                let strategy = if self.capabilities().supports_file_context {
+                    self.context_strategy()
+                } else {
+                    ContextStrategy::Paste
+                };
+                for file in config.context_files {
+                    let path = environment.get_root_dir().join(&file);
+                    let label = format!("context file {}", file.display());
+                    match &strategy {
+                        ContextStrategy::Paste => match std::fs::read_to_string(&path) {
+                            Ok(content) => messages.push((label, context_file_message(&file, task_name, &content))),
+                            Err(e) => eprintln!("Warning: Could not read context file {}: {}", path.display(), e),
+                        },
+                        ContextStrategy::Command(command) if path.is_file() => {
+                            messages.push((label, format!("{} {}", command, path.display())));
+                        },
+                        ContextStrategy::Command(_) => eprintln!("Warning: Context file {} not found", path.display()),
+                        // Given to the CLI as it starts, by `context_args`
+                        ContextStrategy::Flag(_) => {},
+                    }
+                }
+            },
+            Err(e) => eprintln!("Warning: Could not load task config: {:#}", e),
+        }
+        
+        let vars = template::task_vars(environment, task_name);
+        messages.into_iter()
+            .map(|(label, message)| (label, template::expand(&message, &vars)))
+            .collect()
+    }
+    
+    /// Wrap text as one message for the CLI, on one line if it can't take a multi-line paste
+    pub fn paste_message(&self, text: &str) -> String {
+        self.capabilities().paste_message(text)
+    }
+    
+    /// Get what the CLI can do, which the session checks before relying on it
+    pub fn capabilities(&self) -> Capabilities {
+        match self {
+            CliHandler::Q(handler) => handler.capabilities(),
+            CliHandler::Plugin(handler) => handler.capabilities(),
+        }
+    }
+    
+    /// Get the input that clears the CLI's conversation on a task switch, if the handler decides it
+    pub fn clear_input(&self) -> Option<Vec<String>> {
+        match self {
+            CliHandler::Q(handler) => Some(handler.clear_input()),
+            CliHandler::Plugin(_) => None,
+        }
+    }
+    
+    /// Get the context messages a task switch sends, if the handler decides them
+    ///
+    /// A plugin's `switch_task` hook sends what it likes, so only Q's are known.
+    pub fn switch_context_messages(&self, environment: &Environment, task_name: &str, task_dir: &std::path::Path) -> Option<Vec<(String, String)>> {
+        match self {
+            CliHandler::Q(_) => Some(self.task_context_messages(environment, task_name, task_dir)),
+            CliHandler::Plugin(_) => None,
+        }
+    }
+    
+    /// Get the arguments that give the CLI a task's context files as it starts,
+    /// for CLIs that take them with a flag
+    pub fn context_args(&self, environment: &Environment, task_name: &str, task_dir: &std::path::Path) -> Vec<String> {
+        if !self.capabilities().supports_file_context {
+            return Vec::new();
+        }
+        let ContextStrategy::Flag(flag) = self.context_strategy() else {
+            return Vec::new();
+        };
+        let Ok(config) = TaskConfig::load_for_task(task_dir) else {
+            return Vec::new();
+        };
+        
+        let vars = template::task_vars(environment, task_name);
+        let files: Vec<String> = config.context_files.iter()
+            .filter_map(|file| {
+                let path = environment.get_root_dir().join(file);
+                match std::fs::read_to_string(&path) {
+                    Ok(content) => Some(template::expand(&context_file_message(file, task_name, &content), &vars)),
+                    Err(e) => {
+                        eprintln!("Warning: Could not read context file {}: {}", path.display(), e);
+                        None
+                    },
+                }
+            })
+            .collect();
+        if files.is_empty() {
+            return Vec::new();
+        }
+        vec![flag, files.join("\n")]
+    }
+    
+    /// Get the commands that save the CLI's conversation to a file and load it
+    /// back, if the CLI can keep conversations
+    pub fn conversation_commands(&self, path: &std::path::Path) -> Option<ConversationCommands> {
+        match self {
+            CliHandler::Q(handler) => handler.conversation_commands(path),
+            CliHandler::Plugin(handler) => handler.conversation_commands(path),
+        }
+    }
+    
+    /// Get how the CLI takes files as context
+    pub fn context_strategy(&self) -> ContextStrategy {
+        match self {
+            CliHandler::Q(handler) => handler.context_strategy(),
+            CliHandler::Plugin(handler) => handler.context_strategy(),
+        }
+    }
+    
+    /// Check whether the output ends with the CLI's input prompt
+    pub fn is_prompt(&self, output: &str) -> bool {
+        match self {
+            CliHandler::Q(handler) => handler.is_prompt(output),
+            CliHandler::Plugin(handler) => handler.is_prompt(output),
+        }
+    }
+    
     /// Check if this CLI handler can handle the given command
     pub fn can_handle_command(&self, command: &str) -> bool {
         match self {
             CliHandler::Q(handler) => handler.can_handle_command(command),
+            CliHandler::Plugin(handler) => handler.can_handle_command(command),
         }
     }
+    
+    /// Get the CLI's own slash commands, without their slashes, if the handler knows them
+    pub fn native_commands(&self) -> Option<Vec<String>> {
+        match self {
+            CliHandler::Q(handler) => handler.native_commands(),
+            CliHandler::Plugin(handler) => handler.native_commands(),
+        }
+    }
+    
+    /// Find instructions for signing in to the CLI in its output, if it is asking
+    pub fn auth_prompt(&self, output: &str) -> Option<String> {
+        match self {
+            CliHandler::Q(handler) => handler.auth_prompt(output),
+            CliHandler::Plugin(handler) => handler.auth_prompt(output),
+        }
+    }
+    
+    /// Get the input that makes the CLI stop generating a response
+    pub fn cancel_sequence(&self) -> String {
+        match self {
+            CliHandler::Q(handler) => handler.cancel_sequence(),
+            CliHandler::Plugin(handler) => handler.cancel_sequence(),
+        }
+    }
+    
+    /// Ask the CLI to exit with its own quit command, so it can end cleanly
+    pub async fn on_quit(&self, process_input_tx: &mpsc::Sender<String>) -> Result<()> {
+        match self {
+            CliHandler::Q(handler) => handler.on_quit(process_input_tx).await,
+            CliHandler::Plugin(handler) => handler.on_quit(process_input_tx).await,
+        }
+    }
+}
+
+/// The slash commands that belong to the wrapped CLI rather than to grill
+///
+/// Declared by the CLI's handler and its profile's `native_commands`. When
+/// neither declares any, grill can't tell, and any command it doesn't know
+/// might be the CLI's.
+#[derive(Debug, Clone, Default)]
+pub struct NativeCommands {
+    declared: Option<Vec<String>>,
+}
+
+impl NativeCommands {
+    /// Gather the commands a CLI's handler and profile declare
+    pub fn for_cli(handler: &CliHandler, profile: &CliProfile) -> Self {
+        let mut declared = handler.native_commands();
+        if !profile.native_commands.is_empty() {
+            declared.get_or_insert_with(Vec::new)
+                .extend(profile.native_commands.iter().map(|command| command.trim_start_matches('/').to_string()));
+        }
+        Self { declared }
+    }
+    
+    /// Check whether a command is declared as the CLI's, so it goes to the CLI even if grill has one by that name
+    pub fn declares(&self, command: &str) -> bool {
+        let name = command.split_whitespace().next().unwrap_or("").trim_start_matches('/');
+        self.declared.as_ref().is_some_and(|commands| commands.iter().any(|declared| declared == name))
+    }
+    
+    /// Check whether a command grill doesn't know could be the CLI's
+    pub fn may_include(&self, command: &str) -> bool {
+        self.declared.is_none() || self.declares(command)
+    }
+    
+    /// The commands declared as the CLI's, or `None` if it may have any
+    pub fn declared(&self) -> Option<&[String]> {
+        self.declared.as_deref()
+    }
+}
+
+/// What a CLI can do, as reported by its handler
+///
+/// The session checks these rather than assuming every CLI behaves like Q.
+/// A plugin reports them from its `capabilities` hook, where any it leaves
+/// out are false.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    /// The CLI can clear its conversation without restarting
+    pub supports_clear: bool,
+    /// The CLI can save its conversation and load it back
+    pub supports_resume: bool,
+    /// The CLI can be given files through its own context system
+    pub supports_file_context: bool,
+    /// The CLI takes a bracketed paste as one message, newlines and all
+    pub multiline_input: bool,
+    /// The CLI asks to confirm clearing its conversation
+    pub needs_confirmation: bool,
+    /// The CLI answers `/help` with its own help
+    pub native_help: bool,
+}
+
+impl Capabilities {
+    /// Get the capabilities of a known CLI from its command line
+    ///
+    /// Nothing is assumed of any other CLI, so text it is sent is joined
+    /// onto one line rather than risking each line being taken as a message.
+    pub fn for_command(command: &str) -> Self {
+        let known = Self {
+            supports_clear: true,
+            supports_file_context: true,
+            multiline_input: true,
+            native_help: true,
+            ..Self::default()
+        };
+        match CommandLine::parse(command).program_name() {
+            "q" => Self { supports_resume: true, needs_confirmation: true, ..known },
+            "aider" | "claude" => known,
+            _ => Self::default(),
+        }
+    }
+    
+    /// Wrap text as one message for the CLI, on one line if it can't take a multi-line paste
+    pub fn paste_message(&self, text: &str) -> String {
+        if self.multiline_input {
+            crate::clipboard::paste_message(text)
+        } else {
+            crate::clipboard::paste_message(&crate::clipboard::join_lines(text))
+        }
+    }
+}
+
+/// How a CLI takes files as context
+///
+/// Pasting a file into the chat works with any CLI. CLIs with a context
+/// system of their own can be pointed at the file instead, which keeps it out
+/// of the conversation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextStrategy {
+    /// Paste the file's contents into the chat
+    #[default]
+    Paste,
+    /// Give the file's path to one of the CLI's commands, such as Q's `/context add`
+    Command(String),
+    /// Give the file's contents to the CLI with a flag as it starts, such as claude's `--append-system-prompt`
+    Flag(String),
+}
+
+impl ContextStrategy {
+    /// Get the strategy for a known CLI from its command line, pasting for any other
+    pub fn for_command(command: &str) -> Self {
+        match CommandLine::parse(command).program_name() {
+            "q" => ContextStrategy::Command("/context add".to_string()),
+            "aider" => ContextStrategy::Command("/read".to_string()),
+            "claude" => ContextStrategy::Flag("--append-system-prompt".to_string()),
+            _ => ContextStrategy::Paste,
+        }
+    }
+}
+
+/// Commands that save a CLI's conversation to a file and load it back
+///
+/// A task's conversation is saved when grill leaves the task and loaded when
+/// it comes back, so each task carries on its own conversation.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ConversationCommands {
+    /// Input that saves the conversation, replacing any saved before
+    pub save: String,
+    /// Input that loads the saved conversation
+    pub load: String,
+}
+
+/// Build the message that pastes a context file into the chat
+fn context_file_message(file: &std::path::Path, task_name: &str, content: &str) -> String {
+    format!("Here is {} for task '{}': \n\n{}\n", file.display(), task_name, content)
+}
+
+/// Build the message that gives the CLI a task's persona, as a (label, message) pair
+///
+/// Personas live in `.grill/personas/<name>.md`, apart from any task's
+/// instructions, so one role can be shared by several tasks.
+pub fn persona_message(environment: &Environment, task_name: &str, config: &TaskConfig) -> Option<(String, String)> {
+    let name = config.persona.as_deref()?;
+    match environment.load_persona(name) {
+        Ok(persona) => Some((
+            format!("persona {}", name),
+            format!("For task '{}', take on this role: \n\n{}\n", task_name, persona.trim()),
+        )),
+        Err(e) => {
+            eprintln!("Warning: {:#}", e);
+            None
+        },
+    }
 }
 
+/// Output of known CLIs asking the user to sign in: Q's device login and
+/// `q login`, and CLIs such as aider asking for an API key
+const AUTH_PATTERNS: &[&str] = &[
+    r"https://\S*device\S*",
+    r"(?i)\bq login\b",
+    r"(?i)\b(sign|log) ?in\b.*\b(browser|url|code)\b",
+    r"(?i)\b(enter|paste|provide|set)\b.*\bapi[ _]key\b",
+    r"(?i)\bapi[ _]key\b.*\b(not set|missing|required|invalid)\b",
+];
+
+fn auth_patterns() -> &'static [Regex] {
+    static PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+    PATTERNS.get_or_init(|| {
+        AUTH_PATTERNS.iter()
+            .map(|pattern| Regex::new(pattern).expect("sign-in pattern is valid"))
+            .collect()
+    })
+}
+
+/// Q's input prompt, alone on the last line of output
+///
+/// Q shows "> " when it is waiting for input, "[profile] > " with a profile
+/// and "!> " once every tool is trusted.
+fn q_prompt() -> &'static Regex {
+    static PROMPT: OnceLock<Regex> = OnceLock::new();
+    PROMPT.get_or_init(|| Regex::new(r"^(\[[^\]\s]+\] )?!?> ?$").expect("prompt pattern is valid"))
+}
+
+/// Commands that known CLIs exit on, by program name
+///
+/// Any other CLI is sent Ctrl+D, which ends most REPLs.
+const QUIT_COMMANDS: &[(&str, &str)] = &[("q", "/quit"), ("aider", "/exit")];
+
+/// Input that clears a CLI's conversation
+const CLEAR_INPUT: &str = "/clear\r";
+
+/// Input that confirms clearing, for CLIs that ask
+const CONFIRM_INPUT: &str = "y\r";
+
+/// Q's own slash commands, without the slash
+const Q_COMMANDS: &[&str] = &[
+    "clear", "model", "settings", "tools", "profile", "context", "compact", "usage", "editor",
+    "issue", "prompts", "hooks", "mcp", "save", "load", "subscribe", "knowledge",
+];
+
 /// Handler for Amazon Q CLI
 #[derive(Clone)]
 pub struct QCliHandler {
@@ -105,9 +507,18 @@ impl QCliHandler {
         Ok(false) // Not handled, let the default handler take care of it
     }
     
+    /// Check whether this runs Q itself rather than standing in for another CLI
+    fn is_q(&self) -> bool {
+        CommandLine::parse(&self.command).runs("q", Some("chat"))
+    }
+    
     fn get_help_text(&self) -> String {
-        // Q-specific help text
-        String::from("\nQ CLI Help (below):\n")
+        // Standing in for another CLI, there's nothing to say about it
+        if self.is_q() {
+            String::from("Amazon Q Developer CLI, run as `q chat`.")
+        } else {
+            String::new()
+        }
     }
     
     fn on_start(
@@ -133,31 +544,33 @@ impl QCliHandler {
         Ok(Some(output))
     }
     
-    /// Clear the CLI's context and switch to a new task
+    /// Clear the CLI's context, unless `clear` is false, and switch to a new task
     async fn clear_context_and_switch_task(
         &self,
         new_task_name: &str,
-        task_dir: &std::path::Path,
+        messages: &[(String, String)],
+        clear: bool,
         process_input_tx: &mpsc::Sender<String>,
         output_tx: &mpsc::Sender<String>,
     ) -> Result<()> {
-        // Send clear command to Q CLI
         let _ = output_tx.try_send(format!("\nSwitching to task: {}\n", new_task_name));
-        let _ = output_tx.try_send("Clearing current context...\n".to_string());
-        
-        // Send /clear command to Q CLI to clear the conversation
-        process_input_tx.send("/clear\r".to_string()).await
-            .context("Failed to send clear command to Q CLI")?;
-        
-        // Q CLI asks for confirmation, send y.
-        process_input_tx.send("y\r".to_string()).await
-            .context("Failed to send clear command to Q CLI")?;
-
-        // Give the CLI a moment to process the clear command
-        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        if clear {
+            let _ = output_tx.try_send("Clearing current context...\n".to_string());
+            
+            // Send /clear to Q CLI to clear the conversation, confirming it if asked
+            for input in self.clear_input() {
+                process_input_tx.send(input).await
+                    .context("Failed to send clear command to Q CLI")?;
+            }
+            
+            // Give the CLI a moment to process the clear command
+            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+        } else {
+            let _ = output_tx.try_send("Keeping the current conversation\n".to_string());
+        }
         
         // Load task context files if they exist
-        self.load_task_context(new_task_name, task_dir, process_input_tx, output_tx).await?;
+        self.load_task_context(messages, process_input_tx, output_tx).await?;
         
         let _ = output_tx.try_send(format!("Successfully switched to task: {}\n\n", new_task_name));
         
@@ -167,79 +580,250 @@ impl QCliHandler {
     /// Load task context into the CLI
     async fn load_task_context(
         &self,
-        task_name: &str,
-        task_dir: &std::path::Path,
+        messages: &[(String, String)],
         process_input_tx: &mpsc::Sender<String>,
         output_tx: &mpsc::Sender<String>,
     ) -> Result<()> {
-        // Load instructions.md if it exists
-        let instructions_path = task_dir.join("instructions.md");
-        if instructions_path.exists() {
-            match std::fs::read_to_string(&instructions_path) {
-                Ok(instructions) => {
-                    let _ = output_tx.try_send("Loading task instructions...\n".to_string());
-                    
-                    // Send the instructions as a message to Q CLI
-                    let context_message = format!("Here are the instructions for task '{}': \n\n{}\n", task_name, instructions);
-                    process_input_tx.send(format!("{}\r", context_message)).await
-                        .context("Failed to send instructions to Q CLI")?;
-                    
-                    // Give the CLI time to process
-                    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-                },
-                Err(e) => {
-                    eprintln!("Warning: Could not read instructions.md: {}", e);
-                }
-            }
+        for (label, context_message) in messages {
+            let _ = output_tx.try_send(format!("Loading task {}...\n", label));
+            
+            // Send the context as one message, however many lines it has
+            process_input_tx.send(self.capabilities().paste_message(context_message)).await
+                .context(format!("Failed to send {} to Q CLI", label))?;
+            
+            // Give the CLI time to process
+            tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
         }
         
-        // Load state.md if it exists and has meaningful content
-        let state_path = task_dir.join("state.md");
-        if state_path.exists() {
-            match std::fs::read_to_string(&state_path) {
-                Ok(state) => {
-                    // Only load state if it's not just the default template
-                    if !state.trim().starts_with("# Task State\n\nTask state will be tracked here.") {
-                        let _ = output_tx.try_send("Loading task state...\n".to_string());
-                        
-                        // Send the state as context to Q CLI
-                        let context_message = format!("Here is the current state for task '{}': \n\n{}\n", task_name, state);
-                        process_input_tx.send(format!("{}\r", context_message)).await
-                            .context("Failed to send state to Q CLI")?;
-                        
-                        // Give the CLI time to process
-                        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-                    }
-                },
-                Err(e) => {
-                    eprintln!("Warning: Could not read state.md: {}", e);
-                }
+        Ok(())
+    }
+    
+    /// Build the context messages for a task as (label, message) pairs
+    fn task_context_messages(&self, task_name: &str, task_dir: &std::path::Path) -> Vec<(String, String)> {
+        let mut messages = Vec::new();
+        
+        let task = match Task::from_dir(task_name, task_dir) {
+            Ok(task) => task,
+            Err(e) => {
+                eprintln!("Warning: Could not load task '{}': {:#}", task_name, e);
+                return messages;
             }
+        };
+        
+        if let Some(instructions) = task.instructions() {
+            messages.push((
+                "instructions".to_string(),
+                format!("Here are the instructions for task '{}': \n\n{}\n", task_name, instructions),
+            ));
         }
         
-        Ok(())
+        // State is only sent once it has meaningful content
+        if let Some(state) = task.state() {
+            messages.push((
+                "state".to_string(),
+                format!("Here is the current state for task '{}': \n\n{}\n", task_name, state),
+            ));
+        }
+        
+        messages
+    }
+    
+    /// Check whether the output ends with Q CLI's input prompt
+    fn is_prompt(&self, output: &str) -> bool {
+        q_prompt().is_match(&crate::ansi::last_line(output))
     }
     
     /// Check if this handler can handle the given command
+    ///
+    /// Q handles `q chat` with any options; standing in for another CLI, it
+    /// handles that CLI's program, wherever it is installed.
     fn can_handle_command(&self, command: &str) -> bool {
-        // Q CLI handler can handle any command that starts with "q chat"
-        command.contains("q chat") || command.contains("q") && command.contains("chat")
+        let command = CommandLine::parse(command);
+        if self.is_q() {
+            command.runs("q", Some("chat"))
+        } else {
+            command.program_name() == CommandLine::parse(&self.command).program_name()
+        }
+    }
+    
+    /// Get Q's own slash commands, if this runs Q rather than standing in for another CLI
+    fn native_commands(&self) -> Option<Vec<String>> {
+        self.is_q()
+            .then(|| Q_COMMANDS.iter().map(|command| command.to_string()).collect())
+    }
+    
+    /// Q stops a response on Ctrl+C and stays running
+    fn cancel_sequence(&self) -> String {
+        "\x03".to_string()
+    }
+    
+    /// Spot Q, or a CLI it stands in for, asking the user to sign in
+    fn auth_prompt(&self, output: &str) -> Option<String> {
+        find_auth_prompt(output)
+    }
+    
+    /// Q's capabilities, or those of the CLI it stands in for
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::for_command(&self.command)
+    }
+    
+    /// Get the input that clears the CLI's conversation, none if it can't
+    fn clear_input(&self) -> Vec<String> {
+        let capabilities = self.capabilities();
+        if !capabilities.supports_clear {
+            return Vec::new();
+        }
+        let mut input = vec![CLEAR_INPUT.to_string()];
+        if capabilities.needs_confirmation {
+            input.push(CONFIRM_INPUT.to_string());
+        }
+        input
+    }
+    
+    /// Q keeps conversations with `/save` and `/load`; the CLIs it stands in for can't
+    fn conversation_commands(&self, path: &std::path::Path) -> Option<ConversationCommands> {
+        if !self.capabilities().supports_resume {
+            return None;
+        }
+        Some(ConversationCommands {
+            save: format!("/save {} --force", path.display()),
+            load: format!("/load {}", path.display()),
+        })
+    }
+    
+    /// Q registers files with `/context add`; the CLIs it stands in for use their own way
+    fn context_strategy(&self) -> ContextStrategy {
+        ContextStrategy::for_command(&self.command)
+    }
+    
+    /// Q exits on its own `/quit`; the CLIs it stands in for get theirs
+    async fn on_quit(&self, process_input_tx: &mpsc::Sender<String>) -> Result<()> {
+        process_input_tx.send(quit_input(&self.command)).await
+            .context("Failed to send quit command to CLI")
+    }
+}
+
+/// Find the first line of output asking the user to sign in
+pub fn find_auth_prompt(output: &str) -> Option<String> {
+    crate::ansi::strip_ansi(output).lines()
+        .map(str::trim)
+        .find(|line| auth_patterns().iter().any(|pattern| pattern.is_match(line)))
+        .map(str::to_string)
+}
+
+/// Get the input that makes a CLI exit, from its command line
+pub fn quit_input(command: &str) -> String {
+    let command = CommandLine::parse(command);
+    match QUIT_COMMANDS.iter().find(|(name, _)| *name == command.program_name()) {
+        Some((_, quit)) => format!("{}\r", quit),
+        None => "\x04".to_string(),
+    }
+}
+
+/// A CLI's command line, split into the program it runs and its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandLine {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl CommandLine {
+    /// Split a command line into its program and arguments, as a shell would
+    pub fn parse(command: &str) -> Self {
+        let mut words = crate::clis::split_command(command).into_iter();
+        Self {
+            program: words.next().unwrap_or_default(),
+            args: words.collect(),
+        }
+    }
+    
+    /// Get the program's file name, without its directory
+    pub fn program_name(&self) -> &str {
+        std::path::Path::new(&self.program).file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.program)
+    }
+    
+    /// Get the first argument that isn't an option, such as `chat` in `q chat`
+    pub fn subcommand(&self) -> Option<&str> {
+        self.args.iter().map(String::as_str).find(|arg| !arg.starts_with('-'))
+    }
+    
+    /// Check whether this runs a program, by file name, with the given subcommand if any
+    pub fn runs(&self, program: &str, subcommand: Option<&str>) -> bool {
+        self.program_name() == program && (subcommand.is_none() || self.subcommand() == subcommand)
     }
 }
 
+/// Creates a built-in handler for a command
+type CreateHandler = fn(String) -> CliHandler;
+
+/// Built-in handlers, by the program and subcommand they run
+///
+/// These take precedence over plugins. Other commands go to the first plugin
+/// that wants them, and then to the default handler.
+const BUILTIN_HANDLERS: &[(&str, Option<&str>, CreateHandler)] = &[
+    ("q", Some("chat"), |command| CliHandler::Q(QCliHandler::new(command))),
+];
+
 /// Factory for creating CLI handlers
 pub struct CliHandlerFactory;
 
 impl CliHandlerFactory {
     /// Create a CLI handler based on the command
     pub fn create_handler(command: String) -> CliHandler {
-        // Determine which handler to use based on the command
-        if command.contains("q chat") {
-            CliHandler::Q(QCliHandler::new(command))
-        } else {
-            // Default to Q handler for now
-            // In the future, we can add more handlers here
-            CliHandler::Q(QCliHandler::new(command))
+        // Built-in handlers take precedence over plugins
+        let command_line = CommandLine::parse(&command);
+        if let Some((_, _, create)) = BUILTIN_HANDLERS.iter()
+            .find(|(program, subcommand, _)| command_line.runs(program, *subcommand))
+        {
+            return create(command);
+        }
+        
+        // Ask each plugin on PATH whether it wants this command
+        if let Some(handler) = Self::create_plugin_handler(&command) {
+            return CliHandler::Plugin(handler);
+        }
+        
+        // Default to Q handler for now
+        CliHandler::Q(QCliHandler::new(command))
+    }
+    
+    /// Find the first discovered plugin that can handle the command
+    ///
+    /// The answer is kept for the rest of the process, so switching tasks
+    /// reuses the running plugin rather than asking every plugin again.
+    /// Plugins that decline are stopped straight away.
+    fn create_plugin_handler(command: &str) -> Option<PluginCliHandler> {
+        static CHOSEN: OnceLock<Mutex<HashMap<String, Option<PluginCliHandler>>>> = OnceLock::new();
+        let mut chosen = CHOSEN.get_or_init(Default::default).lock().unwrap();
+        match chosen.get(command) {
+            Some(Some(handler)) if handler.is_alive() => return Some(handler.clone()),
+            Some(None) => return None,
+            _ => {},
+        }
+        
+        let handler = Self::discover_plugin_handler(command);
+        chosen.insert(command.to_string(), handler.clone());
+        handler
+    }
+    
+    fn discover_plugin_handler(command: &str) -> Option<PluginCliHandler> {
+        for info in plugin::discover_plugins() {
+            match PluginCliHandler::spawn(&info, command.to_string()) {
+                Ok(handler) => {
+                    if handler.can_handle_command(command) {
+                        log::info!("Using handler plugin '{}' for '{}'", handler.name(), command);
+                        return Some(handler);
+                    }
+                    handler.stop();
+                },
+                Err(e) => {
+                    eprintln!("Warning: Failed to start handler plugin '{}': {}", info.name, e);
+                }
+            }
         }
+        
+        None
     }
 }
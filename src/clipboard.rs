@@ -0,0 +1,160 @@
+use anyhow::{Result, Context, anyhow};
+use std::io::{self, Write};
+use std::process::{Command as ProcessCommand, Stdio};
+
+use crate::clis::find_binary;
+use crate::config::{ClipboardConfig, ClipboardMethod};
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode bytes as standard padded base64
+pub fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let group = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(group >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Build the OSC 52 sequence that asks the terminal to put `text` on the clipboard
+///
+/// This works over SSH and inside tmux (with `set-clipboard on`), since the
+/// terminal emulator does the copying.
+pub fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64(text.as_bytes()))
+}
+
+/// Copy text to the system clipboard through the terminal
+pub fn copy(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(osc52(text).as_bytes())?;
+    stdout.flush()
+}
+
+/// How copied text reaches the clipboard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Copied {
+    /// A clipboard program took it
+    Program(String),
+    /// The terminal has to be sent this escape sequence
+    Terminal(String),
+}
+
+/// Clipboard programs as (copy, paste) commands, most specific first
+fn clipboard_programs() -> Vec<(&'static str, &'static str)> {
+    let mut programs = Vec::new();
+    if cfg!(target_os = "macos") {
+        programs.push(("pbcopy", "pbpaste"));
+    }
+    if cfg!(windows) {
+        programs.push(("clip", "powershell -NoProfile -Command Get-Clipboard"));
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        programs.push(("wl-copy", "wl-paste --no-newline"));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        programs.push(("xclip -selection clipboard", "xclip -selection clipboard -o"));
+        programs.push(("xsel --clipboard --input", "xsel --clipboard --output"));
+    }
+    programs
+}
+
+/// Find an installed clipboard program, as its copy and paste commands
+fn find_program() -> Option<(&'static str, &'static str)> {
+    clipboard_programs().into_iter()
+        .find(|(copy, _)| copy.split_whitespace().next().and_then(find_binary).is_some())
+}
+
+fn check_enabled(config: &ClipboardConfig) -> Result<()> {
+    if config.method == ClipboardMethod::Off {
+        return Err(anyhow!("The clipboard is turned off (method = \"off\" under [clipboard])"));
+    }
+    Ok(())
+}
+
+/// Copy text to the system clipboard as configured
+///
+/// Returns `Copied::Terminal` with an OSC 52 sequence when the terminal has
+/// to do the copying.
+pub fn copy_text(config: &ClipboardConfig, text: &str) -> Result<Copied> {
+    check_enabled(config)?;
+    if config.method == ClipboardMethod::Osc52 {
+        return Ok(Copied::Terminal(osc52(text)));
+    }
+    
+    let command = config.copy_command.clone()
+        .or_else(|| find_program().map(|(copy, _)| copy.to_string()));
+    match command {
+        Some(command) => {
+            run(&command, Some(text))?;
+            Ok(Copied::Program(command))
+        },
+        None if config.method == ClipboardMethod::Auto => Ok(Copied::Terminal(osc52(text))),
+        None => Err(anyhow!("No clipboard program found; set copy_command under [clipboard]")),
+    }
+}
+
+/// Read the system clipboard as configured
+///
+/// Terminals rarely let programs read the clipboard, so this always needs a
+/// clipboard program.
+pub fn paste_text(config: &ClipboardConfig) -> Result<String> {
+    check_enabled(config)?;
+    let command = config.paste_command.clone()
+        .or_else(|| find_program().map(|(_, paste)| paste.to_string()))
+        .ok_or_else(|| anyhow!("No clipboard program found; set paste_command under [clipboard]"))?;
+    run(&command, None)
+}
+
+/// Run a clipboard program, optionally writing `input` to it, and return what it prints
+fn run(command: &str, input: Option<&str>) -> Result<String> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or_else(|| anyhow!("The clipboard command is empty"))?;
+    let mut child = ProcessCommand::new(program)
+        .args(parts)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        // Copy programs like xclip stay behind to serve the selection, holding stdout open
+        .stdout(if input.is_some() { Stdio::null() } else { Stdio::piped() })
+        .stderr(Stdio::piped())
+        .spawn()
+        .context(format!("Failed to run '{}'", command))?;
+    
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin.write_all(input.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "'{}' failed: {}",
+            command,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Join text's lines into one, for CLIs that would take each line as a message
+pub fn join_lines(text: &str) -> String {
+    text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+/// Wrap text so the CLI receives it as one pasted message
+///
+/// Text spanning several lines is sent as a bracketed paste, so its newlines
+/// don't submit each line on its own.
+pub fn paste_message(text: &str) -> String {
+    let text = text.trim_end_matches(['\r', '\n']);
+    if text.contains('\n') {
+        format!("\x1b[200~{}\x1b[201~\r", text)
+    } else {
+        format!("{}\r", text)
+    }
+}
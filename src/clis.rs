@@ -0,0 +1,208 @@
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+use std::process::{Command as ProcessCommand, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, TaskConfig};
+use crate::environment::Environment;
+use crate::process::CommandNotFound;
+
+/// CLIs grill knows how to suggest, as (binary, command, product name)
+const KNOWN_CLIS: &[(&str, &str, &str)] = &[
+    ("q", "q chat", "Amazon Q CLI"),
+    ("claude", "claude", "Claude Code"),
+    ("aider", "aider", "aider"),
+    ("gemini", "gemini", "Gemini CLI"),
+    ("codex", "codex", "Codex CLI"),
+];
+
+/// How long `--version` may take before the CLI is reported without a version
+const VERSION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A CLI defined in the configuration
+#[derive(Debug, Clone)]
+pub struct CliInfo {
+    /// Name under `[clis]`, or `default` for a default CLI that isn't listed there
+    pub name: String,
+    pub command: String,
+    /// Where the command's program was found, if it is installed
+    pub binary: Option<PathBuf>,
+    pub is_default: bool,
+}
+
+/// Split a CLI command into its program and arguments, as a shell would
+///
+/// Quotes and backslashes work as in `sh`, so paths and arguments can
+/// contain spaces. A command with unbalanced quotes is split on whitespace.
+pub fn split_command(command: &str) -> Vec<String> {
+    shell_words::split(command)
+        .unwrap_or_else(|_| command.split_whitespace().map(str::to_string).collect())
+}
+
+/// Get the program a CLI command runs
+pub fn program(command: &str) -> Option<String> {
+    split_command(command).into_iter().next()
+}
+
+/// Find a program on `PATH`, or check it exists if it is a path
+pub fn find_binary(program: &str) -> Option<PathBuf> {
+    let candidate = Path::new(program);
+    if candidate.components().count() > 1 {
+        return candidate.is_file().then(|| candidate.to_path_buf());
+    }
+    
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .flat_map(|dir| executable_names(program).into_iter().map(move |name| dir.join(name)))
+        .find(|path| path.is_file())
+}
+
+#[cfg(unix)]
+fn executable_names(program: &str) -> Vec<String> {
+    vec![program.to_string()]
+}
+
+#[cfg(windows)]
+fn executable_names(program: &str) -> Vec<String> {
+    vec![program.to_string(), format!("{}.exe", program), format!("{}.cmd", program)]
+}
+
+/// Ask a binary for its version with `--version`
+///
+/// Returns the first line it prints, or `None` if it fails or takes too long.
+pub fn version(binary: &Path) -> Option<String> {
+    let mut child = ProcessCommand::new(binary)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    
+    // Some CLIs start an interactive session instead of printing a version
+    let started = Instant::now();
+    while child.try_wait().ok()?.is_none() {
+        if started.elapsed() > VERSION_TIMEOUT {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    
+    let text = if output.stdout.is_empty() { output.stderr } else { output.stdout };
+    String::from_utf8_lossy(&text)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// List the CLIs defined in a configuration, sorted by name
+///
+/// The default CLI is flagged; if it isn't one of `[clis]` it is listed as `default`.
+pub fn list(config: &Config) -> Vec<CliInfo> {
+    let default_cli = config.get_default_cli();
+    let mut clis: Vec<CliInfo> = config.clis.iter()
+        .map(|(name, cli)| CliInfo {
+            name: name.clone(),
+            command: cli.command_line(),
+            binary: program(&cli.command).and_then(|program| find_binary(&program)),
+            is_default: cli.command == default_cli || name == default_cli,
+        })
+        .collect();
+    clis.sort_by(|a, b| a.name.cmp(&b.name));
+    
+    if !clis.iter().any(|cli| cli.is_default) {
+        clis.insert(0, CliInfo {
+            name: "default".to_string(),
+            command: default_cli.to_string(),
+            binary: program(default_cli).and_then(|program| find_binary(&program)),
+            is_default: true,
+        });
+    }
+    
+    clis
+}
+
+/// Check that the program a CLI runs is installed
+///
+/// `cli` is a `cli` or `default_cli` setting. The error suggests installed
+/// alternatives, from the configuration first.
+pub fn check_installed(config: &Config, cli: &str) -> Result<()> {
+    let command = config.resolve_cli(cli).command_line();
+    let program = program(&command)
+        .ok_or_else(|| anyhow!("The CLI command is empty; set default_cli in .grill/config.toml"))?;
+    if find_binary(&program).is_some() {
+        return Ok(());
+    }
+    
+    let mut suggestions: Vec<String> = list(config).into_iter()
+        .filter(|cli| cli.binary.is_some())
+        .map(|cli| cli.command)
+        .collect();
+    for (binary, known, _) in KNOWN_CLIS {
+        if !suggestions.iter().any(|command| command == known) && find_binary(binary).is_some() {
+            suggestions.push(known.to_string());
+        }
+    }
+    
+    let mut message = format!("'{}' is not installed (no '{}' found on PATH).", command, program);
+    if suggestions.is_empty() {
+        message.push_str(" Install it, or set default_cli in .grill/config.toml to a CLI that is installed.");
+    } else {
+        message.push_str(" Installed CLIs you could use instead:");
+        for suggestion in suggestions {
+            message.push_str(&format!("\n  {}", suggestion));
+        }
+        message.push_str("\nSet default_cli in .grill/config.toml (or cli in the task's config.toml) to use one.");
+    }
+    
+    Err(anyhow!(message))
+}
+
+/// Turn a failure to start a task's CLI into advice if its program isn't installed
+///
+/// The message names the config file that selected the CLI, since that is
+/// where it has to be changed. Other errors are returned unchanged.
+pub fn explain_start_error(error: anyhow::Error, environment: &Environment, task_name: &str) -> anyhow::Error {
+    let program = match error.downcast_ref::<CommandNotFound>() {
+        Some(not_found) => not_found.program.clone(),
+        None => return error,
+    };
+    
+    let install = KNOWN_CLIS.iter()
+        .find(|(binary, _, _)| *binary == program)
+        .map(|(_, _, product)| format!("install {}", product))
+        .unwrap_or_else(|| format!("install '{}'", program));
+    
+    // Frontmatter in instructions.md overrides the task's config.toml
+    let task_source = environment.get_task_dir(task_name).ok().and_then(|dir| {
+        let instructions = dir.join(crate::task::INSTRUCTIONS_FILE);
+        let from_frontmatter = std::fs::read_to_string(&instructions).ok()
+            .and_then(|text| crate::frontmatter::parse(&text).ok().and_then(|(frontmatter, _)| frontmatter))
+            .is_some_and(|frontmatter| frontmatter.cli.is_some());
+        if from_frontmatter {
+            return Some(instructions);
+        }
+        let config = crate::config::find_config_file(&dir);
+        TaskConfig::load(&config).is_ok_and(|config| config.get_cli().is_some()).then_some(config)
+    });
+    let (setting, source) = match task_source {
+        Some(path) => ("cli", path),
+        None => ("default_cli", environment.get_config_path()),
+    };
+    
+    anyhow!(
+        "Command '{}' not found \u{2014} {} or set {} in {}",
+        program,
+        install,
+        setting,
+        source.display()
+    )
+}
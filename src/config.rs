@@ -1,23 +1,781 @@
-use anyhow::{Result, Context};
-use serde::{Serialize, Deserialize};
-use std::path::Path;
+use anyhow::{Result, Context, anyhow};
+use serde::{Serialize, Deserialize, Deserializer};
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
 use std::fs;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Names a config file may have, in order of preference
+///
+/// The format is chosen by the extension.
+pub const CONFIG_FILE_NAMES: &[&str] = &["config.toml", "config.yaml", "config.yml", "config.json"];
+
+/// Find the config file in a directory, whichever format it is in
+///
+/// Returns the path of `config.toml` if there is none.
+pub fn find_config_file(dir: &Path) -> PathBuf {
+    CONFIG_FILE_NAMES.iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+        .unwrap_or_else(|| dir.join(CONFIG_FILE_NAMES[0]))
+}
+
+/// Parse the contents of a config file in the format given by its extension
+fn parse_config<T: DeserializeOwned>(path: &Path, content: &str) -> Result<T> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("toml");
+    match extension {
+        // An empty YAML document parses as nothing rather than an empty map
+        "yaml" | "yml" if content.trim().is_empty() => Ok(serde_yaml::from_str("{}")?),
+        "yaml" | "yml" => Ok(serde_yaml::from_str(content)?),
+        "json" => Ok(serde_json::from_str(content)?),
+        _ => Ok(toml::from_str(content)?),
+    }
+}
+
+/// Serialize a config in the format given by its file's extension
+fn serialize_config<T: Serialize>(path: &Path, config: &T) -> Result<String> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("toml");
+    match extension {
+        "yaml" | "yml" => Ok(serde_yaml::to_string(config)?),
+        "json" => Ok(serde_json::to_string_pretty(config)? + "\n"),
+        _ => Ok(toml::to_string(config)?),
+    }
+}
 
 /// Global configuration for grill
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
+    /// Schema version, used to upgrade older workspaces; files without one are version 0
+    #[serde(default)]
+    pub version: u32,
+    
     /// Default CLI to use
-    #[serde(default = "default_cli")]
+    #[serde(default = "default_cli", deserialize_with = "deserialize_command")]
     pub default_cli: String,
     
-    /// Available CLIs
+    /// Available CLIs, by name
+    #[serde(default)]
+    pub clis: HashMap<String, CliProfile>,
+    
+    /// Start the CLI with `$SHELL -lc`, so it sees the PATH, version manager
+    /// shims and aliases it would in a terminal
     #[serde(default)]
-    pub clis: HashMap<String, String>,
+    pub shell_wrap: bool,
     
     /// Hooks to run on task switch
     #[serde(default)]
     pub hooks: HashMap<String, String>,
+    
+    /// Short names for grill commands, such as `"/sw" = "/task switch"`
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    
+    /// Git integration
+    #[serde(default)]
+    pub git: GitConfig,
+    
+    /// Context commands
+    #[serde(default)]
+    pub context: ContextConfig,
+    
+    /// Throughput tuning for chatty CLIs
+    #[serde(default)]
+    pub performance: PerformanceConfig,
+    
+    /// How grill uses the terminal
+    #[serde(default)]
+    pub terminal: TerminalConfig,
+    
+    /// How `/copy` and `/paste` reach the system clipboard
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    
+    /// Rules that rewrite or hide the CLI's output before it is shown
+    #[serde(default)]
+    pub filters: FiltersConfig,
+    
+    /// Actions taken when the CLI's output matches a pattern
+    #[serde(default)]
+    pub triggers: TriggersConfig,
+    
+    /// What happens when a session is left alone
+    #[serde(default)]
+    pub idle: IdleConfig,
+    
+    /// When the CLI counts as stuck
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    
+    /// Where session metrics are exported
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    
+    /// Checks that the CLI has started properly
+    #[serde(default)]
+    pub startup: StartupConfig,
+    
+    /// How CLIs run on another machine over ssh are driven
+    #[serde(default)]
+    pub remote: RemoteConfig,
+    
+    /// Which of grill's environment variables the CLI inherits
+    #[serde(default)]
+    pub environment: EnvironmentConfig,
+    
+    /// Checks on what is sent to the CLI
+    #[serde(default)]
+    pub input: InputConfig,
+    
+    /// Keys bound to grill's actions
+    #[serde(default)]
+    pub keys: KeysConfig,
+}
+
+/// Output filters, applied in order to each chunk of the CLI's output
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FiltersConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<FilterRule>,
+}
+
+/// A regular expression and what to do with the output it matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterRule {
+    /// Regular expression, in which `^` and `$` match at each line
+    pub pattern: String,
+    
+    #[serde(default)]
+    pub action: FilterAction,
+    
+    /// Text that replaces each match, which may refer to groups as `$1` or `${name}`
+    #[serde(default)]
+    pub replacement: String,
+}
+
+/// What a filter rule does with matching output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FilterAction {
+    /// Replace each match with the rule's replacement, by default removing it
+    #[default]
+    Replace,
+    /// Remove every line containing a match
+    Drop,
+}
+
+/// Output triggers, checked against everything the CLI prints
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TriggersConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<TriggerRule>,
+}
+
+/// A regular expression and what to do when the CLI's output matches it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerRule {
+    /// Regular expression, matched against the output without escape sequences
+    pub pattern: String,
+    
+    #[serde(flatten)]
+    pub action: TriggerAction,
+}
+
+/// What a trigger does when its pattern matches
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum TriggerAction {
+    /// Run a shell command, with the matched text on its stdin
+    Run { command: String },
+    /// Send text to the CLI after `delay` seconds
+    Send {
+        input: String,
+        #[serde(default)]
+        delay: u64,
+    },
+    /// Show a notification, by default the matched text
+    Notify {
+        #[serde(default)]
+        message: Option<String>,
+    },
+    /// Mark the current task as blocked, by default giving the matched text as the reason
+    Block {
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    /// Send the last message again after `delay` seconds
+    Retry {
+        #[serde(default)]
+        delay: u64,
+    },
+}
+
+/// What grill does once nothing has been typed for a while
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdleConfig {
+    /// Minutes without input before the session counts as idle; 0 turns this off
+    #[serde(default)]
+    pub timeout_minutes: u64,
+    
+    #[serde(default)]
+    pub action: IdleAction,
+}
+
+/// What to do when a session becomes idle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdleAction {
+    /// Show a notification
+    #[default]
+    Notify,
+    /// Ask the CLI to summarize the task's progress and save it as the task's state
+    Summarize,
+    /// Pause the CLI until something is typed
+    Pause,
+}
+
+/// When grill tells you the CLI has stopped answering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartbeatConfig {
+    /// Seconds without output after a message before the CLI counts as stuck; 0 turns this off
+    #[serde(default = "default_stuck_seconds")]
+    pub stuck_seconds: u64,
+}
+
+impl HeartbeatConfig {
+    /// Get how long the CLI may be quiet while answering, if stuck CLIs are looked for
+    pub fn stuck_after(&self) -> Option<std::time::Duration> {
+        (self.stuck_seconds > 0).then(|| std::time::Duration::from_secs(self.stuck_seconds))
+    }
+}
+
+fn default_stuck_seconds() -> u64 {
+    120
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self { stuck_seconds: default_stuck_seconds() }
+    }
+}
+
+/// Exporting the workspace's session metrics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsConfig {
+    /// File kept up to date with the metrics in Prometheus text format, for
+    /// node_exporter's textfile collector; relative paths are in the workspace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub textfile: Option<PathBuf>,
+}
+
+/// How grill checks that the CLI has started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupConfig {
+    /// Seconds the CLI has to show its first prompt before grill warns; 0 turns this off
+    #[serde(default = "default_startup_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+impl StartupConfig {
+    /// Get how long the CLI has to show its first prompt, if it is checked at all
+    pub fn timeout(&self) -> Option<std::time::Duration> {
+        (self.timeout_seconds > 0).then(|| std::time::Duration::from_secs(self.timeout_seconds))
+    }
+}
+
+fn default_startup_timeout_seconds() -> u64 {
+    30
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self { timeout_seconds: default_startup_timeout_seconds() }
+    }
+}
+
+/// Checks made before input is sent to the CLI, and how it is written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputConfig {
+    /// Bytes a paste or message may have before grill asks before sending it; 0 never asks
+    #[serde(default = "default_confirm_bytes")]
+    pub confirm_bytes: usize,
+    
+    /// Bytes written to the CLI's terminal at a time; 0 writes each input whole
+    #[serde(default = "default_chunk_bytes")]
+    pub chunk_bytes: usize,
+    
+    /// Milliseconds to wait between the chunks of a long input
+    #[serde(default = "default_chunk_delay_ms")]
+    pub chunk_delay_ms: u64,
+    
+    /// Most bytes written to the CLI per second, in chunks; 0 is unlimited
+    #[serde(default)]
+    pub bytes_per_second: usize,
+}
+
+impl InputConfig {
+    /// Get how long to wait after writing a chunk of `bytes` before the next one
+    pub fn chunk_delay(&self, bytes: usize) -> std::time::Duration {
+        let delay = std::time::Duration::from_millis(self.chunk_delay_ms);
+        if self.bytes_per_second == 0 {
+            return delay;
+        }
+        delay.max(std::time::Duration::from_secs_f64(bytes as f64 / self.bytes_per_second as f64))
+    }
+}
+
+fn default_confirm_bytes() -> usize {
+    100 * 1024
+}
+
+fn default_chunk_bytes() -> usize {
+    1024
+}
+
+fn default_chunk_delay_ms() -> u64 {
+    10
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            confirm_bytes: default_confirm_bytes(),
+            chunk_bytes: default_chunk_bytes(),
+            chunk_delay_ms: default_chunk_delay_ms(),
+            bytes_per_second: 0,
+        }
+    }
+}
+
+/// Keys bound to grill's actions
+///
+/// A binding is a key such as `ctrl+c` or `/`, the same key twice such as
+/// `esc esc`, or a prefix and a key such as `ctrl+g [`. An empty binding
+/// turns the action off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeysConfig {
+    /// Quit grill
+    #[serde(default = "default_quit_key")]
+    pub quit: String,
+    
+    /// Start typing a grill command
+    #[serde(default = "default_command_mode_key")]
+    pub command_mode: String,
+    
+    /// Stop the CLI's response, like `/stop`
+    #[serde(default = "default_stop_generation_key")]
+    pub stop_generation: String,
+    
+    /// Pick a task to switch to, like `/task switch`
+    #[serde(default = "default_switch_task_picker_key")]
+    pub switch_task_picker: String,
+    
+    /// Page through the output
+    #[serde(default = "default_copy_mode_key")]
+    pub copy_mode: String,
+    
+    /// Detach from a session started with `--detach`
+    #[serde(default = "default_detach_key")]
+    pub detach: String,
+    
+    /// Key that runs a grill command when followed by one of `leader_keys`
+    #[serde(default = "default_leader_key")]
+    pub leader: String,
+    
+    /// Commands run by the leader key and a letter; one ending in a space is
+    /// left at the prompt to finish, such as `n = "/note "`
+    #[serde(default = "default_leader_keys")]
+    pub leader_keys: HashMap<String, String>,
+}
+
+fn default_quit_key() -> String {
+    "ctrl+c".to_string()
+}
+
+fn default_command_mode_key() -> String {
+    "/".to_string()
+}
+
+fn default_stop_generation_key() -> String {
+    "esc esc".to_string()
+}
+
+fn default_switch_task_picker_key() -> String {
+    "ctrl+t".to_string()
+}
+
+fn default_copy_mode_key() -> String {
+    "ctrl+g [".to_string()
+}
+
+fn default_detach_key() -> String {
+    "ctrl+]".to_string()
+}
+
+fn default_leader_key() -> String {
+    "ctrl+g".to_string()
+}
+
+fn default_leader_keys() -> HashMap<String, String> {
+    [("t", "/task"), ("s", "/task switch"), ("q", "/quit"), ("n", "/note ")]
+        .into_iter()
+        .map(|(key, command)| (key.to_string(), command.to_string()))
+        .collect()
+}
+
+impl Default for KeysConfig {
+    fn default() -> Self {
+        Self {
+            quit: default_quit_key(),
+            command_mode: default_command_mode_key(),
+            stop_generation: default_stop_generation_key(),
+            switch_task_picker: default_switch_task_picker_key(),
+            copy_mode: default_copy_mode_key(),
+            detach: default_detach_key(),
+            leader: default_leader_key(),
+            leader_keys: default_leader_keys(),
+        }
+    }
+}
+
+/// Environment variables passed on to the CLI
+///
+/// Patterns are variable names where `*` matches any run of characters, such
+/// as `AWS_*`. Variables in a CLI profile's `env` are always set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvironmentConfig {
+    /// Variables the CLI inherits; when empty, it inherits all of them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    
+    /// Variables the CLI never inherits, even if allowed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+impl EnvironmentConfig {
+    /// Check whether the CLI inherits a variable
+    pub fn passes(&self, name: &str) -> bool {
+        let allowed = self.allow.is_empty() || self.allow.iter().any(|pattern| name_matches(pattern, name));
+        allowed && !self.deny.iter().any(|pattern| name_matches(pattern, name))
+    }
+}
+
+/// Check whether a variable name matches a pattern, where `*` matches any run of characters
+fn name_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Settings for CLIs whose command is `ssh`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteConfig {
+    /// Seconds between keepalive messages ssh sends the server; 0 leaves ssh's own setting
+    #[serde(default = "default_keepalive_seconds")]
+    pub keepalive_seconds: u64,
+    
+    /// Times to reconnect when the connection drops before giving up; 0 turns this off
+    #[serde(default = "default_reconnect_attempts")]
+    pub reconnect_attempts: u32,
+    
+    /// Extra milliseconds to wait for a remote CLI, to cover network latency
+    #[serde(default = "default_latency_ms")]
+    pub latency_ms: u64,
+}
+
+impl RemoteConfig {
+    /// Get the extra wait for a remote CLI as a duration
+    pub fn latency(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.latency_ms)
+    }
+}
+
+fn default_keepalive_seconds() -> u64 {
+    15
+}
+
+fn default_reconnect_attempts() -> u32 {
+    3
+}
+
+fn default_latency_ms() -> u64 {
+    1000
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            keepalive_seconds: default_keepalive_seconds(),
+            reconnect_attempts: default_reconnect_attempts(),
+            latency_ms: default_latency_ms(),
+        }
+    }
+}
+
+/// Settings for `/copy` and `/paste`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClipboardConfig {
+    #[serde(default)]
+    pub method: ClipboardMethod,
+    
+    /// Program that reads text to copy from stdin, instead of one grill finds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub copy_command: Option<String>,
+    
+    /// Program that prints the clipboard, instead of one grill finds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paste_command: Option<String>,
+}
+
+/// How grill reaches the system clipboard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardMethod {
+    /// A clipboard program if one is installed, otherwise the terminal (OSC 52)
+    #[default]
+    Auto,
+    /// Always copy through the terminal, which works over SSH
+    Osc52,
+    /// Only use clipboard programs
+    Command,
+    /// Never touch the clipboard, e.g. on shared or headless machines
+    Off,
+}
+
+/// Settings for the terminal grill runs in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerminalConfig {
+    /// Capture the mouse, so the wheel scrolls copy mode or reaches CLIs that ask for it
+    #[serde(default)]
+    pub mouse: bool,
+    
+    /// Turn file paths in output into clickable links (OSC 8)
+    #[serde(default = "default_hyperlinks")]
+    pub hyperlinks: bool,
+    
+    /// Pretty-print the CLI's markdown, for CLIs that print it as plain text
+    #[serde(default)]
+    pub render_markdown: bool,
+    
+    /// Act on `OSC 7777;grill:` requests from programs running inside the CLI
+    ///
+    /// Off by default: anything that gets text into the CLI's output can send
+    /// them, including a file the CLI prints or a model's answer.
+    #[serde(default)]
+    pub backchannel: bool,
+}
+
+fn default_hyperlinks() -> bool {
+    true
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            mouse: false,
+            hyperlinks: default_hyperlinks(),
+            render_markdown: false,
+            backchannel: false,
+        }
+    }
+}
+
+/// Settings that trade throughput against latency and memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceConfig {
+    /// Output chunks queued for the display before the overflow policy applies
+    #[serde(default = "default_channel_capacity")]
+    pub output_channel_capacity: usize,
+    
+    /// Input messages queued for the CLI before senders wait
+    #[serde(default = "default_channel_capacity")]
+    pub input_channel_capacity: usize,
+    
+    /// What to do with output when the display can't keep up
+    #[serde(default)]
+    pub display_overflow: OverflowPolicy,
+    
+    /// Most bytes of output held back by `coalesce` before the oldest is dropped
+    #[serde(default = "default_max_pending_output")]
+    pub max_pending_output: usize,
+    
+    /// Bytes read from the CLI's terminal at a time
+    #[serde(default = "default_read_buffer_size")]
+    pub read_buffer_size: usize,
+    
+    /// Milliseconds output is gathered before it is written to the terminal
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+    
+    /// Bytes of gathered output written without waiting for the flush interval
+    #[serde(default = "default_output_batch_size")]
+    pub output_batch_size: usize,
+}
+
+impl PerformanceConfig {
+    /// Get the flush interval as a duration
+    pub fn flush_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.flush_interval_ms)
+    }
+}
+
+fn default_channel_capacity() -> usize {
+    100
+}
+
+fn default_read_buffer_size() -> usize {
+    1024
+}
+
+fn default_flush_interval_ms() -> u64 {
+    crate::io::OUTPUT_FLUSH_INTERVAL.as_millis() as u64
+}
+
+fn default_output_batch_size() -> usize {
+    crate::io::OUTPUT_BATCH_SIZE
+}
+
+fn default_max_pending_output() -> usize {
+    1024 * 1024
+}
+
+impl Default for PerformanceConfig {
+    fn default() -> Self {
+        Self {
+            output_channel_capacity: default_channel_capacity(),
+            input_channel_capacity: default_channel_capacity(),
+            display_overflow: OverflowPolicy::default(),
+            max_pending_output: default_max_pending_output(),
+            read_buffer_size: default_read_buffer_size(),
+            flush_interval_ms: default_flush_interval_ms(),
+            output_batch_size: default_output_batch_size(),
+        }
+    }
+}
+
+/// How output is handled when the display falls behind the CLI
+///
+/// Transcripts always get every byte; only what is shown on screen is affected.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Wait for the display, which in turn stalls the CLI
+    #[default]
+    Block,
+    /// Merge queued chunks into one, so the CLI never waits
+    Coalesce,
+    /// Throw away chunks that don't fit
+    Drop,
+}
+
+/// Settings for the `/context` commands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextConfig {
+    /// Maximum bytes of command output sent by `/context run`
+    #[serde(default = "default_max_output")]
+    pub max_output: usize,
+    
+    /// Don't send the task's persona, instructions, state and context files when a session starts
+    #[serde(default)]
+    pub skip_context: bool,
+    
+    /// Send a map of the repository to the CLI when a session starts
+    #[serde(default)]
+    pub repo_map_on_start: bool,
+    
+    /// Maximum size of the repository map in bytes
+    #[serde(default = "default_max_repo_map")]
+    pub max_repo_map: usize,
+    
+    /// Record each task's conversation and replay its end when a session starts
+    #[serde(default)]
+    pub resume_context: bool,
+    
+    /// Number of exchanges replayed by `resume_context`
+    #[serde(default = "default_resume_exchanges")]
+    pub resume_exchanges: usize,
+    
+    /// Have CLIs that can save conversations keep one per task, resumed when the task is
+    #[serde(default)]
+    pub resume_conversations: bool,
+    
+    /// Whether the CLI's conversation is cleared when switching tasks in the same CLI
+    #[serde(default)]
+    pub clear_on_switch: ClearOnSwitch,
+    
+    /// Show what a task switch will do before doing it, as `/task <name> --dry-run` does
+    #[serde(default)]
+    pub preview_switch: bool,
+}
+
+/// Whether a task switch clears the CLI's conversation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClearOnSwitch {
+    /// Clear it on every switch
+    #[default]
+    Always,
+    /// Ask each time, in grill, before clearing it
+    Ask,
+    /// Keep it, adding the new task's context to the conversation
+    Never,
+}
+
+fn default_max_output() -> usize {
+    crate::context::DEFAULT_MAX_OUTPUT
+}
+
+fn default_max_repo_map() -> usize {
+    crate::context::DEFAULT_MAX_REPO_MAP
+}
+
+fn default_resume_exchanges() -> usize {
+    crate::transcript::DEFAULT_RESUME_EXCHANGES
+}
+
+impl Default for ContextConfig {
+    fn default() -> Self {
+        Self {
+            max_output: default_max_output(),
+            skip_context: false,
+            repo_map_on_start: false,
+            max_repo_map: default_max_repo_map(),
+            resume_context: false,
+            resume_exchanges: default_resume_exchanges(),
+            resume_conversations: false,
+            clear_on_switch: ClearOnSwitch::Always,
+            preview_switch: false,
+        }
+    }
+}
+
+/// Git integration settings
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GitConfig {
+    /// Give each task its own git worktree and run its CLI there
+    #[serde(default)]
+    pub worktrees: bool,
+    
+    /// Task to start for a branch, by branch name
+    #[serde(default)]
+    pub branch_task_map: HashMap<String, String>,
+    
+    /// Branches named `<prefix><task>` start that task
+    #[serde(default)]
+    pub branch_prefix: Option<String>,
 }
 
 fn default_cli() -> String {
@@ -27,12 +785,30 @@ fn default_cli() -> String {
 impl Default for Config {
     fn default() -> Self {
         let mut clis = HashMap::new();
-        clis.insert("q".to_string(), "q chat".to_string());
+        clis.insert("q".to_string(), CliProfile::from_command("q chat"));
         
         Self {
+            version: crate::migrate::CURRENT_VERSION,
             default_cli: default_cli(),
             clis,
+            shell_wrap: false,
             hooks: HashMap::new(),
+            aliases: HashMap::new(),
+            git: GitConfig::default(),
+            context: ContextConfig::default(),
+            performance: PerformanceConfig::default(),
+            terminal: TerminalConfig::default(),
+            clipboard: ClipboardConfig::default(),
+            filters: FiltersConfig::default(),
+            triggers: TriggersConfig::default(),
+            idle: IdleConfig::default(),
+            heartbeat: HeartbeatConfig::default(),
+            metrics: MetricsConfig::default(),
+            startup: StartupConfig::default(),
+            remote: RemoteConfig::default(),
+            environment: EnvironmentConfig::default(),
+            input: InputConfig::default(),
+            keys: KeysConfig::default(),
         }
     }
 }
@@ -47,8 +823,8 @@ impl Config {
         let content = fs::read_to_string(path)
             .context("Failed to read config file")?;
         
-        let config: Config = toml::from_str(&content)
-            .context("Failed to parse config file")?;
+        let config: Config = parse_config(path, &content)
+            .context(format!("Failed to parse config file {}", path.display()))?;
         
         Ok(config)
     }
@@ -57,47 +833,369 @@ impl Config {
     pub fn get_default_cli(&self) -> &str {
         &self.default_cli
     }
+    
+    /// Resolve a `cli` or `default_cli` setting: the name of a CLI under
+    /// `[clis]`, or otherwise a command to run as is
+    pub fn resolve_cli(&self, cli: &str) -> CliProfile {
+        self.clis.get(cli)
+            .cloned()
+            .unwrap_or_else(|| CliProfile::from_command(cli))
+    }
+}
+
+/// How to run a CLI
+///
+/// Under `[clis]` this is either just the command, or a table that also sets
+/// extra arguments and environment variables, so one binary can be set up
+/// several ways (models, regions, accounts).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CliEntry")]
+pub struct CliProfile {
+    /// Command line to run
+    pub command: String,
+    
+    /// Arguments added after the command
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+    
+    /// Environment variables set for the CLI
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    
+    /// The CLI's own slash commands, passed to it as typed, such as `["add", "drop"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub native_commands: Vec<String>,
+}
+
+/// A `[clis]` entry as written in the config file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CliEntry {
+    Command(CommandWords),
+    Profile {
+        command: CommandWords,
+        #[serde(default)]
+        args: Vec<String>,
+        #[serde(default)]
+        env: HashMap<String, String>,
+        #[serde(default)]
+        native_commands: Vec<String>,
+    },
+}
+
+impl From<CliEntry> for CliProfile {
+    fn from(entry: CliEntry) -> Self {
+        match entry {
+            CliEntry::Command(command) => Self::from_command(&String::from(command)),
+            CliEntry::Profile { command, args, env, native_commands } => Self { command: command.into(), args, env, native_commands },
+        }
+    }
+}
+
+impl CliProfile {
+    /// Run a command with no extra arguments or environment
+    pub fn from_command(command: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            ..Self::default()
+        }
+    }
+    
+    /// Get the full command line, including the extra arguments, quoted as needed
+    pub fn command_line(&self) -> String {
+        std::iter::once(self.command.clone())
+            .chain(self.args.iter().map(|arg| shell_words::quote(arg).into_owned()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// A command as written in a config file: a command line, or an array of its words
+///
+/// The array form, such as `["q", "chat", "--profile", "my profile"]`,
+/// needs no quoting. Either way grill keeps it as a command line.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum CommandWords {
+    Line(String),
+    Words(Vec<String>),
+}
+
+impl From<CommandWords> for String {
+    fn from(command: CommandWords) -> Self {
+        match command {
+            CommandWords::Line(line) => line,
+            CommandWords::Words(words) => shell_words::join(words),
+        }
+    }
+}
+
+/// Read a command given as a command line or an array of words
+pub fn deserialize_command<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    CommandWords::deserialize(deserializer).map(String::from)
+}
+
+/// Read an optional command given as a command line or an array of words
+pub fn deserialize_optional_command<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    Option::<CommandWords>::deserialize(deserializer).map(|command| command.map(String::from))
+}
+
+/// Where a task is in its lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TaskStatus {
+    #[default]
+    Todo,
+    InProgress,
+    Blocked,
+    Done,
+}
+
+impl TaskStatus {
+    fn is_todo(&self) -> bool {
+        *self == TaskStatus::Todo
+    }
+}
+
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TaskStatus::Todo => "todo",
+            TaskStatus::InProgress => "in-progress",
+            TaskStatus::Blocked => "blocked",
+            TaskStatus::Done => "done",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Limits applied to a task's CLI when it starts, inherited by every program it runs
+///
+/// Each process gets its own allowance; they are not shared between processes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Largest address space, in megabytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_mb: Option<u64>,
+    
+    /// Most CPU time, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cpu_seconds: Option<u64>,
+    
+    /// Most files open at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_open_files: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Check whether no limit is set
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Container engines that can run a task's CLI
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxEngine {
+    Docker,
+    Podman,
+}
+
+impl SandboxEngine {
+    /// Get the engine's command-line program
+    pub fn program(&self) -> &'static str {
+        match self {
+            SandboxEngine::Docker => "docker",
+            SandboxEngine::Podman => "podman",
+        }
+    }
 }
 
 /// Task-specific configuration
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// A config loaded with `load` can be changed and written back with `save`.
+/// Keys grill doesn't know about are kept as they are.
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct TaskConfig {
     /// CLI to use for this task
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_optional_command", skip_serializing_if = "Option::is_none")]
     pub cli: Option<String>,
     
+    /// One-line summary of the task, shown when picking a task
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    
+    /// Role the CLI takes on, from `.grill/personas/<persona>.md`, sent before the instructions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub persona: Option<String>,
+    
+    /// Whether switching to this task clears the CLI's conversation, overriding `[context]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clear_on_switch: Option<ClearOnSwitch>,
+    
+    /// Lifecycle status
+    #[serde(default, skip_serializing_if = "TaskStatus::is_todo")]
+    pub status: TaskStatus,
+    
+    /// Why the task is blocked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_reason: Option<String>,
+    
     /// Task-specific hooks
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub hooks: HashMap<String, String>,
-}
-
-impl Default for TaskConfig {
-    fn default() -> Self {
-        Self {
-            cli: None,
-            hooks: HashMap::new(),
-        }
-    }
+    
+    /// Labels for the task
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    
+    /// Files sent to the CLI along with the instructions, relative to the workspace
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_files: Vec<PathBuf>,
+    
+    /// Tasks that should be done before this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    
+    /// Resource limits for the CLI and everything it runs
+    #[serde(default, skip_serializing_if = "ResourceLimits::is_empty")]
+    pub limits: ResourceLimits,
+    
+    /// Container engine that confines the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<SandboxEngine>,
+    
+    /// Image of the sandbox container, which must provide the CLI
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox_image: Option<String>,
+    
+    /// Extra arguments to the engine's `run`, such as `--network none`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sandbox_args: Vec<String>,
+    
+    /// Commands run alongside the CLI while the task is active, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sidecars: BTreeMap<String, String>,
+    
+    /// Command rerun whenever the task's files change, such as `cargo test`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watch: Option<String>,
+    
+    /// Settings grill doesn't use, preserved when saving
+    #[serde(flatten)]
+    pub extra: toml::Table,
+    
+    /// File the config was loaded from, and is saved to
+    #[serde(skip)]
+    path: Option<PathBuf>,
 }
 
 impl TaskConfig {
     /// Load task configuration from a file
     pub fn load(path: &Path) -> Result<Self> {
-        if !path.exists() {
-            return Ok(Self::default());
-        }
+        let mut config = if path.exists() {
+            let content = fs::read_to_string(path)
+                .context("Failed to read task config file")?;
+            parse_config(path, &content)
+                .context(format!("Failed to parse task config file {}", path.display()))?
+        } else {
+            Self::default()
+        };
         
-        let content = fs::read_to_string(path)
-            .context("Failed to read task config file")?;
+        config.path = Some(path.to_path_buf());
+        Ok(config)
+    }
+    
+    /// Write the config back to the file it was loaded from, in the same format
+    ///
+    /// Comments in the file are not kept.
+    pub fn save(&self) -> Result<()> {
+        let path = self.path.as_ref()
+            .ok_or_else(|| anyhow!("This task config includes frontmatter from instructions.md and can't be saved"))?;
+        
+        crate::environment::write_atomic(path, serialize_config(path, self)?)
+            .context(format!("Failed to write task config file {}", path.display()))
+    }
+    
+    /// Load a task's configuration from its directory
+    ///
+    /// Frontmatter in `instructions.md` is merged over the config file: its `cli`
+    /// takes precedence, and its tags and context files are added.
+    pub fn load_for_task(task_dir: &Path) -> Result<Self> {
+        let mut config = Self::load(&find_config_file(task_dir))?;
         
-        let config: TaskConfig = toml::from_str(&content)
-            .context("Failed to parse task config file")?;
+        let instructions_path = task_dir.join(crate::task::INSTRUCTIONS_FILE);
+        if instructions_path.exists() {
+            let instructions = fs::read_to_string(&instructions_path)
+                .context("Failed to read instructions file")?;
+            let (frontmatter, _) = crate::frontmatter::parse(&instructions)
+                .context(format!("Invalid frontmatter in {}", instructions_path.display()))?;
+            if let Some(frontmatter) = frontmatter {
+                config.merge_frontmatter(frontmatter);
+                // Saving would copy the frontmatter into the config file
+                config.path = None;
+            }
+        }
         
         Ok(config)
     }
     
+    fn merge_frontmatter(&mut self, frontmatter: crate::frontmatter::Frontmatter) {
+        if frontmatter.cli.is_some() {
+            self.cli = frontmatter.cli;
+        }
+        if frontmatter.description.is_some() {
+            self.description = frontmatter.description;
+        }
+        if frontmatter.persona.is_some() {
+            self.persona = frontmatter.persona;
+        }
+        for tag in frontmatter.tags {
+            if !self.tags.contains(&tag) {
+                self.tags.push(tag);
+            }
+        }
+        self.context_files.extend(frontmatter.context_files);
+        for dependency in frontmatter.depends_on {
+            if !self.depends_on.contains(&dependency) {
+                self.depends_on.push(dependency);
+            }
+        }
+    }
+    
     /// Get the CLI command for this task
     pub fn get_cli(&self) -> Option<&str> {
         self.cli.as_deref()
     }
+    
+    /// Set the CLI command for this task, or use the workspace default
+    #[allow(dead_code)]
+    pub fn set_cli(&mut self, cli: Option<String>) {
+        self.cli = cli;
+    }
+    
+    /// Set the status, with the reason when the task is blocked
+    pub fn set_status(&mut self, status: TaskStatus, reason: Option<&str>) {
+        self.status = status;
+        self.blocked_reason = reason
+            .filter(|_| status == TaskStatus::Blocked)
+            .map(str::to_string);
+    }
+    
+    /// Add a tag, unless the task already has it
+    #[allow(dead_code)]
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.tags.iter().any(|existing| existing == tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+    
+    /// Remove a tag, returning whether the task had it
+    #[allow(dead_code)]
+    pub fn remove_tag(&mut self, tag: &str) -> bool {
+        let before = self.tags.len();
+        self.tags.retain(|existing| existing != tag);
+        self.tags.len() != before
+    }
 }
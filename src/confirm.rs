@@ -0,0 +1,53 @@
+/// Lines of a large input shown when asking whether to send it
+const PREVIEW_LINES: usize = 3;
+
+/// Longest preview line shown, in characters
+const PREVIEW_WIDTH: usize = 72;
+
+/// Roughly how many bytes of text make up one token
+const BYTES_PER_TOKEN: usize = 4;
+
+/// Check whether an input is large enough to ask before sending it
+///
+/// A limit of 0 never asks.
+pub fn needs_confirmation(text: &str, limit: usize) -> bool {
+    limit > 0 && text.len() > limit
+}
+
+/// Estimate how many tokens a model will count for some text
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(BYTES_PER_TOKEN)
+}
+
+/// Describe a large input: its size, estimated tokens and first lines
+pub fn summary(text: &str) -> String {
+    let lines = text.lines().count();
+    let mut summary = format!(
+        "{} ({} lines, about {} tokens):\n",
+        format_size(text.len()),
+        lines,
+        estimate_tokens(text),
+    );
+    for line in text.lines().take(PREVIEW_LINES) {
+        let shown: String = line.chars().take(PREVIEW_WIDTH).collect();
+        let more = if shown.len() < line.len() { "..." } else { "" };
+        summary.push_str(&format!("  | {}{}\n", shown, more));
+    }
+    if lines > PREVIEW_LINES {
+        summary.push_str(&format!("  | ... {} more lines\n", lines - PREVIEW_LINES));
+    }
+    summary
+}
+
+/// Format a byte count for people
+pub fn format_size(bytes: usize) -> String {
+    const KB: f64 = 1024.0;
+    let size = bytes as f64;
+    if size < KB {
+        format!("{} bytes", bytes)
+    } else if size < KB * KB {
+        format!("{:.1} KB", size / KB)
+    } else {
+        format!("{:.1} MB", size / (KB * KB))
+    }
+}
@@ -0,0 +1,167 @@
+use anyhow::{Result, Context};
+use ignore::WalkBuilder;
+use std::fs;
+use std::path::Path;
+use std::process::Command as ProcessCommand;
+
+use crate::hooks::{self, HookOutput};
+
+/// Build a context message with the workspace's git diff
+///
+/// Returns `None` when there are no changes to show.
+pub fn diff_message(dir: &Path, staged: bool) -> Result<Option<String>> {
+    let mut args = vec!["diff"];
+    if staged {
+        args.push("--staged");
+    }
+    
+    let output = ProcessCommand::new("git")
+        .args(&args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git diff")?;
+    
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    
+    let diff = String::from_utf8_lossy(&output.stdout);
+    if diff.trim().is_empty() {
+        return Ok(None);
+    }
+    
+    let which = if staged { "staged changes" } else { "uncommitted changes" };
+    Ok(Some(format!(
+        "Here are the {} in the workspace (git diff):\n\n```diff\n{}```\n",
+        which, diff
+    )))
+}
+
+/// How long a command run by `/context run` may take, such as a slow test suite
+pub const RUN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// Default limit on command output sent by `/context run`, in bytes
+pub const DEFAULT_MAX_OUTPUT: usize = 20_000;
+
+/// Build a context message with the output of a shell command
+///
+/// Output longer than `max_output` bytes keeps only its end, where errors
+/// and test summaries usually are.
+pub fn run_message(dir: &Path, command: &str, max_output: usize) -> Result<String> {
+    let output = hooks::run_hook(command, dir, &[], None, Some(RUN_TIMEOUT))?;
+    Ok(output_message(command, output, max_output))
+}
+
+/// Build a context message with the output of a command that has been run
+pub fn output_message(command: &str, output: HookOutput, max_output: usize) -> String {
+    let mut combined = output.stdout;
+    if !output.stderr.is_empty() {
+        if !combined.is_empty() && !combined.ends_with('\n') {
+            combined.push('\n');
+        }
+        combined.push_str(&output.stderr);
+    }
+    
+    let status = if output.success { "succeeded" } else { "failed" };
+    format!(
+        "Here is the output of `{}` ({}):\n\n```\n{}\n```\n",
+        command,
+        status,
+        truncate_start(combined.trim_end(), max_output)
+    )
+}
+
+/// Keep the last `limit` bytes of `text`, noting how much was cut
+pub fn truncate_start(text: &str, limit: usize) -> String {
+    if text.len() <= limit {
+        return text.to_string();
+    }
+    
+    let mut cut = text.len() - limit;
+    while !text.is_char_boundary(cut) {
+        cut += 1;
+    }
+    format!("[... {} bytes truncated ...]\n{}", cut, &text[cut..])
+}
+
+/// Default limit on the size of a repository map, in bytes
+pub const DEFAULT_MAX_REPO_MAP: usize = 30_000;
+
+/// Maximum number of symbols listed per file
+const MAX_SYMBOLS_PER_FILE: usize = 20;
+
+/// Build a map of the repository: every file, with its top-level symbols
+///
+/// Honours `.gitignore` and `.grillignore` files. Stops adding files once the
+/// map reaches `max_size` bytes.
+pub fn repo_map(dir: &Path, max_size: usize) -> String {
+    let mut files = Vec::new();
+    let walker = WalkBuilder::new(dir)
+        .require_git(false)
+        .add_custom_ignore_filename(".grillignore")
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+    
+    for entry in walker.flatten() {
+        if entry.file_type().is_some_and(|t| t.is_file()) {
+            if let Ok(relative) = entry.path().strip_prefix(dir) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    
+    let mut map = String::new();
+    for (i, file) in files.iter().enumerate() {
+        let mut entry = format!("{}\n", file.display());
+        for symbol in top_level_symbols(&dir.join(file)) {
+            entry.push_str(&format!("    {}\n", symbol));
+        }
+        
+        if map.len() + entry.len() > max_size {
+            map.push_str(&format!("... {} more files\n", files.len() - i));
+            break;
+        }
+        map.push_str(&entry);
+    }
+    
+    map
+}
+
+/// Build a context message with the repository map
+pub fn repo_map_message(dir: &Path, max_size: usize) -> String {
+    format!(
+        "Here is a map of the project's files and their top-level symbols:\n\n```\n{}```\n",
+        repo_map(dir, max_size)
+    )
+}
+
+/// Find top-level declarations in a source file
+///
+/// A line-based heuristic covering common languages: only unindented lines
+/// that start with a declaration keyword count, with bodies cut off.
+fn top_level_symbols(path: &Path) -> Vec<String> {
+    let prefixes: &[&str] = match path.extension().and_then(|e| e.to_str()) {
+        Some("rs") => &["pub fn ", "fn ", "pub struct ", "struct ", "pub enum ", "enum ", "pub trait ", "trait ", "impl ", "impl<", "pub mod ", "mod ", "pub const ", "pub type "],
+        Some("py") => &["def ", "async def ", "class "],
+        Some("js" | "jsx" | "ts" | "tsx" | "mjs") => &["function ", "async function ", "class ", "export ", "interface ", "type "],
+        Some("go") => &["func ", "type "],
+        Some("java" | "kt" | "cs") => &["public ", "class ", "interface ", "enum "],
+        Some("rb") => &["def ", "class ", "module "],
+        Some("c" | "h" | "cpp" | "hpp" | "cc") => &["struct ", "class ", "typedef ", "enum "],
+        _ => return Vec::new(),
+    };
+    
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => return Vec::new(),
+    };
+    
+    content.lines()
+        .filter(|line| prefixes.iter().any(|prefix| line.starts_with(prefix)))
+        .map(|line| line.split(['{', ';']).next().unwrap_or(line).trim_end().trim_end_matches(':').to_string())
+        .take(MAX_SYMBOLS_PER_FILE)
+        .collect()
+}
@@ -0,0 +1,470 @@
+use anyhow::{Result, Context, anyhow};
+use serde::{Serialize, Deserialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::io::Command;
+use crate::keys::KeyMap;
+use crate::session::SessionState;
+
+/// Requests accepted on the session control socket, one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Report the session status
+    Status,
+    /// Switch the session to another task
+    SwitchTask { task: String },
+    /// Send text to the child CLI followed by a carriage return
+    ///
+    /// When `task` is set the session must be running that task.
+    Send {
+        text: String,
+        #[serde(default)]
+        task: Option<String>,
+    },
+    /// End the session
+    Quit,
+    /// Stream session output to this client and, unless observing, accept its keystrokes
+    Attach {
+        #[serde(default)]
+        observe: bool,
+    },
+    /// Raw input from an attached client
+    Input { data: String },
+    /// A grill command typed in an attached client
+    Command { command: Command },
+}
+
+/// Responses written back on the control socket, one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlResponse {
+    /// The request was accepted
+    Ok { message: String },
+    /// Current session status
+    Status { task: String, command: String, pid: u32 },
+    /// The request failed
+    Error { message: String },
+    /// Session output streamed to attached clients
+    Output { data: String },
+}
+
+/// Handles the session exposes to control clients
+#[derive(Clone)]
+pub struct ControlHandles {
+    /// What the session is currently running
+    pub session: Arc<RwLock<SessionState>>,
+    pub command: String,
+    pub input_tx: broadcast::Sender<String>,
+    pub command_tx: broadcast::Sender<Command>,
+    /// Session output, mirrored for attached clients
+    pub output_mirror: broadcast::Sender<String>,
+    /// Recent output replayed to clients when they attach
+    pub replay: Arc<Mutex<String>>,
+}
+
+/// Control socket listener for a running session
+///
+/// The socket file is removed when the server is dropped.
+pub struct ControlServer {
+    path: PathBuf,
+}
+
+impl ControlServer {
+    /// Bind the control socket and start accepting clients
+    #[cfg(unix)]
+    pub fn start(path: PathBuf, handles: ControlHandles) -> Result<Self> {
+        use tokio::net::UnixListener;
+        
+        if path.exists() {
+            // Refuse to steal the socket from a live session
+            if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+                return Err(anyhow!("Another grill session is already listening on {}", path.display()));
+            }
+            std::fs::remove_file(&path)
+                .context("Failed to remove stale control socket")?;
+        }
+        
+        let listener = UnixListener::bind(&path)
+            .context(format!("Failed to bind control socket {}", path.display()))?;
+        
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let handles = handles.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = Self::serve_client(stream, handles).await {
+                                log::warn!("Control client error: {}", e);
+                            }
+                        });
+                    },
+                    Err(e) => {
+                        log::warn!("Failed to accept control client: {}", e);
+                        break;
+                    }
+                }
+            }
+        });
+        
+        Ok(Self { path })
+    }
+    
+    #[cfg(not(unix))]
+    pub fn start(_path: PathBuf, _handles: ControlHandles) -> Result<Self> {
+        Err(anyhow!("The control socket is only supported on Unix platforms"))
+    }
+    
+    #[cfg(unix)]
+    async fn serve_client(stream: tokio::net::UnixStream, handles: ControlHandles) -> Result<()> {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        
+        // Responses and streamed output share the socket through one writer
+        let (response_tx, mut response_rx) = mpsc::channel::<ControlResponse>(100);
+        let writer_task = tokio::spawn(async move {
+            while let Some(response) = response_rx.recv().await {
+                let mut encoded = match serde_json::to_string(&response) {
+                    Ok(encoded) => encoded,
+                    Err(_) => continue,
+                };
+                encoded.push('\n');
+                if writer.write_all(encoded.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+        
+        // Observers only ever receive output, and a client attaches once
+        let mut attached = false;
+        let mut observing = false;
+        
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            
+            let response = match serde_json::from_str::<ControlRequest>(&line) {
+                Ok(ControlRequest::Attach { .. }) if attached => {
+                    ControlResponse::Error { message: "Already attached".to_string() }
+                },
+                Ok(ControlRequest::Attach { observe }) => {
+                    attached = true;
+                    observing = observe;
+                    Self::start_streaming(&handles, response_tx.clone());
+                    continue;
+                },
+                Ok(_) if observing => {
+                    ControlResponse::Error { message: "Observers cannot send input".to_string() }
+                },
+                Ok(ControlRequest::Input { data }) => {
+                    let _ = handles.input_tx.send(data);
+                    continue;
+                },
+                Ok(ControlRequest::Command { command }) => {
+                    let _ = handles.command_tx.send(command);
+                    continue;
+                },
+                Ok(request) => Self::handle_request(request, &handles),
+                Err(e) => ControlResponse::Error { message: format!("Invalid request: {}", e) },
+            };
+            
+            if response_tx.send(response).await.is_err() {
+                break;
+            }
+        }
+        
+        drop(response_tx);
+        writer_task.abort();
+        Ok(())
+    }
+    
+    /// Replay recent output to a newly attached client, then stream live output
+    fn start_streaming(handles: &ControlHandles, response_tx: mpsc::Sender<ControlResponse>) {
+        // Subscribe before taking the replay so no output falls in between
+        let mut output_rx = handles.output_mirror.subscribe();
+        let replay = handles.replay.lock().unwrap().clone();
+        
+        tokio::spawn(async move {
+            if !replay.is_empty() && response_tx.send(ControlResponse::Output { data: replay }).await.is_err() {
+                return;
+            }
+            
+            loop {
+                match output_rx.recv().await {
+                    Ok(data) => {
+                        if response_tx.send(ControlResponse::Output { data }).await.is_err() {
+                            break;
+                        }
+                    },
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+    
+    /// Translate a control request into session actions
+    fn handle_request(request: ControlRequest, handles: &ControlHandles) -> ControlResponse {
+        let result = match request {
+            ControlRequest::Status => {
+                return ControlResponse::Status {
+                    task: handles.session.read().unwrap().task.name().to_string(),
+                    command: handles.command.clone(),
+                    pid: std::process::id(),
+                };
+            },
+            ControlRequest::SwitchTask { task } => {
+                handles.command_tx.send(Command::SwitchTask(task.clone()))
+                    .map(|_| format!("Switching to task: {}", task))
+                    .map_err(|e| e.to_string())
+            },
+            ControlRequest::Send { text, task } => {
+                if let Some(task) = task {
+                    let current = handles.session.read().unwrap().task.name().to_string();
+                    if current != task {
+                        return ControlResponse::Error {
+                            message: format!("Session is running task '{}', not '{}'", current, task),
+                        };
+                    }
+                }
+                
+                handles.input_tx.send(format!("{}\r", text))
+                    .map(|_| format!("Sent {} bytes", text.len()))
+                    .map_err(|e| e.to_string())
+            },
+            ControlRequest::Quit => {
+                handles.command_tx.send(Command::Quit)
+                    .map(|_| "Quitting session".to_string())
+                    .map_err(|e| e.to_string())
+            },
+            ControlRequest::Attach { .. } | ControlRequest::Input { .. } | ControlRequest::Command { .. } => {
+                Err("Streaming requests are handled by the connection".to_string())
+            },
+        };
+        
+        match result {
+            Ok(message) => ControlResponse::Ok { message },
+            Err(message) => ControlResponse::Error { message },
+        }
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Send a single request to a running session and wait for its response
+#[cfg(unix)]
+pub fn send_request(path: &Path, request: &ControlRequest) -> Result<ControlResponse> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    
+    let mut stream = UnixStream::connect(path)
+        .context(format!("No running grill session found at {}", path.display()))?;
+    
+    let mut encoded = serde_json::to_string(request)?;
+    encoded.push('\n');
+    stream.write_all(encoded.as_bytes())
+        .context("Failed to send control request")?;
+    
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)
+        .context("Failed to read control response")?;
+    
+    serde_json::from_str(line.trim())
+        .context("Invalid control response")
+}
+
+#[cfg(not(unix))]
+pub fn send_request(_path: &Path, _request: &ControlRequest) -> Result<ControlResponse> {
+    Err(anyhow!("The control socket is only supported on Unix platforms"))
+}
+
+/// Attach the current terminal to a running session
+///
+/// Keystrokes are handled by a local `IoHandler` so grill commands keep working,
+/// with input and commands forwarded over the socket. Returns when the user
+/// detaches (Ctrl+] unless rebound) or the session ends.
+#[cfg(unix)]
+pub async fn attach(path: &Path, keys: KeyMap) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    use crate::io::IoHandler;
+    
+    let stream = UnixStream::connect(path).await
+        .context(format!("No running grill session found at {}", path.display()))?;
+    let (reader, mut writer) = stream.into_split();
+    
+    let (mut io_handler, input_tx, output_tx, command_tx) = IoHandler::new();
+    io_handler.set_keys(keys);
+    let mut input_rx = input_tx.subscribe();
+    let mut command_rx = command_tx.subscribe();
+    
+    // Forward session output to the local terminal
+    let reader_task = tokio::spawn(async move {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let data = match serde_json::from_str::<ControlResponse>(&line) {
+                Ok(ControlResponse::Output { data }) => data,
+                Ok(ControlResponse::Error { message }) => format!("\nError: {}\n", message),
+                _ => continue,
+            };
+            
+            if output_tx.send(data).await.is_err() {
+                break;
+            }
+        }
+    });
+    
+    // Forward local keystrokes and commands to the session
+    let writer_task = tokio::spawn(async move {
+        let mut encoded = serde_json::to_string(&ControlRequest::Attach { observe: false })?;
+        encoded.push('\n');
+        writer.write_all(encoded.as_bytes()).await?;
+        
+        loop {
+            let request = tokio::select! {
+                input = input_rx.recv() => match input {
+                    Ok(data) => ControlRequest::Input { data },
+                    Err(_) => break,
+                },
+                command = command_rx.recv() => match command {
+                    Ok(Command::Detach) => break,
+                    Ok(Command::Quit) => {
+                        let mut encoded = serde_json::to_string(&ControlRequest::Quit)?;
+                        encoded.push('\n');
+                        writer.write_all(encoded.as_bytes()).await?;
+                        break;
+                    },
+                    Ok(command) => ControlRequest::Command { command },
+                    Err(_) => break,
+                },
+            };
+            
+            let mut encoded = serde_json::to_string(&request)?;
+            encoded.push('\n');
+            writer.write_all(encoded.as_bytes()).await?;
+        }
+        
+        Ok::<(), anyhow::Error>(())
+    });
+    
+    // Stop displaying output once the user detaches or the session goes away
+    let reader_abort = reader_task.abort_handle();
+    tokio::spawn(async move {
+        let _ = writer_task.await;
+        reader_abort.abort();
+    });
+    
+    io_handler.start().await
+}
+
+#[cfg(not(unix))]
+pub async fn attach(_path: &Path, _keys: KeyMap) -> Result<()> {
+    Err(anyhow!("Attaching is only supported on Unix platforms"))
+}
+
+/// Watch a running session without the ability to send it input
+///
+/// The terminal stays in normal mode, so Ctrl+C simply stops observing.
+#[cfg(unix)]
+pub async fn observe(path: &Path) -> Result<()> {
+    use std::io::Write;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    
+    let stream = UnixStream::connect(path).await
+        .context(format!("No running grill session found at {}", path.display()))?;
+    let (reader, mut writer) = stream.into_split();
+    
+    let mut encoded = serde_json::to_string(&ControlRequest::Attach { observe: true })?;
+    encoded.push('\n');
+    writer.write_all(encoded.as_bytes()).await?;
+    
+    let mut stdout = std::io::stdout();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if let Ok(ControlResponse::Output { data }) = serde_json::from_str::<ControlResponse>(&line) {
+            stdout.write_all(data.as_bytes())?;
+            stdout.flush()?;
+        }
+    }
+    
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn observe(_path: &Path) -> Result<()> {
+    Err(anyhow!("Observing is only supported on Unix platforms"))
+}
+
+/// Watch several sessions at once, prefixing each output line with `[task]`
+///
+/// Output is read-only and line buffered per session so that lines from
+/// different tasks never interleave mid-line.
+#[cfg(unix)]
+pub async fn view(sessions: Vec<(String, PathBuf)>) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    
+    let (line_tx, mut line_rx) = mpsc::channel::<String>(100);
+    let mut connected = 0;
+    
+    for (task, path) in sessions {
+        // Sockets left behind by sessions that crashed are skipped
+        let stream = match UnixStream::connect(&path).await {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        connected += 1;
+        
+        let line_tx = line_tx.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut encoded = serde_json::to_string(&ControlRequest::Attach { observe: true })?;
+            encoded.push('\n');
+            writer.write_all(encoded.as_bytes()).await?;
+            
+            let mut lines = BufReader::new(reader).lines();
+            let mut pending = String::new();
+            while let Some(line) = lines.next_line().await? {
+                if let Ok(ControlResponse::Output { data }) = serde_json::from_str::<ControlResponse>(&line) {
+                    pending.push_str(&data.replace('\r', ""));
+                    while let Some(pos) = pending.find('\n') {
+                        let line: String = pending.drain(..=pos).collect();
+                        let line = line.trim_end();
+                        if !line.is_empty() {
+                            line_tx.send(format!("[{}] {}", task, line)).await?;
+                        }
+                    }
+                }
+            }
+            
+            line_tx.send(format!("[{}] (session ended)", task)).await?;
+            Ok::<(), anyhow::Error>(())
+        });
+    }
+    
+    if connected == 0 {
+        return Err(anyhow!("No running grill sessions found"));
+    }
+    
+    drop(line_tx);
+    while let Some(line) = line_rx.recv().await {
+        println!("{}", line);
+    }
+    
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn view(_sessions: Vec<(String, PathBuf)>) -> Result<()> {
+    Err(anyhow!("Viewing sessions is only supported on Unix platforms"))
+}
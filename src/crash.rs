@@ -0,0 +1,148 @@
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, Once};
+
+use crate::ansi;
+
+/// Most recent output kept for a crash report, in bytes
+pub const MAX_OUTPUT: usize = 64 * 1024;
+
+/// Most recent messages and commands kept for a crash report
+pub const MAX_COMMANDS: usize = 20;
+
+/// What a crash report is made of, kept up to date while a session runs
+///
+/// Output is kept as the CLI printed it and only stripped of escape
+/// sequences when a report is written, so the report reads cleanly in any
+/// editor instead of replaying the CLI's screen.
+#[derive(Debug, Default)]
+pub struct CrashLog {
+    summary: Vec<(String, String)>,
+    output: String,
+    commands: VecDeque<String>,
+}
+
+impl CrashLog {
+    /// Start a log for a session, described by `summary` as name and value pairs
+    pub fn new(summary: Vec<(String, String)>) -> Self {
+        Self { summary, ..Self::default() }
+    }
+    
+    /// Keep output from the CLI, dropping all but the last `MAX_OUTPUT` bytes
+    pub fn output(&mut self, output: &str) {
+        self.output.push_str(output);
+        if self.output.len() > MAX_OUTPUT {
+            let mut cut = self.output.len() - MAX_OUTPUT;
+            while !self.output.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.output.drain(..cut);
+        }
+    }
+    
+    /// Keep a message sent to the CLI or a command given to grill
+    pub fn command(&mut self, command: &str) {
+        if self.commands.len() == MAX_COMMANDS {
+            self.commands.pop_front();
+        }
+        self.commands.push_back(command.to_string());
+    }
+    
+    /// Build a crash report saying what happened, with a backtrace if there is one
+    pub fn report(&self, reason: &str, backtrace: Option<&str>) -> String {
+        let mut report = format!("# grill crash report\n\n{}\n\nTime: {}\n", reason.trim_end(), Utc::now().to_rfc3339());
+        for (name, value) in &self.summary {
+            report.push_str(&format!("{}: {}\n", name, value));
+        }
+        
+        report.push_str("\n## Recent messages and commands\n\n");
+        if self.commands.is_empty() {
+            report.push_str("(none)\n");
+        }
+        for command in &self.commands {
+            report.push_str(&format!("- {}\n", command.trim_end()));
+        }
+        
+        let output = ansi::strip_ansi(&self.output);
+        report.push_str(&format!("\n## Recent output\n\n```\n{}\n```\n", output.trim_end()));
+        
+        if let Some(backtrace) = backtrace {
+            report.push_str(&format!("\n## Backtrace\n\n```\n{}\n```\n", backtrace.trim_end()));
+        }
+        report
+    }
+}
+
+/// Describe what grill is running for a crash report
+pub fn summary(workspace: &Path, task: &str, cli: &str, handler: &str) -> Vec<(String, String)> {
+    let variable = |name: &str| std::env::var(name).unwrap_or_else(|_| "(not set)".to_string());
+    [
+        ("Version", crate::version::grill_version()),
+        ("Build", crate::version::build_info()),
+        ("Workspace", workspace.display().to_string()),
+        ("Task", task.to_string()),
+        ("CLI", cli.to_string()),
+        ("Handler", handler.to_string()),
+        ("TERM", variable("TERM")),
+        ("SHELL", variable("SHELL")),
+    ]
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+/// Write a crash report to a new file in `dir`, returning its path
+pub fn write_report(dir: &Path, report: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(format!("crash-{}.md", Utc::now().format("%Y%m%d-%H%M%S-%3f")));
+    fs::write(&path, report)
+        .with_context(|| format!("Failed to write crash report {}", path.display()))?;
+    Ok(path)
+}
+
+/// The log the panic hook reports from, and where its reports go
+type Current = Option<(PathBuf, Arc<Mutex<CrashLog>>)>;
+
+static CURRENT: Mutex<Current> = Mutex::new(None);
+static HOOK: Once = Once::new();
+
+/// Write a crash report from `log` into `dir` if grill panics
+///
+/// The terminal is restored before anything is printed, so the panic
+/// message and the report's path can be read. A later call replaces the log,
+/// as a restarted session does.
+pub fn install(dir: PathBuf, log: Arc<Mutex<CrashLog>>) {
+    *CURRENT.lock().unwrap_or_else(|e| e.into_inner()) = Some((dir, log));
+    HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if crossterm::terminal::is_raw_mode_enabled().unwrap_or(false) {
+                crate::io::restore_terminal();
+            }
+            previous(info);
+            
+            // The panic may have struck while either lock was held
+            let Ok(current) = CURRENT.try_lock() else {
+                return;
+            };
+            let Some((dir, log)) = current.as_ref() else {
+                return;
+            };
+            let reason = format!("grill panicked: {}", info);
+            let backtrace = Backtrace::force_capture().to_string();
+            let report = match log.try_lock() {
+                Ok(log) => log.report(&reason, Some(&backtrace)),
+                Err(_) => CrashLog::default().report(&reason, Some(&backtrace)),
+            };
+            match write_report(dir, &report) {
+                Ok(path) => eprintln!("Crash report written to {}", path.display()),
+                Err(e) => eprintln!("{:#}", e),
+            }
+        }));
+    });
+}
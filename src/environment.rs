@@ -1,14 +1,29 @@
 use anyhow::{Result, Context, anyhow};
-use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::config::{Config, TaskConfig, TaskStatus};
+use crate::git;
+use crate::task::{self, Task};
+
+/// Suffix format of deleted task directories in `.grill/trash`
+const TRASH_TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S%3f";
 
 /// Represents the grill environment
 #[derive(Clone)]
 pub struct Environment {
+    root_dir: PathBuf,
     grill_dir: PathBuf,
     tasks_dir: PathBuf,
-    config_file: PathBuf,
+    global_tasks_dir: Option<PathBuf>,
     current_task_file: PathBuf,
+    /// When each task was last used, as JSON
+    recent_tasks_file: PathBuf,
+    socket_file: PathBuf,
 }
 
 impl Environment {
@@ -16,17 +31,36 @@ impl Environment {
     pub fn new(root_dir: PathBuf) -> Self {
         let grill_dir = root_dir.join(".grill");
         let tasks_dir = grill_dir.join("tasks");
-        let config_file = grill_dir.join("config.toml");
         let current_task_file = grill_dir.join("current_task");
+        let recent_tasks_file = grill_dir.join("recent_tasks.json");
+        let socket_file = grill_dir.join("session.sock");
+        let global_tasks_dir = std::env::var_os("GRILL_GLOBAL_TASKS_DIR")
+            .map(PathBuf::from)
+            .or_else(|| dirs::data_dir().map(|dir| dir.join("grill").join("tasks")));
         
         Self {
+            root_dir,
             grill_dir,
             tasks_dir,
-            config_file,
+            global_tasks_dir,
             current_task_file,
+            recent_tasks_file,
+            socket_file,
         }
     }
     
+    /// Find the environment for a directory
+    ///
+    /// Like git with `.git`, this walks up from `start` to the nearest
+    /// directory containing `.grill`. If there is none, the environment is
+    /// rooted at `start` itself so it can be initialized there.
+    pub fn discover(start: &Path) -> Self {
+        let root_dir = start.ancestors()
+            .find(|dir| dir.join(".grill").is_dir())
+            .unwrap_or(start);
+        Self::new(root_dir.to_path_buf())
+    }
+    
     /// Initialize a new grill environment
     pub fn init(&self) -> Result<()> {
         // Create .grill directory
@@ -34,20 +68,22 @@ impl Environment {
             .context("Failed to create tasks directory")?;
         
         // Create default config file if it doesn't exist
-        if !self.config_file.exists() {
+        let config_file = self.get_config_path();
+        if !config_file.exists() {
             let default_config = r#"# Grill Configuration
+version = 1
 default_cli = "q chat"
 
 [clis]
 q = "q chat"
 "#;
-            fs::write(&self.config_file, default_config)
+            write_atomic(&config_file, default_config)
                 .context("Failed to write default config file")?;
         }
         
         // Create current_task file if it doesn't exist
         if !self.current_task_file.exists() {
-            fs::write(&self.current_task_file, "default")
+            write_atomic(&self.current_task_file, "default")
                 .context("Failed to write current task file")?;
             
             // Create default task
@@ -57,14 +93,49 @@ q = "q chat"
         Ok(())
     }
     
+    /// Use a different directory for global tasks, or none at all
+    #[allow(dead_code)]
+    pub fn with_global_tasks_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.global_tasks_dir = dir;
+        self
+    }
+    
+    /// Check whether a task comes from the global task library
+    ///
+    /// A project task with the same name takes precedence over a global one.
+    pub fn is_global_task(&self, name: &str) -> bool {
+        if validate_task_name(name).is_err() || self.tasks_dir.join(name).exists() {
+            return false;
+        }
+        self.global_tasks_dir.as_ref().is_some_and(|dir| dir.join(name).is_dir())
+    }
+    
+    /// Refuse to modify a global task from within a project
+    fn ensure_project_task(&self, name: &str) -> Result<()> {
+        if self.is_global_task(name) {
+            return Err(anyhow!(
+                "Task '{}' is a global task shared by all projects; manage it in {}",
+                name,
+                self.global_tasks_dir.as_ref().map(|dir| dir.display().to_string()).unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Get the directory of a task, whether or not it exists, after checking the name
+    fn task_path(&self, name: &str) -> Result<PathBuf> {
+        validate_task_name(name)?;
+        Ok(self.tasks_dir.join(name))
+    }
+    
     /// Check if the environment exists
     pub fn exists(&self) -> bool {
-        self.grill_dir.exists() && self.config_file.exists()
+        self.grill_dir.exists() && self.get_config_path().exists()
     }
     
     /// Create a new task
     pub fn create_task(&self, name: &str) -> Result<()> {
-        let task_dir = self.tasks_dir.join(name);
+        let task_dir = self.task_path(name)?;
         
         if task_dir.exists() {
             return Err(anyhow!("Task '{}' already exists", name));
@@ -74,26 +145,28 @@ q = "q chat"
             .context(format!("Failed to create task directory for '{}'", name))?;
         
         // Create task-specific files
-        let instructions_file = task_dir.join("instructions.md");
-        let state_file = task_dir.join("state.md");
-        let config_file = task_dir.join("config.toml");
-        
-        fs::write(&instructions_file, "# Task Instructions\n\nAdd your instructions here.\n")
+        write_atomic(&task_dir.join(task::INSTRUCTIONS_FILE), task::DEFAULT_INSTRUCTIONS)
             .context(format!("Failed to create instructions file for task '{}'", name))?;
         
-        fs::write(&state_file, "# Task State\n\nTask state will be tracked here.\n")
+        write_atomic(&task_dir.join(task::STATE_FILE), task::DEFAULT_STATE)
             .context(format!("Failed to create state file for task '{}'", name))?;
         
-        fs::write(&config_file, "# Task Configuration\ncli = \"q chat\"\n")
+        write_atomic(&task_dir.join("config.toml"), task::DEFAULT_CONFIG)
             .context(format!("Failed to create config file for task '{}'", name))?;
         
         Ok(())
     }
     
     /// Get the current task name
+    ///
+    /// Falls back to the most recently used task if no current task is set.
     pub fn get_current_task(&self) -> Result<String> {
         if !self.current_task_file.exists() {
-            return Err(anyhow!("No current task set"));
+            return self.list_recent_tasks()?
+                .into_iter()
+                .find(|(_, last_used)| last_used.is_some())
+                .map(|(name, _)| name)
+                .ok_or_else(|| anyhow!("No current task set"));
         }
         
         let task = fs::read_to_string(&self.current_task_file)
@@ -102,9 +175,16 @@ q = "q chat"
         Ok(task.trim().to_string())
     }
     
-    /// Get the path to a task directory
+    /// Get the path to a task directory, looking in the global task library
+    /// if the project has no task with that name
     pub fn get_task_dir(&self, name: &str) -> Result<PathBuf> {
-        let task_dir = self.tasks_dir.join(name);
+        if self.is_global_task(name) {
+            if let Some(dir) = &self.global_tasks_dir {
+                return Ok(dir.join(name));
+            }
+        }
+        
+        let task_dir = self.task_path(name)?;
         
         if !task_dir.exists() {
             return Err(anyhow!("Task '{}' does not exist", name));
@@ -113,28 +193,251 @@ q = "q chat"
         Ok(task_dir)
     }
     
-    /// Get the path to the config file
+    /// Get the directory the environment was created in
+    pub fn get_root_dir(&self) -> PathBuf {
+        self.root_dir.clone()
+    }
+    
+    /// Get the path to the .grill directory
+    pub fn get_grill_dir(&self) -> PathBuf {
+        self.grill_dir.clone()
+    }
+    
+    /// Find the task mapped to the current git branch
+    ///
+    /// Checks `git.branch_task_map` first, then the `git.branch_prefix` naming
+    /// convention. Returns the branch and task when a mapped task exists.
+    pub fn get_branch_task(&self) -> Result<Option<(String, String)>> {
+        let config = Config::load(&self.get_config_path())?;
+        let branch = match git::current_branch(&self.root_dir) {
+            Some(branch) => branch,
+            None => return Ok(None),
+        };
+        
+        let task = config.git.branch_task_map.get(&branch).cloned().or_else(|| {
+            config.git.branch_prefix.as_deref()
+                .and_then(|prefix| branch.strip_prefix(prefix))
+                .map(|task| task.to_string())
+        });
+        
+        Ok(task
+            .filter(|task| self.get_task_dir(task).is_ok())
+            .map(|task| (branch, task)))
+    }
+    
+    /// Get the path to a task's git worktree
+    pub fn get_worktree_path(&self, name: &str) -> PathBuf {
+        self.grill_dir.join("worktrees").join(name)
+    }
+    
+    /// Get the directory a task's CLI runs in
+    ///
+    /// With `git.worktrees` enabled this is the task's own worktree, created on
+    /// first use; otherwise the CLI runs wherever grill was started.
+    pub fn get_task_working_dir(&self, name: &str) -> Result<Option<PathBuf>> {
+        validate_task_name(name)?;
+        let config = Config::load(&self.get_config_path())?;
+        if !config.git.worktrees {
+            return Ok(None);
+        }
+        
+        let worktrees_dir = self.grill_dir.join("worktrees");
+        fs::create_dir_all(&worktrees_dir)
+            .context("Failed to create worktrees directory")?;
+        
+        // Keep the worktrees out of the main checkout's status
+        let ignore_file = worktrees_dir.join(".gitignore");
+        if !ignore_file.exists() {
+            write_atomic(&ignore_file, "*\n")
+                .context("Failed to write worktrees .gitignore")?;
+        }
+        
+        let repo_dir = git::repo_root(&self.root_dir)
+            .context("git.worktrees is enabled but this is not a git repository")?;
+        let path = git::ensure_worktree(&repo_dir, &self.get_worktree_path(name), name)?;
+        Ok(Some(path))
+    }
+    
+    /// Get the path to a workflow file
+    pub fn get_workflow_path(&self, name: &str) -> PathBuf {
+        self.grill_dir.join("workflows").join(format!("{}.toml", name))
+    }
+    
+    /// Get the path to a prompt template
+    pub fn get_prompt_path(&self, name: &str) -> PathBuf {
+        self.grill_dir.join("prompts").join(format!("{}.md", name))
+    }
+    
+    /// Read a prompt template by name
+    pub fn load_prompt(&self, name: &str) -> Result<String> {
+        check_file_name("prompt", name)?;
+        let path = self.get_prompt_path(name);
+        if !path.exists() {
+            return Err(anyhow!("Prompt '{}' does not exist; add it as .grill/prompts/{}.md", name, name));
+        }
+        fs::read_to_string(&path)
+            .context(format!("Failed to read prompt '{}'", name))
+    }
+    
+    /// Get the path to a persona's role instructions
+    pub fn get_persona_path(&self, name: &str) -> PathBuf {
+        self.grill_dir.join("personas").join(format!("{}.md", name))
+    }
+    
+    /// Read a persona's role instructions, without any frontmatter
+    pub fn load_persona(&self, name: &str) -> Result<String> {
+        check_file_name("persona", name)?;
+        let path = self.get_persona_path(name);
+        if !path.exists() {
+            return Err(anyhow!("Persona '{}' does not exist; add it as .grill/personas/{}.md", name, name));
+        }
+        let content = fs::read_to_string(&path)
+            .context(format!("Failed to read persona '{}'", name))?;
+        Ok(crate::frontmatter::strip(&content).to_string())
+    }
+    
+    /// List available prompt templates
+    pub fn list_prompts(&self) -> Result<Vec<String>> {
+        let prompts_dir = self.grill_dir.join("prompts");
+        if !prompts_dir.exists() {
+            return Ok(Vec::new());
+        }
+        
+        let mut prompts = Vec::new();
+        for entry in fs::read_dir(&prompts_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("md") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                prompts.push(name.to_string());
+            }
+        }
+        
+        prompts.sort();
+        Ok(prompts)
+    }
+    
+    /// List available workflows
+    pub fn list_workflows(&self) -> Result<Vec<String>> {
+        let mut workflows = Vec::new();
+        let workflows_dir = self.grill_dir.join("workflows");
+        
+        if !workflows_dir.exists() {
+            return Ok(workflows);
+        }
+        
+        for entry in fs::read_dir(&workflows_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|s| s.to_str()) {
+                workflows.push(name.to_string());
+            }
+        }
+        
+        workflows.sort();
+        Ok(workflows)
+    }
+    
+    /// Get the path to the config file, in whichever format it is written
     pub fn get_config_path(&self) -> PathBuf {
-        self.config_file.clone()
+        crate::config::find_config_file(&self.grill_dir)
+    }
+    
+    /// Get the path to the control socket of the session running a task
+    pub fn get_task_socket_path(&self, name: &str) -> Result<PathBuf> {
+        let sessions_dir = self.grill_dir.join("sessions");
+        fs::create_dir_all(&sessions_dir)
+            .context("Failed to create sessions directory")?;
+        
+        validate_task_name(name)?;
+        Ok(sessions_dir.join(format!("{}.sock", name)))
+    }
+    
+    /// Get the path to the lock held by the first session in the workspace
+    pub fn get_lock_path(&self) -> PathBuf {
+        self.grill_dir.join("session.lock")
+    }
+    
+    /// Get the path to the lock held by the session running a task
+    pub fn get_task_lock_path(&self, name: &str) -> Result<PathBuf> {
+        validate_task_name(name)?;
+        Ok(self.grill_dir.join("sessions").join(format!("{}.lock", name)))
+    }
+    
+    /// List the control sockets of running sessions as (task, socket path) pairs
+    pub fn list_session_sockets(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut sockets = Vec::new();
+        let sessions_dir = self.grill_dir.join("sessions");
+        
+        if !sessions_dir.exists() {
+            return Ok(sockets);
+        }
+        
+        for entry in fs::read_dir(&sessions_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("sock") {
+                continue;
+            }
+            if let Some(task) = path.file_stem().and_then(|s| s.to_str()) {
+                sockets.push((task.to_string(), path.clone()));
+            }
+        }
+        
+        sockets.sort();
+        Ok(sockets)
+    }
+    
+    /// Get the path to the log of session events
+    pub fn get_events_path(&self) -> PathBuf {
+        self.grill_dir.join("events.jsonl")
+    }
+    
+    /// Get the directory crash reports are written to
+    pub fn get_crash_dir(&self) -> PathBuf {
+        self.grill_dir.join("crash")
+    }
+    
+    /// Get the path to the recorded conversation of a task
+    pub fn get_transcript_path(&self, name: &str) -> PathBuf {
+        self.grill_dir.join("transcripts").join(format!("{}.jsonl", name))
+    }
+    
+    /// Get the path to the messages sent to a task's CLI
+    pub fn get_input_history_path(&self, name: &str) -> Result<PathBuf> {
+        Ok(self.get_task_dir(name)?.join(crate::history::INPUT_HISTORY_FILE))
+    }
+    
+    /// Get the directory holding a task's macros
+    pub fn get_macros_dir(&self, name: &str) -> Result<PathBuf> {
+        Ok(self.get_task_dir(name)?.join(crate::macros::MACROS_DIR))
+    }
+    
+    /// Get the path to the log file of detached sessions
+    pub fn get_session_log_path(&self) -> PathBuf {
+        self.grill_dir.join("session.log")
+    }
+    
+    /// Get the path to the session control socket
+    pub fn get_socket_path(&self) -> PathBuf {
+        self.socket_file.clone()
     }
     
     // The following methods are kept for future use but marked as allow(dead_code)
     
     /// Set the current task
     pub fn set_current_task(&self, name: &str) -> Result<()> {
-        let task_dir = self.tasks_dir.join(name);
-        
-        if !task_dir.exists() {
-            return Err(anyhow!("Task '{}' does not exist", name));
-        }
+        self.get_task_dir(name)?;
         
-        fs::write(&self.current_task_file, name)
+        write_atomic(&self.current_task_file, name)
             .context(format!("Failed to set current task to '{}'", name))?;
         
         Ok(())
     }
     
-    /// List all tasks
+    /// List all tasks: the project's own, then global tasks it doesn't override
     pub fn list_tasks(&self) -> Result<Vec<String>> {
         let mut tasks = Vec::new();
         
@@ -151,12 +454,168 @@ q = "q chat"
             }
         }
         
+        if let Some(global_dir) = self.global_tasks_dir.as_ref().filter(|dir| dir.is_dir()) {
+            let mut global = Vec::new();
+            for entry in fs::read_dir(global_dir)? {
+                let entry = entry?;
+                if entry.file_type()?.is_dir() {
+                    if let Some(name) = entry.file_name().to_str() {
+                        if validate_task_name(name).is_ok() && !tasks.iter().any(|task| task == name) {
+                            global.push(name.to_string());
+                        }
+                    }
+                }
+            }
+            global.sort();
+            tasks.extend(global);
+        }
+        
         Ok(tasks)
     }
     
-    /// Delete a task
-    pub fn delete_task(&self, name: &str) -> Result<()> {
-        let task_dir = self.tasks_dir.join(name);
+    /// List all tasks with when they were last used, most recent first
+    ///
+    /// Tasks that have never been used follow in `list_tasks` order.
+    pub fn list_recent_tasks(&self) -> Result<Vec<(String, Option<DateTime<Utc>>)>> {
+        let last_used = self.load_recent_tasks()?;
+        let mut tasks: Vec<_> = self.list_tasks()?
+            .into_iter()
+            .map(|name| {
+                let time = last_used.get(&name).copied();
+                (name, time)
+            })
+            .collect();
+        tasks.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+        
+        Ok(tasks)
+    }
+    
+    /// Record that a task was just used
+    pub fn touch_task(&self, name: &str) -> Result<()> {
+        let mut last_used = self.load_recent_tasks()?;
+        last_used.insert(name.to_string(), Utc::now());
+        
+        write_atomic(&self.recent_tasks_file, serde_json::to_string_pretty(&last_used)?)
+            .context("Failed to record recently used task")?;
+        
+        Ok(())
+    }
+    
+    fn load_recent_tasks(&self) -> Result<BTreeMap<String, DateTime<Utc>>> {
+        if !self.recent_tasks_file.exists() {
+            return Ok(BTreeMap::new());
+        }
+        
+        let content = fs::read_to_string(&self.recent_tasks_file)
+            .context("Failed to read recently used tasks")?;
+        serde_json::from_str(&content)
+            .context("Failed to parse recently used tasks")
+    }
+    
+    /// Append a timestamped note to a task's notes.md
+    pub fn add_note(&self, name: &str, text: &str) -> Result<()> {
+        let notes_path = Task::load(self, name)?.notes_path();
+        
+        let mut notes = if notes_path.exists() {
+            fs::read_to_string(&notes_path)
+                .context("Failed to read notes file")?
+        } else {
+            format!("# Notes for {}\n\n", name)
+        };
+        notes.push_str(&format!("- {} {}\n", chrono::Local::now().format("%Y-%m-%d %H:%M"), text));
+        
+        write_atomic(&notes_path, notes)
+            .context(format!("Failed to write notes for task '{}'", name))?;
+        
+        Ok(())
+    }
+    
+    /// Read a task's notes, if it has any
+    pub fn get_notes(&self, name: &str) -> Result<Option<String>> {
+        let notes_path = Task::load(self, name)?.notes_path();
+        if !notes_path.exists() {
+            return Ok(None);
+        }
+        
+        let notes = fs::read_to_string(&notes_path)
+            .context("Failed to read notes file")?;
+        Ok(Some(notes))
+    }
+    
+    /// Load a task's configuration
+    pub fn get_task_config(&self, name: &str) -> Result<TaskConfig> {
+        Task::load(self, name).map(Task::into_config)
+    }
+    
+    /// Set a task's status, with the reason when it is blocked
+    pub fn set_task_status(&self, name: &str, status: TaskStatus, reason: Option<&str>) -> Result<()> {
+        self.ensure_project_task(name)?;
+        let mut config = TaskConfig::load(&Task::load(self, name)?.config_path())?;
+        config.set_status(status, reason);
+        config.save()
+            .context(format!("Failed to update status of task '{}'", name))?;
+        
+        Ok(())
+    }
+    
+    /// List a task's dependencies that aren't done yet
+    ///
+    /// Dependencies that don't exist are included, since they can't be done.
+    pub fn unfinished_dependencies(&self, name: &str) -> Result<Vec<String>> {
+        let config = self.get_task_config(name)?;
+        Ok(config.depends_on
+            .into_iter()
+            .filter(|dependency| {
+                self.get_task_config(dependency)
+                    .map_or(true, |config| config.status != TaskStatus::Done)
+            })
+            .collect())
+    }
+    
+    /// Record use of a task, and mark it as in progress if work on it hasn't started yet
+    pub fn mark_task_started(&self, name: &str) -> Result<()> {
+        self.touch_task(name)?;
+        
+        // Global tasks are shared, so their status isn't per-project progress
+        if self.is_global_task(name) {
+            return Ok(());
+        }
+        if self.get_task_config(name)?.status == TaskStatus::Todo {
+            self.set_task_status(name, TaskStatus::InProgress, None)?;
+        }
+        Ok(())
+    }
+    
+    /// Move a task out of the task list into `.grill/archive`
+    pub fn archive_task(&self, name: &str) -> Result<PathBuf> {
+        self.ensure_project_task(name)?;
+        let task_dir = self.get_task_dir(name)?;
+        
+        if self.get_current_task()? == name {
+            return Err(anyhow!("Cannot archive the current task; switch to another task first"));
+        }
+        
+        let archive_dir = self.grill_dir.join("archive");
+        fs::create_dir_all(&archive_dir)
+            .context("Failed to create archive directory")?;
+        
+        let mut target = archive_dir.join(name);
+        if target.exists() {
+            target = archive_dir.join(format!("{}-{}", name, chrono::Utc::now().format("%Y%m%d%H%M%S")));
+        }
+        
+        fs::rename(&task_dir, &target)
+            .context(format!("Failed to archive task '{}'", name))?;
+        
+        Ok(target)
+    }
+    
+    /// Delete a task by moving it to `.grill/trash/<name>-<timestamp>`
+    ///
+    /// Deleted tasks can be brought back with `restore_task`.
+    pub fn delete_task(&self, name: &str) -> Result<PathBuf> {
+        self.ensure_project_task(name)?;
+        let task_dir = self.task_path(name)?;
         
         if !task_dir.exists() {
             return Err(anyhow!("Task '{}' does not exist", name));
@@ -168,9 +627,124 @@ q = "q chat"
             return Err(anyhow!("Cannot delete the current task"));
         }
         
-        fs::remove_dir_all(&task_dir)
+        let trash_dir = self.grill_dir.join("trash");
+        fs::create_dir_all(&trash_dir)
+            .context("Failed to create trash directory")?;
+        
+        let target = trash_dir.join(format!("{}-{}", name, chrono::Utc::now().format(TRASH_TIMESTAMP_FORMAT)));
+        fs::rename(&task_dir, &target)
             .context(format!("Failed to delete task '{}'", name))?;
         
+        Ok(target)
+    }
+    
+    /// List deleted tasks as (task name, trash path) pairs, oldest first
+    pub fn list_trash(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut entries = Vec::new();
+        let trash_dir = self.grill_dir.join("trash");
+        
+        if !trash_dir.exists() {
+            return Ok(Vec::new());
+        }
+        
+        for entry in fs::read_dir(&trash_dir)? {
+            let path = entry?.path();
+            let file_name = match path.file_name().and_then(|n| n.to_str()) {
+                Some(file_name) => file_name.to_string(),
+                None => continue,
+            };
+            if let Some((name, timestamp)) = file_name.rsplit_once('-') {
+                if timestamp.len() == 17 && timestamp.chars().all(|c| c.is_ascii_digit()) {
+                    entries.push((timestamp.to_string(), name.to_string(), path));
+                }
+            }
+        }
+        
+        entries.sort();
+        Ok(entries.into_iter().map(|(_, name, path)| (name, path)).collect())
+    }
+    
+    /// Restore the most recently deleted task with this name
+    pub fn restore_task(&self, name: &str) -> Result<()> {
+        let task_dir = self.task_path(name)?;
+        if task_dir.exists() {
+            return Err(anyhow!("Task '{}' already exists", name));
+        }
+        
+        let (_, path) = self.list_trash()?
+            .into_iter()
+            .rev()
+            .find(|(task, _)| task == name)
+            .ok_or_else(|| anyhow!("No deleted task named '{}'", name))?;
+        
+        fs::rename(&path, &task_dir)
+            .context(format!("Failed to restore task '{}'", name))?;
+        
         Ok(())
     }
 }
+
+/// Words that follow `/task` as subcommands and so can't name a task
+const RESERVED_TASK_NAMES: &[&str] = &["init", "delete", "restore", "list", "time", "done", "block", "switch"];
+
+/// Check that a task name is safe to use as a directory name
+///
+/// Task names come straight from user input and are joined into paths, so
+/// anything that could escape `.grill/tasks` or produce a confusing directory
+/// is rejected.
+pub fn validate_task_name(name: &str) -> Result<()> {
+    if name.is_empty() {
+        return Err(anyhow!("Task name cannot be empty"));
+    }
+    if name.len() > 100 {
+        return Err(anyhow!("Task name '{}' is too long (at most 100 characters)", name));
+    }
+    if name.starts_with('.') {
+        return Err(anyhow!("Task name '{}' cannot start with '.'", name));
+    }
+    if let Some(c) = name.chars().find(|c| matches!(c, '/' | '\\' | ':') || c.is_control() || c.is_whitespace()) {
+        return Err(anyhow!("Task name '{}' cannot contain {:?}", name.escape_default(), c));
+    }
+    if RESERVED_TASK_NAMES.contains(&name) {
+        return Err(anyhow!("'{}' is a /task subcommand and cannot be used as a task name", name));
+    }
+    
+    Ok(())
+}
+
+/// Check that a name given to a command can't reach outside its directory
+fn check_file_name(kind: &str, name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(anyhow!("Invalid {} name '{}'", kind, name));
+    }
+    Ok(())
+}
+
+/// Write a file so readers see either the old contents or the new, never a mix
+///
+/// The contents go to a temporary file in the same directory, which is then
+/// renamed over the target. A crash or a concurrent writer can't leave the
+/// file half written.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    
+    let file_name = path.file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(contents.as_ref())?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
@@ -0,0 +1,312 @@
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Something that happened in a session, as recorded in `.grill/events.jsonl`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub time: DateTime<Utc>,
+    /// Identifies the session the event belongs to
+    pub session: String,
+    /// Task active when the event happened
+    pub task: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+/// Kinds of session events
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum EventKind {
+    /// The session started
+    SessionStart,
+    /// The session ended
+    SessionEnd,
+    /// The session switched to another task
+    TaskSwitch { to: String },
+    /// A message was sent to the CLI
+    Message,
+    /// The CLI exited on its own with a failure status
+    Crash { exit_code: u32 },
+    /// The CLI was started again with `/restart`
+    Restart,
+    /// The CLI answered a message: its first output came `first_byte_ms` after
+    /// the message, and it was back at its prompt `prompt_ms` after that
+    Response { first_byte_ms: u64, prompt_ms: u64 },
+    /// Bytes sent to and received from the CLI over the session, recorded as it ends
+    Traffic { bytes_in: u64, bytes_out: u64 },
+}
+
+/// Appends the events of one session to the workspace's event log
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    path: PathBuf,
+    session: String,
+}
+
+impl EventLog {
+    /// Create a log for a new session
+    pub fn new(path: PathBuf) -> Self {
+        let session = format!("{}-{}", Utc::now().timestamp_millis(), std::process::id());
+        Self { path, session }
+    }
+    
+    /// Get the identifier of the session
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+    
+    /// Record an event
+    ///
+    /// Failures are logged rather than returned: statistics are never worth
+    /// interrupting a session for.
+    pub fn record(&self, task: &str, kind: EventKind) {
+        let event = Event {
+            time: Utc::now(),
+            session: self.session.clone(),
+            task: task.to_string(),
+            kind,
+        };
+        
+        if let Err(e) = self.append(&event) {
+            log::warn!("Failed to record session event: {}", e);
+        }
+    }
+    
+    fn append(&self, event: &Event) -> Result<()> {
+        let line = serde_json::to_string(event)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .context("Failed to open event log")?;
+        writeln!(file, "{}", line)?;
+        Ok(())
+    }
+}
+
+/// Read all events from an event log, skipping lines that can't be parsed
+pub fn load(path: &Path) -> Result<Vec<Event>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let content = fs::read_to_string(path)
+        .context("Failed to read event log")?;
+    
+    Ok(content.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Usage of a single task
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct TaskStats {
+    pub sessions: usize,
+    pub messages: usize,
+}
+
+/// How long a kind of wait took over a number of samples, in milliseconds
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Latency {
+    pub count: usize,
+    pub total_ms: u64,
+    pub median_ms: u64,
+    pub p95_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Latency {
+    /// Summarize samples given in milliseconds
+    pub fn from_samples(mut samples: Vec<u64>) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        samples.sort_unstable();
+        let percentile = |p: usize| samples[((samples.len() - 1) * p + 50) / 100];
+        Self {
+            count: samples.len(),
+            total_ms: samples.iter().sum(),
+            median_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// Usage aggregated over every session in a workspace
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub sessions: usize,
+    /// Total wall-clock time of all sessions, in seconds
+    pub duration_secs: i64,
+    pub messages: usize,
+    pub crashes: usize,
+    pub restarts: usize,
+    /// Bytes sent to the CLI, over the sessions that recorded it
+    pub bytes_in: u64,
+    /// Bytes received from the CLI, over the sessions that recorded it
+    pub bytes_out: u64,
+    /// From a message to the first output of the CLI's answer
+    pub time_to_first_byte: Latency,
+    /// From the first output of the CLI's answer to its prompt
+    pub first_byte_to_prompt: Latency,
+    pub tasks: HashMap<String, TaskStats>,
+}
+
+impl Stats {
+    /// Aggregate events into statistics
+    ///
+    /// Sessions that never recorded an end (e.g. killed) last until their
+    /// final event.
+    pub fn from_events(events: &[Event]) -> Self {
+        let mut stats = Stats::default();
+        let mut spans: HashMap<&str, (DateTime<Utc>, DateTime<Utc>)> = HashMap::new();
+        let mut first_bytes = Vec::new();
+        let mut prompts = Vec::new();
+        
+        for event in events {
+            let span = spans.entry(event.session.as_str()).or_insert((event.time, event.time));
+            span.0 = span.0.min(event.time);
+            span.1 = span.1.max(event.time);
+            
+            match &event.kind {
+                EventKind::SessionStart => {
+                    stats.sessions += 1;
+                    stats.tasks.entry(event.task.clone()).or_default().sessions += 1;
+                },
+                EventKind::TaskSwitch { to } => {
+                    stats.tasks.entry(to.clone()).or_default().sessions += 1;
+                },
+                EventKind::Message => {
+                    stats.messages += 1;
+                    stats.tasks.entry(event.task.clone()).or_default().messages += 1;
+                },
+                EventKind::Crash { .. } => stats.crashes += 1,
+                EventKind::Restart => stats.restarts += 1,
+                EventKind::Response { first_byte_ms, prompt_ms } => {
+                    first_bytes.push(*first_byte_ms);
+                    prompts.push(*prompt_ms);
+                },
+                EventKind::Traffic { bytes_in, bytes_out } => {
+                    stats.bytes_in += bytes_in;
+                    stats.bytes_out += bytes_out;
+                },
+                EventKind::SessionEnd => {},
+            }
+        }
+        stats.time_to_first_byte = Latency::from_samples(first_bytes);
+        stats.first_byte_to_prompt = Latency::from_samples(prompts);
+        
+        stats.duration_secs = spans.values()
+            .map(|(start, end)| (*end - *start).num_seconds())
+            .sum();
+        
+        stats
+    }
+    
+    /// Format a human-readable report
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        report.push_str(&format!("Sessions:       {}\n", self.sessions));
+        report.push_str(&format!("Total time:     {}\n", format_duration(self.duration_secs)));
+        report.push_str(&format!("Messages sent:  {}\n", self.messages));
+        report.push_str(&format!("CLI crashes:    {}\n", self.crashes));
+        report.push_str(&format!("CLI restarts:   {}\n", self.restarts));
+        report.push_str(&format!("Bytes sent:     {}\n", self.bytes_in));
+        report.push_str(&format!("Bytes received: {}\n", self.bytes_out));
+        if self.time_to_first_byte.count > 0 {
+            report.push_str(&format!(
+                "First output:   {} median, {} 95th percentile\n",
+                format_millis(self.time_to_first_byte.median_ms),
+                format_millis(self.time_to_first_byte.p95_ms)
+            ));
+            report.push_str(&format!(
+                "Answer time:    {} median, {} 95th percentile\n",
+                format_millis(self.first_byte_to_prompt.median_ms),
+                format_millis(self.first_byte_to_prompt.p95_ms)
+            ));
+        }
+        
+        let mut tasks: Vec<_> = self.tasks.iter().collect();
+        tasks.sort_by(|a, b| b.1.messages.cmp(&a.1.messages).then(a.0.cmp(b.0)));
+        
+        if !tasks.is_empty() {
+            report.push_str("\nMost active tasks:\n");
+            for (name, task) in tasks.iter().take(10) {
+                report.push_str(&format!(
+                    "  {:<20} {:>5} messages  {:>3} sessions\n",
+                    name, task.messages, task.sessions
+                ));
+            }
+        }
+        
+        report
+    }
+}
+
+/// Format a number of seconds as e.g. `2h 05m` or `3m 12s`
+pub fn format_duration(secs: i64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, (secs % 3600) / 60, secs % 60);
+    if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else {
+        format!("{}m {:02}s", minutes, seconds)
+    }
+}
+
+/// Format milliseconds as e.g. `850ms` or `12.3s`
+pub fn format_millis(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+/// Wall-clock seconds spent in each task
+///
+/// Time runs from a session's start or a switch to the task until the next
+/// switch or the end of the session. Sessions that never recorded an end
+/// count until their final event.
+pub fn task_times(events: &[Event]) -> HashMap<String, i64> {
+    let mut times: HashMap<String, i64> = HashMap::new();
+    let mut active: HashMap<&str, (String, DateTime<Utc>)> = HashMap::new();
+    
+    let mut sorted: Vec<&Event> = events.iter().collect();
+    sorted.sort_by_key(|event| event.time);
+    
+    for event in sorted {
+        let session = event.session.as_str();
+        
+        // Close the interval of whatever task was active in this session
+        let next = match &event.kind {
+            EventKind::SessionStart => Some(event.task.clone()),
+            EventKind::TaskSwitch { to } => Some(to.clone()),
+            EventKind::SessionEnd => None,
+            _ => continue,
+        };
+        if let Some((task, since)) = active.remove(session) {
+            *times.entry(task).or_default() += (event.time - since).num_seconds();
+        }
+        if let Some(task) = next {
+            active.insert(session, (task, event.time));
+        }
+    }
+    
+    // Sessions without an end event
+    for (session, (task, since)) in active {
+        let last = events.iter()
+            .filter(|event| event.session == session)
+            .map(|event| event.time)
+            .max()
+            .unwrap_or(since);
+        *times.entry(task).or_default() += (last - since).num_seconds();
+    }
+    
+    times
+}
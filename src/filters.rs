@@ -0,0 +1,60 @@
+use anyhow::{Result, Context};
+use regex::{Regex, RegexBuilder};
+
+use crate::config::{FilterAction, FiltersConfig};
+
+/// A compiled `[filters]` rule
+#[derive(Debug)]
+struct Filter {
+    pattern: Regex,
+    action: FilterAction,
+    replacement: String,
+}
+
+/// The chain of `[filters]` rules between the CLI handler and the display
+///
+/// Rules see the output a chunk at a time, as it is read from the CLI, so
+/// they shouldn't rely on matching across chunks.
+#[derive(Debug, Default)]
+pub struct OutputFilter {
+    filters: Vec<Filter>,
+}
+
+impl OutputFilter {
+    /// Compile the configured rules, in order
+    pub fn new(config: &FiltersConfig) -> Result<Self> {
+        let filters = config.rules.iter()
+            .map(|rule| {
+                let pattern = RegexBuilder::new(&rule.pattern)
+                    .multi_line(true)
+                    .build()
+                    .context(format!("Invalid pattern '{}' under [filters]", rule.pattern))?;
+                Ok(Filter { pattern, action: rule.action, replacement: rule.replacement.clone() })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { filters })
+    }
+    
+    /// Run a chunk of output through every rule
+    ///
+    /// Returns `None` when the rules removed all of it.
+    pub fn apply(&self, output: String) -> Option<String> {
+        let mut filtered = output;
+        for filter in &self.filters {
+            filtered = match filter.action {
+                FilterAction::Replace => filter.pattern.replace_all(&filtered, filter.replacement.as_str()).into_owned(),
+                FilterAction::Drop => filtered
+                    .split_inclusive('\n')
+                    .filter(|line| !filter.pattern.is_match(line))
+                    .collect(),
+            };
+        }
+        
+        // Output the CLI handler passed on is only dropped if the rules emptied it
+        if filtered.is_empty() && !self.filters.is_empty() {
+            None
+        } else {
+            Some(filtered)
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::{Result, Context};
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// Task settings given at the top of `instructions.md`
+///
+/// YAML frontmatter is fenced by `---` lines and TOML frontmatter by `+++`
+/// lines.
+#[derive(Debug, Default, Deserialize)]
+pub struct Frontmatter {
+    /// CLI to use for the task
+    #[serde(default, deserialize_with = "crate::config::deserialize_optional_command")]
+    pub cli: Option<String>,
+    
+    /// One-line summary of the task
+    #[serde(default)]
+    pub description: Option<String>,
+    
+    /// Persona the CLI takes on for the task
+    #[serde(default)]
+    pub persona: Option<String>,
+    
+    /// Labels for the task
+    #[serde(default)]
+    pub tags: Vec<String>,
+    
+    /// Files sent to the CLI along with the instructions, relative to the workspace
+    #[serde(default)]
+    pub context_files: Vec<PathBuf>,
+    
+    /// Tasks that should be done before this one
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+}
+
+/// Split a document into its frontmatter, if any, and the rest of the text
+pub fn parse(text: &str) -> Result<(Option<Frontmatter>, &str)> {
+    let Some((fence, frontmatter, rest)) = split(text) else {
+        return Ok((None, text));
+    };
+    
+    let frontmatter = match fence {
+        // An empty YAML document parses as nothing rather than an empty map
+        "---" => serde_yaml::from_str::<Option<Frontmatter>>(frontmatter)
+            .context("Failed to parse YAML frontmatter")?
+            .unwrap_or_default(),
+        _ => toml::from_str(frontmatter)
+            .context("Failed to parse TOML frontmatter")?,
+    };
+    
+    Ok((Some(frontmatter), rest))
+}
+
+/// Strip the frontmatter from a document, leaving its text
+pub fn strip(text: &str) -> &str {
+    split(text).map_or(text, |(_, _, rest)| rest)
+}
+
+/// Split a document into its fence, frontmatter and the rest of the text
+fn split(text: &str) -> Option<(&'static str, &str, &str)> {
+    let fence = ["---", "+++"].into_iter()
+        .find(|fence| text.lines().next().is_some_and(|line| line.trim_end() == *fence))?;
+    
+    let start = text.find('\n')? + 1;
+    let mut offset = start;
+    for line in text[start..].split_inclusive('\n') {
+        if line.trim_end() == fence {
+            let rest = text[offset + line.len()..].trim_start_matches(['\r', '\n']);
+            return Some((fence, &text[start..offset], rest));
+        }
+        offset += line.len();
+    }
+    
+    None
+}
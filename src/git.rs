@@ -0,0 +1,60 @@
+use anyhow::{Result, Context, anyhow};
+use std::path::{Path, PathBuf};
+use std::process::Command as ProcessCommand;
+
+/// Run git in `dir`, returning its trimmed stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = ProcessCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git")?;
+    
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Get the top-level directory of the repository containing `dir`
+pub fn repo_root(dir: &Path) -> Result<PathBuf> {
+    git(dir, &["rev-parse", "--show-toplevel"]).map(PathBuf::from)
+}
+
+/// Get the branch checked out in `dir`, if any
+pub fn current_branch(dir: &Path) -> Option<String> {
+    git(dir, &["symbolic-ref", "--short", "-q", "HEAD"]).ok()
+        .filter(|branch| !branch.is_empty())
+}
+
+/// Name of the branch a task's worktree checks out
+pub fn task_branch(task_name: &str) -> String {
+    format!("grill/{}", task_name)
+}
+
+/// Make sure a task has a worktree at `path`, creating it if needed
+///
+/// The worktree checks out the task's own branch, created from the current
+/// HEAD the first time.
+pub fn ensure_worktree(repo_dir: &Path, path: &Path, task_name: &str) -> Result<PathBuf> {
+    if path.exists() {
+        return Ok(path.to_path_buf());
+    }
+    
+    let branch = task_branch(task_name);
+    let path_str = path.to_string_lossy();
+    let branch_exists = git(repo_dir, &["rev-parse", "--verify", "--quiet", &format!("refs/heads/{}", branch)]).is_ok();
+    
+    if branch_exists {
+        git(repo_dir, &["worktree", "add", &path_str, &branch])?;
+    } else {
+        git(repo_dir, &["worktree", "add", "-b", &branch, &path_str])?;
+    }
+    
+    Ok(path.to_path_buf())
+}
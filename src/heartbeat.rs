@@ -0,0 +1,72 @@
+use std::time::{Duration, Instant};
+
+/// How often to look again while the CLI isn't being waited on
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Notice shown when the CLI has gone quiet in the middle of answering
+pub fn stuck_notice(quiet: Duration) -> String {
+    format!(
+        "\n\x1b[1;33m[grill] The CLI appears stuck: no output for {} since your last message.\x1b[0m\n[grill] /retry sends the message again, /restart starts the CLI again, or keep waiting.\n",
+        crate::events::format_duration(quiet.as_secs() as i64)
+    )
+}
+
+/// Watches for a CLI that stops producing output after being sent a message
+///
+/// The clock starts when a message is submitted and restarts with every
+/// chunk of output, so a long answer that keeps streaming never counts.
+/// Getting back to the prompt means the CLI has answered.
+#[derive(Debug)]
+pub struct Heartbeat {
+    threshold: Duration,
+    waiting_since: Option<Instant>,
+    reported: bool,
+}
+
+impl Heartbeat {
+    /// Report the CLI as stuck once it has been quiet for `threshold` while answering
+    pub fn new(threshold: Duration) -> Self {
+        Self { threshold, waiting_since: None, reported: false }
+    }
+    
+    /// Note a message submitted to the CLI
+    pub fn input(&mut self, now: Instant) {
+        self.waiting_since = Some(now);
+        self.reported = false;
+    }
+    
+    /// Note output from the CLI, and whether it ended at the CLI's prompt
+    pub fn output(&mut self, now: Instant, at_prompt: bool) {
+        if at_prompt {
+            self.waiting_since = None;
+        } else if self.waiting_since.is_some() {
+            self.waiting_since = Some(now);
+        }
+    }
+    
+    /// Check whether the CLI has become stuck, which is reported once per message
+    pub fn check(&mut self, now: Instant) -> bool {
+        let Some(since) = self.waiting_since else {
+            return false;
+        };
+        if self.reported || now.duration_since(since) < self.threshold {
+            return false;
+        }
+        self.reported = true;
+        true
+    }
+    
+    /// Get when to check again: when the CLI would become stuck, or shortly
+    /// if it isn't being waited on
+    pub fn next_check(&self, now: Instant) -> Instant {
+        match self.waiting_since {
+            Some(since) if !self.reported => since + self.threshold,
+            _ => now + IDLE_CHECK_INTERVAL,
+        }
+    }
+    
+    /// Get how long the CLI has been quiet while answering
+    pub fn quiet_for(&self, now: Instant) -> Duration {
+        self.waiting_since.map_or(Duration::ZERO, |since| now.duration_since(since))
+    }
+}
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::cli_handler::NativeCommands;
+use crate::keys::{Action, KeyMap};
+
+/// Lines per page when the terminal's height is unknown, as in detached sessions
+pub const DEFAULT_PAGE_LINES: usize = 24;
+
+/// A part of the help shown by `/help <topic>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Topic {
+    pub name: String,
+    /// One line for the list of topics
+    pub summary: String,
+    pub body: String,
+}
+
+impl Topic {
+    /// Create a topic from its lines of help
+    pub fn new(name: &str, summary: &str, lines: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            summary: summary.to_string(),
+            body: lines.join("\n"),
+        }
+    }
+}
+
+/// Help assembled from grill's own topics, the CLI's handler and the user's commands
+#[derive(Debug, Clone)]
+pub struct Help {
+    topics: Vec<Topic>,
+    /// Name of the wrapped CLI's topic, also found as `cli`
+    cli: Option<String>,
+}
+
+impl Default for Help {
+    fn default() -> Self {
+        Self::new(&KeyMap::default())
+    }
+}
+
+impl Help {
+    /// Create grill's own topics, with the keys as they are bound
+    pub fn new(keys: &KeyMap) -> Self {
+        let mut topics = core_topics();
+        let at = topics.iter().position(|topic| topic.name == "session").unwrap_or(topics.len());
+        topics.insert(at, keys_topic(keys));
+        Self { topics, cli: None }
+    }
+    
+    /// Add a topic after grill's own
+    pub fn add_topic(&mut self, topic: Topic) {
+        self.topics.push(topic);
+    }
+    
+    /// Add the wrapped CLI's topic
+    pub fn add_cli_topic(&mut self, topic: Topic) {
+        self.cli = Some(topic.name.clone());
+        self.topics.push(topic);
+    }
+    
+    /// Check whether a topic is the wrapped CLI's, whose own help follows grill's
+    pub fn is_cli_topic(&self, name: &str) -> bool {
+        match (self.find(name), &self.cli) {
+            (Some(topic), Some(cli)) => &topic.name == cli,
+            _ => false,
+        }
+    }
+    
+    /// List the topics, for `/help`
+    pub fn overview(&self) -> String {
+        let mut text = String::from("Grill Help. Type /help <topic> for its commands:\n");
+        for topic in &self.topics {
+            text.push_str(&format!("  /help {:<10} {}\n", topic.name, topic.summary));
+        }
+        text.push_str("\nCommon commands: /task switch, /context diff, /stop, /retry, /quit\n");
+        text
+    }
+    
+    /// Find a topic by its name, or the start of its name if only one topic has it
+    pub fn find(&self, name: &str) -> Option<&Topic> {
+        let mut name = name.trim_start_matches('/').to_lowercase();
+        if name == "cli" {
+            if let Some(cli) = &self.cli {
+                name = cli.clone();
+            }
+        }
+        if let Some(topic) = self.topics.iter().find(|topic| topic.name == name) {
+            return Some(topic);
+        }
+        let mut matches = self.topics.iter().filter(|topic| topic.name.starts_with(&name));
+        match (matches.next(), matches.next()) {
+            (Some(topic), None) => Some(topic),
+            _ => None,
+        }
+    }
+    
+    /// Show one page of a topic, or of the overview, that fits in `lines` lines
+    pub fn page(&self, topic: Option<&str>, page: usize, lines: usize) -> String {
+        let (text, command) = match topic {
+            None => (self.overview(), "/help".to_string()),
+            Some(name) => match self.find(name) {
+                Some(topic) => (format!("{}\n", topic.body), format!("/help {}", topic.name)),
+                None => {
+                    let names: Vec<&str> = self.topics.iter().map(|topic| topic.name.as_str()).collect();
+                    return format!("No help on '{}'. Topics: {}\n", name, names.join(", "));
+                },
+            },
+        };
+        paginate(&text, &command, page, lines)
+    }
+}
+
+/// Cut text into pages that fit the terminal, with a footer saying how to see the next
+///
+/// Pages count from 1; one past the last shows the last.
+pub fn paginate(text: &str, command: &str, page: usize, lines: usize) -> String {
+    // Leave room for the footer and the prompt
+    let per_page = lines.saturating_sub(3).max(5);
+    let all: Vec<&str> = text.lines().collect();
+    let pages = all.len().div_ceil(per_page).max(1);
+    let page = page.clamp(1, pages);
+    
+    let mut shown = all.iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .fold(String::new(), |mut shown, line| {
+            shown.push_str(line);
+            shown.push('\n');
+            shown
+        });
+    if page < pages {
+        shown.push_str(&format!("-- Page {} of {}; {} {} for more --\n", page, pages, command, page + 1));
+    } else if pages > 1 {
+        shown.push_str(&format!("-- Page {} of {} --\n", page, pages));
+    }
+    shown
+}
+
+/// Name the CLI's topic after its program, as in `/help q`
+pub fn cli_name(command: &str) -> String {
+    let program = crate::clis::program(command).unwrap_or_else(|| "cli".to_string());
+    Path::new(&program).file_stem()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "cli".to_string())
+}
+
+/// Describe the CLI grill is wrapping: what its handler says and which commands are its own
+pub fn cli_topic(name: &str, handler_help: &str, native_commands: &NativeCommands) -> Topic {
+    let mut body = String::new();
+    if !handler_help.trim().is_empty() {
+        body.push_str(&format!("{}\n\n", handler_help.trim()));
+    }
+    match native_commands.declared() {
+        Some(commands) if !commands.is_empty() => {
+            let commands: Vec<String> = commands.iter().map(|command| format!("/{}", command)).collect();
+            body.push_str(&format!("These go to {} as typed:\n  {}\n", name, commands.join(" ")));
+        },
+        _ => body.push_str(&format!("Slash commands grill doesn't know go to {} as typed.\n", name)),
+    }
+    body.push_str(&format!("\n{}'s own help follows.", name));
+    Topic {
+        name: name.to_string(),
+        summary: format!("{}'s own commands and help", name),
+        body,
+    }
+}
+
+/// List the user's own commands: aliases, prompt templates and the task's macros
+pub fn custom_topic(aliases: &HashMap<String, String>, prompts: &[String], macros: &[String]) -> Topic {
+    let mut body = String::new();
+    if aliases.is_empty() {
+        body.push_str("No aliases. Add them under [aliases] in .grill/config.toml\n");
+    } else {
+        body.push_str("Aliases:\n");
+        let mut sorted: Vec<_> = aliases.iter().collect();
+        sorted.sort();
+        for (alias, command) in sorted {
+            let alias = format!("/{}", alias.trim_start_matches('/'));
+            body.push_str(&format!("  {:<20} {}\n", alias, command));
+        }
+    }
+    if prompts.is_empty() {
+        body.push_str("No prompts. Add templates as .grill/prompts/<name>.md\n");
+    } else {
+        body.push_str("Prompts:\n");
+        for prompt in prompts {
+            body.push_str(&format!("  /prompt {}\n", prompt));
+        }
+    }
+    if macros.is_empty() {
+        body.push_str("No macros in this task. Record one with /macro record <name>");
+    } else {
+        body.push_str("Macros:");
+        for name in macros {
+            body.push_str(&format!("\n  /macro play {}", name));
+        }
+    }
+    Topic {
+        name: "custom".to_string(),
+        summary: "Your aliases, prompts and macros".to_string(),
+        body,
+    }
+}
+
+/// Describe the keys, as bound under `[keys]`
+fn keys_topic(keys: &KeyMap) -> Topic {
+    let actions = [
+        (Action::Quit, "Quit grill"),
+        (Action::CommandMode, "Start typing a grill command"),
+        (Action::StopGeneration, "Stop the CLI's response"),
+        (Action::SwitchTaskPicker, "Pick a task to switch to"),
+        (Action::CopyMode, "Page through output in copy mode"),
+        (Action::Detach, "Detach from a session started with --detach"),
+    ];
+    let mut lines: Vec<String> = actions.iter()
+        .filter_map(|(action, description)| keys.binding(*action).map(|binding| (binding.to_string(), description)))
+        .map(|(binding, description)| format!("  {:<21} {}", binding, description))
+        .collect();
+    if let Some((leader, commands)) = keys.leader() {
+        for (letter, command) in commands {
+            lines.push(format!("  {:<21} {}", format!("{} {}", leader, letter), command.trim_end()));
+        }
+    }
+    lines.extend([
+        "  A prefix pressed twice, such as Ctrl+G Ctrl+G, sends it to the CLI".to_string(),
+        "  While typing a grill command:".to_string(),
+        "  Left/Right, Home/End  Move the cursor (also Ctrl+B/F, Ctrl+A/E)".to_string(),
+        "  Ctrl+W                Delete the word before the cursor".to_string(),
+        "  Ctrl+U                Delete everything before the cursor".to_string(),
+        "  Rebind keys under [keys] in .grill/config.toml".to_string(),
+    ]);
+    Topic {
+        name: "keys".to_string(),
+        summary: "Keyboard shortcuts".to_string(),
+        body: lines.join("\n"),
+    }
+}
+
+/// Grill's own topics, apart from the keys
+fn core_topics() -> Vec<Topic> {
+    vec![
+        Topic::new("tasks", "Create, switch and organize tasks", &[
+            "  /task                 Show the current task",
+            "  /task list            List all available tasks",
+            "  /task time            Show time spent in each task",
+            "  /task done [n] [--archive]  Mark a task as done, optionally archiving it",
+            "  /task block <reason>  Mark the current task as blocked",
+            "  /task <n>             Switch to the specified task",
+            "  /task switch [n]      Switch task, picking it from a fuzzy finder if no name is given",
+            "  /task <n> --dry-run   Show what switching to a task would do, without switching",
+            "  /task init <n>        Create a new task",
+            "  /task delete <n> --force  Move a task to the trash",
+            "  /task restore <n>     Restore a deleted task",
+            "  /note <text>          Add a timestamped note to the current task",
+            "  /notes                Show the current task's notes",
+        ]),
+        Topic::new("context", "Send diffs, command output and templates to the CLI", &[
+            "  /context diff [--staged]  Send the git diff to the CLI",
+            "  /context run <cmd>    Run a command and send its output to the CLI",
+            "  /context run <name>   Send a sidecar's recent output to the CLI",
+            "  /context map          Send a map of the repository to the CLI",
+            "  /prompt <n> [args]    Send the prompt template .grill/prompts/<n>.md, filled in with args",
+            "  /paste [--force]      Send the clipboard to the CLI as one message",
+        ]),
+        Topic::new("messages", "Stop, retry and recall messages", &[
+            "  /stop                 Stop the CLI's response (or press Esc twice)",
+            "  /retry [edit]         Send the last message again, or put it back at the prompt to edit",
+            "  /history input [n]    Recall a message sent earlier in this task, or send message n again",
+            "  /copy                 Copy the CLI's last response to the clipboard",
+        ]),
+        Topic::new("macros", "Record and replay what you type", &[
+            "  /macro record <name>  Record what you type until /macro stop, then save it as a macro",
+            "  /macro play <name>    Replay a macro, waiting for the CLI between steps",
+            "  /macro stop           Save the macro being recorded, or stop one playing",
+            "  /macro                List the task's macros",
+        ]),
+        Topic::new("display", "Open files and render markdown", &[
+            "  /open [n]             List files mentioned in output, or open one in $EDITOR",
+            "  /render [on|off]      Turn markdown rendering of responses on or off",
+        ]),
+        Topic::new("session", "Aliases, versions, help and quitting", &[
+            "  /alias                List the command aliases set under [aliases] in the config",
+            "  /grill <command>      Run grill's command where the CLI has one by the same name",
+            "  /version              Show grill's version, the CLI's and the workspace, for bug reports",
+            "  /help [topic] [page]  Show help on a topic, a page at a time",
+            "  /restart              Stop the CLI and start it again, e.g. when it is stuck",
+            "  /quit                 Exit grill",
+        ]),
+    ]
+}
@@ -0,0 +1,116 @@
+use anyhow::{Result, Context};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Name of the file in a task's directory that keeps the messages sent to its CLI
+pub const INPUT_HISTORY_FILE: &str = "input_history";
+
+/// Most messages kept in a task's input history
+pub const MAX_INPUT_HISTORY: usize = 1000;
+
+/// Append a message to an input history, one JSON string per line
+pub fn append(path: &Path, message: &str) -> Result<()> {
+    let line = serde_json::to_string(message)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("Failed to open input history")?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Read an input history, oldest first, skipping lines that can't be parsed
+pub fn load(path: &Path) -> Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let content = fs::read_to_string(path)
+        .context("Failed to read input history")?;
+    Ok(content.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Get the most recent distinct messages in an input history, newest first
+pub fn recent(path: &Path, count: usize) -> Result<Vec<String>> {
+    let mut messages: Vec<String> = Vec::new();
+    for message in load(path)?.into_iter().rev() {
+        if messages.len() == count {
+            break;
+        }
+        if !messages.contains(&message) {
+            messages.push(message);
+        }
+    }
+    Ok(messages)
+}
+
+/// Drop all but the last `max` messages of an input history
+pub fn trim(path: &Path, max: usize) -> Result<()> {
+    let messages = load(path)?;
+    if messages.len() <= max {
+        return Ok(());
+    }
+    
+    let mut content = String::new();
+    for message in &messages[messages.len() - max..] {
+        content.push_str(&serde_json::to_string(message)?);
+        content.push('\n');
+    }
+    crate::environment::write_atomic(path, content).context("Failed to write input history")
+}
+
+/// Picks the submitted lines out of the raw keystrokes sent to the CLI
+///
+/// Escape sequences, such as the arrow keys, are skipped, so lines recalled
+/// from the CLI's own history aren't seen.
+#[derive(Debug, Default)]
+pub struct LineTracker {
+    line: String,
+    escape: bool,
+    csi: bool,
+}
+
+impl LineTracker {
+    /// Check whether nothing has been typed on the current line
+    pub fn is_empty(&self) -> bool {
+        self.line.is_empty()
+    }
+    
+    /// Record keystrokes, returning the lines they submitted
+    pub fn input(&mut self, input: &str) -> Vec<String> {
+        let mut submitted = Vec::new();
+        for c in input.chars() {
+            if self.csi {
+                self.csi = !('\x40'..='\x7e').contains(&c);
+                continue;
+            }
+            if self.escape {
+                self.escape = false;
+                self.csi = c == '[';
+                continue;
+            }
+            
+            match c {
+                '\x1b' => self.escape = true,
+                '\r' | '\n' => {
+                    let line = std::mem::take(&mut self.line);
+                    if !line.trim().is_empty() {
+                        submitted.push(line.trim().to_string());
+                    }
+                },
+                '\x08' | '\x7f' => {
+                    self.line.pop();
+                },
+                // Ctrl+C and Ctrl+U abandon the line
+                '\x03' | '\x15' => self.line.clear(),
+                c if !c.is_control() => self.line.push(c),
+                _ => {},
+            }
+        }
+        submitted
+    }
+}
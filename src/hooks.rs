@@ -0,0 +1,209 @@
+use anyhow::{anyhow, Result, Context};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::{Command as ProcessCommand, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, TaskConfig};
+use crate::environment::Environment;
+use crate::signal::{self, Signal};
+
+/// How long a hook may run before it is stopped
+pub const HOOK_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Result of running a hook command
+#[derive(Debug, Clone)]
+pub struct HookOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Run a hook command through the shell in `dir`
+///
+/// `vars` are exported to the hook's environment and `stdin`, if given, is
+/// written to its standard input. A hook still running after `timeout` is
+/// stopped, along with anything it started, and an error returned.
+pub fn run_hook(
+    command: &str,
+    dir: &Path,
+    vars: &[(&str, &str)],
+    stdin: Option<&str>,
+    timeout: Option<Duration>,
+) -> Result<HookOutput> {
+    let mut process = shell_command(command);
+    process
+        .current_dir(dir)
+        .stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Lead a process group, so stopping it also stops what it started
+        process.process_group(0);
+    }
+    
+    for (key, value) in vars {
+        process.env(key, value);
+    }
+    
+    let mut child = process.spawn()
+        .context(format!("Failed to run hook '{}'", command))?;
+    
+    // Input is written while the output is read, so a hook that prints
+    // before reading all of it can't fill a pipe and stall
+    if let (Some(input), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+        let input = input.to_string();
+        // A hook that doesn't read its input is fine
+        thread::spawn(move || child_stdin.write_all(input.as_bytes()));
+    }
+    let stdout = read_to_end(child.stdout.take());
+    let stderr = read_to_end(child.stderr.take());
+    
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().context(format!("Failed to wait for hook '{}'", command))? {
+            break status;
+        }
+        if let Some(timeout) = timeout.filter(|timeout| started.elapsed() >= *timeout) {
+            if signal::send(child.id(), Signal::Kill).is_err() {
+                let _ = child.kill();
+            }
+            let _ = child.wait();
+            return Err(anyhow!("Hook '{}' was stopped after running for {}s", command, timeout.as_secs()));
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    
+    Ok(HookOutput {
+        success: status.success(),
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// Read a hook's output stream to the end on its own thread
+fn read_to_end(stream: Option<impl Read + Send + 'static>) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        if let Some(mut stream) = stream {
+            let _ = stream.read_to_end(&mut output);
+        }
+        String::from_utf8_lossy(&output).to_string()
+    })
+}
+
+/// Build a command that runs `command` in the platform's shell
+#[cfg(unix)]
+pub fn shell_command(command: &str) -> ProcessCommand {
+    let mut process = ProcessCommand::new("sh");
+    process.args(["-c", command]);
+    process
+}
+
+/// Build a command that runs `command` in the platform's shell
+#[cfg(windows)]
+pub fn shell_command(command: &str) -> ProcessCommand {
+    let mut process = ProcessCommand::new("cmd");
+    process.args(["/C", command]);
+    process
+}
+
+/// Event fired before switching to another task
+pub const ON_SWITCH: &str = "on_switch";
+
+/// Event fired when the session quits
+pub const ON_QUIT: &str = "on_quit";
+
+/// Event fired when the CLI finishes a response; hooks get the response on stdin
+pub const ON_RESPONSE: &str = "on_response";
+
+/// Prefix of hooks implemented by grill itself
+const BUILTIN_PREFIX: &str = "builtin:";
+
+/// Run the hooks configured for an event
+///
+/// Hooks come from `[hooks]` in the global config followed by the task's own
+/// config, keyed by event name. `target_task` is the task being switched to,
+/// if any, and `input` is written to each hook's stdin. Every hook runs even
+/// if an earlier one fails; failures are returned as messages for the caller
+/// to show.
+pub fn run_event(
+    environment: &Environment,
+    event: &str,
+    task_name: &str,
+    target_task: Option<&str>,
+    input: Option<&str>,
+) -> Vec<String> {
+    let mut commands = Vec::new();
+    
+    match Config::load(&environment.get_config_path()) {
+        Ok(config) => commands.extend(config.hooks.get(event).cloned()),
+        Err(e) => return vec![format!("Failed to load hooks: {}", e)],
+    }
+    if let Ok(task_dir) = environment.get_task_dir(task_name) {
+        match TaskConfig::load_for_task(&task_dir) {
+            Ok(config) => commands.extend(config.hooks.get(event).cloned()),
+            Err(e) => return vec![format!("Failed to load hooks: {}", e)],
+        }
+    }
+    
+    if commands.is_empty() {
+        return Vec::new();
+    }
+    
+    // Hooks act on the directory the task's CLI has been working in
+    let dir = match environment.get_task_working_dir(task_name) {
+        Ok(dir) => dir.unwrap_or_else(|| environment.get_root_dir()),
+        Err(e) => return vec![format!("Failed to find working directory for hooks: {}", e)],
+    };
+    
+    let vars = [
+        ("GRILL_EVENT", event),
+        ("GRILL_TASK", task_name),
+        ("GRILL_TARGET_TASK", target_task.unwrap_or("")),
+    ];
+    
+    let mut failures = Vec::new();
+    for hook in commands {
+        let command = match hook.strip_prefix(BUILTIN_PREFIX) {
+            Some(preset) => match builtin_command(preset, event, target_task) {
+                Some(command) => command,
+                None => {
+                    failures.push(format!("Unknown built-in hook '{}'", hook));
+                    continue;
+                }
+            },
+            None => hook.clone(),
+        };
+        
+        match run_hook(&command, &dir, &vars, input, Some(HOOK_TIMEOUT)) {
+            Ok(output) if output.success => {},
+            Ok(output) => failures.push(format!("Hook '{}' failed: {}", hook, output.stderr.trim())),
+            Err(e) => failures.push(e.to_string()),
+        }
+    }
+    
+    failures
+}
+
+/// Expand a built-in hook preset to a shell command
+fn builtin_command(preset: &str, event: &str, target_task: Option<&str>) -> Option<String> {
+    match preset {
+        // Commit whatever the agent changed so switching or quitting never loses work
+        "git-checkpoint" => {
+            let message = match (event, target_task) {
+                (ON_SWITCH, Some(task)) => format!("grill: checkpoint before switching to {}", task),
+                (ON_QUIT, _) => "grill: checkpoint before quitting".to_string(),
+                _ => format!("grill: checkpoint on {}", event),
+            };
+            Some(format!(
+                "git add -A && (git diff --cached --quiet || git commit -q -m '{}')",
+                message.replace('\'', "'\\''")
+            ))
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::{Duration, Instant};
+
+/// Message asking the CLI for the summary saved by the `summarize` idle action
+///
+/// It is one line, so the transcript recorder sees it as a single prompt.
+pub const SUMMARY_PROMPT: &str = "Summarize the progress on this task so far as markdown notes for picking it up later: what is done, what is in progress, what is next and any open questions. Reply with the notes only.";
+
+/// Tracks how long it has been since the user typed anything
+#[derive(Debug)]
+pub struct IdleTracker {
+    timeout: Duration,
+    last_input: Instant,
+    idle: bool,
+}
+
+impl IdleTracker {
+    /// Start tracking, counting from `now`
+    pub fn new(timeout: Duration, now: Instant) -> Self {
+        Self { timeout, last_input: now, idle: false }
+    }
+    
+    /// Note input from the user, returning whether the session had been idle
+    pub fn input(&mut self, now: Instant) -> bool {
+        self.last_input = now;
+        std::mem::take(&mut self.idle)
+    }
+    
+    /// Check whether the session has become idle, which is reported once per idle spell
+    pub fn check(&mut self, now: Instant) -> bool {
+        if self.idle || now.duration_since(self.last_input) < self.timeout {
+            return false;
+        }
+        self.idle = true;
+        true
+    }
+    
+    /// Get when to check again: when the session becomes idle, or a while
+    /// later if it already is
+    pub fn next_check(&self, now: Instant) -> Instant {
+        if self.idle {
+            now + self.timeout
+        } else {
+            self.last_input + self.timeout
+        }
+    }
+}
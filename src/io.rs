@@ -1,12 +1,52 @@
 use anyhow::Result;
-use std::io::{self, Write};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
 use tokio::sync::{mpsc, broadcast};
+use serde::{Serialize, Deserialize};
 use std::thread;
+use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
-    terminal::{disable_raw_mode, enable_raw_mode},
+    event::{self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEventKind},
+    execute,
+    terminal::{self as term, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use crate::cli_handler::NativeCommands;
+use crate::clipboard;
+use crate::confirm;
+use crate::keys::{Action, KeyMap, KeyMatch};
+use crate::links::{self, Linker};
+use crate::macros::MacroState;
+use crate::markdown::MarkdownRenderer;
+use crate::mouse::{self, MouseState};
+use crate::picker::{Picker, PickerItem};
+use crate::scrollback::{CopyMode, Scrollback};
+use crate::terminal::{TerminalInfo, TerminalMode, TerminalSupport};
+
+/// Maximum number of bytes of output kept for replaying to attaching clients
+pub const REPLAY_LIMIT: usize = 64 * 1024;
+
+/// How long output is gathered before it is written to the terminal
+pub const OUTPUT_FLUSH_INTERVAL: Duration = Duration::from_millis(8);
+
+/// Number of bytes of gathered output that are written without waiting any longer
+pub const OUTPUT_BATCH_SIZE: usize = 64 * 1024;
+
+/// Grill's own commands, suggested when a command is mistyped
+pub const COMMANDS: &[&str] = &[
+    "/task", "/context", "/history", "/stop", "/retry", "/prompt", "/macro", "/note", "/notes",
+    "/copy", "/paste", "/open", "/render", "/alias", "/version", "/help", "/quit", "/restart",
+];
+
+/// Most edits between a mistyped command and the command it is taken for
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Lists the tasks offered by `/task switch` without a task name
+pub type TaskSource = Arc<dyn Fn() -> Vec<PickerItem> + Send + Sync>;
+
+/// Lists the messages offered by `/history input`, newest first
+pub type HistorySource = Arc<dyn Fn() -> Vec<String> + Send + Sync>;
 
 /// Handles input/output between the user and the child process
 pub struct IoHandler {
@@ -14,32 +54,140 @@ pub struct IoHandler {
     output_rx: mpsc::Receiver<String>,
     command_tx: broadcast::Sender<Command>,
     running: Arc<Mutex<bool>>,
+    output_mirror: Option<(broadcast::Sender<String>, Arc<Mutex<String>>)>,
+    task_source: Option<TaskSource>,
+    history_source: Option<HistorySource>,
+    flush_interval: Duration,
+    batch_size: usize,
+    scrollback: Arc<Mutex<Scrollback>>,
+    display: Arc<Mutex<Display>>,
+    mouse_capture: bool,
+    mouse_state: Arc<Mutex<MouseState>>,
+    links: Option<Arc<Mutex<Linker>>>,
+    render_markdown: Arc<AtomicBool>,
+    macros: Option<Arc<Mutex<MacroState>>>,
+    confirm_bytes: usize,
+    multiline_input: bool,
+    keys: KeyMap,
+    aliases: Arc<HashMap<String, String>>,
+    native_commands: Arc<NativeCommands>,
+}
+
+/// What choosing an item in the picker does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PickerAction {
+    /// Switch to the chosen task
+    SwitchTask,
+    /// Type the chosen message at the CLI's prompt, for editing before it is sent
+    Recall,
+}
+
+/// Output held back while copy mode covers the screen
+#[derive(Debug, Default)]
+struct Display {
+    paused: bool,
+    held: String,
 }
 
 /// Commands that can be sent to the IoHandler
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Command {
     /// Switch to a different task
     SwitchTask(String),
+    /// Show what switching to a task would do, without switching
+    PreviewSwitch(String),
     /// List all tasks
     ListTasks,
     /// Show current task
     CurrentTask,
+    /// List the messages sent to the current task's CLI, or send the numbered one again
+    InputHistory(Option<usize>),
+    /// Make the CLI stop generating its response
+    Stop,
+    /// Send the last message to the CLI again, or put it back at the CLI's prompt to edit first
+    Retry { edit: bool },
     /// Create a new task
     CreateTask(String),
-    /// Delete a task
+    /// Delete a task (moving it to the trash)
     DeleteTask(String),
-    /// Show help
+    /// Explain how to confirm deleting a task
+    ConfirmDeleteTask(String),
+    /// Restore a deleted task from the trash
+    RestoreTask(String),
+    /// Show time spent in each task
+    TaskTime,
+    /// Mark a task (the current one if not given) as done, optionally archiving it
+    TaskDone {
+        task: Option<String>,
+        archive: bool,
+    },
+    /// Mark the current task as blocked, with a reason
+    BlockTask(String),
+    /// Add a note to the current task
+    AddNote(String),
+    /// Show the current task's notes
+    ShowNotes,
+    /// Copy the CLI's last response to the clipboard
+    Copy,
+    /// Send the clipboard to the CLI as one message, asking first if it's large unless forced
+    Paste {
+        force: bool,
+    },
+    /// Expand a prompt template with arguments and send it to the CLI
+    SendPrompt {
+        name: String,
+        args: String,
+    },
+    /// List the prompt templates
+    ListPrompts,
+    /// Start recording a macro of what is typed
+    MacroRecord(String),
+    /// Save the macro being recorded, or stop the one playing
+    MacroStop,
+    /// Replay a recorded macro
+    MacroPlay(String),
+    /// List the current task's macros
+    ListMacros,
+    /// Show grill's version and what the session is running, for bug reports
+    Version,
+    /// Show the list of help topics
     Help,
+    /// Show a page of help on one topic, or of the topic list if there's none
+    HelpTopic {
+        topic: Option<String>,
+        page: usize,
+    },
     /// Quit the application
     Quit,
+    /// Stop the CLI and start it again for the current task
+    Restart,
+    /// Detach from a session without stopping it (only meaningful when attached)
+    Detach,
+    /// Send the workspace's git diff to the CLI as context
+    ContextDiff {
+        /// Only staged changes
+        staged: bool,
+    },
+    /// Run a shell command and send its output to the CLI as context
+    ContextRun(String),
+    /// Send a map of the repository to the CLI as context
+    ContextMap,
+    /// List the command aliases from the config
+    ListAliases,
+    /// A slash command that isn't grill's, as typed; the CLI gets it if it's one of its own
+    Unknown(String),
 }
 
 impl IoHandler {
     /// Create a new IoHandler
     pub fn new() -> (Self, broadcast::Sender<String>, mpsc::Sender<String>, broadcast::Sender<Command>) {
+        Self::with_capacity(100)
+    }
+    
+    /// Create a new IoHandler that buffers up to `output_capacity` chunks of output
+    pub fn with_capacity(output_capacity: usize) -> (Self, broadcast::Sender<String>, mpsc::Sender<String>, broadcast::Sender<Command>) {
         let (input_tx, _) = broadcast::channel(100);
-        let (output_tx, output_rx) = mpsc::channel(100);
+        let (output_tx, output_rx) = mpsc::channel(output_capacity.max(1));
         let (command_tx, _) = broadcast::channel(100);
         let running = Arc::new(Mutex::new(true));
         
@@ -48,42 +196,333 @@ impl IoHandler {
             output_rx,
             command_tx: command_tx.clone(),
             running,
+            output_mirror: None,
+            task_source: None,
+            history_source: None,
+            flush_interval: OUTPUT_FLUSH_INTERVAL,
+            batch_size: OUTPUT_BATCH_SIZE,
+            scrollback: Arc::new(Mutex::new(Scrollback::default())),
+            display: Arc::new(Mutex::new(Display::default())),
+            mouse_capture: false,
+            mouse_state: Arc::new(Mutex::new(MouseState::default())),
+            links: None,
+            render_markdown: Arc::new(AtomicBool::new(false)),
+            macros: None,
+            confirm_bytes: 0,
+            multiline_input: true,
+            keys: KeyMap::default(),
+            aliases: Arc::new(HashMap::new()),
+            native_commands: Arc::new(NativeCommands::default()),
         };
         
         (handler, input_tx.clone(), output_tx, command_tx.clone())
     }
     
     /// Start the IO handler
+    ///
+    /// Falls back to reading whole lines when the terminal can't do raw mode.
     pub async fn start(&mut self) -> Result<()> {
-        // Enable raw mode for character-by-character input
-        enable_raw_mode()?;
+        let mut support = TerminalInfo::from_env().support();
+        if support.mode == TerminalMode::Raw {
+            // Enable raw mode for character-by-character input
+            if let Err(e) = enable_raw_mode() {
+                support = TerminalSupport { mode: TerminalMode::Line, reason: Some(format!("raw mode is unavailable: {}", e)) };
+            }
+        }
+        let raw = support.mode == TerminalMode::Raw;
+        if raw {
+            if self.mouse_capture {
+                execute!(io::stdout(), EnableMouseCapture)?;
+            }
+            // Pastes arrive whole, so large ones can be held back; not every terminal can
+            let _ = execute!(io::stdout(), EnableBracketedPaste);
+            self.spawn_raw_input();
+        } else {
+            eprintln!(
+                "grill: using line mode ({}); type a line and press Enter to send it",
+                support.reason.as_deref().unwrap_or("requested")
+            );
+            self.spawn_line_input();
+        }
         
+        // Set up stdout writer
+        let mut stdout = io::stdout();
+        let mut newlines = NewlineTranslator::default();
+        let mut markdown: Option<MarkdownRenderer> = None;
+        
+        // CLIs that stream a token at a time would otherwise cost a write and a flush per token
+        while let Some(output) = next_batch(&mut self.output_rx, self.flush_interval, self.batch_size).await {
+            self.mirror_output(&output);
+            self.scrollback.lock().unwrap().push(&output);
+            self.mouse_state.lock().unwrap().observe(&output);
+            
+            // Markdown is only rendered in raw mode, where styles can be shown
+            let output = if raw && self.render_markdown.load(Ordering::Relaxed) {
+                markdown.get_or_insert_with(MarkdownRenderer::default).process(&output)
+            } else {
+                markdown = None;
+                output
+            };
+            
+            // Files are always remembered for /open, but only linked in raw mode
+            let output = match &self.links {
+                Some(links) => {
+                    let linked = links.lock().unwrap().process(&output);
+                    if raw { linked } else { output }
+                },
+                None => output,
+            };
+            
+            // In raw mode, we need to convert \n to \r\n for proper display
+            let formatted_output = if raw { newlines.translate(&output) } else { output };
+            
+            // Write to stdout, unless copy mode is covering it
+            let mut display = self.display.lock().unwrap();
+            if display.paused {
+                display.held.push_str(&formatted_output);
+                continue;
+            }
+            stdout.write_all(formatted_output.as_bytes())?;
+            stdout.flush()?;
+        }
+        
+        // Ensure raw mode is disabled
+        if raw && self.mouse_capture {
+            let _ = execute!(io::stdout(), DisableMouseCapture);
+        }
+        if raw {
+            let _ = execute!(io::stdout(), DisableBracketedPaste);
+        }
+        let _ = disable_raw_mode();
+        
+        Ok(())
+    }
+    
+    /// Remember files mentioned in output for `/open`, and link them
+    pub fn set_links(&mut self, links: Arc<Mutex<Linker>>) {
+        self.links = Some(links);
+    }
+    
+    /// Record the slash commands typed into `macros` while a macro is being recorded
+    pub fn set_macros(&mut self, macros: Arc<Mutex<MacroState>>) {
+        self.macros = Some(macros);
+    }
+    
+    /// Render the CLI's markdown until `/render off`
+    pub fn set_render_markdown(&mut self, enabled: bool) {
+        self.render_markdown.store(enabled, Ordering::Relaxed);
+    }
+    
+    /// Expand the first word of typed commands that is one of `aliases`
+    pub fn set_aliases(&mut self, aliases: HashMap<String, String>) {
+        self.aliases = Arc::new(aliases);
+    }
+    
+    /// Send the CLI's own commands to it as typed, even where grill has a command by the same name
+    pub fn set_native_commands(&mut self, native_commands: NativeCommands) {
+        self.native_commands = Arc::new(native_commands);
+    }
+    
+    /// Paste into the CLI on one line when it can't take a multi-line paste
+    pub fn set_multiline_input(&mut self, enabled: bool) {
+        self.multiline_input = enabled;
+    }
+    
+    /// Ask before sending a paste of more than `bytes` to the CLI; 0 never asks
+    pub fn set_confirm_bytes(&mut self, bytes: usize) {
+        self.confirm_bytes = bytes;
+    }
+    
+    /// Bind grill's actions to the configured keys
+    pub fn set_keys(&mut self, keys: KeyMap) {
+        self.keys = keys;
+    }
+    
+    /// Get the flag that keeps keys being read; clearing it stops reading them
+    pub fn running(&self) -> Arc<Mutex<bool>> {
+        Arc::clone(&self.running)
+    }
+    
+    /// Capture the mouse while grill runs in raw mode
+    pub fn set_mouse_capture(&mut self, enabled: bool) {
+        self.mouse_capture = enabled;
+    }
+    
+    /// Read keys in raw mode, handling commands and the pickers as they are typed
+    fn spawn_raw_input(&self) {
         // Set up stdin reader for character-by-character input
         let input_tx = self.input_tx.clone();
         let command_tx = self.command_tx.clone();
         let running = Arc::clone(&self.running);
+        let task_source = self.task_source.clone();
+        let history_source = self.history_source.clone();
+        let scrollback = Arc::clone(&self.scrollback);
+        let display = Arc::clone(&self.display);
+        let mouse_state = Arc::clone(&self.mouse_state);
+        let mouse_capture = self.mouse_capture;
+        let links = self.links.clone();
+        let render_markdown = Arc::clone(&self.render_markdown);
+        let macros = self.macros.clone();
+        let confirm_bytes = self.confirm_bytes;
+        let multiline_input = self.multiline_input;
+        let mut keys = self.keys.clone();
+        let aliases = Arc::clone(&self.aliases);
+        let native_commands = Arc::clone(&self.native_commands);
         
         thread::spawn(move || -> Result<()> {
-            let mut command_buffer = String::new();
+            let mut command_line = LineEditor::default();
             let mut in_command_mode = false;
+            let mut picker: Option<(Picker, PickerAction)> = None;
+            let mut copy_mode: Option<CopyMode> = None;
+            let mut held_paste: Option<String> = None;
             
             while *running.lock().unwrap() {
                 // Check for keyboard events
                 if event::poll(std::time::Duration::from_millis(100))? {
-                    if let Event::Key(key_event) = event::read()? {
-                        match key_event {
-                            // Handle Ctrl+C to quit
-                            KeyEvent {
-                                code: KeyCode::Char('c'),
-                                modifiers: KeyModifiers::CONTROL,
-                                ..
-                            } => {
-                                if let Err(e) = command_tx.send(Command::Quit) {
-                                    eprintln!("Failed to send quit command: {}", e);
+                    let event = event::read()?;
+                    if let Event::Mouse(mouse_event) = event {
+                        let up = match mouse_event.kind {
+                            MouseEventKind::ScrollUp => true,
+                            MouseEventKind::ScrollDown => false,
+                            _ => continue,
+                        };
+                        let delta = if up { -mouse::WHEEL_LINES } else { mouse::WHEEL_LINES };
+                        
+                        // The wheel scrolls copy mode, goes to CLIs that want it, or opens copy mode
+                        if let Some(active) = copy_mode.as_mut() {
+                            active.move_by(delta);
+                            let _ = active.render(terminal_size().0 as usize);
+                        } else if mouse_state.lock().unwrap().is_reporting() {
+                            let sequence = mouse_state.lock().unwrap().wheel(up, mouse_event.column, mouse_event.row);
+                            if let Err(e) = input_tx.send(sequence) {
+                                eprintln!("Failed to send input: {}", e);
+                            }
+                        } else if up && picker.is_none() {
+                            let mut active = Self::enter_copy_mode(&scrollback, &display)?;
+                            active.move_by(delta);
+                            active.render(terminal_size().0 as usize)?;
+                            copy_mode = Some(active);
+                        }
+                        continue;
+                    }
+                    if let Event::Paste(text) = event {
+                        let text = if multiline_input { text } else { clipboard::join_lines(&text) };
+                        if in_command_mode {
+                            // Only the first line of a paste fits in a command
+                            let line = text.lines().next().unwrap_or("");
+                            print!("{}", command_line.insert(line));
+                            io::stdout().flush()?;
+                        } else if confirm::needs_confirmation(&text, confirm_bytes) {
+                            let summary = confirm::summary(&text).replace('\n', "\r\n");
+                            print!("\r\n[grill] Large paste: {}Send it to the CLI? [y/N] ", summary);
+                            io::stdout().flush()?;
+                            held_paste = Some(text);
+                        } else if let Err(e) = input_tx.send(Self::paste_input(&text)) {
+                            eprintln!("Failed to send input: {}", e);
+                        }
+                        continue;
+                    }
+                    if let Event::Key(mut key_event) = event {
+                        // Windows reports key releases too; only presses are typed
+                        if key_event.kind == KeyEventKind::Release {
+                            continue;
+                        }
+                        // A held paste waits for its answer before anything else
+                        if let Some(text) = held_paste.take() {
+                            if matches!(key_event.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                                print!("y\r\n");
+                                if let Err(e) = input_tx.send(Self::paste_input(&text)) {
+                                    eprintln!("Failed to send input: {}", e);
                                 }
-                                break;
+                            } else {
+                                print!("\r\n[grill] Paste discarded\r\n");
                             }
-                            
+                            io::stdout().flush()?;
+                            continue;
+                        }
+                        // The picker takes all keys while it is open
+                        if let Some((active, action)) = picker.as_mut() {
+                            if Self::handle_picker_key(active, *action, key_event, &command_tx, &input_tx) {
+                                picker = None;
+                            }
+                            continue;
+                        }
+                        
+                        // Copy mode takes all keys while it is open
+                        if let Some(active) = copy_mode.as_mut() {
+                            if Self::handle_copy_key(active, key_event) {
+                                copy_mode = None;
+                                Self::leave_copy_mode(&display)?;
+                            }
+                            continue;
+                        }
+                        
+                        // The [keys] bindings come first; a prefix pressed twice goes to the CLI
+                        match keys.press(&key_event, in_command_mode, Instant::now()) {
+                            KeyMatch::Pending => continue,
+                            KeyMatch::Literal(input) => {
+                                if let Err(e) = input_tx.send(input) {
+                                    eprintln!("Failed to send input: {}", e);
+                                }
+                                continue;
+                            },
+                            KeyMatch::Action(action) => {
+                                match action {
+                                    Action::Quit => {
+                                        // Keys keep being read until the session has shut down
+                                        if let Err(e) = command_tx.send(Command::Quit) {
+                                            eprintln!("Failed to send quit command: {}", e);
+                                        }
+                                    },
+                                    Action::CommandMode => {
+                                        in_command_mode = true;
+                                        print!("{}", command_line.insert("/"));
+                                        io::stdout().flush()?;
+                                    },
+                                    Action::StopGeneration => {
+                                        if let Err(e) = command_tx.send(Command::Stop) {
+                                            eprintln!("Failed to send command: {}", e);
+                                        }
+                                    },
+                                    Action::SwitchTaskPicker => match &task_source {
+                                        Some(source) => {
+                                            let opened = Picker::new(source());
+                                            opened.render()?;
+                                            picker = Some((opened, PickerAction::SwitchTask));
+                                        },
+                                        None => Self::process_command_buffer("/task switch", &command_tx),
+                                    },
+                                    Action::CopyMode => {
+                                        copy_mode = Some(Self::enter_copy_mode(&scrollback, &display)?);
+                                    },
+                                    Action::Detach => {
+                                        if let Err(e) = command_tx.send(Command::Detach) {
+                                            eprintln!("Failed to send detach command: {}", e);
+                                        }
+                                    },
+                                }
+                                continue;
+                            },
+                            KeyMatch::Unbound { held: Some(input) } => {
+                                if let Err(e) = input_tx.send(input) {
+                                    eprintln!("Failed to send input: {}", e);
+                                }
+                            },
+                            KeyMatch::Unbound { held: None } => {},
+                            KeyMatch::Command(command) => {
+                                print!("{}", command_line.insert(&command));
+                                io::stdout().flush()?;
+                                in_command_mode = true;
+                                // A command ending in a space is left at the prompt to finish
+                                if command.ends_with(' ') {
+                                    continue;
+                                }
+                                // Otherwise run it as if Enter was pressed
+                                key_event = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+                            },
+                        }
+                        
+                        match key_event {
                             // Handle Enter key
                             KeyEvent {
                                 code: KeyCode::Enter,
@@ -91,9 +530,45 @@ impl IoHandler {
                             } => {
                                 if in_command_mode {
                                     // Process the command and show a newline
+                                    print!("{}", command_line.end());
                                     println!();
-                                    Self::process_command_buffer(&command_buffer, &command_tx, &input_tx);
-                                    command_buffer.clear();
+                                    let (command_buffer, to_cli) = match route_command(&command_line.text(), &aliases, &native_commands) {
+                                        Route::Cli(command) => (command, true),
+                                        Route::Grill(command) => (command, false),
+                                    };
+                                    match (&task_source, &history_source) {
+                                        _ if to_cli => {
+                                            if let Err(e) = input_tx.send(format!("{}\r", command_buffer)) {
+                                                eprintln!("Failed to send command to CLI: {}", e);
+                                            }
+                                        },
+                                        (Some(source), _) if Self::is_task_picker_command(&command_buffer) => {
+                                            let opened = Picker::new(source());
+                                            opened.render()?;
+                                            picker = Some((opened, PickerAction::SwitchTask));
+                                        },
+                                        (_, Some(source)) if Self::is_history_picker_command(&command_buffer) => {
+                                            let items = source().into_iter()
+                                                .map(|message| PickerItem { name: message, description: None })
+                                                .collect();
+                                            let opened = Picker::with_prompt(items, "Recall message: ", "no matching messages");
+                                            opened.render()?;
+                                            picker = Some((opened, PickerAction::Recall));
+                                        },
+                                        _ if Self::is_open_command(&command_buffer) => {
+                                            Self::open_file(&command_buffer, &links, &display, true)?;
+                                        },
+                                        _ if Self::is_render_command(&command_buffer) => {
+                                            Self::set_rendering(&command_buffer, &render_markdown, true)?;
+                                        },
+                                        _ => {
+                                            if let Some(macros) = &macros {
+                                                macros.lock().unwrap().record(&command_buffer);
+                                            }
+                                            Self::process_command_buffer(&command_buffer, &command_tx);
+                                        },
+                                    }
+                                    command_line.clear();
                                     in_command_mode = false;
                                 } else {
                                     // Send carriage return to the process
@@ -109,17 +584,9 @@ impl IoHandler {
                                 modifiers: KeyModifiers::NONE,
                                 ..
                             } => {
-                                if c == '/' && !in_command_mode && command_buffer.is_empty() {
-                                    // Start command mode
-                                    in_command_mode = true;
-                                    command_buffer.push(c);
-                                    // Show the slash character
-                                    print!("{}", c);
-                                    io::stdout().flush().unwrap();
-                                } else if in_command_mode {
-                                    // Add to command buffer and show character
-                                    command_buffer.push(c);
-                                    print!("{}", c);
+                                if in_command_mode {
+                                    // Insert into the command at the cursor and show it
+                                    print!("{}", command_line.insert(&c.to_string()));
                                     io::stdout().flush().unwrap();
                                 } else {
                                     // Send character to process
@@ -135,12 +602,9 @@ impl IoHandler {
                                 ..
                             } => {
                                 if in_command_mode {
-                                    if command_buffer.pop().is_some() {
-                                        // Show backspace visually
-                                        print!("\x08 \x08");
-                                        io::stdout().flush().unwrap();
-                                    }
-                                    if command_buffer.is_empty() {
+                                    print!("{}", command_line.backspace());
+                                    io::stdout().flush().unwrap();
+                                    if command_line.is_empty() {
                                         in_command_mode = false;
                                     }
                                 } else {
@@ -151,15 +615,34 @@ impl IoHandler {
                                 }
                             }
                             
+                            // Move within a command and edit it, readline style
+                            KeyEvent {
+                                code,
+                                modifiers,
+                                ..
+                            } if in_command_mode && Self::is_editing_key(code, modifiers) => {
+                                let echo = match (code, modifiers) {
+                                    (KeyCode::Left, _) | (KeyCode::Char('b'), KeyModifiers::CONTROL) => command_line.left(),
+                                    (KeyCode::Right, _) | (KeyCode::Char('f'), KeyModifiers::CONTROL) => command_line.right(),
+                                    (KeyCode::Home, _) | (KeyCode::Char('a'), KeyModifiers::CONTROL) => command_line.home(),
+                                    (KeyCode::End, _) | (KeyCode::Char('e'), KeyModifiers::CONTROL) => command_line.end(),
+                                    (KeyCode::Char('w'), KeyModifiers::CONTROL) => command_line.delete_word(),
+                                    _ => command_line.kill_line(),
+                                };
+                                print!("{}", echo);
+                                io::stdout().flush().unwrap();
+                                if command_line.is_empty() {
+                                    in_command_mode = false;
+                                }
+                            }
+                            
                             // Handle other special keys
                             KeyEvent {
                                 code: KeyCode::Tab,
                                 ..
-                            } => {
-                                if !in_command_mode {
-                                    if let Err(e) = input_tx.send("\t".to_string()) {
-                                        eprintln!("Failed to send tab: {}", e);
-                                    }
+                            } if !in_command_mode => {
+                                if let Err(e) = input_tx.send("\t".to_string()) {
+                                    eprintln!("Failed to send tab: {}", e);
                                 }
                             }
                             
@@ -171,36 +654,371 @@ impl IoHandler {
             }
             
             // Disable raw mode when exiting
+            if mouse_capture {
+                let _ = execute!(io::stdout(), DisableMouseCapture);
+            }
+            let _ = execute!(io::stdout(), DisableBracketedPaste);
             let _ = disable_raw_mode();
             Ok(())
         });
+    }
+    
+    /// Check whether a key moves the cursor or edits the line in command mode
+    fn is_editing_key(code: KeyCode, modifiers: KeyModifiers) -> bool {
+        match code {
+            KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End => true,
+            KeyCode::Char('a' | 'b' | 'e' | 'f' | 'u' | 'w') => modifiers == KeyModifiers::CONTROL,
+            _ => false,
+        }
+    }
+    
+    /// Type pasted text at the CLI's prompt, as a bracketed paste if it spans lines
+    ///
+    /// It isn't submitted, just as if it had been pasted into the CLI directly.
+    fn paste_input(text: &str) -> String {
+        if text.contains('\n') || text.contains('\r') {
+            format!("\x1b[200~{}\x1b[201~", text)
+        } else {
+            text.to_string()
+        }
+    }
+    
+    /// Hold back output and show the scrollback in the alternate screen
+    fn enter_copy_mode(scrollback: &Mutex<Scrollback>, display: &Mutex<Display>) -> Result<CopyMode> {
+        display.lock().unwrap().paused = true;
+        execute!(io::stdout(), EnterAlternateScreen)?;
         
-        // Set up stdout writer
+        let (width, height) = terminal_size();
+        let copy_mode = CopyMode::new(scrollback.lock().unwrap().lines(), height.saturating_sub(1) as usize);
+        copy_mode.render(width as usize)?;
+        Ok(copy_mode)
+    }
+    
+    /// Return to the normal screen and write the output held back meanwhile
+    fn leave_copy_mode(display: &Mutex<Display>) -> Result<()> {
+        execute!(io::stdout(), LeaveAlternateScreen)?;
+        Self::resume_display(display)
+    }
+    
+    /// Write the output held back while the display was paused, and stop holding it back
+    fn resume_display(display: &Mutex<Display>) -> Result<()> {
         let mut stdout = io::stdout();
+        let mut display = display.lock().unwrap();
+        display.paused = false;
+        stdout.write_all(std::mem::take(&mut display.held).as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+    
+    /// Check whether a command is `/open`, which grill handles where it is typed
+    fn is_open_command(buffer: &str) -> bool {
+        buffer.split_whitespace().next() == Some("/open")
+    }
+    
+    /// Check whether a command is `/render`, which grill handles where it is typed
+    fn is_render_command(buffer: &str) -> bool {
+        buffer.split_whitespace().next() == Some("/render")
+    }
+    
+    /// Turn markdown rendering on or off, or show whether it is on
+    fn set_rendering(buffer: &str, render_markdown: &AtomicBool, raw: bool) -> Result<()> {
+        let newline = if raw { "\r\n" } else { "\n" };
+        let mut stdout = io::stdout();
+        match buffer.split_whitespace().nth(1) {
+            _ if !raw => write!(stdout, "Markdown rendering needs a terminal that supports raw mode{}", newline)?,
+            Some("on") => {
+                render_markdown.store(true, Ordering::Relaxed);
+                write!(stdout, "Markdown rendering is on{}", newline)?;
+            },
+            Some("off") => {
+                render_markdown.store(false, Ordering::Relaxed);
+                write!(stdout, "Markdown rendering is off{}", newline)?;
+            },
+            Some(other) => write!(stdout, "Unknown option '{}'; use /render on or /render off{}", other, newline)?,
+            None => {
+                let state = if render_markdown.load(Ordering::Relaxed) { "on" } else { "off" };
+                write!(stdout, "Markdown rendering is {}{}", state, newline)?;
+            },
+        }
+        stdout.flush()?;
+        Ok(())
+    }
+    
+    /// List the files mentioned in output, or open the one numbered in the command
+    ///
+    /// The editor takes over the terminal until it exits; output that arrives
+    /// meanwhile is shown afterwards.
+    fn open_file(buffer: &str, links: &Option<Arc<Mutex<Linker>>>, display: &Mutex<Display>, raw: bool) -> Result<()> {
+        let newline = if raw { "\r\n" } else { "\n" };
+        let mut stdout = io::stdout();
+        let refs = links.as_ref()
+            .map(|links| links.lock().unwrap().refs().iter().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
         
-        // Process output directly
-        while let Some(output) = self.output_rx.recv().await {
-            // In raw mode, we need to convert \n to \r\n for proper display
-            let formatted_output = output.replace('\n', "\r\n");
-            
-            // Write to stdout
-            stdout.write_all(formatted_output.as_bytes())?;
+        let Some(number) = buffer.split_whitespace().nth(1) else {
+            if refs.is_empty() {
+                write!(stdout, "No files have been mentioned yet{}", newline)?;
+            } else {
+                write!(stdout, "Files mentioned, most recent first:{}", newline)?;
+                for (i, file_ref) in refs.iter().enumerate() {
+                    write!(stdout, "  {:>2}  {}{}", i + 1, file_ref.display(), newline)?;
+                }
+                write!(stdout, "Open one with /open <n>{}", newline)?;
+            }
             stdout.flush()?;
+            return Ok(());
+        };
+        let Some(file_ref) = number.parse::<usize>().ok().and_then(|n| refs.get(n.wrapping_sub(1))) else {
+            write!(stdout, "No file numbered {}; /open lists them{}", number, newline)?;
+            stdout.flush()?;
+            return Ok(());
+        };
+        
+        let editor = ["VISUAL", "EDITOR"].iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|editor| !editor.trim().is_empty())
+            .unwrap_or_else(|| if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() });
+        let command = links::editor_command(&editor, file_ref);
+        
+        display.lock().unwrap().paused = true;
+        if raw {
+            disable_raw_mode()?;
+        }
+        let status = std::process::Command::new(&command[0]).args(&command[1..]).status();
+        if raw {
+            enable_raw_mode()?;
+        }
+        if let Err(e) = status {
+            write!(stdout, "Failed to run {}: {}{}", command[0], e, newline)?;
+        }
+        Self::resume_display(display)
+    }
+    
+    /// Handle a key while copy mode is open, returning whether it closed
+    fn handle_copy_key(copy_mode: &mut CopyMode, key_event: KeyEvent) -> bool {
+        if copy_mode.is_searching() {
+            match key_event.code {
+                KeyCode::Esc => copy_mode.cancel_search(),
+                KeyCode::Enter => copy_mode.submit_search(),
+                KeyCode::Backspace => copy_mode.pop_search(),
+                KeyCode::Char(c) => copy_mode.push_search(c),
+                _ => return false,
+            }
+        } else {
+            match (key_event.code, key_event.modifiers) {
+                (KeyCode::Esc, _) | (KeyCode::Char('q'), _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => return true,
+                (KeyCode::Char('y'), _) | (KeyCode::Enter, _) => {
+                    if let Err(e) = clipboard::copy(&copy_mode.selection()) {
+                        log::warn!("Failed to copy to the clipboard: {}", e);
+                    }
+                    return true;
+                },
+                (KeyCode::Up, _) | (KeyCode::Char('k'), _) => copy_mode.move_by(-1),
+                (KeyCode::Down, _) | (KeyCode::Char('j'), _) => copy_mode.move_by(1),
+                (KeyCode::PageUp, _) | (KeyCode::Char('b'), _) => copy_mode.page(-1),
+                (KeyCode::PageDown, _) | (KeyCode::Char('f'), _) | (KeyCode::Char(' '), _) => copy_mode.page(1),
+                (KeyCode::Home, _) | (KeyCode::Char('g'), KeyModifiers::NONE) => copy_mode.top(),
+                (KeyCode::End, _) | (KeyCode::Char('G'), _) => copy_mode.bottom(),
+                (KeyCode::Char('/'), _) => copy_mode.start_search(),
+                (KeyCode::Char('n'), _) => {
+                    copy_mode.search(false);
+                },
+                (KeyCode::Char('N'), _) => {
+                    copy_mode.search(true);
+                },
+                (KeyCode::Char('v'), _) => copy_mode.toggle_mark(),
+                _ => return false,
+            }
         }
         
-        // Ensure raw mode is disabled
-        let _ = disable_raw_mode();
+        let _ = copy_mode.render(terminal_size().0 as usize);
+        false
+    }
+    
+    /// Read whole lines, for terminals that can't do raw mode
+    fn spawn_line_input(&self) {
+        let input_tx = self.input_tx.clone();
+        let command_tx = self.command_tx.clone();
+        let links = self.links.clone();
+        let display = Arc::clone(&self.display);
+        let render_markdown = Arc::clone(&self.render_markdown);
+        let macros = self.macros.clone();
+        let aliases = Arc::clone(&self.aliases);
+        let native_commands = Arc::clone(&self.native_commands);
+        
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            let mut line = String::new();
+            loop {
+                line.clear();
+                match stdin.lock().read_line(&mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {},
+                }
+                
+                let line = line.trim_end_matches(['\r', '\n']);
+                let routed = line.starts_with('/').then(|| route_command(line, &aliases, &native_commands));
+                let (line, to_cli) = match &routed {
+                    Some(Route::Cli(command)) => (command.as_str(), true),
+                    Some(Route::Grill(command)) => (command.as_str(), false),
+                    None => (line, false),
+                };
+                if to_cli {
+                    if let Err(e) = input_tx.send(format!("{}\r", line)) {
+                        eprintln!("Failed to send input: {}", e);
+                    }
+                } else if Self::is_open_command(line) {
+                    if let Err(e) = Self::open_file(line, &links, &display, false) {
+                        eprintln!("Failed to open file: {}", e);
+                    }
+                } else if Self::is_render_command(line) {
+                    let _ = Self::set_rendering(line, &render_markdown, false);
+                } else if line.starts_with('/') {
+                    if let Some(macros) = &macros {
+                        macros.lock().unwrap().record(line);
+                    }
+                    Self::process_command_buffer(line, &command_tx);
+                } else if let Err(e) = input_tx.send(format!("{}\r", line)) {
+                    eprintln!("Failed to send input: {}", e);
+                }
+            }
+        });
+        
+        // Without raw mode, Ctrl+C reaches grill as a signal rather than a key
+        let command_tx = self.command_tx.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                let _ = command_tx.send(Command::Quit);
+            }
+        });
+    }
+    
+    /// Gather output for up to `flush_interval`, or `batch_size` bytes, before writing it
+    pub fn set_output_batching(&mut self, flush_interval: Duration, batch_size: usize) {
+        self.flush_interval = flush_interval;
+        self.batch_size = batch_size.max(1);
+    }
+    
+    /// Mirror all output to `output_mirror`, keeping recent output in `replay`
+    ///
+    /// Lets control clients watch the session and redraw the screen when they
+    /// attach part way through.
+    pub fn set_output_mirror(
+        &mut self,
+        output_mirror: broadcast::Sender<String>,
+        replay: Arc<Mutex<String>>,
+    ) {
+        self.output_mirror = Some((output_mirror, replay));
+    }
+    
+    /// Offer a fuzzy finder over the given tasks for `/task switch`
+    pub fn set_task_source(&mut self, task_source: TaskSource) {
+        self.task_source = Some(task_source);
+    }
+    
+    /// Offer a fuzzy finder over the messages sent to the CLI for `/history input`
+    pub fn set_history_source(&mut self, history_source: HistorySource) {
+        self.history_source = Some(history_source);
+    }
+    
+    /// Build the command for a task switch, previewing it with `--dry-run`
+    fn switch_command(task_name: &str, flags: &[&str]) -> Command {
+        if flags.contains(&"--dry-run") {
+            Command::PreviewSwitch(task_name.to_string())
+        } else {
+            Command::SwitchTask(task_name.to_string())
+        }
+    }
+    
+    /// Check whether a command asks to pick the task to switch to
+    fn is_task_picker_command(buffer: &str) -> bool {
+        buffer.split_whitespace().eq(["/task", "switch"])
+    }
+    
+    /// Check whether a command should open the input history picker
+    fn is_history_picker_command(buffer: &str) -> bool {
+        buffer.split_whitespace().eq(["/history", "input"])
+    }
+    
+    /// Handle a key while a picker is open, returning whether it closed
+    fn handle_picker_key(
+        picker: &mut Picker,
+        action: PickerAction,
+        key_event: KeyEvent,
+        command_tx: &broadcast::Sender<Command>,
+        input_tx: &broadcast::Sender<String>,
+    ) -> bool {
+        match (key_event.code, key_event.modifiers) {
+            (KeyCode::Esc, _) | (KeyCode::Char('c'), KeyModifiers::CONTROL) => {
+                let _ = picker.clear();
+                return true;
+            },
+            (KeyCode::Enter, _) => {
+                let _ = picker.clear();
+                match (picker.selected(), action) {
+                    (Some(task_name), PickerAction::SwitchTask) => {
+                        if let Err(e) = command_tx.send(Command::SwitchTask(task_name)) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                    (Some(message), PickerAction::Recall) => {
+                        if let Err(e) = input_tx.send(message) {
+                            eprintln!("Failed to send input: {}", e);
+                        }
+                    },
+                    (None, _) => {},
+                }
+                return true;
+            },
+            (KeyCode::Up, _) | (KeyCode::Char('p'), KeyModifiers::CONTROL) => picker.up(),
+            (KeyCode::Down, _) | (KeyCode::Tab, _) | (KeyCode::Char('n'), KeyModifiers::CONTROL) => picker.down(),
+            (KeyCode::Backspace, _) => picker.pop(),
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => picker.push(c),
+            _ => return false,
+        }
+        
+        let _ = picker.render();
+        false
+    }
+    
+    /// Send output to the mirror and append it to the replay buffer
+    fn mirror_output(&self, output: &str) {
+        if let Some((output_mirror, replay)) = &self.output_mirror {
+            {
+                let mut replay = replay.lock().unwrap();
+                replay.push_str(output);
+                if replay.len() > REPLAY_LIMIT {
+                    let mut cut = replay.len() - REPLAY_LIMIT;
+                    while !replay.is_char_boundary(cut) {
+                        cut += 1;
+                    }
+                    replay.drain(..cut);
+                }
+            }
+            
+            // No attached clients is not an error
+            let _ = output_mirror.send(output.to_string());
+        }
+    }
+    
+    /// Forward output to the mirror only, without touching the terminal
+    ///
+    /// Used by headless sessions: nothing is written to stdout and raw mode is
+    /// never enabled.
+    pub async fn bridge(&mut self) -> Result<()> {
+        while let Some(output) = self.output_rx.recv().await {
+            self.mirror_output(&output);
+        }
         
         Ok(())
     }
     
     /// Process command buffer and send appropriate command
-    fn process_command_buffer(
-        buffer: &str, 
-        command_tx: &broadcast::Sender<Command>,
-        input_tx: &broadcast::Sender<String>
-    ) {
-        let parts: Vec<&str> = buffer.trim().split_whitespace().collect();
+    ///
+    /// Commands grill doesn't know are sent on as `Command::Unknown`, for the
+    /// session to pass to the CLI or explain.
+    pub fn process_command_buffer(buffer: &str, command_tx: &broadcast::Sender<Command>) {
+        let parts: Vec<&str> = buffer.split_whitespace().collect();
         
         if parts.is_empty() {
             return;
@@ -216,8 +1034,19 @@ impl IoHandler {
                         }
                     },
                     Some(&"delete") if parts.len() > 2 => {
+                        let task_name = parts[2].to_string();
+                        let command = if parts[3..].contains(&"--force") {
+                            Command::DeleteTask(task_name)
+                        } else {
+                            Command::ConfirmDeleteTask(task_name)
+                        };
+                        if let Err(e) = command_tx.send(command) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                    Some(&"restore") if parts.len() > 2 => {
                         let task_name = parts[2];
-                        if let Err(e) = command_tx.send(Command::DeleteTask(task_name.to_string())) {
+                        if let Err(e) = command_tx.send(Command::RestoreTask(task_name.to_string())) {
                             eprintln!("Failed to send command: {}", e);
                         }
                     },
@@ -226,8 +1055,36 @@ impl IoHandler {
                             eprintln!("Failed to send command: {}", e);
                         }
                     },
+                    Some(&"done") => {
+                        let archive = parts[2..].contains(&"--archive");
+                        let task = parts[2..].iter().find(|part| !part.starts_with("--")).map(|task| task.to_string());
+                        if let Err(e) = command_tx.send(Command::TaskDone { task, archive }) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                    Some(&"block") => {
+                        let reason = parts[2..].join(" ");
+                        if let Err(e) = command_tx.send(Command::BlockTask(reason)) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                    Some(&"switch") => {
+                        // Without a picker, show the tasks to choose from
+                        let command = match parts[2..].iter().find(|part| !part.starts_with("--")) {
+                            Some(task_name) => Self::switch_command(task_name, &parts[2..]),
+                            None => Command::ListTasks,
+                        };
+                        if let Err(e) = command_tx.send(command) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                    Some(&"time") => {
+                        if let Err(e) = command_tx.send(Command::TaskTime) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
                     Some(task_name) => {
-                        if let Err(e) = command_tx.send(Command::SwitchTask(task_name.to_string())) {
+                        if let Err(e) = command_tx.send(Self::switch_command(task_name, &parts[2..])) {
                             eprintln!("Failed to send command: {}", e);
                         }
                     },
@@ -238,30 +1095,426 @@ impl IoHandler {
                     },
                 }
             },
+            "/context" => {
+                match parts.get(1) {
+                    Some(&"diff") => {
+                        let staged = parts.get(2).is_some_and(|arg| *arg == "--staged" || *arg == "staged");
+                        if let Err(e) = command_tx.send(Command::ContextDiff { staged }) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                    Some(&"map") => {
+                        if let Err(e) = command_tx.send(Command::ContextMap) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                    Some(&"run") if parts.len() > 2 => {
+                        // Keep the command's own spacing and quoting
+                        let command = buffer.trim_start()[parts[0].len()..].trim_start()[parts[1].len()..].trim();
+                        if let Err(e) = command_tx.send(Command::ContextRun(command.to_string())) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                    _ => {
+                        let help = Command::HelpTopic { topic: Some("context".to_string()), page: 1 };
+                        if let Err(e) = command_tx.send(help) {
+                            eprintln!("Failed to send command: {}", e);
+                        }
+                    },
+                }
+            },
+            "/history" if parts.get(1) == Some(&"input") => {
+                let number = parts.get(2).and_then(|number| number.parse().ok());
+                if let Err(e) = command_tx.send(Command::InputHistory(number)) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/stop" => {
+                if let Err(e) = command_tx.send(Command::Stop) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/retry" => {
+                let edit = parts.get(1) == Some(&"edit");
+                if let Err(e) = command_tx.send(Command::Retry { edit }) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/prompt" => {
+                let command = match parts.get(1) {
+                    Some(name) => {
+                        // Keep the arguments' own spacing
+                        let args = buffer.trim_start()[parts[0].len()..].trim_start()[name.len()..].trim();
+                        Command::SendPrompt { name: name.to_string(), args: args.to_string() }
+                    },
+                    None => Command::ListPrompts,
+                };
+                if let Err(e) = command_tx.send(command) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/macro" => {
+                let command = match (parts.get(1), parts.get(2)) {
+                    (Some(&"record"), Some(name)) => Command::MacroRecord(name.to_string()),
+                    (Some(&"play"), Some(name)) => Command::MacroPlay(name.to_string()),
+                    (Some(&"stop"), _) => Command::MacroStop,
+                    _ => Command::ListMacros,
+                };
+                if let Err(e) = command_tx.send(command) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/note" if parts.len() > 1 => {
+                let text = buffer.trim_start()[parts[0].len()..].trim();
+                if let Err(e) = command_tx.send(Command::AddNote(text.to_string())) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/notes" => {
+                if let Err(e) = command_tx.send(Command::ShowNotes) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/copy" => {
+                if let Err(e) = command_tx.send(Command::Copy) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/paste" => {
+                let force = parts[1..].contains(&"--force");
+                if let Err(e) = command_tx.send(Command::Paste { force }) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
             "/quit" => {
                 if let Err(e) = command_tx.send(Command::Quit) {
                     eprintln!("Failed to send command: {}", e);
                 }
             },
+            "/restart" => {
+                if let Err(e) = command_tx.send(Command::Restart) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/version" => {
+                if let Err(e) = command_tx.send(Command::Version) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
+            "/alias" => {
+                if let Err(e) = command_tx.send(Command::ListAliases) {
+                    eprintln!("Failed to send command: {}", e);
+                }
+            },
             "/help" => {
-                if let Err(e) = command_tx.send(Command::Help) {
+                // `/help 2` pages through the topic list, `/help tasks 2` through a topic
+                let command = match (parts.get(1), parts.get(2)) {
+                    (None, _) => Command::Help,
+                    (Some(page), None) if page.parse::<usize>().is_ok() => Command::HelpTopic {
+                        topic: None,
+                        page: page.parse().unwrap_or(1),
+                    },
+                    (Some(topic), page) => Command::HelpTopic {
+                        topic: Some(topic.to_string()),
+                        page: page.and_then(|page| page.parse().ok()).unwrap_or(1),
+                    },
+                };
+                if let Err(e) = command_tx.send(command) {
                     eprintln!("Failed to send command: {}", e);
                 }
             },
             _ => {
-                // Unknown command - pass it through to the underlying CLI
-                let full_command = format!("{}\r", buffer);
-                if let Err(e) = input_tx.send(full_command) {
-                    eprintln!("Failed to send command to CLI: {}", e);
+                if let Err(e) = command_tx.send(Command::Unknown(buffer.trim().to_string())) {
+                    eprintln!("Failed to send command: {}", e);
                 }
             }
         }
     }
 }
 
+/// Prefix that gives a command to grill even when the CLI has one by the same name
+pub const GRILL_PREFIX: &str = "/grill";
+
+/// Where a typed command goes, once aliases are expanded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Route {
+    /// One of grill's commands, or one it doesn't know
+    Grill(String),
+    /// One of the CLI's own commands, sent to it as typed
+    Cli(String),
+}
+
+/// Decide whether a typed command is grill's or the CLI's
+///
+/// The CLI's declared commands win over grill's, so `/grill context diff`
+/// reaches grill's `/context` when the CLI has a `/context` of its own.
+pub fn route_command(buffer: &str, aliases: &HashMap<String, String>, native_commands: &NativeCommands) -> Route {
+    let trimmed = buffer.trim();
+    if let Some(rest) = trimmed.strip_prefix(GRILL_PREFIX).filter(|rest| rest.starts_with(' ')) {
+        let rest = rest.trim_start();
+        let command = if rest.starts_with('/') { rest.to_string() } else { format!("/{}", rest) };
+        return Route::Grill(expand_alias(&command, aliases));
+    }
+    
+    let command = expand_alias(trimmed, aliases);
+    if native_commands.declares(&command) {
+        Route::Cli(command)
+    } else {
+        Route::Grill(command)
+    }
+}
+
+/// Replace a command's first word with what it is an alias for, keeping its arguments
+///
+/// Aliases may be written with or without their slash, and aren't expanded
+/// again, so one can't loop.
+pub fn expand_alias(buffer: &str, aliases: &HashMap<String, String>) -> String {
+    let trimmed = buffer.trim_start();
+    let name = trimmed.split_whitespace().next().unwrap_or("");
+    let Some(bare) = name.strip_prefix('/') else {
+        return buffer.to_string();
+    };
+    let expansion = aliases.get(name).or_else(|| aliases.get(bare));
+    match expansion {
+        Some(expansion) => format!("{}{}", expansion.trim(), &trimmed[name.len()..]),
+        None => buffer.to_string(),
+    }
+}
+
+/// Explain that a command isn't known, suggesting the command it's closest to
+pub fn unknown_command_message(command: &str) -> String {
+    let name = command.split_whitespace().next().unwrap_or(command);
+    let mut message = format!("Unknown command: {} (type /help)", name);
+    if let Some(suggestion) = suggest_command(name) {
+        message.push_str(&format!(". Did you mean {}?", suggestion));
+    }
+    message
+}
+
+/// Find grill's command a mistyped one most likely meant
+pub fn suggest_command(name: &str) -> Option<&'static str> {
+    COMMANDS.iter()
+        .map(|command| (edit_distance(name, command), *command))
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, command)| command)
+}
+
+/// Count the insertions, deletions and substitutions that turn one string into another
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Put the terminal back the way grill found it
+pub fn restore_terminal() {
+    let _ = execute!(io::stdout(), DisableMouseCapture, DisableBracketedPaste);
+    let _ = disable_raw_mode();
+}
+
 impl Drop for IoHandler {
     fn drop(&mut self) {
         // Ensure raw mode is disabled when the handler is dropped
         let _ = disable_raw_mode();
     }
 }
+
+/// Wait for output, then gather whatever follows it for up to `interval`
+///
+/// Returns early once `max_size` bytes have been gathered, and `None` when the
+/// channel is closed and empty.
+pub async fn next_batch(output_rx: &mut mpsc::Receiver<String>, interval: Duration, max_size: usize) -> Option<String> {
+    let mut batch = output_rx.recv().await?;
+    let deadline = tokio::time::Instant::now() + interval;
+    while batch.len() < max_size {
+        match tokio::time::timeout_at(deadline, output_rx.recv()).await {
+            Ok(Some(output)) => batch.push_str(&output),
+            Ok(None) | Err(_) => break,
+        }
+    }
+    Some(batch)
+}
+
+/// Get the terminal's width and height, assuming 80x24 if it doesn't say
+fn terminal_size() -> (u16, u16) {
+    term::size().ok().filter(|(width, height)| *width > 0 && *height > 1).unwrap_or((80, 24))
+}
+
+/// Where the translator is in the output, which may split escape sequences across chunks
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum EscapeState {
+    #[default]
+    Text,
+    /// After ESC
+    Escape,
+    /// In a control sequence (`ESC [`), which ends at its final byte
+    Csi,
+    /// In an OSC, DCS, SOS, PM or APC string, which ends at BEL or `ESC \`
+    String,
+    /// After ESC inside a string
+    StringEscape,
+}
+
+/// Turns bare `\n` into `\r\n` for a terminal in raw mode
+///
+/// Output from Windows' ConPTY already uses `\r\n`, which is left alone.
+/// Escape sequences pass through untouched, so CLIs that move the cursor,
+/// set the title or use the alternate screen display correctly.
+#[derive(Debug, Default)]
+pub struct NewlineTranslator {
+    state: EscapeState,
+    previous: Option<char>,
+}
+
+impl NewlineTranslator {
+    /// Translate the next chunk of output
+    pub fn translate(&mut self, output: &str) -> String {
+        let mut formatted = String::with_capacity(output.len());
+        for c in output.chars() {
+            self.state = match (self.state, c) {
+                (EscapeState::Text, '\x1b') => EscapeState::Escape,
+                (EscapeState::Text, '\n') => {
+                    if self.previous != Some('\r') {
+                        formatted.push('\r');
+                    }
+                    EscapeState::Text
+                },
+                (EscapeState::Text, _) => EscapeState::Text,
+                (EscapeState::Escape, '[') => EscapeState::Csi,
+                (EscapeState::Escape, ']' | 'P' | 'X' | '^' | '_') => EscapeState::String,
+                (EscapeState::Escape, _) => EscapeState::Text,
+                (EscapeState::Csi, '\x40'..='\x7e') => EscapeState::Text,
+                (EscapeState::Csi, _) => EscapeState::Csi,
+                (EscapeState::String, '\x07') => EscapeState::Text,
+                (EscapeState::String, '\x1b') => EscapeState::StringEscape,
+                (EscapeState::String, _) => EscapeState::String,
+                (EscapeState::StringEscape, '\\') => EscapeState::Text,
+                (EscapeState::StringEscape, _) => EscapeState::String,
+            };
+            formatted.push(c);
+            self.previous = Some(c);
+        }
+        formatted
+    }
+}
+
+/// The line being typed in command mode, with a cursor that can move within it
+///
+/// Edits return what to print to bring the echoed line up to date, so the
+/// text after the cursor is redrawn when something is inserted or deleted
+/// in the middle.
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    chars: Vec<char>,
+    cursor: usize,
+    /// Characters echoed, and where the terminal's cursor is among them
+    shown_len: usize,
+    shown_cursor: usize,
+}
+
+impl LineEditor {
+    /// Get the line as typed so far
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+    
+    /// Check whether nothing has been typed
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+    
+    /// Forget the line, which has been submitted or abandoned
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+    
+    /// Insert text at the cursor
+    pub fn insert(&mut self, text: &str) -> String {
+        for c in text.chars() {
+            self.chars.insert(self.cursor, c);
+            self.cursor += 1;
+        }
+        self.redraw()
+    }
+    
+    /// Delete the character before the cursor
+    pub fn backspace(&mut self) -> String {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.chars.remove(self.cursor);
+        }
+        self.redraw()
+    }
+    
+    /// Move the cursor one character left
+    pub fn left(&mut self) -> String {
+        self.cursor = self.cursor.saturating_sub(1);
+        self.redraw()
+    }
+    
+    /// Move the cursor one character right
+    pub fn right(&mut self) -> String {
+        self.cursor = (self.cursor + 1).min(self.chars.len());
+        self.redraw()
+    }
+    
+    /// Move the cursor to the start of the line
+    pub fn home(&mut self) -> String {
+        self.cursor = 0;
+        self.redraw()
+    }
+    
+    /// Move the cursor to the end of the line
+    pub fn end(&mut self) -> String {
+        self.cursor = self.chars.len();
+        self.redraw()
+    }
+    
+    /// Delete the word before the cursor, and the spaces after it (Ctrl+W)
+    pub fn delete_word(&mut self) -> String {
+        let mut start = self.cursor;
+        while start > 0 && self.chars[start - 1] == ' ' {
+            start -= 1;
+        }
+        while start > 0 && self.chars[start - 1] != ' ' {
+            start -= 1;
+        }
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+        self.redraw()
+    }
+    
+    /// Delete everything before the cursor (Ctrl+U)
+    pub fn kill_line(&mut self) -> String {
+        self.chars.drain(..self.cursor);
+        self.cursor = 0;
+        self.redraw()
+    }
+    
+    /// Rewrite the echoed line from its start, blanking what's left of a longer one
+    fn redraw(&mut self) -> String {
+        let mut out = String::new();
+        if self.shown_cursor > 0 {
+            out.push_str(&format!("\x1b[{}D", self.shown_cursor));
+        }
+        out.extend(self.chars.iter());
+        let blank = self.shown_len.saturating_sub(self.chars.len());
+        out.push_str(&" ".repeat(blank));
+        let back = self.chars.len() + blank - self.cursor;
+        if back > 0 {
+            out.push_str(&format!("\x1b[{}D", back));
+        }
+        self.shown_len = self.chars.len();
+        self.shown_cursor = self.cursor;
+        out
+    }
+}
@@ -0,0 +1,318 @@
+use anyhow::{Result, Context, anyhow};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::config::KeysConfig;
+
+/// Longest gap between the presses of a key bound twice, such as `esc esc`
+pub const DOUBLE_PRESS: Duration = Duration::from_millis(500);
+
+/// Something grill does when its keys are pressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    CommandMode,
+    StopGeneration,
+    SwitchTaskPicker,
+    CopyMode,
+    Detach,
+}
+
+impl Action {
+    /// Check whether the action's keys are grill's while a command is being typed
+    ///
+    /// The others would take keys away from the command, or act on the CLI
+    /// while the command is half typed.
+    fn in_command_mode(self) -> bool {
+        matches!(self, Action::Quit | Action::CopyMode | Action::Detach)
+    }
+}
+
+/// One key with its modifiers, as in `ctrl+g`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// Parse a key such as `ctrl+g`, `alt+x`, `esc`, `f2` or `/`
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let (modifier_names, key) = if text == "+" {
+            ("", "+")
+        } else if let Some(modifier_names) = text.strip_suffix("++") {
+            (modifier_names, "+")
+        } else {
+            text.rsplit_once('+').unwrap_or(("", text))
+        };
+        
+        let mut modifiers = KeyModifiers::NONE;
+        for name in modifier_names.split('+').filter(|name| !name.is_empty()) {
+            modifiers |= match name.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "option" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(anyhow!("Unknown modifier '{}' in key '{}'", name, text)),
+            };
+        }
+        
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(c.to_ascii_lowercase()),
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => name.strip_prefix('f')
+                    .and_then(|number| number.parse::<u8>().ok())
+                    .filter(|number| (1..=12).contains(number))
+                    .map(KeyCode::F)
+                    .ok_or_else(|| anyhow!("Unknown key '{}'", text))?,
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+    
+    /// Check whether a key press is this key
+    ///
+    /// Shift is part of the character typed, so it's ignored for characters.
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        let ignored = match self.code {
+            KeyCode::Char(_) => KeyModifiers::SHIFT,
+            _ => KeyModifiers::NONE,
+        };
+        if self.modifiers - ignored != event.modifiers - ignored {
+            return false;
+        }
+        match (self.code, event.code) {
+            (KeyCode::Char(bound), KeyCode::Char(pressed)) if self.modifiers.contains(KeyModifiers::CONTROL) => {
+                // Some terminals report Ctrl+] as Ctrl+5
+                bound == pressed.to_ascii_lowercase() || (bound == ']' && pressed == '5')
+            },
+            (bound, pressed) => bound == pressed,
+        }
+    }
+    
+    /// What the key sends to a program in a terminal, for a prefix passed on to the CLI
+    fn input(&self) -> Option<String> {
+        match self.code {
+            KeyCode::Char(c) if self.modifiers == KeyModifiers::CONTROL && ('@'..='_').contains(&c.to_ascii_uppercase()) => {
+                Some((((c.to_ascii_uppercase() as u8) & 0x1f) as char).to_string())
+            },
+            KeyCode::Char(c) if self.modifiers - KeyModifiers::SHIFT == KeyModifiers::NONE => Some(c.to_string()),
+            KeyCode::Tab if self.modifiers == KeyModifiers::NONE => Some("\t".to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "Shift+")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => write!(f, "{}", c.to_ascii_uppercase()),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(number) => write!(f, "F{}", number),
+            KeyCode::PageUp => write!(f, "PageUp"),
+            KeyCode::PageDown => write!(f, "PageDown"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+/// The keys bound to an action: one key, or a prefix and a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyBinding {
+    keys: Vec<KeyChord>,
+}
+
+impl KeyBinding {
+    /// Parse a binding such as `ctrl+c`, `esc esc` or `ctrl+g [`; an empty one binds nothing
+    pub fn parse(text: &str) -> Result<Option<Self>> {
+        let keys = text.split_whitespace()
+            .map(KeyChord::parse)
+            .collect::<Result<Vec<_>>>()?;
+        match keys.len() {
+            0 => Ok(None),
+            1 | 2 => Ok(Some(Self { keys })),
+            _ => Err(anyhow!("'{}' has more than two keys", text)),
+        }
+    }
+    
+    /// Check whether the binding is one key pressed twice, which has to be quick
+    fn is_double(&self) -> bool {
+        self.keys.len() == 2 && self.keys[0] == self.keys[1]
+    }
+}
+
+impl fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self.keys.iter().map(KeyChord::to_string).collect();
+        write!(f, "{}", keys.join(" "))
+    }
+}
+
+/// What a key press turned out to be
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyMatch {
+    /// The key completes a binding
+    Action(Action),
+    /// The key starts a binding, so it waits for the next one
+    Pending,
+    /// The key isn't bound: pass on what the prefix before it would have
+    /// sent, if anything, then handle the key as usual
+    Unbound { held: Option<String> },
+    /// A prefix pressed twice, which sends the prefix key itself to the CLI
+    Literal(String),
+    /// The leader key and a letter, running a grill command
+    Command(String),
+}
+
+/// The `[keys]` bindings, following key presses through prefixes
+#[derive(Debug, Clone)]
+pub struct KeyMap {
+    bindings: Vec<(Action, KeyBinding)>,
+    leader: Option<KeyChord>,
+    /// Letters after the leader, and the commands they run, by letter
+    leader_keys: Vec<(char, String)>,
+    /// The prefix pressed last, and when
+    held: Option<(KeyChord, Instant)>,
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        Self::new(&KeysConfig::default()).expect("default key bindings are valid")
+    }
+}
+
+impl KeyMap {
+    /// Parse the configured bindings
+    pub fn new(config: &KeysConfig) -> Result<Self> {
+        let configured = [
+            (Action::Quit, "quit", &config.quit),
+            (Action::CommandMode, "command_mode", &config.command_mode),
+            (Action::StopGeneration, "stop_generation", &config.stop_generation),
+            (Action::SwitchTaskPicker, "switch_task_picker", &config.switch_task_picker),
+            (Action::CopyMode, "copy_mode", &config.copy_mode),
+            (Action::Detach, "detach", &config.detach),
+        ];
+        let mut bindings = Vec::new();
+        for (action, name, text) in configured {
+            let binding = KeyBinding::parse(text)
+                .context(format!("Invalid key binding {} = \"{}\" under [keys]", name, text))?;
+            if let Some(binding) = binding {
+                bindings.push((action, binding));
+            }
+        }
+        
+        let leader = match config.leader.trim() {
+            "" => None,
+            text => Some(KeyChord::parse(text).context(format!("Invalid key binding leader = \"{}\" under [keys]", text))?),
+        };
+        let mut leader_keys = config.leader_keys.iter()
+            .map(|(key, command)| {
+                let mut chars = key.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(letter), None) => Ok((letter, command.clone())),
+                    _ => Err(anyhow!("Leader key '{}' under [keys.leader_keys] should be a single character", key)),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        leader_keys.sort();
+        Ok(Self { bindings, leader, leader_keys, held: None })
+    }
+    
+    /// Get the keys bound to an action, if any
+    pub fn binding(&self, action: Action) -> Option<&KeyBinding> {
+        self.bindings.iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, binding)| binding)
+    }
+    
+    /// Get the leader key and the commands run by it and each letter, if there's a leader
+    pub fn leader(&self) -> Option<(KeyChord, &[(char, String)])> {
+        self.leader.map(|leader| (leader, self.leader_keys.as_slice()))
+    }
+    
+    /// Follow a key press, pressed at `now`
+    ///
+    /// A prefix that isn't followed by one of its bindings is passed on only
+    /// when the next key isn't bound either.
+    pub fn press(&mut self, event: &KeyEvent, in_command_mode: bool, now: Instant) -> KeyMatch {
+        let bindings: Vec<&(Action, KeyBinding)> = self.bindings.iter()
+            .filter(|(action, _)| !in_command_mode || action.in_command_mode())
+            .collect();
+        
+        let mut held_input = None;
+        if let Some((held, at)) = self.held.take() {
+            let completed = bindings.iter().find(|(_, binding)| {
+                binding.keys.len() == 2
+                    && binding.keys[0] == held
+                    && binding.keys[1].matches(event)
+                    && (!binding.is_double() || now.duration_since(at) <= DOUBLE_PRESS)
+            });
+            if let Some((action, _)) = completed {
+                return KeyMatch::Action(*action);
+            }
+            if let Some(command) = self.leader_command(held, event, in_command_mode) {
+                return KeyMatch::Command(command);
+            }
+            held_input = held.input();
+            if held.matches(event) {
+                if let Some(input) = held_input {
+                    return KeyMatch::Literal(input);
+                }
+            }
+        }
+        
+        if let Some((action, _)) = bindings.iter().find(|(_, binding)| binding.keys.len() == 1 && binding.keys[0].matches(event)) {
+            return KeyMatch::Action(*action);
+        }
+        if let Some((_, binding)) = bindings.iter().find(|(_, binding)| binding.keys.len() == 2 && binding.keys[0].matches(event)) {
+            self.held = Some((binding.keys[0], now));
+            return KeyMatch::Pending;
+        }
+        if let Some(leader) = self.leader.filter(|leader| !in_command_mode && leader.matches(event)) {
+            self.held = Some((leader, now));
+            return KeyMatch::Pending;
+        }
+        KeyMatch::Unbound { held: held_input }
+    }
+    
+    /// Find the command run by a letter pressed after the leader
+    fn leader_command(&self, held: KeyChord, event: &KeyEvent, in_command_mode: bool) -> Option<String> {
+        if in_command_mode || self.leader != Some(held) || event.modifiers - KeyModifiers::SHIFT != KeyModifiers::NONE {
+            return None;
+        }
+        let KeyCode::Char(letter) = event.code else {
+            return None;
+        };
+        self.leader_keys.iter()
+            .find(|(key, _)| *key == letter)
+            .map(|(_, command)| command.clone())
+    }
+}
@@ -5,3 +5,48 @@ pub mod process;
 pub mod io;
 pub mod session;
 pub mod cli_handler;
+pub mod plugin;
+pub mod control;
+pub mod ansi;
+pub mod runner;
+pub mod hooks;
+pub mod workflow;
+pub mod git;
+pub mod context;
+pub mod events;
+pub mod workspaces;
+pub mod clis;
+pub mod template;
+pub mod frontmatter;
+pub mod migrate;
+pub mod picker;
+pub mod transcript;
+pub mod lock;
+pub mod sidecar;
+pub mod watch;
+pub mod heartbeat;
+pub mod metrics;
+pub mod bench;
+pub mod crash;
+pub mod switch;
+pub mod signal;
+pub mod terminal;
+pub mod clipboard;
+pub mod scrollback;
+pub mod mouse;
+pub mod links;
+pub mod filters;
+pub mod history;
+pub mod macros;
+pub mod backchannel;
+pub mod mcp;
+pub mod triggers;
+pub mod idle;
+pub mod sandbox;
+pub mod remote;
+pub mod confirm;
+pub mod help;
+pub mod version;
+pub mod keys;
+pub mod markdown;
+pub mod startup;
@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::Regex;
+
+/// Most file references remembered for `/open`
+pub const MAX_FILE_REFS: usize = 50;
+
+/// A file mentioned in the CLI's output, optionally at a line and column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRef {
+    /// The path as it was written
+    pub text: String,
+    /// Where the file is
+    pub path: PathBuf,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+}
+
+impl FileRef {
+    /// Describe the reference the way it was written
+    pub fn display(&self) -> String {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", self.text, line, column),
+            (Some(line), None) => format!("{}:{}", self.text, line),
+            _ => self.text.clone(),
+        }
+    }
+}
+
+fn pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(r"(?:~|\.{1,2})?/?(?:[\w.@+-]+/)*[\w@+-][\w.@+-]*\.[A-Za-z0-9]+(?::(\d+))?(?::(\d+))?")
+            .expect("file reference pattern is valid")
+    })
+}
+
+/// Find references to files under `root` in plain text
+///
+/// Only paths that exist are returned, which rules out things like version
+/// numbers and abbreviations. Returns the byte range of each reference.
+pub fn find(text: &str, root: &Path) -> Vec<(std::ops::Range<usize>, FileRef)> {
+    pattern().captures_iter(text)
+        .filter_map(|captures| {
+            let whole = captures.get(0)?;
+            // Part of a longer word, such as a URL, rather than a path
+            if text[..whole.start()].chars().next_back().is_some_and(|c| c.is_alphanumeric() || c == ':' || c == '/') {
+                return None;
+            }
+            
+            let line_len = captures.get(1).map_or(0, |m| m.len() + 1) + captures.get(2).map_or(0, |m| m.len() + 1);
+            let written = &whole.as_str()[..whole.len() - line_len];
+            let path = match written.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir()?.join(rest),
+                None => root.join(written),
+            };
+            if !path.is_file() {
+                return None;
+            }
+            
+            Some((whole.range(), FileRef {
+                text: written.to_string(),
+                path,
+                line: captures.get(1).and_then(|m| m.as_str().parse().ok()),
+                column: captures.get(2).and_then(|m| m.as_str().parse().ok()),
+            }))
+        })
+        .collect()
+}
+
+/// Wrap text in an OSC 8 hyperlink to a file
+pub fn hyperlink(text: &str, path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let url = path.to_string_lossy().replace(' ', "%20");
+    let url = if url.starts_with('/') { format!("file://{}", url) } else { format!("file:///{}", url.replace('\\', "/")) };
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Remembers files mentioned in output, and links them on the way to the screen
+#[derive(Debug)]
+pub struct Linker {
+    root: PathBuf,
+    hyperlinks: bool,
+    /// Most recent first
+    refs: VecDeque<FileRef>,
+}
+
+impl Linker {
+    /// Look for files under `root`, wrapping them in hyperlinks if `hyperlinks` is set
+    pub fn new(root: PathBuf, hyperlinks: bool) -> Self {
+        Self { root, hyperlinks, refs: VecDeque::new() }
+    }
+    
+    /// Record the files mentioned in a chunk of output, returning it with links added
+    ///
+    /// Escape sequences are passed through, and never searched for paths.
+    pub fn process(&mut self, output: &str) -> String {
+        let mut processed = String::with_capacity(output.len());
+        for (text, is_escape) in split_escapes(output) {
+            if is_escape {
+                processed.push_str(text);
+                continue;
+            }
+            
+            let mut last = 0;
+            for (range, file_ref) in find(text, &self.root) {
+                processed.push_str(&text[last..range.start]);
+                if self.hyperlinks {
+                    processed.push_str(&hyperlink(&text[range.clone()], &file_ref.path));
+                } else {
+                    processed.push_str(&text[range.clone()]);
+                }
+                last = range.end;
+                self.remember(file_ref);
+            }
+            processed.push_str(&text[last..]);
+        }
+        processed
+    }
+    
+    /// Get the remembered references, most recent first
+    pub fn refs(&self) -> &VecDeque<FileRef> {
+        &self.refs
+    }
+    
+    fn remember(&mut self, file_ref: FileRef) {
+        self.refs.retain(|existing| *existing != file_ref);
+        self.refs.push_front(file_ref);
+        self.refs.truncate(MAX_FILE_REFS);
+    }
+}
+
+/// Split output into runs of text and whole escape sequences
+fn split_escapes(output: &str) -> Vec<(&str, bool)> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut chars = output.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c != '\x1b' {
+            continue;
+        }
+        if i > start {
+            parts.push((&output[start..i], false));
+        }
+        
+        let mut end = output.len();
+        match chars.next() {
+            Some((_, '[')) => {
+                for (j, c) in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        end = j + c.len_utf8();
+                        break;
+                    }
+                }
+            },
+            Some((_, ']' | 'P' | 'X' | '^' | '_')) => {
+                while let Some((j, c)) = chars.next() {
+                    if c == '\x07' {
+                        end = j + 1;
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek().is_some_and(|(_, next)| *next == '\\') {
+                        end = j + 2;
+                        chars.next();
+                        break;
+                    }
+                }
+            },
+            Some((j, c)) => end = j + c.len_utf8(),
+            None => {},
+        }
+        parts.push((&output[i..end], true));
+        start = end;
+    }
+    if start < output.len() {
+        parts.push((&output[start..], false));
+    }
+    parts
+}
+
+/// Build the command that opens a file in `editor`, at its line if known
+///
+/// Editors that take `file:line` are recognized by name; any other editor
+/// gets `+line file`, which vi, vim, nano, emacs and helix all understand.
+pub fn editor_command(editor: &str, file_ref: &FileRef) -> Vec<String> {
+    let mut command: Vec<String> = editor.split_whitespace().map(str::to_string).collect();
+    let program = command.first()
+        .and_then(|program| Path::new(program).file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let path = file_ref.path.to_string_lossy().to_string();
+    
+    match (program.as_str(), file_ref.line) {
+        ("code" | "code-insiders" | "cursor" | "codium", Some(line)) => {
+            command.push("-g".to_string());
+            command.push(format!("{}:{}:{}", path, line, file_ref.column.unwrap_or(1)));
+        },
+        ("subl" | "zed", Some(line)) => command.push(format!("{}:{}", path, line)),
+        (_, Some(line)) => {
+            command.push(format!("+{}", line));
+            command.push(path);
+        },
+        (_, None) => command.push(path),
+    }
+    command
+}
@@ -0,0 +1,84 @@
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+/// The session holding a lock, as written into the lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub task: String,
+    pub started: DateTime<Utc>,
+}
+
+/// An advisory lock on a file, held until it is dropped
+///
+/// The operating system releases the lock when the process exits, so a
+/// crashed session never leaves a stale lock behind.
+#[derive(Debug)]
+pub struct SessionLock {
+    file: File,
+}
+
+impl SessionLock {
+    /// Try to lock `path` for a session running `task`
+    ///
+    /// Returns `None` if another session holds the lock.
+    pub fn acquire(path: &Path, task: &str) -> Result<Option<Self>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context("Failed to create lock directory")?;
+        }
+        
+        // Don't truncate yet: the file describes whoever holds the lock
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .context(format!("Failed to open lock file {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {},
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(e)) => {
+                return Err(e).context(format!("Failed to lock {}", path.display()));
+            },
+        }
+        
+        let holder = LockHolder {
+            pid: std::process::id(),
+            task: task.to_string(),
+            started: Utc::now(),
+        };
+        file.set_len(0)?;
+        file.rewind()?;
+        file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+        file.flush()?;
+        
+        Ok(Some(Self { file }))
+    }
+    
+    /// Record the task the holding session is now running
+    pub fn set_task(&mut self, task: &str) -> Result<()> {
+        let mut content = String::new();
+        self.file.rewind()?;
+        self.file.read_to_string(&mut content)?;
+        let mut holder: LockHolder = serde_json::from_str(&content)?;
+        holder.task = task.to_string();
+        
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        self.file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Read who holds a lock, if the lock file describes anyone
+pub fn holder(path: &Path) -> Option<LockHolder> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
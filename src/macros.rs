@@ -0,0 +1,152 @@
+use anyhow::{Result, Context, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Directory in a task's directory that holds its macros
+pub const MACROS_DIR: &str = "macros";
+
+/// How long the CLI must stay quiet before a step counts as done, when its prompt isn't recognized
+pub const PLAYBACK_QUIET: Duration = Duration::from_secs(2);
+
+/// Longest a step may take before playback moves on anyway
+pub const PLAYBACK_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Check that a macro name is safe to use as a file name
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.len() > 100 {
+        return Err(anyhow!("Macro names must be 1 to 100 characters long"));
+    }
+    if let Some(c) = name.chars().find(|c| !(c.is_alphanumeric() || matches!(c, '-' | '_'))) {
+        return Err(anyhow!("Macro name '{}' cannot contain {:?}", name.escape_default(), c));
+    }
+    Ok(())
+}
+
+/// Get the file a macro is kept in
+pub fn path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.txt", name))
+}
+
+/// Save a macro, one step per line
+pub fn save(dir: &Path, name: &str, steps: &[String]) -> Result<()> {
+    validate_name(name)?;
+    fs::create_dir_all(dir).context("Failed to create macros directory")?;
+    let mut content = steps.join("\n");
+    content.push('\n');
+    crate::environment::write_atomic(&path(dir, name), content).context(format!("Failed to save macro '{}'", name))
+}
+
+/// Load a macro's steps, skipping blank lines
+pub fn load(dir: &Path, name: &str) -> Result<Vec<String>> {
+    validate_name(name)?;
+    let path = path(dir, name);
+    if !path.exists() {
+        return Err(anyhow!("Macro '{}' does not exist", name));
+    }
+    let content = fs::read_to_string(&path).context(format!("Failed to read macro '{}'", name))?;
+    Ok(content.lines()
+        .map(|line| line.trim_end_matches('\r'))
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// List the macros in a directory, sorted by name
+pub fn list(dir: &Path) -> Result<Vec<String>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "txt" {
+                return None;
+            }
+            Some(path.file_stem()?.to_string_lossy().to_string())
+        })
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Whether a line controls macros, and so is never recorded or played
+pub fn is_macro_command(line: &str) -> bool {
+    line.split_whitespace().next() == Some("/macro")
+}
+
+/// The macro being recorded and the one being played, shared by the session and the terminal
+///
+/// Messages to the CLI and slash commands are recorded as they were typed,
+/// so a saved macro can be read and edited by hand.
+#[derive(Debug, Default)]
+pub struct MacroState {
+    recording: Option<(String, Vec<String>)>,
+    playing: Option<String>,
+}
+
+impl MacroState {
+    /// Start recording a macro, discarding any recording in progress
+    pub fn start_recording(&mut self, name: &str) {
+        self.recording = Some((name.to_string(), Vec::new()));
+    }
+    
+    /// Stop recording, returning the macro's name and steps
+    pub fn stop_recording(&mut self) -> Option<(String, Vec<String>)> {
+        self.recording.take()
+    }
+    
+    /// Get the name of the macro being recorded
+    pub fn recording(&self) -> Option<&str> {
+        self.recording.as_ref().map(|(name, _)| name.as_str())
+    }
+    
+    /// Record a line typed by the user, if a macro is being recorded
+    pub fn record(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() || is_macro_command(line) {
+            return;
+        }
+        if let Some((_, steps)) = &mut self.recording {
+            steps.push(line.to_string());
+        }
+    }
+    
+    /// Mark a macro as playing
+    pub fn start_playing(&mut self, name: &str) {
+        self.playing = Some(name.to_string());
+    }
+    
+    /// Stop playback after the current step, returning the macro's name
+    pub fn stop_playing(&mut self) -> Option<String> {
+        self.playing.take()
+    }
+    
+    /// Get the name of the macro being played
+    pub fn playing(&self) -> Option<&str> {
+        self.playing.as_deref()
+    }
+}
+
+/// Wait until the CLI is ready for the next step
+///
+/// `activity` carries, for each chunk of output, whether it ended at the
+/// CLI's prompt. Without a recognized prompt, the step is done once output
+/// has started and then stayed quiet for `quiet`.
+pub async fn wait_for_prompt(activity: &mut broadcast::Receiver<bool>, quiet: Duration, timeout: Duration) {
+    let deadline = tokio::time::Instant::now() + timeout;
+    let mut seen_output = false;
+    loop {
+        let wait = if seen_output { quiet } else { deadline.saturating_duration_since(tokio::time::Instant::now()) };
+        match tokio::time::timeout(wait, activity.recv()).await {
+            Ok(Ok(true)) => return,
+            Ok(Ok(false)) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => seen_output = true,
+            Ok(Err(broadcast::error::RecvError::Closed)) | Err(_) => return,
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return;
+        }
+    }
+}
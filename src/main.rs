@@ -1,6 +1,8 @@
 use clap::{Parser, Subcommand};
 use anyhow::Result;
 use std::env;
+use std::io::{IsTerminal, Read};
+use std::time::Duration;
 
 mod environment;
 mod task;
@@ -9,11 +11,60 @@ mod process;
 mod io;
 mod session;
 mod cli_handler;
+mod plugin;
+mod control;
+mod ansi;
+mod runner;
+mod hooks;
+mod workflow;
+mod git;
+mod context;
+mod events;
+mod workspaces;
+mod clis;
+mod template;
+mod frontmatter;
+mod migrate;
+mod picker;
+mod transcript;
+mod lock;
+mod sidecar;
+mod watch;
+mod heartbeat;
+mod metrics;
+mod bench;
+mod crash;
+mod switch;
+mod signal;
+mod terminal;
+mod clipboard;
+mod scrollback;
+mod mouse;
+mod links;
+mod filters;
+mod history;
+mod macros;
+mod backchannel;
+mod mcp;
+mod triggers;
+mod idle;
+mod sandbox;
+mod remote;
+mod confirm;
+mod help;
+mod version;
+mod keys;
+mod markdown;
+mod startup;
 
 /// Grill - An interactive CLI tool to augment existing LLM CLIs
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
+    /// Output format for non-interactive modes (`grill run`, piped stdin)
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    output: runner::OutputFormat,
+    
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -29,7 +80,179 @@ enum Commands {
         /// Name of the task to start
         #[arg(short, long)]
         task: Option<String>,
+        
+        /// Run the session in the background; reconnect with `grill attach`
+        #[arg(short, long)]
+        detach: bool,
+        
+        /// Run without a terminal (used internally by --detach)
+        #[arg(long, hide = true)]
+        headless: bool,
+        
+        /// Start a session for the project at this path instead of the current one
+        #[arg(short, long)]
+        workspace: Option<std::path::PathBuf>,
+    },
+    
+    /// Send a single prompt to a task's CLI, print the response and exit
+    Run {
+        /// Task whose CLI and context to use (defaults to the current task)
+        #[arg(short, long)]
+        task: Option<String>,
+        
+        /// Seconds to wait for each response
+        #[arg(long, default_value_t = 300)]
+        timeout: u64,
+        
+        /// Prompt to send
+        prompt: String,
+    },
+    
+    /// Attach this terminal to a detached session (Ctrl+] to detach again)
+    Attach {
+        /// Attach to the session running this task instead of the primary session
+        #[arg(short, long)]
+        task: Option<String>,
+        
+        /// Only watch the output; input is never sent to the session
+        #[arg(short, long)]
+        observe: bool,
+    },
+    
+    /// Watch the output of all running sessions, prefixed with their task names
+    View {
+        /// Only show these tasks
+        #[arg(short, long)]
+        task: Vec<String>,
+    },
+    
+    /// Send a message to the running session's CLI (reads stdin if no message is given)
+    Send {
+        /// Only send if the session is running this task
+        #[arg(short, long)]
+        task: Option<String>,
+        
+        /// Send the message even if it's larger than `[input] confirm_bytes`
+        #[arg(short, long)]
+        force: bool,
+        
+        /// Message to send
+        message: Option<String>,
+    },
+    
+    /// Control a running grill session through its control socket
+    Ctl {
+        /// Control the session running this task instead of the primary session
+        #[arg(short, long)]
+        task: Option<String>,
+        
+        #[command(subcommand)]
+        action: CtlAction,
+    },
+    
+    /// Manage tasks
+    Task {
+        #[command(subcommand)]
+        action: TaskAction,
+    },
+    
+    /// Show usage statistics for the sessions run in this workspace
+    Stats {
+        /// Print the statistics as JSON (same as `--output json`)
+        #[arg(long)]
+        json: bool,
+    },
+    
+    /// Measure how quickly CLIs start, answer a trivial prompt and take in context
+    Bench {
+        /// CLI to measure, by name under [clis] or as a command; repeat to compare several (defaults to default_cli)
+        #[arg(long)]
+        cli: Vec<String>,
+        
+        /// Times to send the prompt
+        #[arg(long, default_value_t = 3)]
+        rounds: usize,
+        
+        /// Bytes of context to paste in; 0 skips it
+        #[arg(long, default_value_t = bench::DEFAULT_CONTEXT_BYTES)]
+        context_bytes: usize,
+        
+        /// Prompt to time the round trips with
+        #[arg(long, default_value = bench::DEFAULT_PROMPT)]
+        prompt: String,
+        
+        /// Seconds to wait for the CLI each time
+        #[arg(long, default_value_t = 300)]
+        timeout: u64,
+    },
+    
+    /// List the grill workspaces used on this machine, most recent first
+    Workspaces,
+    
+    /// List the configured CLIs, where they are installed and their versions
+    Clis,
+    
+    /// Run workflows defined in .grill/workflows
+    Workflow {
+        #[command(subcommand)]
+        action: WorkflowAction,
+    },
+    
+    /// Serve this workspace's tasks to an LLM CLI over MCP (stdio)
+    Mcp,
+    
+    /// Show grill's version
+    Version {
+        /// Also show the build, the workspace, and the current task's CLI and its version
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TaskAction {
+    /// List all tasks
+    List {
+        /// Show the time spent in each task
+        #[arg(long)]
+        with_time: bool,
+    },
+    
+    /// Show which tasks depend on which
+    Graph,
+}
+
+#[derive(Subcommand)]
+enum WorkflowAction {
+    /// List available workflows
+    List,
+    
+    /// Run a workflow's steps in order and print a summary
+    Run {
+        /// Name of the workflow (.grill/workflows/<name>.toml)
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum CtlAction {
+    /// Show the status of the running session
+    Status,
+    
+    /// Switch the running session to another task
+    SwitchTask {
+        /// Name of the task to switch to
+        task: String,
     },
+    
+    /// Send text to the wrapped CLI
+    Send {
+        /// Text to send
+        text: String,
+    },
+    
+    /// Quit the running session
+    Quit,
 }
 
 #[tokio::main]
@@ -38,51 +261,565 @@ async fn main() -> Result<()> {
     
     let cli = Cli::parse();
     let current_dir = env::current_dir()?;
-    let env = environment::Environment::new(current_dir);
+    let env = environment::Environment::discover(&current_dir);
     
     match cli.command {
         Some(Commands::Init) => {
+            // Always initialize here, even inside another project
+            let env = environment::Environment::new(current_dir);
             println!("Initializing grill environment...");
             env.init()?;
+            workspaces::record(&env.get_root_dir());
             println!("Grill environment initialized successfully.");
             Ok(())
         },
-        Some(Commands::Start { task }) => {
+        Some(Commands::Start { task, detach, headless, workspace }) => {
+            let env = match workspace {
+                Some(path) => {
+                    let env = environment::Environment::discover(&path);
+                    if !env.exists() {
+                        eprintln!("Error: No grill environment found in {}.", path.display());
+                        std::process::exit(1);
+                    }
+                    // The CLI and everything grill runs should work in that project
+                    env::set_current_dir(env.get_root_dir())?;
+                    env
+                },
+                None => env,
+            };
+            
             if !env.exists() {
                 eprintln!("Error: No grill environment found. Run 'grill init' first.");
                 std::process::exit(1);
             }
+            workspaces::record(&env.get_root_dir());
+            upgrade_workspace(&env)?;
+            
+            let task = resolve_start_task(&env, task)?;
+            
+            if detach {
+                return spawn_detached(&env, task);
+            }
+            
+            if !headless && !std::io::stdin().is_terminal() {
+                let task = match task {
+                    Some(task) => task,
+                    None => env.get_current_task()?,
+                };
+                return run_batch(&env, &task, cli.output).await;
+            }
             
             println!("Starting grill session...");
-            start_session(env, task).await?;
+            start_session(env, task, headless).await?;
             Ok(())
         },
+        Some(Commands::Attach { task, observe }) => {
+            let socket_path = resolve_socket_path(&env, task.as_deref())?;
+            if observe {
+                control::observe(&socket_path).await?;
+                println!("\nSession ended.");
+            } else {
+                let config = config::Config::load(&env.get_config_path())?;
+                control::attach(&socket_path, keys::KeyMap::new(&config.keys)?).await?;
+                println!("\nDetached from session.");
+            }
+            Ok(())
+        },
+        Some(Commands::Run { task, timeout, prompt }) => {
+            if !env.exists() {
+                eprintln!("Error: No grill environment found. Run 'grill init' first.");
+                std::process::exit(1);
+            }
+            
+            let task = match task {
+                Some(task) => task,
+                None => env.get_current_task()?,
+            };
+            run_once(&env, &task, &prompt, Duration::from_secs(timeout), cli.output).await
+        },
+        Some(Commands::View { task }) => {
+            let mut sessions = env.list_session_sockets()?;
+            if !task.is_empty() {
+                sessions.retain(|(name, _)| task.contains(name));
+            }
+            control::view(sessions).await
+        },
+        Some(Commands::Send { task, force, message }) => {
+            let text = match message {
+                Some(message) => message,
+                None => {
+                    let mut text = String::new();
+                    std::io::stdin().read_to_string(&mut text)?;
+                    text.trim_end().to_string()
+                }
+            };
+            
+            if text.is_empty() {
+                eprintln!("Error: Nothing to send.");
+                std::process::exit(1);
+            }
+            let limit = config::Config::load(&env.get_config_path())?.input.confirm_bytes;
+            if !force && confirm::needs_confirmation(&text, limit) {
+                eprint!("Error: The message is {}", confirm::summary(&text));
+                eprintln!("Run with --force to send it anyway.");
+                std::process::exit(1);
+            }
+            
+            let socket_path = resolve_socket_path(&env, task.as_deref())?;
+            let request = control::ControlRequest::Send { text, task };
+            print_control_response(control::send_request(&socket_path, &request)?)
+        },
+        Some(Commands::Ctl { task, action }) => {
+            let request = match action {
+                CtlAction::Status => control::ControlRequest::Status,
+                CtlAction::SwitchTask { task } => control::ControlRequest::SwitchTask { task },
+                CtlAction::Send { text } => control::ControlRequest::Send { text, task: None },
+                CtlAction::Quit => control::ControlRequest::Quit,
+            };
+            let socket_path = resolve_socket_path(&env, task.as_deref())?;
+            print_control_response(control::send_request(&socket_path, &request)?)
+        },
+        Some(Commands::Task { action }) => {
+            if !env.exists() {
+                eprintln!("Error: No grill environment found. Run 'grill init' first.");
+                std::process::exit(1);
+            }
+            
+            match action {
+                TaskAction::List { with_time } => {
+                    let current = env.get_current_task()?;
+                    let times = if with_time {
+                        events::task_times(&events::load(&env.get_events_path())?)
+                    } else {
+                        Default::default()
+                    };
+                    
+                    for (task, last_used) in env.list_recent_tasks()? {
+                        let marker = if task == current { "*" } else { " " };
+                        let config = env.get_task_config(&task)?;
+                        let mut status = if env.is_global_task(&task) {
+                            "global".to_string()
+                        } else {
+                            config.status.to_string()
+                        };
+                        if let Some(reason) = &config.blocked_reason {
+                            status.push_str(&format!(": {}", reason));
+                        }
+                        let last_used = task::format_last_used(last_used);
+                        if with_time {
+                            let secs = times.get(&task).copied().unwrap_or(0);
+                            println!("{} {:<20} {:<16}  {:>8}  {}", marker, task, last_used, events::format_duration(secs), status);
+                        } else {
+                            println!("{} {:<20} {:<16}  {}", marker, task, last_used, status);
+                        }
+                    }
+                    Ok(())
+                },
+                TaskAction::Graph => {
+                    print!("{}", task::dependency_graph(&env)?);
+                    Ok(())
+                },
+            }
+        },
+        Some(Commands::Workspaces) => {
+            let registry = workspaces::registry_path()
+                .ok_or_else(|| anyhow::anyhow!("Could not determine the user data directory"))?;
+            let known = workspaces::load(&registry)?;
+            if known.is_empty() {
+                println!("No workspaces recorded yet.");
+                return Ok(());
+            }
+            
+            for workspace in known {
+                let missing = if workspace.path.join(".grill").is_dir() { "" } else { "  (missing)" };
+                println!(
+                    "{}  {}{}",
+                    workspace.last_used.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                    workspace.path.display(),
+                    missing
+                );
+            }
+            Ok(())
+        },
+        Some(Commands::Bench { cli: names, rounds, context_bytes, prompt, timeout }) => {
+            let config = config::Config::load(&env.get_config_path())?;
+            let names = if names.is_empty() {
+                vec![config.get_default_cli().to_string()]
+            } else {
+                names
+            };
+            let options = bench::BenchOptions { rounds, context_bytes, prompt, timeout: Duration::from_secs(timeout) };
+            let working_dir = env.exists().then(|| env.get_root_dir());
+            
+            let mut failed = false;
+            for name in names {
+                match bench::run(&name, &config.resolve_cli(&name), working_dir.clone(), &config, &options).await {
+                    Ok(result) => match cli.output {
+                        runner::OutputFormat::Text => println!("{}", result.report()),
+                        runner::OutputFormat::Json => println!("{}", serde_json::to_string(&result)?),
+                    },
+                    Err(e) => {
+                        eprintln!("Error: Benchmarking '{}' failed: {:#}", name, e);
+                        failed = true;
+                    },
+                }
+            }
+            if failed {
+                std::process::exit(1);
+            }
+            Ok(())
+        },
+        Some(Commands::Clis) => {
+            let config = config::Config::load(&env.get_config_path())?;
+            for cli in clis::list(&config) {
+                let marker = if cli.is_default { "*" } else { " " };
+                let location = match &cli.binary {
+                    Some(binary) => {
+                        let version = clis::version(binary).unwrap_or_else(|| "unknown version".to_string());
+                        format!("{} ({})", binary.display(), version)
+                    },
+                    None => "not installed".to_string(),
+                };
+                println!("{} {:<12} {:<24} {}", marker, cli.name, cli.command, location);
+            }
+            
+            if let Err(e) = clis::check_installed(&config, config.get_default_cli()) {
+                eprintln!("\nError: the default CLI {}", e);
+                std::process::exit(1);
+            }
+            Ok(())
+        },
+        Some(Commands::Version { verbose }) => {
+            if !verbose {
+                println!("{}", version::grill_version());
+                return Ok(());
+            }
+            
+            let mut info = version::VersionInfo::default();
+            if env.exists() {
+                let task = env.get_current_task()?;
+                let cli = session::Session::get_cli_for_task(&env, &task)?;
+                let handler = cli_handler::CliHandlerFactory::create_handler(cli.command_line());
+                info = version::VersionInfo {
+                    workspace: Some(env.get_root_dir()),
+                    task: Some(task),
+                    cli: Some(cli.command_line()),
+                    handler: Some(handler.name()),
+                };
+            }
+            print!("{}", version::report(&info));
+            Ok(())
+        },
+        Some(Commands::Stats { json }) => {
+            if !env.exists() {
+                eprintln!("Error: No grill environment found. Run 'grill init' first.");
+                std::process::exit(1);
+            }
+            
+            let events = events::load(&env.get_events_path())?;
+            let stats = events::Stats::from_events(&events);
+            if json || cli.output == runner::OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&stats)?);
+                return Ok(());
+            }
+            if events.is_empty() {
+                println!("No sessions recorded yet.");
+                return Ok(());
+            }
+            print!("{}", stats.report());
+            Ok(())
+        },
+        Some(Commands::Workflow { action }) => {
+            if !env.exists() {
+                eprintln!("Error: No grill environment found. Run 'grill init' first.");
+                std::process::exit(1);
+            }
+            
+            match action {
+                WorkflowAction::List => {
+                    let workflows = env.list_workflows()?;
+                    if workflows.is_empty() {
+                        println!("No workflows found in .grill/workflows");
+                    }
+                    for name in workflows {
+                        let description = workflow::Workflow::load(&env.get_workflow_path(&name))
+                            .ok()
+                            .and_then(|w| w.description);
+                        match description {
+                            Some(description) => println!("{} - {}", name, description),
+                            None => println!("{}", name),
+                        }
+                    }
+                    Ok(())
+                },
+                WorkflowAction::Run { name } => run_workflow(&env, &name, cli.output).await,
+            }
+        },
+        Some(Commands::Mcp) => {
+            if !env.exists() {
+                eprintln!("Error: No grill environment found. Run 'grill init' first.");
+                std::process::exit(1);
+            }
+            
+            // stdout carries the protocol, so nothing else may be printed there
+            let server = mcp::McpServer::new(env);
+            server.serve(std::io::stdin().lock(), std::io::stdout().lock())
+        },
         None => {
             // Default behavior when no subcommand is provided
             if !env.exists() {
                 eprintln!("Error: No grill environment found. Run 'grill init' first.");
                 std::process::exit(1);
             }
+            workspaces::record(&env.get_root_dir());
+            upgrade_workspace(&env)?;
+            
+            let task = resolve_start_task(&env, None)?;
+            
+            if !std::io::stdin().is_terminal() {
+                let task = match task {
+                    Some(task) => task,
+                    None => env.get_current_task()?,
+                };
+                return run_batch(&env, &task, cli.output).await;
+            }
             
             println!("Starting grill session with default settings...");
-            start_session(env, None).await?;
+            start_session(env, task, false).await?;
             Ok(())
         }
     }
 }
 
-async fn start_session(env: environment::Environment, task_name: Option<String>) -> Result<()> {
-    // Create a new session
-    let mut session = session::Session::new(env);
+/// Upgrade an older workspace to the current layout before using it
+fn upgrade_workspace(env: &environment::Environment) -> Result<()> {
+    if let Some(upgrade) = migrate::migrate(env)? {
+        eprintln!(
+            "Upgraded this workspace from version {} to {} (previous files backed up to {}).",
+            upgrade.from,
+            upgrade.to,
+            upgrade.backup.display()
+        );
+    }
+    Ok(())
+}
+
+/// Pick the task to start when none was given on the command line
+///
+/// A task mapped to the current git branch wins over the last current task,
+/// and becomes the current task.
+fn resolve_start_task(env: &environment::Environment, task: Option<String>) -> Result<Option<String>> {
+    if task.is_some() {
+        return Ok(task);
+    }
     
-    // Start the session
-    session.start(task_name).await?;
+    match env.get_branch_task()? {
+        Some((branch, task)) => {
+            if env.get_current_task()? != task {
+                eprintln!("Using task '{}' for branch '{}'", task, branch);
+                env.set_current_task(&task)?;
+            }
+            Ok(Some(task))
+        },
+        None => Ok(None),
+    }
+}
+
+async fn start_session(env: environment::Environment, task_name: Option<String>, headless: bool) -> Result<()> {
+    // Fail before touching the terminal if the task's CLI isn't installed
+    let task = match &task_name {
+        Some(task) => task.clone(),
+        None => env.get_current_task()?,
+    };
+    let cli = session::Session::get_cli_for_task(&env, &task)?;
+    let config = config::Config::load(&env.get_config_path())?;
+    let cli = match env.get_task_config(&task)?.sandbox {
+        // A sandboxed CLI is installed in its image, and only the engine has to be here
+        Some(engine) => Some(engine.program().to_string()),
+        // The login shell may put the CLI on a PATH grill doesn't have
+        None if config.shell_wrap => None,
+        None => Some(cli.command_line()),
+    };
+    if let Some(cli) = cli {
+        clis::check_installed(&config, &cli)?;
+    }
     
-    // Wait for the session to complete
-    while session.is_running() {
-        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let mut task_name = task_name;
+    loop {
+        // Create a new session
+        let mut session = session::Session::new(env.clone());
+        session.set_headless(headless);
+        
+        // Start the session
+        session.start(task_name).await?;
+        
+        // Wait for the session to complete
+        while session.is_running() {
+            session.check_process()?;
+            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+        }
+        session.shutdown().await;
+        
+        // After /restart the task carries on in a new session with a new CLI
+        match session.restart_task() {
+            Some(task) => task_name = Some(task),
+            None => break,
+        }
     }
     
     println!("Session ended.");
     Ok(())
 }
+
+/// Start a task's CLI, load its context, send one prompt and print the response
+async fn run_once(
+    env: &environment::Environment,
+    task: &str,
+    prompt: &str,
+    limit: Duration,
+    format: runner::OutputFormat,
+) -> Result<()> {
+    let mut runner = runner::Runner::start_for_task(env, task)?;
+    
+    runner.wait_until_ready(limit).await?;
+    runner.load_task_context(env, task, limit).await?;
+    let exchange = runner.exchange(task, prompt, limit).await;
+    
+    runner.stop()?;
+    exchange.print(format);
+    
+    if !exchange.is_ok() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Run a workflow and print a summary report, exiting with an error if any step failed
+async fn run_workflow(env: &environment::Environment, name: &str, format: runner::OutputFormat) -> Result<()> {
+    let path = env.get_workflow_path(name);
+    if !path.exists() {
+        eprintln!("Error: Workflow '{}' not found at {}", name, path.display());
+        std::process::exit(1);
+    }
+    
+    let workflow = workflow::Workflow::load(&path)?;
+    let results = workflow.run(env, format).await?;
+    
+    // JSON output is the per-step records; the report is for people
+    if format == runner::OutputFormat::Text {
+        print!("{}", workflow::format_report(&results, workflow.steps.len()));
+    }
+    
+    let passed = results.len() == workflow.steps.len() && results.iter().all(|r| r.failure.is_none());
+    if !passed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Send newline-delimited prompts from stdin one at a time, printing each response
+///
+/// Used instead of an interactive session when stdin is not a terminal, e.g.
+/// `cat prompts.txt | grill start`.
+async fn run_batch(env: &environment::Environment, task: &str, format: runner::OutputFormat) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    
+    let limit = runner::DEFAULT_RESPONSE_TIMEOUT;
+    let mut runner = runner::Runner::start_for_task(env, task)?;
+    
+    runner.wait_until_ready(limit).await?;
+    runner.load_task_context(env, task, limit).await?;
+    
+    let mut failed = false;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        let prompt = line.trim();
+        if prompt.is_empty() {
+            continue;
+        }
+        
+        let exchange = runner.exchange(task, prompt, limit).await;
+        exchange.print(format);
+        if format == runner::OutputFormat::Text {
+            println!();
+        }
+        
+        if !exchange.is_ok() {
+            failed = true;
+            break;
+        }
+    }
+    
+    runner.stop()?;
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Pick the control socket for a task, preferring the session running it
+fn resolve_socket_path(env: &environment::Environment, task: Option<&str>) -> Result<std::path::PathBuf> {
+    if let Some(name) = task {
+        let task_socket = env.get_task_socket_path(name)?;
+        if task_socket.exists() {
+            return Ok(task_socket);
+        }
+    }
+    Ok(env.get_socket_path())
+}
+
+/// Re-launch grill as a headless background session that outlives this terminal
+fn spawn_detached(env: &environment::Environment, task: Option<String>) -> Result<()> {
+    let log_path = env.get_session_log_path();
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)?;
+    
+    let mut command = std::process::Command::new(env::current_exe()?);
+    command.args(["start", "--headless"]);
+    if let Some(task) = task {
+        command.args(["--task", &task]);
+    }
+    command
+        .stdin(std::process::Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    
+    // A separate process group keeps terminal hangups and Ctrl+C away from the session
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        const DETACHED_PROCESS: u32 = 0x0000_0008;
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP | DETACHED_PROCESS);
+    }
+    
+    let child = command.spawn()?;
+    println!("Started detached grill session (pid {}).", child.id());
+    println!("Run 'grill attach' to connect. Logs: {}", log_path.display());
+    Ok(())
+}
+
+/// Print a control response, exiting with an error status if the request failed
+fn print_control_response(response: control::ControlResponse) -> Result<()> {
+    match response {
+        control::ControlResponse::Ok { message } => println!("{}", message),
+        control::ControlResponse::Status { task, command, pid } => {
+            println!("Task: {}", task);
+            println!("Command: {}", command);
+            println!("PID: {}", pid);
+        },
+        control::ControlResponse::Error { message } => {
+            eprintln!("Error: {}", message);
+            std::process::exit(1);
+        },
+        control::ControlResponse::Output { data } => print!("{}", data),
+    }
+    Ok(())
+}
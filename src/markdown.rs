@@ -0,0 +1,205 @@
+/// Styles used when rendering
+const BOLD: &str = "\x1b[1m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const DIM: &str = "\x1b[2m";
+const CODE: &str = "\x1b[36m";
+const KEYWORD: &str = "\x1b[35m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Words highlighted in fenced code, common to many languages
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "case", "class", "const", "continue", "def", "default",
+    "do", "elif", "else", "enum", "export", "extends", "false", "fn", "for", "from", "func",
+    "function", "if", "impl", "import", "in", "interface", "let", "loop", "match", "mod",
+    "mut", "new", "nil", "None", "null", "package", "pass", "pub", "raise", "return", "self",
+    "static", "struct", "switch", "this", "throw", "trait", "True", "False", "true", "try",
+    "type", "use", "var", "where", "while", "with", "yield",
+];
+
+/// Languages whose comments start with `#`
+const HASH_COMMENTS: &[&str] = &["python", "py", "sh", "bash", "zsh", "ruby", "rb", "yaml", "yml", "toml", "perl"];
+
+/// Pretty-prints markdown in the CLI's output, a line at a time
+///
+/// Lines are rendered when they end. Until then they are shown as they
+/// arrive, and redrawn once complete if rendering changes them. Lines that
+/// already contain escape sequences are left alone, since the CLI styled them
+/// itself.
+#[derive(Debug, Default)]
+pub struct MarkdownRenderer {
+    /// Language of the fenced code block being shown, if in one
+    code_block: Option<String>,
+    /// The line being received
+    partial: String,
+}
+
+impl MarkdownRenderer {
+    /// Render a chunk of output
+    pub fn process(&mut self, output: &str) -> String {
+        let mut rendered = String::with_capacity(output.len());
+        let mut rest = output;
+        while let Some(end) = rest.find('\n') {
+            let shown = self.partial.len();
+            self.partial.push_str(&rest[..end]);
+            rest = &rest[end + 1..];
+            
+            let line = std::mem::take(&mut self.partial);
+            let (text, cr) = match line.strip_suffix('\r') {
+                Some(text) => (text, "\r"),
+                None => (line.as_str(), ""),
+            };
+            let styled = self.render_line(text);
+            if styled == text {
+                rendered.push_str(&line[shown..]);
+            } else {
+                // Redraw the line, which was shown unrendered while it arrived
+                if shown > 0 {
+                    rendered.push_str("\r\x1b[2K");
+                }
+                rendered.push_str(&styled);
+                rendered.push_str(cr);
+            }
+            rendered.push('\n');
+        }
+        
+        self.partial.push_str(rest);
+        rendered.push_str(rest);
+        rendered
+    }
+    
+    /// Render one complete line
+    fn render_line(&mut self, line: &str) -> String {
+        if line.contains('\x1b') {
+            return line.to_string();
+        }
+        let trimmed = line.trim_start();
+        
+        if let Some(fence) = trimmed.strip_prefix("```") {
+            return match self.code_block.take() {
+                Some(_) => format!("{}{}{}", DIM, "\u{2500}".repeat(20), RESET),
+                None => {
+                    let language = fence.trim().to_string();
+                    let label = format!("{}{} {}{}", DIM, "\u{2500}".repeat(3), language, RESET);
+                    self.code_block = Some(language);
+                    label
+                },
+            };
+        }
+        if let Some(language) = &self.code_block {
+            return highlight(line, language);
+        }
+        
+        if trimmed.starts_with('#') {
+            let level = trimmed.chars().take_while(|c| *c == '#').count();
+            if let Some(title) = trimmed[level..].strip_prefix(' ').filter(|_| level <= 6) {
+                let style = if level == 1 { format!("{}{}", BOLD, UNDERLINE) } else { BOLD.to_string() };
+                return format!("{}{}{}", style, inline(title.trim()), RESET);
+            }
+        }
+        if matches!(trimmed, "---" | "***" | "___") {
+            return format!("{}{}{}", DIM, "\u{2500}".repeat(40), RESET);
+        }
+        
+        let indent = &line[..line.len() - trimmed.len()];
+        if let Some(quote) = trimmed.strip_prefix("> ") {
+            return format!("{}{}\u{2502}{} {}", indent, DIM, RESET, inline(quote));
+        }
+        if let Some(item) = trimmed.strip_prefix("- ").or_else(|| trimmed.strip_prefix("* ")) {
+            return format!("{}\u{2022} {}", indent, inline(item));
+        }
+        format!("{}{}", indent, inline(trimmed))
+    }
+}
+
+/// Render `**bold**`, `*italic*`, `_italic_` and `` `code` `` within a line
+fn inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut rendered = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let closing = |marker: &str, from: usize| -> Option<usize> {
+            let marker: Vec<char> = marker.chars().collect();
+            (from..chars.len().saturating_sub(marker.len() - 1))
+                .find(|&j| chars[j..j + marker.len()] == marker[..])
+                .filter(|&j| j > from && !chars[j - 1].is_whitespace())
+        };
+        
+        let styled = if chars[i] == '`' {
+            closing("`", i + 1).map(|end| (CODE, i + 1, end, end + 1))
+        } else if chars[i..].starts_with(&['*', '*']) {
+            closing("**", i + 2).map(|end| (BOLD, i + 2, end, end + 2))
+        } else if matches!(chars[i], '*' | '_')
+            // An underscore inside a word, as in snake_case, isn't emphasis
+            && (i == 0 || !chars[i - 1].is_alphanumeric())
+            && chars.get(i + 1).is_some_and(|c| !c.is_whitespace())
+        {
+            closing(&chars[i].to_string(), i + 1)
+                .filter(|&end| chars.get(end + 1).is_none_or(|c| !c.is_alphanumeric()))
+                .map(|end| (ITALIC, i + 1, end, end + 1))
+        } else {
+            None
+        };
+        
+        match styled {
+            Some((style, start, end, next)) => {
+                rendered.push_str(style);
+                rendered.extend(&chars[start..end]);
+                rendered.push_str(RESET);
+                i = next;
+            },
+            None => {
+                rendered.push(chars[i]);
+                i += 1;
+            },
+        }
+    }
+    rendered
+}
+
+/// Color a line of code: keywords, strings, numbers and comments
+fn highlight(line: &str, language: &str) -> String {
+    let hash_comments = HASH_COMMENTS.contains(&language.to_lowercase().as_str());
+    let chars: Vec<char> = line.chars().collect();
+    let mut highlighted = String::with_capacity(line.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let comment = (c == '/' && chars.get(i + 1) == Some(&'/')) || (c == '#' && hash_comments);
+        if comment {
+            highlighted.push_str(DIM);
+            highlighted.extend(&chars[i..]);
+            highlighted.push_str(RESET);
+            break;
+        }
+        
+        if c == '"' || c == '\'' {
+            let mut end = i + 1;
+            while end < chars.len() && chars[end] != c {
+                end += if chars[end] == '\\' { 2 } else { 1 };
+            }
+            let end = (end + 1).min(chars.len());
+            highlighted.push_str(STRING);
+            highlighted.extend(&chars[i..end]);
+            highlighted.push_str(RESET);
+            i = end;
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = (i..chars.len()).find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_')).unwrap_or(chars.len());
+            let word: String = chars[i..end].iter().collect();
+            if KEYWORDS.contains(&word.as_str()) {
+                highlighted.push_str(&format!("{}{}{}", KEYWORD, word, RESET));
+            } else if c.is_ascii_digit() {
+                highlighted.push_str(&format!("{}{}{}", NUMBER, word, RESET));
+            } else {
+                highlighted.push_str(&word);
+            }
+            i = end;
+        } else {
+            highlighted.push(c);
+            i += 1;
+        }
+    }
+    highlighted
+}
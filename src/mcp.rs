@@ -0,0 +1,198 @@
+use anyhow::{Result, Context, anyhow};
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+
+use crate::environment::Environment;
+use crate::task::{self, Task};
+
+/// MCP protocol version this server speaks
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// JSON-RPC error codes used in responses
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const PARSE_ERROR: i64 = -32700;
+
+/// An MCP server exposing a workspace's tasks as tools
+///
+/// Speaks JSON-RPC over stdio, one message per line, so LLM CLIs that
+/// support MCP can run `grill mcp` and read and update tasks themselves.
+pub struct McpServer {
+    environment: Environment,
+}
+
+impl McpServer {
+    /// Create a server for a workspace
+    pub fn new(environment: Environment) -> Self {
+        Self { environment }
+    }
+    
+    /// Answer requests until the input ends
+    pub fn serve(&self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        for line in input.lines() {
+            let line = line.context("Failed to read MCP request")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle(&message),
+                Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+            };
+            if let Some(response) = response {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Handle one JSON-RPC message, returning the response unless it was a notification
+    pub fn handle(&self, message: &Value) -> Option<Value> {
+        let id = message.get("id")?.clone();
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        
+        let result = match method {
+            "initialize" => json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "grill", "version": env!("CARGO_PKG_VERSION") },
+            }),
+            "ping" => json!({}),
+            "tools/list" => json!({ "tools": tools() }),
+            "tools/call" => {
+                let Some(name) = params.get("name").and_then(Value::as_str) else {
+                    return Some(error_response(id, INVALID_PARAMS, "Missing tool name"));
+                };
+                let arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+                // Tool failures are results the model can read, not protocol errors
+                match self.call_tool(name, &arguments) {
+                    Ok(text) => json!({ "content": [{ "type": "text", "text": text }] }),
+                    Err(e) => json!({ "content": [{ "type": "text", "text": format!("{:#}", e) }], "isError": true }),
+                }
+            },
+            _ => return Some(error_response(id, METHOD_NOT_FOUND, &format!("Unknown method '{}'", method))),
+        };
+        Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+    }
+    
+    /// Run a tool, returning the text shown to the model
+    pub fn call_tool(&self, name: &str, arguments: &Value) -> Result<String> {
+        match name {
+            "list_tasks" => {
+                let current = self.environment.get_current_task().ok();
+                let mut lines = Vec::new();
+                for name in self.environment.list_tasks()? {
+                    let marker = if current.as_deref() == Some(name.as_str()) { " (current)" } else { "" };
+                    let status = self.environment.get_task_config(&name)?.status;
+                    lines.push(format!("{}: {}{}", name, status, marker));
+                }
+                Ok(lines.join("\n"))
+            },
+            "read_state" => {
+                let task = Task::load(&self.environment, &self.task_argument(arguments)?)?;
+                Ok(task.state().unwrap_or("The task has no state yet.").to_string())
+            },
+            "write_state" => {
+                let mut task = Task::load(&self.environment, &self.task_argument(arguments)?)?;
+                task.save_state(string_argument(arguments, "state")?)?;
+                Ok(format!("Replaced the state of task '{}'", task.name()))
+            },
+            "append_state" => {
+                let mut task = Task::load(&self.environment, &self.task_argument(arguments)?)?;
+                task.append_state(string_argument(arguments, "text")?)?;
+                Ok(format!("Updated the state of task '{}'", task.name()))
+            },
+            "create_task" => {
+                let name = string_argument(arguments, "name")?;
+                self.environment.create_task(name)?;
+                if let Some(instructions) = arguments.get("instructions").and_then(Value::as_str) {
+                    let path = Task::load(&self.environment, name)?.instructions_path();
+                    crate::environment::write_atomic(&path, instructions)
+                        .context(format!("Failed to write instructions for task '{}'", name))?;
+                }
+                Ok(format!("Created task '{}'", name))
+            },
+            "add_note" => {
+                let task = self.task_argument(arguments)?;
+                self.environment.add_note(&task, string_argument(arguments, "text")?)?;
+                Ok(format!("Added note to task '{}'", task))
+            },
+            _ => Err(anyhow!("Unknown tool '{}'", name)),
+        }
+    }
+    
+    /// Get the task a tool call names, defaulting to the current task
+    fn task_argument(&self, arguments: &Value) -> Result<String> {
+        match arguments.get("task").and_then(Value::as_str) {
+            Some(task) => Ok(task.to_string()),
+            None => self.environment.get_current_task(),
+        }
+    }
+}
+
+/// Get a required string argument of a tool call
+fn string_argument<'a>(arguments: &'a Value, name: &str) -> Result<&'a str> {
+    arguments.get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| anyhow!("Missing string argument '{}'", name))
+}
+
+/// Build a JSON-RPC error response
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Describe the tools the server offers
+fn tools() -> Value {
+    let task_name = json!({ "type": "string", "description": "Task name; defaults to the current task" });
+    json!([
+        {
+            "name": "list_tasks",
+            "description": "List grill's tasks with their status, marking the current task",
+            "inputSchema": { "type": "object", "properties": {} },
+        },
+        {
+            "name": "read_state",
+            "description": format!("Read a task's {}, where progress on the task is tracked", task::STATE_FILE),
+            "inputSchema": { "type": "object", "properties": { "task": task_name } },
+        },
+        {
+            "name": "write_state",
+            "description": format!("Replace a task's {} with new markdown", task::STATE_FILE),
+            "inputSchema": {
+                "type": "object",
+                "properties": { "task": task_name, "state": { "type": "string" } },
+                "required": ["state"],
+            },
+        },
+        {
+            "name": "append_state",
+            "description": format!("Add text to the end of a task's {}", task::STATE_FILE),
+            "inputSchema": {
+                "type": "object",
+                "properties": { "task": task_name, "text": { "type": "string" } },
+                "required": ["text"],
+            },
+        },
+        {
+            "name": "create_task",
+            "description": "Create a new task, optionally with its instructions",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "name": { "type": "string" }, "instructions": { "type": "string" } },
+                "required": ["name"],
+            },
+        },
+        {
+            "name": "add_note",
+            "description": "Record a timestamped note on a task",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "task": task_name, "text": { "type": "string" } },
+                "required": ["text"],
+            },
+        },
+    ])
+}
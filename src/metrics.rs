@@ -0,0 +1,111 @@
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::events::{self, Latency, Stats};
+
+/// How long the CLI took to answer a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseTime {
+    /// From the message to the first output of the answer
+    pub first_byte: Duration,
+    /// From the first output of the answer to the CLI's prompt
+    pub to_prompt: Duration,
+}
+
+/// Times the CLI's answers to messages
+///
+/// Output with nothing visible in it, such as the echo of the Enter that
+/// submitted the message, doesn't count as the start of an answer.
+#[derive(Debug, Default)]
+pub struct ResponseTimer {
+    sent: Option<Instant>,
+    first_byte: Option<Instant>,
+}
+
+impl ResponseTimer {
+    /// Note a message submitted to the CLI
+    pub fn input(&mut self, now: Instant) {
+        self.sent = Some(now);
+        self.first_byte = None;
+    }
+    
+    /// Note output from the CLI, and whether it ended at the CLI's prompt
+    ///
+    /// Returns how long the answer took once the CLI is back at its prompt.
+    pub fn output(&mut self, now: Instant, output: &str, at_prompt: bool) -> Option<ResponseTime> {
+        let sent = self.sent?;
+        if self.first_byte.is_none() && (at_prompt || output.chars().any(|c| !c.is_whitespace() && !c.is_control())) {
+            self.first_byte = Some(now);
+        }
+        if !at_prompt {
+            return None;
+        }
+        
+        let first_byte = self.first_byte.take().unwrap_or(now);
+        self.sent = None;
+        Some(ResponseTime {
+            first_byte: first_byte.duration_since(sent),
+            to_prompt: now.duration_since(first_byte),
+        })
+    }
+}
+
+/// Format statistics as Prometheus metrics, labelled with the workspace they are for
+pub fn prometheus(stats: &Stats, workspace: &str) -> String {
+    let label = format!("workspace=\"{}\"", escape_label(workspace));
+    let mut text = String::new();
+    let mut counter = |name: &str, help: &str, value: f64| {
+        let _ = writeln!(text, "# HELP {} {}", name, help);
+        let _ = writeln!(text, "# TYPE {} counter", name);
+        let _ = writeln!(text, "{}{{{}}} {}", name, label, value);
+    };
+    counter("grill_sessions_total", "Sessions started.", stats.sessions as f64);
+    counter("grill_session_seconds_total", "Wall-clock time spent in sessions.", stats.duration_secs as f64);
+    counter("grill_messages_total", "Messages sent to the CLI.", stats.messages as f64);
+    counter("grill_crashes_total", "Times the CLI exited with a failure status.", stats.crashes as f64);
+    counter("grill_restarts_total", "Times the CLI was restarted with /restart.", stats.restarts as f64);
+    counter("grill_input_bytes_total", "Bytes sent to the CLI, counted as sessions end.", stats.bytes_in as f64);
+    counter("grill_output_bytes_total", "Bytes received from the CLI, counted as sessions end.", stats.bytes_out as f64);
+    
+    summary(&mut text, "grill_first_byte_seconds", "Time from a message to the first output of the answer.", &label, &stats.time_to_first_byte);
+    summary(&mut text, "grill_answer_seconds", "Time from the first output of an answer to the prompt.", &label, &stats.first_byte_to_prompt);
+    
+    let mut tasks: Vec<_> = stats.tasks.iter().collect();
+    tasks.sort_by(|a, b| a.0.cmp(b.0));
+    let _ = writeln!(text, "# HELP grill_task_messages_total Messages sent to the CLI, by task.");
+    let _ = writeln!(text, "# TYPE grill_task_messages_total counter");
+    for (name, task) in tasks {
+        let _ = writeln!(text, "grill_task_messages_total{{{},task=\"{}\"}} {}", label, escape_label(name), task.messages);
+    }
+    
+    text
+}
+
+/// Write a latency as a Prometheus summary in seconds
+fn summary(text: &mut String, name: &str, help: &str, label: &str, latency: &Latency) {
+    let seconds = |ms: u64| ms as f64 / 1000.0;
+    let _ = writeln!(text, "# HELP {} {}", name, help);
+    let _ = writeln!(text, "# TYPE {} summary", name);
+    if latency.count > 0 {
+        let _ = writeln!(text, "{}{{{},quantile=\"0.5\"}} {}", name, label, seconds(latency.median_ms));
+        let _ = writeln!(text, "{}{{{},quantile=\"0.95\"}} {}", name, label, seconds(latency.p95_ms));
+    }
+    let _ = writeln!(text, "{}_sum{{{}}} {}", name, label, seconds(latency.total_ms));
+    let _ = writeln!(text, "{}_count{{{}}} {}", name, label, latency.count);
+}
+
+/// Escape a Prometheus label value
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Rewrite the metrics textfile from the workspace's event log
+///
+/// The file is replaced in one step, so the collector never reads half of it.
+pub fn write_textfile(path: &Path, events_path: &Path, workspace: &str) -> Result<()> {
+    let stats = Stats::from_events(&events::load(events_path)?);
+    crate::environment::write_atomic(path, prometheus(&stats, workspace).as_bytes())
+        .context(format!("Failed to write {}", path.display()))
+}
@@ -0,0 +1,165 @@
+use anyhow::{Result, Context, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, Config};
+use crate::environment::Environment;
+
+/// Version of the `.grill` layout and config schema this build writes
+pub const CURRENT_VERSION: u32 = 1;
+
+/// A step that upgrades a workspace from one version to the next
+type Migration = fn(&Environment) -> Result<()>;
+
+/// Migrations in order; entry `n` upgrades version `n` to `n + 1`
+const MIGRATIONS: &[Migration] = &[
+    migrate_task_files,
+];
+
+/// Outcome of upgrading a workspace
+#[derive(Debug)]
+pub struct Upgrade {
+    pub from: u32,
+    pub to: u32,
+    /// Where the files were copied before they were changed
+    pub backup: PathBuf,
+}
+
+/// Bring a workspace up to the current version
+///
+/// Config files are copied to `.grill/backups/<timestamp>` before anything is
+/// changed. Returns `None` if the workspace is already current, and fails if it
+/// was written by a newer version of grill.
+pub fn migrate(environment: &Environment) -> Result<Option<Upgrade>> {
+    let config_path = environment.get_config_path();
+    let from = Config::load(&config_path)?.version;
+    
+    if from > CURRENT_VERSION {
+        return Err(anyhow!(
+            "{} is from a newer version of grill (config version {}, this grill supports {}); upgrade grill to use it",
+            config_path.display(),
+            from,
+            CURRENT_VERSION
+        ));
+    }
+    if from == CURRENT_VERSION {
+        return Ok(None);
+    }
+    
+    let backup = back_up(environment)?;
+    for migration in &MIGRATIONS[from as usize..] {
+        migration(environment)?;
+    }
+    set_version(&config_path, CURRENT_VERSION)?;
+    
+    Ok(Some(Upgrade { from, to: CURRENT_VERSION, backup }))
+}
+
+/// Copy the workspace's config files, and any task files, to a new backup directory
+fn back_up(environment: &Environment) -> Result<PathBuf> {
+    let grill_dir = environment.get_grill_dir();
+    let backup = grill_dir
+        .join("backups")
+        .join(chrono::Utc::now().format("%Y%m%d%H%M%S%3f").to_string());
+    fs::create_dir_all(&backup)
+        .context("Failed to create backup directory")?;
+    
+    for name in config::CONFIG_FILE_NAMES {
+        let path = grill_dir.join(name);
+        if path.exists() {
+            fs::copy(&path, backup.join(name))
+                .context(format!("Failed to back up {}", path.display()))?;
+        }
+    }
+    
+    let tasks_dir = grill_dir.join("tasks");
+    if tasks_dir.exists() {
+        copy_dir(&tasks_dir, &backup.join("tasks"))?;
+    }
+    
+    Ok(backup)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)
+                .context(format!("Failed to back up {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Write `version` into a config file, keeping its format
+fn set_version(path: &Path, version: u32) -> Result<()> {
+    let content = if path.exists() {
+        fs::read_to_string(path).context("Failed to read config file")?
+    } else {
+        String::new()
+    };
+    
+    let updated = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml") | Some("yml") => {
+            let mut value: serde_yaml::Mapping = if content.trim().is_empty() {
+                serde_yaml::Mapping::new()
+            } else {
+                serde_yaml::from_str(&content)?
+            };
+            value.insert("version".into(), version.into());
+            serde_yaml::to_string(&value)?
+        },
+        Some("json") => {
+            let mut value: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&content)?;
+            value.insert("version".to_string(), version.into());
+            serde_json::to_string_pretty(&value)?
+        },
+        _ => {
+            let mut table: toml::Table = content.parse()?;
+            table.insert("version".to_string(), toml::Value::Integer(version.into()));
+            toml::to_string(&table)?
+        },
+    };
+    
+    crate::environment::write_atomic(path, updated)
+        .context(format!("Failed to update version of {}", path.display()))
+}
+
+/// Version 0 to 1: early workspaces kept each task in `.grill/tasks/<name>.toml`;
+/// tasks are now directories holding `config.toml`, instructions and state
+fn migrate_task_files(environment: &Environment) -> Result<()> {
+    let tasks_dir = environment.get_grill_dir().join("tasks");
+    if !tasks_dir.exists() {
+        return Ok(());
+    }
+    
+    for entry in fs::read_dir(&tasks_dir)? {
+        let path = entry?.path();
+        if !path.is_file() || path.extension().is_none_or(|extension| extension != "toml") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else {
+            continue;
+        };
+        
+        // A task that already has a directory has been set up by hand since
+        if tasks_dir.join(&name).exists() {
+            log::warn!("Leaving {} alone: task '{}' already has a directory", path.display(), name);
+            continue;
+        }
+        if let Err(e) = crate::environment::validate_task_name(&name) {
+            log::warn!("Leaving {} alone: {}", path.display(), e);
+            continue;
+        }
+        
+        environment.create_task(&name)?;
+        fs::rename(&path, tasks_dir.join(&name).join("config.toml"))
+            .context(format!("Failed to migrate task '{}'", name))?;
+    }
+    
+    Ok(())
+}
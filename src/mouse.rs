@@ -0,0 +1,84 @@
+/// Lines the scrollback moves for each notch of the mouse wheel
+pub const WHEEL_LINES: isize = 3;
+
+/// Parameters of DEC private modes that turn on mouse reporting
+const REPORTING_MODES: &[u32] = &[9, 1000, 1001, 1002, 1003];
+
+/// DEC private mode for SGR-encoded mouse reports
+const SGR_MODE: u32 = 1006;
+
+/// Whether the CLI has asked the terminal to report mouse events
+///
+/// Tracked from the `ESC [ ? … h` and `ESC [ ? … l` sequences in its output,
+/// which may be split across chunks.
+#[derive(Debug, Default)]
+pub struct MouseState {
+    reporting: bool,
+    sgr: bool,
+    /// The private mode sequence being read, after `ESC [ ?`
+    pending: Option<String>,
+    after_escape: bool,
+    after_csi: bool,
+}
+
+impl MouseState {
+    /// Whether the CLI wants mouse events
+    pub fn is_reporting(&self) -> bool {
+        self.reporting
+    }
+    
+    /// Look for mode changes in the CLI's output
+    pub fn observe(&mut self, output: &str) {
+        for c in output.chars() {
+            if let Some(params) = &mut self.pending {
+                match c {
+                    '0'..='9' | ';' if params.len() < 32 => params.push(c),
+                    'h' | 'l' => {
+                        let params = self.pending.take().unwrap_or_default();
+                        self.set_modes(&params, c == 'h');
+                    },
+                    _ => self.pending = None,
+                }
+                continue;
+            }
+            
+            if self.after_csi {
+                self.after_csi = false;
+                if c == '?' {
+                    self.pending = Some(String::new());
+                    continue;
+                }
+            }
+            if self.after_escape {
+                self.after_escape = false;
+                if c == '[' {
+                    self.after_csi = true;
+                    continue;
+                }
+            }
+            self.after_escape = c == '\x1b';
+        }
+    }
+    
+    fn set_modes(&mut self, params: &str, enabled: bool) {
+        for mode in params.split(';').filter_map(|param| param.parse::<u32>().ok()) {
+            if REPORTING_MODES.contains(&mode) {
+                self.reporting = enabled;
+            } else if mode == SGR_MODE {
+                self.sgr = enabled;
+            }
+        }
+    }
+    
+    /// Encode a wheel notch at a 0-based cell the way the CLI asked for
+    pub fn wheel(&self, up: bool, column: u16, row: u16) -> String {
+        let button = if up { 64 } else { 65 };
+        if self.sgr {
+            format!("\x1b[<{};{};{}M", button, column + 1, row + 1)
+        } else {
+            // Sent as text, the legacy encoding only reaches column and row 94
+            let encode = |value: u16| char::from(32 + (value + 1).min(94) as u8);
+            format!("\x1b[M{}{}{}", char::from(32 + button), encode(column), encode(row))
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::io::{self, Write};
+use crossterm::{
+    cursor::{MoveToColumn, MoveUp},
+    queue,
+    terminal::{Clear, ClearType},
+};
+
+/// Most matches shown below the query at once
+const MAX_ROWS: usize = 8;
+
+/// Prompt shown before the query when picking a task
+const PROMPT: &str = "Switch to task: ";
+
+/// An item offered by the picker, such as a task
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickerItem {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Score how well `query` fuzzily matches `candidate`
+///
+/// Every character of the query must appear in the candidate in order,
+/// ignoring case. Matches that are consecutive or start a word score higher.
+/// Returns `None` if the query doesn't match.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        let found = position + candidate[position..].iter().position(|c| *c == wanted)?;
+        
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        
+        previous = Some(found);
+        position = found + 1;
+    }
+    
+    // Prefer shorter candidates among equal matches
+    Some(score * 100 - candidate.len() as i64)
+}
+
+/// Filter items by a query, best matches first
+///
+/// Names count for more than descriptions. An empty query keeps every item
+/// in its original order.
+pub fn filter<'a>(query: &str, items: &'a [PickerItem]) -> Vec<&'a PickerItem> {
+    if query.is_empty() {
+        return items.iter().collect();
+    }
+    
+    let mut matches: Vec<(i64, &PickerItem)> = items.iter()
+        .filter_map(|item| {
+            let by_name = score(query, &item.name).map(|score| score * 2);
+            let by_description = item.description.as_deref().and_then(|description| score(query, description));
+            by_name.max(by_description).map(|score| (score, item))
+        })
+        .collect();
+    // Stable, so equal scores keep their original order
+    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    
+    matches.into_iter().map(|(_, item)| item).collect()
+}
+
+/// Inline fuzzy finder drawn below the cursor
+#[derive(Debug)]
+pub struct Picker {
+    items: Vec<PickerItem>,
+    query: String,
+    selected: usize,
+    prompt: &'static str,
+    empty: &'static str,
+}
+
+impl Picker {
+    /// Create a picker over the given tasks
+    pub fn new(items: Vec<PickerItem>) -> Self {
+        Self::with_prompt(items, PROMPT, "no matching tasks")
+    }
+    
+    /// Create a picker over any items, showing `prompt` before the query and `empty` when nothing matches
+    pub fn with_prompt(items: Vec<PickerItem>, prompt: &'static str, empty: &'static str) -> Self {
+        Self { items, query: String::new(), selected: 0, prompt, empty }
+    }
+    
+    /// Get the items matching the current query
+    pub fn matches(&self) -> Vec<&PickerItem> {
+        filter(&self.query, &self.items)
+    }
+    
+    /// Get the name of the highlighted item
+    pub fn selected(&self) -> Option<String> {
+        self.matches().get(self.selected).map(|item| item.name.clone())
+    }
+    
+    /// Add a character to the query
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+    
+    /// Remove the last character of the query
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+    
+    /// Highlight the previous match
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+    
+    /// Highlight the next match
+    pub fn down(&mut self) {
+        let visible = self.matches().len().min(MAX_ROWS);
+        if self.selected + 1 < visible {
+            self.selected += 1;
+        }
+    }
+    
+    /// Draw the query and matches, leaving the cursor after the query
+    pub fn render(&self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        queue!(stdout, MoveToColumn(0), Clear(ClearType::FromCursorDown))?;
+        write!(stdout, "{}{}", self.prompt, self.query)?;
+        
+        let matches = self.matches();
+        let mut rows = 0;
+        for (i, item) in matches.iter().take(MAX_ROWS).enumerate() {
+            let marker = if i == self.selected { ">" } else { " " };
+            write!(stdout, "\r\n{} {}", marker, item.name)?;
+            if let Some(description) = &item.description {
+                write!(stdout, "  - {}", description)?;
+            }
+            rows += 1;
+        }
+        if matches.is_empty() {
+            write!(stdout, "\r\n  ({})", self.empty)?;
+            rows += 1;
+        }
+        
+        let column = (self.prompt.chars().count() + self.query.chars().count()) as u16;
+        queue!(stdout, MoveUp(rows), MoveToColumn(column))?;
+        stdout.flush()
+    }
+    
+    /// Erase the picker from the screen
+    pub fn clear(&self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        queue!(stdout, MoveToColumn(0), Clear(ClearType::FromCursorDown))?;
+        stdout.flush()
+    }
+}
@@ -0,0 +1,515 @@
+use anyhow::{Result, Context, anyhow};
+use serde::{Serialize, Deserialize};
+use serde_json::{json, Value};
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command as ProcessCommand, Stdio};
+use std::sync::mpsc::{self as std_mpsc, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::cli_handler::{Capabilities, ContextStrategy, ConversationCommands};
+use crate::io::Command;
+
+/// Prefix of executables on PATH that are treated as handler plugins
+pub const PLUGIN_PREFIX: &str = "grill-handler-";
+
+/// Longest grill waits for a plugin to answer a hook before giving up on it
+pub const PLUGIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A handler plugin discovered on PATH
+#[derive(Debug, Clone)]
+pub struct PluginInfo {
+    /// Plugin name (the executable name without the `grill-handler-` prefix)
+    pub name: String,
+    /// Full path to the plugin executable
+    pub path: PathBuf,
+}
+
+/// Discover handler plugins on PATH
+///
+/// The first executable found for a given name wins, mirroring how the shell
+/// resolves commands.
+pub fn discover_plugins() -> Vec<PluginInfo> {
+    let mut plugins: Vec<PluginInfo> = Vec::new();
+    
+    let path_var = match env::var_os("PATH") {
+        Some(path_var) => path_var,
+        None => return plugins,
+    };
+    
+    for dir in env::split_paths(&path_var) {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let file_name = match file_name.to_str() {
+                Some(name) => name,
+                None => continue,
+            };
+            
+            let name = match file_name.strip_prefix(PLUGIN_PREFIX) {
+                Some(name) if !name.is_empty() => name,
+                _ => continue,
+            };
+            
+            let path = entry.path();
+            if !is_executable(&path) || plugins.iter().any(|p| p.name == name) {
+                continue;
+            }
+            
+            plugins.push(PluginInfo {
+                name: name.to_string(),
+                path,
+            });
+        }
+    }
+    
+    plugins
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// A request sent to a plugin, one JSON object per line on its stdin
+#[derive(Debug, Serialize)]
+struct PluginRequest<'a> {
+    hook: &'a str,
+    #[serde(flatten)]
+    params: Value,
+}
+
+/// A response read from a plugin, one JSON object per line on its stdout
+#[derive(Debug, Deserialize)]
+struct PluginResponse {
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Messages a plugin asks grill to display and send to the child process
+#[derive(Debug, Default, Deserialize)]
+struct PluginActions {
+    #[serde(default)]
+    output: Vec<String>,
+    #[serde(default)]
+    input: Vec<String>,
+    #[serde(default)]
+    handled: bool,
+}
+
+/// A grill command as a plugin's `process_command` hook receives it
+///
+/// Kept apart from [`Command`], so reshaping grill's own commands can't
+/// break plugins. `args` are the command's arguments as the user gives them.
+#[derive(Debug, Serialize)]
+struct PluginCommand {
+    name: &'static str,
+    args: Vec<String>,
+}
+
+impl PluginCommand {
+    fn new(name: &'static str, args: impl IntoIterator<Item = String>) -> Self {
+        Self { name, args: args.into_iter().collect() }
+    }
+    
+    fn flag(name: &'static str, flag: &str, set: bool) -> Self {
+        Self::new(name, set.then(|| flag.to_string()))
+    }
+}
+
+impl From<&Command> for PluginCommand {
+    fn from(command: &Command) -> Self {
+        match command {
+            Command::SwitchTask(task) => Self::new("switch_task", [task.clone()]),
+            Command::PreviewSwitch(task) => Self::new("preview_switch", [task.clone()]),
+            Command::ListTasks => Self::new("list_tasks", None),
+            Command::CurrentTask => Self::new("current_task", None),
+            Command::InputHistory(number) => Self::new("input_history", number.map(|number| number.to_string())),
+            Command::Stop => Self::new("stop", None),
+            Command::Retry { edit } => Self::flag("retry", "--edit", *edit),
+            Command::CreateTask(task) => Self::new("create_task", [task.clone()]),
+            Command::DeleteTask(task) => Self::new("delete_task", [task.clone()]),
+            Command::ConfirmDeleteTask(task) => Self::new("confirm_delete_task", [task.clone()]),
+            Command::RestoreTask(task) => Self::new("restore_task", [task.clone()]),
+            Command::TaskTime => Self::new("task_time", None),
+            Command::TaskDone { task, archive } => Self::new(
+                "task_done",
+                task.iter().cloned().chain(archive.then(|| "--archive".to_string())),
+            ),
+            Command::BlockTask(reason) => Self::new("block_task", [reason.clone()]),
+            Command::AddNote(note) => Self::new("add_note", [note.clone()]),
+            Command::ShowNotes => Self::new("show_notes", None),
+            Command::Copy => Self::new("copy", None),
+            Command::Paste { force } => Self::flag("paste", "--force", *force),
+            Command::SendPrompt { name, args } => Self::new(
+                "send_prompt",
+                std::iter::once(name.clone()).chain((!args.is_empty()).then(|| args.clone())),
+            ),
+            Command::ListPrompts => Self::new("list_prompts", None),
+            Command::MacroRecord(name) => Self::new("macro_record", [name.clone()]),
+            Command::MacroStop => Self::new("macro_stop", None),
+            Command::MacroPlay(name) => Self::new("macro_play", [name.clone()]),
+            Command::ListMacros => Self::new("list_macros", None),
+            Command::Version => Self::new("version", None),
+            Command::Help => Self::new("help", None),
+            Command::HelpTopic { topic, page } => Self::new(
+                "help_topic",
+                topic.iter().cloned().chain(std::iter::once(page.to_string())),
+            ),
+            Command::Quit => Self::new("quit", None),
+            Command::Restart => Self::new("restart", None),
+            Command::Detach => Self::new("detach", None),
+            Command::ContextDiff { staged } => Self::flag("context_diff", "--staged", *staged),
+            Command::ContextRun(command) => Self::new("context_run", [command.clone()]),
+            Command::ContextMap => Self::new("context_map", None),
+            Command::ListAliases => Self::new("list_aliases", None),
+            Command::Unknown(command) => Self::new("unknown", [command.clone()]),
+        }
+    }
+}
+
+/// The running plugin process and its pipes
+///
+/// Responses are read on a thread of their own, so a plugin that stops
+/// answering can be given up on rather than holding up the session.
+struct PluginProcess {
+    child: Child,
+    stdin: ChildStdin,
+    responses: std_mpsc::Receiver<String>,
+    /// Set once the plugin has timed out, exited or broken its pipe
+    dead: bool,
+}
+
+impl PluginProcess {
+    fn spawn(path: &Path) -> Result<Self> {
+        let mut child = ProcessCommand::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .context(format!("Failed to spawn plugin '{}'", path.display()))?;
+        
+        let stdin = child.stdin.take()
+            .ok_or_else(|| anyhow!("Failed to open plugin stdin"))?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| anyhow!("Failed to open plugin stdout"))?;
+        
+        let (response_tx, responses) = std_mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if response_tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        
+        Ok(Self {
+            child,
+            stdin,
+            responses,
+            dead: false,
+        })
+    }
+    
+    fn call(&mut self, hook: &str, params: Value) -> Result<Value> {
+        if self.dead {
+            return Err(anyhow!("Plugin has stopped responding"));
+        }
+        
+        let request = serde_json::to_string(&PluginRequest { hook, params })?;
+        
+        let written = writeln!(self.stdin, "{}", request).and_then(|_| self.stdin.flush());
+        if let Err(e) = written {
+            self.stop();
+            return Err(e).context("Failed to write request to plugin");
+        }
+        
+        let line = match self.responses.recv_timeout(PLUGIN_TIMEOUT) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => {
+                self.stop();
+                return Err(anyhow!("Plugin did not answer '{}' within {} seconds", hook, PLUGIN_TIMEOUT.as_secs()));
+            },
+            Err(RecvTimeoutError::Disconnected) => {
+                self.stop();
+                return Err(anyhow!("Plugin exited while handling '{}'", hook));
+            },
+        };
+        
+        let response: PluginResponse = serde_json::from_str(line.trim())
+            .context(format!("Invalid response from plugin for '{}'", hook))?;
+        
+        match response.error {
+            Some(error) => Err(anyhow!("Plugin error in '{}': {}", hook, error)),
+            None => Ok(response.result),
+        }
+    }
+    
+    /// Kill the plugin, after which every call fails at once
+    fn stop(&mut self) {
+        self.dead = true;
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Handler that delegates the CliHandler hooks to an external plugin
+#[derive(Clone)]
+pub struct PluginCliHandler {
+    name: String,
+    command: String,
+    process: Arc<Mutex<PluginProcess>>,
+}
+
+impl PluginCliHandler {
+    /// Spawn the plugin executable and wrap it as a handler for `command`
+    pub fn spawn(info: &PluginInfo, command: String) -> Result<Self> {
+        let process = PluginProcess::spawn(&info.path)?;
+        
+        Ok(Self {
+            name: info.name.clone(),
+            command,
+            process: Arc::new(Mutex::new(process)),
+        })
+    }
+    
+    /// Get the plugin name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
+    /// Check whether the plugin is still answering
+    ///
+    /// Once it has timed out or exited, grill carries on without it: data
+    /// passes through unchanged and the CLI is treated as one grill doesn't know.
+    pub fn is_alive(&self) -> bool {
+        self.process.lock().map(|process| !process.dead).unwrap_or(false)
+    }
+    
+    /// Kill the plugin process, for a plugin that isn't going to be used
+    pub fn stop(&self) {
+        if let Ok(mut process) = self.process.lock() {
+            process.stop();
+        }
+    }
+    
+    fn call(&self, hook: &str, params: Value) -> Result<Value> {
+        let mut process = self.process.lock()
+            .map_err(|_| anyhow!("Plugin '{}' is poisoned", self.name))?;
+        let was_alive = !process.dead;
+        let result = process.call(hook, params);
+        if let (true, true, Err(e)) = (was_alive, process.dead, &result) {
+            log::warn!("Handler plugin '{}' stopped answering, carrying on without it: {:#}", self.name, e);
+        }
+        result
+    }
+    
+    fn call_actions(&self, hook: &str, params: Value) -> Result<PluginActions> {
+        let result = self.call(hook, params)?;
+        if result.is_null() {
+            return Ok(PluginActions::default());
+        }
+        serde_json::from_value(result)
+            .context(format!("Invalid actions from plugin for '{}'", hook))
+    }
+    
+    pub fn get_command(&self) -> &str {
+        &self.command
+    }
+    
+    pub fn process_command(
+        &self,
+        command: Command,
+        output_tx: &mpsc::Sender<String>,
+        current_task: &str,
+    ) -> Result<bool> {
+        let actions = self.call_actions("process_command", json!({
+            "command": PluginCommand::from(&command),
+            "task": current_task,
+        }))?;
+        
+        for output in actions.output {
+            let _ = output_tx.try_send(output);
+        }
+        
+        Ok(actions.handled)
+    }
+    
+    pub fn get_help_text(&self) -> String {
+        match self.call("help_text", json!({})) {
+            Ok(Value::String(text)) => text,
+            _ => format!("\n{} plugin help is unavailable\n", self.name),
+        }
+    }
+    
+    pub fn on_start(
+        &self,
+        task_name: &str,
+        output_tx: &mpsc::Sender<String>,
+    ) -> Result<()> {
+        let actions = self.call_actions("on_start", json!({ "task": task_name }))?;
+        
+        for output in actions.output {
+            let _ = output_tx.try_send(output);
+        }
+        
+        Ok(())
+    }
+    
+    pub fn intercept_input(&self, input: String) -> Result<Option<String>> {
+        match self.call("intercept_input", json!({ "input": &input })) {
+            Ok(Value::String(modified)) => Ok(Some(modified)),
+            Ok(Value::Null) => Ok(None),
+            Ok(other) => Err(anyhow!("Plugin returned non-string input: {}", other)),
+            Err(_) if !self.is_alive() => Ok(Some(input)),
+            Err(e) => Err(e),
+        }
+    }
+    
+    pub fn intercept_output(&self, output: String) -> Result<Option<String>> {
+        match self.call("intercept_output", json!({ "output": &output })) {
+            Ok(Value::String(modified)) => Ok(Some(modified)),
+            Ok(Value::Null) => Ok(None),
+            Ok(other) => Err(anyhow!("Plugin returned non-string output: {}", other)),
+            Err(_) if !self.is_alive() => Ok(Some(output)),
+            Err(e) => Err(e),
+        }
+    }
+    
+    pub async fn clear_context_and_switch_task(
+        &self,
+        new_task_name: &str,
+        task_dir: &Path,
+        clear: bool,
+        process_input_tx: &mpsc::Sender<String>,
+        output_tx: &mpsc::Sender<String>,
+    ) -> Result<()> {
+        let actions = self.call_actions("switch_task", json!({
+            "task": new_task_name,
+            "task_dir": task_dir,
+            "clear": clear,
+        }))?;
+        
+        for output in actions.output {
+            let _ = output_tx.try_send(output);
+        }
+        
+        for input in actions.input {
+            process_input_tx.send(input).await
+                .context("Failed to send plugin input to CLI")?;
+        }
+        
+        Ok(())
+    }
+    
+    pub fn task_context_messages(&self, task_name: &str, task_dir: &Path) -> Vec<(String, String)> {
+        let result = self.call("context_messages", json!({
+            "task": task_name,
+            "task_dir": task_dir,
+        }));
+        
+        match result {
+            Ok(Value::Array(messages)) => messages.into_iter()
+                .filter_map(|m| m.as_str().map(|m| ("context".to_string(), m.to_string())))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+    
+    pub fn is_prompt(&self, output: &str) -> bool {
+        let tail = crate::ansi::last_line(output);
+        matches!(self.call("is_prompt", json!({ "output": tail })), Ok(Value::Bool(true)))
+    }
+    
+    pub fn can_handle_command(&self, command: &str) -> bool {
+        matches!(self.call("can_handle", json!({ "command": command })), Ok(Value::Bool(true)))
+    }
+    
+    /// Get the CLI's own slash commands, if the plugin lists them
+    pub fn native_commands(&self) -> Option<Vec<String>> {
+        match self.call("native_commands", json!({})) {
+            Ok(Value::Array(commands)) => Some(commands.into_iter()
+                .filter_map(|command| command.as_str().map(|command| command.trim_start_matches('/').to_string()))
+                .collect()),
+            _ => None,
+        }
+    }
+    
+    /// Get the sign-in instructions the plugin finds in the output, if any,
+    /// looking for them as for any CLI once the plugin has stopped answering
+    pub fn auth_prompt(&self, output: &str) -> Option<String> {
+        let stripped = crate::ansi::strip_ansi(output);
+        match self.call("auth_prompt", json!({ "output": stripped })) {
+            Ok(Value::String(instructions)) => Some(instructions),
+            Err(_) if !self.is_alive() => crate::cli_handler::find_auth_prompt(output),
+            _ => None,
+        }
+    }
+    
+    /// Get the CLI's capabilities, as for a known CLI unless the plugin says
+    pub fn capabilities(&self) -> Capabilities {
+        match self.call("capabilities", json!({})) {
+            Ok(Value::Null) | Err(_) => Capabilities::for_command(&self.command),
+            Ok(capabilities) => serde_json::from_value(capabilities).unwrap_or_default(),
+        }
+    }
+    
+    /// Get the commands that save and load the CLI's conversation, if the plugin gives them
+    pub fn conversation_commands(&self, path: &Path) -> Option<ConversationCommands> {
+        self.call("conversation_commands", json!({ "path": path })).ok()
+            .and_then(|commands| serde_json::from_value(commands).ok())
+    }
+    
+    /// Get how the CLI takes files as context, as for a known CLI unless the plugin says
+    pub fn context_strategy(&self) -> ContextStrategy {
+        match self.call("context_strategy", json!({})) {
+            Ok(Value::Null) | Err(_) => ContextStrategy::for_command(&self.command),
+            Ok(strategy) => serde_json::from_value(strategy).unwrap_or_default(),
+        }
+    }
+    
+    /// Get the CLI's cancel sequence, Ctrl+C unless the plugin names another
+    pub fn cancel_sequence(&self) -> String {
+        match self.call("cancel_sequence", json!({})) {
+            Ok(Value::String(sequence)) => sequence,
+            _ => "\x03".to_string(),
+        }
+    }
+    
+    /// Ask the CLI to exit with the input the plugin gives, Ctrl+D if it gives none
+    pub async fn on_quit(&self, process_input_tx: &mpsc::Sender<String>) -> Result<()> {
+        let actions = self.call_actions("on_quit", json!({})).unwrap_or_default();
+        let input = if actions.input.is_empty() { vec!["\x04".to_string()] } else { actions.input };
+        for input in input {
+            process_input_tx.send(input).await
+                .context("Failed to send plugin input to CLI")?;
+        }
+        Ok(())
+    }
+}
@@ -1,11 +1,59 @@
 use anyhow::{Result, Context};
-use portable_pty::{native_pty_system, CommandBuilder, PtyPair, PtySize, Child};
+use portable_pty::{native_pty_system, CommandBuilder, PtyPair, PtySize, Child, ExitStatus};
+use std::collections::HashMap;
+use std::fmt;
 use std::io::{Read, Write, ErrorKind};
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use crate::cli_handler::CliHandler;
+use crate::config::{CliProfile, EnvironmentConfig, InputConfig, OverflowPolicy, PerformanceConfig, RemoteConfig, ResourceLimits};
+use crate::remote;
+use crate::sandbox::Sandbox;
+use crate::filters::OutputFilter;
+use crate::backchannel::{Action, Backchannel};
+use crate::signal::{self, Signal};
+
+/// How long a CLI gets to exit after being asked to before it is killed
+const TERMINATE_GRACE: Duration = Duration::from_millis(500);
+
+/// Error returned by `ProcessManager::start` when the CLI's program isn't installed
+#[derive(Debug)]
+pub struct CommandNotFound {
+    pub program: String,
+}
+
+impl fmt::Display for CommandNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Command '{}' not found", self.program)
+    }
+}
+
+impl std::error::Error for CommandNotFound {}
+
+/// How long a write to the CLI keeps being retried without getting anywhere
+const WRITE_RETRY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Input that didn't all reach the CLI
+#[derive(Debug)]
+pub struct WriteError {
+    /// Bytes of the input that were written
+    pub written: usize,
+    /// Bytes in the whole input
+    pub total: usize,
+    pub error: std::io::Error,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Only {} of {} bytes of input reached the CLI: {}", self.written, self.total, self.error)
+    }
+}
+
+impl std::error::Error for WriteError {}
 
 /// Manages the child process for the CLI
 pub struct ProcessManager {
@@ -13,8 +61,22 @@ pub struct ProcessManager {
     child: Option<Box<dyn Child + Send + Sync>>,
     #[allow(dead_code)]
     command: String,
-    #[allow(dead_code)]
     args: Vec<String>,
+    env: HashMap<String, String>,
+    working_dir: Option<PathBuf>,
+    performance: PerformanceConfig,
+    input: InputConfig,
+    output_tap: Option<mpsc::UnboundedSender<String>>,
+    output_filter: Arc<OutputFilter>,
+    backchannel: Option<mpsc::UnboundedSender<Action>>,
+    write_errors: Option<mpsc::UnboundedSender<WriteError>>,
+    output_stats: Arc<OutputStats>,
+    traffic: Arc<Traffic>,
+    limits: ResourceLimits,
+    sandbox: Option<Sandbox>,
+    remote: RemoteConfig,
+    inherit: EnvironmentConfig,
+    shell_wrap: bool,
     input_tx: Option<mpsc::Sender<String>>,
     output_tx: Option<mpsc::Sender<String>>,
     running: Arc<Mutex<bool>>,
@@ -24,15 +86,30 @@ pub struct ProcessManager {
 impl ProcessManager {
     /// Create a new process manager
     pub fn new(command: &str) -> Self {
-        let parts: Vec<&str> = command.split_whitespace().collect();
-        let cmd = parts.first().unwrap_or(&"").to_string();
-        let args = parts.iter().skip(1).map(|s| s.to_string()).collect();
+        let mut parts = crate::clis::split_command(command).into_iter();
+        let cmd = parts.next().unwrap_or_default();
+        let args = parts.collect();
         
         Self {
             pty_pair: None,
             child: None,
             command: cmd,
             args,
+            env: HashMap::new(),
+            working_dir: None,
+            performance: PerformanceConfig::default(),
+            input: InputConfig::default(),
+            output_tap: None,
+            output_filter: Arc::new(OutputFilter::default()),
+            backchannel: None,
+            write_errors: None,
+            output_stats: Arc::new(OutputStats::default()),
+            traffic: Arc::new(Traffic::default()),
+            limits: ResourceLimits::default(),
+            sandbox: None,
+            remote: RemoteConfig::default(),
+            inherit: EnvironmentConfig::default(),
+            shell_wrap: false,
             input_tx: None,
             output_tx: None,
             running: Arc::new(Mutex::new(false)),
@@ -40,6 +117,89 @@ impl ProcessManager {
         }
     }
     
+    /// Create a process manager for a configured CLI, with its arguments and environment
+    pub fn for_cli(cli: &CliProfile) -> Self {
+        let mut manager = Self::new(&cli.command);
+        manager.args.extend(cli.args.iter().cloned());
+        manager.env = cli.env.clone();
+        manager
+    }
+    
+    /// Pass the CLI more arguments, after those it is configured with
+    pub fn add_args(&mut self, args: impl IntoIterator<Item = String>) {
+        self.args.extend(args);
+    }
+    
+    /// Use channel sizes and the display overflow policy from the configuration
+    pub fn set_performance(&mut self, performance: PerformanceConfig) {
+        self.performance = performance;
+    }
+    
+    /// Split long input into chunks, and pace them, as the configuration says
+    pub fn set_input(&mut self, input: InputConfig) {
+        self.input = input;
+    }
+    
+    /// Also send every chunk of output to `tap`, whatever happens to it on the way to the display
+    pub fn set_output_tap(&mut self, tap: mpsc::UnboundedSender<String>) {
+        self.output_tap = Some(tap);
+    }
+    
+    /// Run output through `filter` after the CLI handler has seen it
+    pub fn set_output_filter(&mut self, filter: OutputFilter) {
+        self.output_filter = Arc::new(filter);
+    }
+    
+    /// Take backchannel requests out of the output and send them to `actions`
+    pub fn set_backchannel(&mut self, actions: mpsc::UnboundedSender<Action>) {
+        self.backchannel = Some(actions);
+    }
+    
+    /// Report input that couldn't all be written to the CLI to `errors`, instead of stderr
+    pub fn set_write_errors(&mut self, errors: mpsc::UnboundedSender<WriteError>) {
+        self.write_errors = Some(errors);
+    }
+    
+    /// Get the counters of output that was dropped or merged on the way to the display
+    pub fn output_stats(&self) -> Arc<OutputStats> {
+        Arc::clone(&self.output_stats)
+    }
+    
+    /// Get the counters of bytes written to and read from the CLI
+    pub fn traffic(&self) -> Arc<Traffic> {
+        Arc::clone(&self.traffic)
+    }
+    
+    /// Run the process in `dir` instead of the current directory
+    pub fn set_working_dir(&mut self, dir: Option<PathBuf>) {
+        self.working_dir = dir;
+    }
+    
+    /// Apply resource limits to the process when it starts
+    pub fn set_limits(&mut self, limits: ResourceLimits) {
+        self.limits = limits;
+    }
+    
+    /// Run the process in a container instead of directly
+    pub fn set_sandbox(&mut self, sandbox: Option<Sandbox>) {
+        self.sandbox = sandbox;
+    }
+    
+    /// Use these keepalive and reconnection settings if the CLI runs over ssh
+    pub fn set_remote(&mut self, remote: RemoteConfig) {
+        self.remote = remote;
+    }
+    
+    /// Only pass the variables `inherit` allows from grill's environment to the process
+    pub fn set_inherited_env(&mut self, inherit: EnvironmentConfig) {
+        self.inherit = inherit;
+    }
+    
+    /// Start the process through the user's login shell
+    pub fn set_shell_wrap(&mut self, shell_wrap: bool) {
+        self.shell_wrap = shell_wrap;
+    }
+    
     /// Start the child process
     pub fn start(&mut self, output_tx: mpsc::Sender<String>, cli_handler: CliHandler) -> Result<mpsc::Sender<String>> {
         let pty_system = native_pty_system();
@@ -52,16 +212,69 @@ impl ProcessManager {
             pixel_height: 0,
         }).context("Failed to open pty")?;
         
-        // Build the command
-        let mut cmd = CommandBuilder::new(&self.command);
-        cmd.args(&self.args);
+        // Build the command, keeping a remote CLI's connection up, through a
+        // shell that sets its limits if it has any. A sandboxed CLI runs in
+        // the container's environment, so the login shell is only used here.
+        let (program, args) = if remote::is_ssh(&self.command) {
+            remote::command(&self.command, &self.args, &self.remote)
+        } else {
+            (self.command.clone(), self.args.clone())
+        };
+        let shell_wrapped = self.shell_wrap && self.sandbox.is_none();
+        let (program, args) = match shell_wrapped.then(|| login_shell_command(&program, &args)).flatten() {
+            Some(wrapped) => wrapped,
+            None => (program, args),
+        };
+        let (program, args) = match limited_command(&program, &args, &self.limits) {
+            Some(limited) => limited,
+            None => (program, args),
+        };
+        let mut cmd = match &self.sandbox {
+            // The engine runs here; the CLI, its environment and directory are in the container
+            Some(sandbox) => {
+                let (engine, engine_args) = sandbox.command(&program, &args, &self.env, self.working_dir.as_ref());
+                let mut cmd = CommandBuilder::new(engine);
+                cmd.args(&engine_args);
+                // The engine hands the values on to the container
+                for (key, value) in &self.env {
+                    cmd.env(key, value);
+                }
+                cmd
+            },
+            None => {
+                let mut cmd = CommandBuilder::new(&program);
+                cmd.args(&args);
+                for (key, _) in std::env::vars_os() {
+                    if !self.inherit.passes(&key.to_string_lossy()) {
+                        cmd.env_remove(key);
+                    }
+                }
+                for (key, value) in &self.env {
+                    cmd.env(key, value);
+                }
+                cmd
+            },
+        };
+        if let Some(dir) = &self.working_dir {
+            cmd.cwd(dir);
+        }
         
         // Spawn the command in the pty
-        let child = pair.slave.spawn_command(cmd)
-            .context("Failed to spawn command")?;
+        let spawned_program = match &self.sandbox {
+            Some(sandbox) => sandbox.engine.program().to_string(),
+            None if shell_wrapped => program.clone(),
+            None => self.command.clone(),
+        };
+        let child = match pair.slave.spawn_command(cmd) {
+            Ok(child) => child,
+            Err(e) if is_not_found(&e, &spawned_program) => {
+                return Err(CommandNotFound { program: spawned_program }.into());
+            },
+            Err(e) => return Err(e.context("Failed to spawn command")),
+        };
         
         // Create channels for input/output
-        let (input_tx, mut input_rx) = mpsc::channel::<String>(100);
+        let (input_tx, mut input_rx) = mpsc::channel::<String>(self.performance.input_channel_capacity.max(1));
         
         // Store the pty pair and channels
         self.pty_pair = Some(pair);
@@ -89,9 +302,22 @@ impl ProcessManager {
         
         // Create a separate thread for reading output
         let cli_handler_for_output = cli_handler.clone();
+        let output_tap = self.output_tap.clone();
+        let output_filter = Arc::clone(&self.output_filter);
+        let actions_tx = self.backchannel.clone();
+        let traffic = Arc::clone(&self.traffic);
+        let mut backchannel = Backchannel::default();
+        let mut sink = OutputSink::new(
+            output_tx,
+            self.performance.display_overflow,
+            self.performance.max_pending_output,
+            Arc::clone(&self.output_stats),
+        );
+        
+        let read_buffer_size = self.performance.read_buffer_size.max(1);
         
         thread::spawn(move || {
-            let mut buffer = [0u8; 1024];
+            let mut buffer = vec![0u8; read_buffer_size];
             
             // Give the process a moment to start up
             thread::sleep(Duration::from_millis(500));
@@ -103,14 +329,41 @@ impl ProcessManager {
                         break;
                     },
                     Ok(n) => {
+                        traffic.bytes_out.fetch_add(n as u64, Ordering::Relaxed);
+                        
                         // Convert to string and send to output channel
-                        let output_str = String::from_utf8_lossy(&buffer[0..n]).to_string();
+                        let mut output_str = String::from_utf8_lossy(&buffer[0..n]).to_string();
+                        
+                        // Requests to grill are acted on and never shown
+                        if let Some(actions_tx) = &actions_tx {
+                            let (shown, actions) = backchannel.process(&output_str);
+                            for action in actions {
+                                match action {
+                                    Ok(action) => {
+                                        let _ = actions_tx.send(action);
+                                    },
+                                    Err(e) => log::warn!("{:#}", e),
+                                }
+                            }
+                            if shown.is_empty() {
+                                continue;
+                            }
+                            output_str = shown;
+                        }
                         
-                        // Intercept output using CLI handler
-                        match cli_handler_for_output.intercept_output(output_str) {
+                        // Intercept output using CLI handler, then the configured filters
+                        let intercepted = cli_handler_for_output.intercept_output(output_str)
+                            .map(|output| output.and_then(|output| output_filter.apply(output)));
+                        match intercepted {
                             Ok(Some(modified_output)) => {
-                                if let Err(e) = output_tx.blocking_send(modified_output) {
-                                    eprintln!("Failed to send output: {}", e);
+                                if let Some(tap) = &output_tap {
+                                    let _ = tap.send(modified_output.clone());
+                                }
+                                if !sink.send(modified_output) {
+                                    // The receiver going away after stop() is expected
+                                    if *running.lock().unwrap() {
+                                        eprintln!("Failed to send output: display closed");
+                                    }
                                     break;
                                 }
                             },
@@ -135,6 +388,8 @@ impl ProcessManager {
                 }
             }
             
+            sink.close();
+            
             // Set running to false when the thread exits
             let mut running_lock = running.lock().unwrap();
             *running_lock = false;
@@ -146,6 +401,9 @@ impl ProcessManager {
         
         // Create a mutex-protected writer
         let writer_mutex = Arc::new(Mutex::new(writer));
+        let pacing = self.input.clone();
+        let write_errors = self.write_errors.clone();
+        let traffic = Arc::clone(&self.traffic);
         
         // Process input in a separate thread
         thread::spawn(move || {
@@ -155,17 +413,34 @@ impl ProcessManager {
                     Some(input) => {
                         // Get a lock on the writer
                         if let Ok(mut writer) = writer_mutex.lock() {
-                            // Write the input character/string directly to the process
-                            // For character-by-character input, don't modify the input
-                            if let Err(e) = writer.write_all(input.as_bytes()) {
-                                eprintln!("Failed to write to pty: {}", e);
-                                continue;
+                            // Keystrokes go whole; long input is written a chunk at a
+                            // time, and a write blocks while the CLI's input queue is full
+                            let mut written = 0;
+                            let mut previous: Option<usize> = None;
+                            let mut result = Ok(());
+                            for chunk in input_chunks(input.as_bytes(), pacing.chunk_bytes) {
+                                if let Some(bytes) = previous {
+                                    thread::sleep(pacing.chunk_delay(bytes));
+                                }
+                                previous = Some(chunk.len());
+                                
+                                // Flush the writer to ensure the input is sent immediately
+                                result = write_fully(&mut *writer, chunk, &mut written)
+                                    .and_then(|_| flush_fully(&mut *writer));
+                                if result.is_err() {
+                                    break;
+                                }
                             }
+                            traffic.bytes_in.fetch_add(written as u64, Ordering::Relaxed);
                             
-                            // Flush the writer to ensure the input is sent immediately
-                            if let Err(e) = writer.flush() {
-                                eprintln!("Failed to flush pty writer: {}", e);
-                                continue;
+                            if let Err(error) = result {
+                                let error = WriteError { written, total: input.len(), error };
+                                match &write_errors {
+                                    Some(errors) => {
+                                        let _ = errors.send(error);
+                                    },
+                                    None => eprintln!("{}", error),
+                                }
                             }
                         }
                     },
@@ -180,6 +455,19 @@ impl ProcessManager {
         Ok(input_tx)
     }
     
+    /// Get the child's process id, once it has started
+    pub fn process_id(&self) -> Option<u32> {
+        self.child.as_ref()?.process_id()
+    }
+    
+    /// Check whether the child process has exited, returning its exit status if so
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        match self.child.as_mut() {
+            Some(child) => Ok(child.try_wait()?),
+            None => Ok(None),
+        }
+    }
+    
     /// Stop the child process
     pub fn stop(&mut self) -> Result<()> {
         // Set writer running to false
@@ -192,13 +480,26 @@ impl ProcessManager {
         *running = false;
         drop(running);
         
-        // Kill the child process if it's still running
+        // Stop the child and anything it started, if it's still running
         if let Some(mut child) = self.child.take() {
             if child.try_wait()?.is_none() {
-                child.kill()?;
+                stop_tree(child.as_mut())?;
+            }
+            if let Some(sandbox) = &self.sandbox {
+                sandbox.remove();
             }
         }
         
+        let stats = self.output_stats();
+        if stats.dropped_chunks() > 0 || stats.coalesced_chunks() > 0 {
+            log::warn!(
+                "The display fell behind: dropped {} chunks ({} bytes) of output, coalesced {}",
+                stats.dropped_chunks(),
+                stats.dropped_bytes(),
+                stats.coalesced_chunks()
+            );
+        }
+        
         // Drop the pty pair to close the process
         self.pty_pair = None;
         self.input_tx = None;
@@ -214,3 +515,345 @@ impl Drop for ProcessManager {
         let _ = self.stop();
     }
 }
+
+/// Wrap a command in a shell that applies resource limits before running it
+///
+/// Returns `None` when there are no limits to apply. Limits are set with
+/// `ulimit`, so only on Unix; elsewhere they are ignored with a warning.
+pub fn limited_command(command: &str, args: &[String], limits: &ResourceLimits) -> Option<(String, Vec<String>)> {
+    if limits.is_empty() {
+        return None;
+    }
+    if cfg!(windows) {
+        log::warn!("Resource limits are only supported on Unix platforms");
+        return None;
+    }
+    
+    let mut script = String::new();
+    let settings = [
+        ("-v", limits.max_memory_mb.map(|mb| mb * 1024)),
+        ("-t", limits.max_cpu_seconds),
+        ("-n", limits.max_open_files),
+    ];
+    for (flag, value) in settings {
+        if let Some(value) = value {
+            script.push_str(&format!("ulimit {} {} && ", flag, value));
+        }
+    }
+    script.push_str("exec \"$@\"");
+    
+    let mut wrapped = vec!["-c".to_string(), script, "sh".to_string(), command.to_string()];
+    wrapped.extend(args.iter().cloned());
+    Some(("sh".to_string(), wrapped))
+}
+
+/// Write all of `bytes`, adding what was written to `written`
+///
+/// Short writes are finished, and interrupted or would-block writes are
+/// retried until they have made no progress for `WRITE_RETRY_TIMEOUT`.
+pub fn write_fully(writer: &mut (impl Write + ?Sized), bytes: &[u8], written: &mut usize) -> std::io::Result<()> {
+    let mut remaining = bytes;
+    let mut last_progress = Instant::now();
+    while !remaining.is_empty() {
+        match writer.write(remaining) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(n) => {
+                remaining = &remaining[n..];
+                *written += n;
+                last_progress = Instant::now();
+            },
+            Err(e) if is_transient(&e) && last_progress.elapsed() < WRITE_RETRY_TIMEOUT => retry_pause(&e),
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Flush `writer`, retrying interrupted or would-block flushes for up to `WRITE_RETRY_TIMEOUT`
+pub fn flush_fully(writer: &mut (impl Write + ?Sized)) -> std::io::Result<()> {
+    let started = Instant::now();
+    loop {
+        match writer.flush() {
+            Err(e) if is_transient(&e) && started.elapsed() < WRITE_RETRY_TIMEOUT => retry_pause(&e),
+            result => return result,
+        }
+    }
+}
+
+/// Check whether a write failed only for now, as with EINTR or EAGAIN
+fn is_transient(error: &std::io::Error) -> bool {
+    matches!(error.kind(), ErrorKind::Interrupted | ErrorKind::WouldBlock)
+}
+
+/// Wait before retrying a write; an interrupted one can be retried straight away
+fn retry_pause(error: &std::io::Error) {
+    if error.kind() == ErrorKind::WouldBlock {
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Split input into chunks of at most `chunk_bytes` for writing to the CLI
+///
+/// Chunks end on character boundaries and never inside an escape sequence,
+/// so a pause between them can't turn the `ESC` of a bracketed paste into a
+/// press of the Esc key. A `chunk_bytes` of 0 keeps the input whole.
+pub fn input_chunks(input: &[u8], chunk_bytes: usize) -> Vec<&[u8]> {
+    if chunk_bytes == 0 || input.len() <= chunk_bytes {
+        return vec![input];
+    }
+    
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    while start < input.len() {
+        let mut end = (start + chunk_bytes).min(input.len());
+        if end < input.len() {
+            // Back up to the start of a UTF-8 character
+            while end > start + 1 && (input[end] & 0xC0) == 0x80 {
+                end -= 1;
+            }
+            // Back up to before an unfinished escape sequence
+            let window = end.saturating_sub(MAX_ESCAPE_LEN).max(start);
+            if let Some(escape) = input[window..end].iter().rposition(|&byte| byte == 0x1b).map(|i| window + i) {
+                if escape > start && !escape_finished(&input[escape..end]) {
+                    end = escape;
+                }
+            }
+        }
+        chunks.push(&input[start..end]);
+        start = end;
+    }
+    chunks
+}
+
+/// Longest escape sequence kept out of a chunk boundary
+const MAX_ESCAPE_LEN: usize = 16;
+
+/// Check whether `sequence`, starting with `ESC`, is a whole escape sequence
+fn escape_finished(sequence: &[u8]) -> bool {
+    match sequence.get(1) {
+        None => false,
+        // A CSI sequence ends with a byte from `@` to `~`
+        Some(b'[') => sequence[2..].iter().any(|byte| (0x40..=0x7e).contains(byte)),
+        Some(_) => true,
+    }
+}
+
+/// Wrap a command in the user's login shell, as `$SHELL -lc "<command>"`
+///
+/// The shell reads the user's profile first, so the command finds what it
+/// would in their terminal. Returns `None` on Windows, which has no login
+/// shell, and `sh` is used when `SHELL` isn't set.
+pub fn login_shell_command(command: &str, args: &[String]) -> Option<(String, Vec<String>)> {
+    if cfg!(windows) {
+        log::warn!("shell_wrap is only supported on Unix platforms");
+        return None;
+    }
+    
+    let shell = std::env::var("SHELL").ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or_else(|| "sh".to_string());
+    let words = std::iter::once(command).chain(args.iter().map(String::as_str));
+    Some((shell, vec!["-lc".to_string(), shell_words::join(words)]))
+}
+
+/// Ask a child's process tree to exit, killing it if it doesn't in time
+///
+/// Only the child itself is killed if its tree can't be signalled.
+fn stop_tree(child: &mut (dyn Child + Send + Sync)) -> Result<()> {
+    let Some(pid) = child.process_id() else {
+        child.kill()?;
+        return Ok(());
+    };
+    
+    if signal::send(pid, Signal::Terminate).is_ok() {
+        // A paused CLI has to run again to exit
+        let _ = signal::send(pid, Signal::Resume);
+        let started = std::time::Instant::now();
+        while started.elapsed() < TERMINATE_GRACE {
+            if child.try_wait()?.is_some() {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    
+    if signal::send(pid, Signal::Kill).is_err() {
+        child.kill()?;
+    }
+    Ok(())
+}
+
+/// Check whether a spawn failed because the program doesn't exist
+///
+/// portable-pty reports a program missing from `PATH` as a plain message, so
+/// besides ENOENT this also looks the program up itself.
+fn is_not_found(error: &anyhow::Error, program: &str) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == ErrorKind::NotFound)
+    }) || crate::clis::find_binary(program).is_none()
+}
+
+/// Counts output that didn't reach the display as it was produced
+#[derive(Debug, Default)]
+pub struct OutputStats {
+    dropped_chunks: AtomicU64,
+    dropped_bytes: AtomicU64,
+    coalesced_chunks: AtomicU64,
+}
+
+impl OutputStats {
+    /// Chunks thrown away (in part, for `coalesce`) because the display fell behind
+    pub fn dropped_chunks(&self) -> u64 {
+        self.dropped_chunks.load(Ordering::Relaxed)
+    }
+    
+    /// Bytes thrown away because the display fell behind
+    pub fn dropped_bytes(&self) -> u64 {
+        self.dropped_bytes.load(Ordering::Relaxed)
+    }
+    
+    /// Chunks merged into the one before them while waiting for the display
+    pub fn coalesced_chunks(&self) -> u64 {
+        self.coalesced_chunks.load(Ordering::Relaxed)
+    }
+    
+    fn record_drop(&self, bytes: usize) {
+        self.dropped_chunks.fetch_add(1, Ordering::Relaxed);
+        self.dropped_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+}
+
+/// Counts the bytes written to and read from the CLI
+#[derive(Debug, Default)]
+pub struct Traffic {
+    bytes_in: AtomicU64,
+    bytes_out: AtomicU64,
+}
+
+impl Traffic {
+    /// Bytes of input written to the CLI
+    pub fn bytes_in(&self) -> u64 {
+        self.bytes_in.load(Ordering::Relaxed)
+    }
+    
+    /// Bytes of output read from the CLI, before anything is filtered out
+    pub fn bytes_out(&self) -> u64 {
+        self.bytes_out.load(Ordering::Relaxed)
+    }
+}
+
+/// Delivers output from the PTY reader to the display under an overflow policy
+pub struct OutputSink {
+    output_tx: mpsc::Sender<String>,
+    policy: OverflowPolicy,
+    stats: Arc<OutputStats>,
+    /// Output waiting for the forwarding thread, with `coalesce`
+    pending: Option<Arc<PendingOutput>>,
+}
+
+/// Output held back for the display while it is busy
+struct PendingOutput {
+    state: Mutex<PendingState>,
+    ready: Condvar,
+    limit: usize,
+}
+
+#[derive(Default)]
+struct PendingState {
+    text: String,
+    closed: bool,
+}
+
+impl OutputSink {
+    /// Create a sink; `coalesce` starts a thread that forwards held-back output
+    pub fn new(output_tx: mpsc::Sender<String>, policy: OverflowPolicy, max_pending: usize, stats: Arc<OutputStats>) -> Self {
+        let pending = (policy == OverflowPolicy::Coalesce).then(|| {
+            let pending = Arc::new(PendingOutput {
+                state: Mutex::new(PendingState::default()),
+                ready: Condvar::new(),
+                limit: max_pending.max(1),
+            });
+            let forwarded = Arc::clone(&pending);
+            let output_tx = output_tx.clone();
+            thread::spawn(move || forward_pending(&forwarded, &output_tx));
+            pending
+        });
+        
+        Self { output_tx, policy, stats, pending }
+    }
+    
+    /// Pass a chunk towards the display, returning `false` once the display is gone
+    pub fn send(&mut self, chunk: String) -> bool {
+        match self.policy {
+            OverflowPolicy::Block => self.output_tx.blocking_send(chunk).is_ok(),
+            OverflowPolicy::Drop => match self.output_tx.try_send(chunk) {
+                Ok(()) => true,
+                Err(mpsc::error::TrySendError::Full(chunk)) => {
+                    self.stats.record_drop(chunk.len());
+                    true
+                },
+                Err(mpsc::error::TrySendError::Closed(_)) => false,
+            },
+            OverflowPolicy::Coalesce => {
+                let Some(pending) = &self.pending else {
+                    return false;
+                };
+                let mut state = pending.state.lock().unwrap();
+                if state.closed {
+                    return false;
+                }
+                if !state.text.is_empty() {
+                    self.stats.coalesced_chunks.fetch_add(1, Ordering::Relaxed);
+                }
+                state.text.push_str(&chunk);
+                
+                // Keep the newest output when the display is hopelessly behind
+                if state.text.len() > pending.limit {
+                    let mut cut = state.text.len() - pending.limit;
+                    while !state.text.is_char_boundary(cut) {
+                        cut += 1;
+                    }
+                    state.text.drain(..cut);
+                    self.stats.record_drop(cut);
+                }
+                pending.ready.notify_one();
+                true
+            },
+        }
+    }
+    
+    /// Stop accepting output; anything held back is still delivered
+    pub fn close(&mut self) {
+        if let Some(pending) = &self.pending {
+            pending.state.lock().unwrap().closed = true;
+            pending.ready.notify_one();
+        }
+    }
+}
+
+impl Drop for OutputSink {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Send held-back output to the display, merging whatever piles up in the meantime
+fn forward_pending(pending: &PendingOutput, output_tx: &mpsc::Sender<String>) {
+    loop {
+        let text = {
+            let mut state = pending.state.lock().unwrap();
+            while state.text.is_empty() && !state.closed {
+                state = pending.ready.wait(state).unwrap();
+            }
+            if state.text.is_empty() {
+                return;
+            }
+            std::mem::take(&mut state.text)
+        };
+        
+        if output_tx.blocking_send(text).is_err() {
+            pending.state.lock().unwrap().closed = true;
+            return;
+        }
+    }
+}
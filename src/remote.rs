@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use crate::config::RemoteConfig;
+
+/// Exit status ssh uses for its own errors, such as a dropped connection
+pub const SSH_ERROR_STATUS: i32 = 255;
+
+/// Missed keepalives after which ssh gives up on the server
+const KEEPALIVE_COUNT_MAX: u32 = 3;
+
+/// Check whether a CLI's command runs it on another machine over ssh
+pub fn is_ssh(command: &str) -> bool {
+    let program = crate::clis::program(command).unwrap_or_default();
+    Path::new(&program).file_stem().is_some_and(|name| name == "ssh")
+}
+
+/// Add the options a remote CLI needs to ssh's arguments
+///
+/// The CLI gets a terminal even though ssh was given a command, and ssh
+/// sends keepalives so a dead connection is noticed. Options already in
+/// the arguments are left alone.
+pub fn ssh_args(args: &[String], config: &RemoteConfig) -> Vec<String> {
+    let mut options = Vec::new();
+    if !args.iter().any(|arg| arg == "-t" || arg == "-tt") {
+        options.push("-t".to_string());
+    }
+    if config.keepalive_seconds > 0 && !args.iter().any(|arg| arg.contains("ServerAliveInterval")) {
+        options.extend([
+            "-o".to_string(),
+            format!("ServerAliveInterval={}", config.keepalive_seconds),
+            "-o".to_string(),
+            format!("ServerAliveCountMax={}", KEEPALIVE_COUNT_MAX),
+        ]);
+    }
+    options.extend(args.iter().cloned());
+    options
+}
+
+/// Wrap ssh in a shell that runs it again when the connection drops
+///
+/// ssh exits with 255 when it loses the server, which is retried up to the
+/// configured number of times with a notice in between; any other exit is
+/// the remote CLI's own and ends the loop. Returns `None` when reconnecting
+/// is turned off, or on Windows, where there's no `sh` to run the loop.
+pub fn reconnecting_command(program: &str, args: &[String], config: &RemoteConfig) -> Option<(String, Vec<String>)> {
+    if config.reconnect_attempts == 0 {
+        return None;
+    }
+    if cfg!(windows) {
+        log::warn!("Reconnecting remote CLIs is only supported on Unix platforms");
+        return None;
+    }
+    
+    let attempts = config.reconnect_attempts;
+    let script = format!(
+        "attempt=0; while :; do \"$@\"; status=$?; \
+         if [ $status -ne {error} ] || [ $attempt -ge {attempts} ]; then exit $status; fi; \
+         attempt=$((attempt + 1)); \
+         printf '\\r\\n[grill] Connection lost; reconnecting (%d of {attempts})\\r\\n' $attempt; \
+         sleep $attempt; done",
+        error = SSH_ERROR_STATUS,
+        attempts = attempts,
+    );
+    
+    let mut wrapped = vec!["-c".to_string(), script, "sh".to_string(), program.to_string()];
+    wrapped.extend(args.iter().cloned());
+    Some(("sh".to_string(), wrapped))
+}
+
+/// Build the command that runs a remote CLI over ssh
+pub fn command(program: &str, args: &[String], config: &RemoteConfig) -> (String, Vec<String>) {
+    let args = ssh_args(args, config);
+    reconnecting_command(program, &args, config).unwrap_or_else(|| (program.to_string(), args))
+}
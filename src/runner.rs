@@ -0,0 +1,305 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Instant};
+
+use crate::ansi;
+use crate::clipboard;
+use crate::cli_handler::{CliHandler, CliHandlerFactory};
+use crate::config::{CliProfile, Config, ResourceLimits, TaskConfig};
+use crate::sandbox::Sandbox;
+use crate::remote;
+use crate::environment::Environment;
+use crate::process::{ProcessManager, Traffic};
+use crate::session::Session;
+
+/// How long the CLI may stay silent before a response is considered complete
+///
+/// Only used when the handler doesn't recognise a prompt.
+const DEFAULT_QUIET_PERIOD: Duration = Duration::from_secs(3);
+
+/// How long to wait for a single response by default
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How results of non-interactive runs are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Plain response text
+    Text,
+    /// One JSON record per exchange
+    Json,
+}
+
+/// A single prompt/response exchange, as reported by `--output json`
+#[derive(Debug, Clone, Serialize)]
+pub struct Exchange {
+    pub task: String,
+    pub prompt: String,
+    pub response: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// "ok" or "error"
+    pub status: String,
+    pub error: Option<String>,
+}
+
+impl Exchange {
+    /// Check whether the exchange succeeded
+    pub fn is_ok(&self) -> bool {
+        self.status == "ok"
+    }
+    
+    /// Print the exchange in the requested format
+    pub fn print(&self, format: OutputFormat) {
+        match format {
+            OutputFormat::Text => match (&self.response, &self.error) {
+                (Some(response), _) => println!("{}", response),
+                (None, Some(error)) => eprintln!("Error: {}", error),
+                (None, None) => {},
+            },
+            OutputFormat::Json => {
+                if let Ok(record) = serde_json::to_string(self) {
+                    println!("{}", record);
+                }
+            },
+        }
+    }
+}
+
+/// Drives a wrapped CLI without a terminal: send a message, wait for the prompt,
+/// return what the CLI printed in between
+pub struct Runner {
+    process: ProcessManager,
+    handler: CliHandler,
+    input_tx: mpsc::Sender<String>,
+    output_rx: mpsc::Receiver<String>,
+    quiet_period: Duration,
+}
+
+impl Runner {
+    /// Spawn the CLI for a command
+    #[allow(dead_code)]
+    pub fn start(command: &str) -> Result<Self> {
+        Self::start_in(&CliProfile::from_command(command), None, ResourceLimits::default(), None, &Config::default())
+    }
+    
+    /// Spawn a CLI in a specific directory, with resource limits and optionally in a sandbox
+    ///
+    /// The workspace's remote settings apply when the CLI runs over ssh, and
+    /// also lengthen the quiet period to allow for the connection's latency.
+    pub fn start_in(
+        cli: &CliProfile,
+        working_dir: Option<PathBuf>,
+        limits: ResourceLimits,
+        sandbox: Option<Sandbox>,
+        config: &Config,
+    ) -> Result<Self> {
+        let handler = CliHandlerFactory::create_handler(cli.command_line());
+        Self::spawn(cli, handler, Vec::new(), working_dir, limits, sandbox, config)
+    }
+    
+    /// Spawn a CLI with the handler chosen for it, passing it extra arguments
+    fn spawn(
+        cli: &CliProfile,
+        handler: CliHandler,
+        extra_args: Vec<String>,
+        working_dir: Option<PathBuf>,
+        limits: ResourceLimits,
+        sandbox: Option<Sandbox>,
+        config: &Config,
+    ) -> Result<Self> {
+        let quiet_period = if remote::is_ssh(&cli.command) {
+            DEFAULT_QUIET_PERIOD + config.remote.latency()
+        } else {
+            DEFAULT_QUIET_PERIOD
+        };
+        let mut process = ProcessManager::for_cli(cli);
+        process.add_args(extra_args);
+        process.set_working_dir(working_dir);
+        process.set_limits(limits);
+        process.set_sandbox(sandbox);
+        process.set_remote(config.remote.clone());
+        process.set_inherited_env(config.environment.clone());
+        process.set_shell_wrap(config.shell_wrap);
+        process.set_input(config.input.clone());
+        
+        let (output_tx, output_rx) = mpsc::channel(100);
+        let input_tx = process.start(output_tx, handler.clone())?;
+        
+        Ok(Self {
+            process,
+            handler,
+            input_tx,
+            output_rx,
+            quiet_period,
+        })
+    }
+    
+    /// Spawn the CLI configured for a task, with the task's context files if it takes them as it starts
+    pub fn start_for_task(environment: &Environment, task_name: &str) -> Result<Self> {
+        let cli = Session::get_cli_for_task(environment, task_name)?;
+        let task_dir = environment.get_task_dir(task_name)?;
+        let config = TaskConfig::load_for_task(&task_dir)?;
+        let sandbox = Sandbox::for_task(environment, task_name, &config)?;
+        let workspace_config = Config::load(&environment.get_config_path())?;
+        let handler = CliHandlerFactory::create_handler(cli.command_line());
+        let context_args = handler.context_args(environment, task_name, &task_dir);
+        Self::spawn(&cli, handler, context_args, environment.get_task_working_dir(task_name)?, config.limits, sandbox, &workspace_config)
+            .map_err(|e| crate::clis::explain_start_error(e, environment, task_name))
+    }
+    
+    /// Wait for the CLI to start up and show its first prompt
+    ///
+    /// A CLI that prints nothing at all is considered ready once it has been
+    /// quiet for a while.
+    pub async fn wait_until_ready(&mut self, limit: Duration) -> Result<String> {
+        self.wait(limit, true).await
+    }
+    
+    /// Wait until the CLI shows its prompt, returning the raw output seen so far
+    ///
+    /// Falls back to a quiet period when the handler can't recognise the prompt,
+    /// and returns early if the CLI exits.
+    pub async fn wait_for_prompt(&mut self, limit: Duration) -> Result<String> {
+        self.wait(limit, false).await
+    }
+    
+    async fn wait(&mut self, limit: Duration, allow_silence: bool) -> Result<String> {
+        let deadline = Instant::now() + limit;
+        let mut output = String::new();
+        
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(anyhow!("Timed out after {}s waiting for the CLI prompt", limit.as_secs()));
+            }
+            
+            match timeout(remaining.min(self.quiet_period), self.output_rx.recv()).await {
+                Ok(Some(chunk)) => {
+                    output.push_str(&chunk);
+                    if self.handler.is_prompt(&output) {
+                        return Ok(output);
+                    }
+                },
+                // The CLI exited
+                Ok(None) => return Ok(output),
+                // Quiet for a while after printing something: assume it's done
+                Err(_) if allow_silence || !output.is_empty() => return Ok(output),
+                Err(_) => continue,
+            }
+        }
+    }
+    
+    /// Send a message and return the CLI's response as plain text
+    pub async fn send(&mut self, message: &str, limit: Duration) -> Result<String> {
+        // Anything printed before we send belongs to the previous exchange
+        while self.output_rx.try_recv().is_ok() {}
+        
+        self.input_tx.send(format!("{}\r", message)).await
+            .map_err(|_| anyhow!("The CLI is no longer accepting input"))?;
+        
+        let output = self.wait_for_prompt(limit).await?;
+        Ok(clean_response(&output, message))
+    }
+    
+    /// Send a message and record the outcome and timing instead of failing
+    pub async fn exchange(&mut self, task_name: &str, message: &str, limit: Duration) -> Exchange {
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let result = self.send(message, limit).await;
+        let duration_ms = start.elapsed().as_millis() as u64;
+        
+        let (response, status, error) = match result {
+            Ok(response) => (Some(response), "ok", None),
+            Err(e) => (None, "error", Some(e.to_string())),
+        };
+        
+        Exchange {
+            task: task_name.to_string(),
+            prompt: message.to_string(),
+            response,
+            started_at,
+            duration_ms,
+            status: status.to_string(),
+            error,
+        }
+    }
+    
+    /// Paste text into the CLI as one message, without waiting for the answer
+    ///
+    /// Returns the number of bytes sent. Text spanning several lines is joined
+    /// into one for CLIs that don't take multiline input.
+    pub async fn paste(&mut self, text: &str) -> Result<usize> {
+        while self.output_rx.try_recv().is_ok() {}
+        
+        let text = if self.handler.capabilities().multiline_input {
+            text.to_string()
+        } else {
+            clipboard::join_lines(text)
+        };
+        let input = clipboard::paste_message(&text);
+        let bytes = input.len();
+        self.input_tx.send(input).await
+            .map_err(|_| anyhow!("The CLI is no longer accepting input"))?;
+        Ok(bytes)
+    }
+    
+    /// Check whether the CLI has exited, returning its exit status if so
+    pub fn try_wait(&mut self) -> Result<Option<portable_pty::ExitStatus>> {
+        self.process.try_wait()
+    }
+    
+    /// Get the handler driving the CLI
+    pub fn handler(&self) -> &CliHandler {
+        &self.handler
+    }
+    
+    /// Get the counters of bytes written to and read from the CLI
+    pub fn traffic(&self) -> Arc<Traffic> {
+        self.process.traffic()
+    }
+    
+    /// Give the CLI a task's instructions and state before the real prompt
+    pub async fn load_task_context(&mut self, environment: &Environment, task_name: &str, limit: Duration) -> Result<()> {
+        let task_dir = environment.get_task_dir(task_name)?;
+        
+        for (_, message) in self.handler.task_context_messages(environment, task_name, &task_dir) {
+            self.send(&message, limit).await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Stop the CLI
+    pub fn stop(&mut self) -> Result<()> {
+        self.process.stop()
+    }
+}
+
+/// Turn raw CLI output into the response text
+///
+/// Strips escape sequences, the echoed message and the trailing prompt line.
+pub fn clean_response(output: &str, message: &str) -> String {
+    let stripped = ansi::strip_ansi(output);
+    let mut lines: Vec<&str> = stripped.lines().collect();
+    
+    // The PTY echoes what we typed
+    let first_line = message.lines().next().unwrap_or("").trim();
+    if let Some(pos) = lines.iter().position(|line| !first_line.is_empty() && line.trim().ends_with(first_line)) {
+        let echoed = message.lines().count();
+        lines.drain(..(pos + echoed).min(lines.len()));
+    }
+    
+    // Drop the prompt the CLI printed when it finished
+    if let Some(last) = lines.last() {
+        if last.trim_end().ends_with('>') {
+            lines.pop();
+        }
+    }
+    
+    lines.join("\n").trim().to_string()
+}
@@ -0,0 +1,92 @@
+use anyhow::{Result, anyhow};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{Command as ProcessCommand, Stdio};
+
+use crate::config::{SandboxEngine, TaskConfig};
+use crate::environment::Environment;
+
+/// A container the CLI runs in, with the workspace mounted at the same path
+///
+/// The container is named after the task and this grill process, and is
+/// removed when the session stops the CLI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sandbox {
+    pub engine: SandboxEngine,
+    pub image: String,
+    pub name: String,
+    /// Workspace directory shared with the container
+    pub mount: PathBuf,
+    /// Extra arguments to the engine's `run`
+    pub args: Vec<String>,
+}
+
+impl Sandbox {
+    /// Get the sandbox a task's config asks for, if any
+    pub fn for_task(environment: &Environment, task_name: &str, config: &TaskConfig) -> Result<Option<Self>> {
+        let Some(engine) = config.sandbox else {
+            return Ok(None);
+        };
+        let image = config.sandbox_image.clone()
+            .ok_or_else(|| anyhow!("Task '{}' uses a {} sandbox but sets no sandbox_image", task_name, engine.program()))?;
+        
+        let task: String = task_name.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '-' })
+            .collect();
+        Ok(Some(Self {
+            engine,
+            image,
+            name: format!("grill-{}-{}", task, std::process::id()),
+            mount: environment.get_root_dir(),
+            args: config.sandbox_args.clone(),
+        }))
+    }
+    
+    /// Build the command that runs a CLI inside the container
+    ///
+    /// The CLI's environment is passed into the container by name only, so
+    /// values such as keys don't show up in the engine's arguments; the engine
+    /// has to be given them in its own environment. The CLI starts in
+    /// `working_dir`, or the workspace.
+    pub fn command(
+        &self,
+        program: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        working_dir: Option<&PathBuf>,
+    ) -> (String, Vec<String>) {
+        let mount = self.mount.display().to_string();
+        let workdir = working_dir.unwrap_or(&self.mount).display().to_string();
+        let mut command = vec![
+            "run".to_string(), "--rm".to_string(), "-it".to_string(),
+            "--name".to_string(), self.name.clone(),
+            "-v".to_string(), format!("{}:{}", mount, mount),
+            "-w".to_string(), workdir,
+            "-e".to_string(), "TERM".to_string(),
+        ];
+        let mut env: Vec<_> = env.iter().collect();
+        env.sort();
+        for (key, _) in env {
+            command.push("-e".to_string());
+            command.push(key.clone());
+        }
+        command.extend(self.args.iter().cloned());
+        command.push(self.image.clone());
+        command.push(program.to_string());
+        command.extend(args.iter().cloned());
+        (self.engine.program().to_string(), command)
+    }
+    
+    /// Remove the container, in case stopping the engine's client left it running
+    pub fn remove(&self) {
+        let result = ProcessCommand::new(self.engine.program())
+            .args(["rm", "-f", &self.name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+        if let Err(e) = result {
+            log::warn!("Could not remove sandbox container {}: {}", self.name, e);
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Attribute, SetAttribute},
+    terminal::{Clear, ClearType},
+};
+
+/// Lines of output kept for copy mode
+pub const SCROLLBACK_LINES: usize = 10_000;
+
+/// Where the scrollback is in the output, which may split escape sequences across chunks
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Parse {
+    #[default]
+    Text,
+    Escape,
+    Csi,
+    String,
+    StringEscape,
+}
+
+/// Recent output as plain text lines
+///
+/// Escape sequences are dropped, so what is kept is what could be copied
+/// from the screen, without colors or cursor movement.
+#[derive(Debug)]
+pub struct Scrollback {
+    lines: VecDeque<String>,
+    max_lines: usize,
+    parse: Parse,
+}
+
+impl Default for Scrollback {
+    fn default() -> Self {
+        Self::new(SCROLLBACK_LINES)
+    }
+}
+
+impl Scrollback {
+    /// Create a scrollback keeping up to `max_lines` lines
+    pub fn new(max_lines: usize) -> Self {
+        Self {
+            lines: VecDeque::from([String::new()]),
+            max_lines: max_lines.max(1),
+            parse: Parse::Text,
+        }
+    }
+    
+    /// Add output to the scrollback
+    pub fn push(&mut self, output: &str) {
+        for c in output.chars() {
+            self.parse = match (self.parse, c) {
+                (Parse::Text, '\x1b') => Parse::Escape,
+                (Parse::Text, '\n') => {
+                    self.lines.push_back(String::new());
+                    if self.lines.len() > self.max_lines {
+                        self.lines.pop_front();
+                    }
+                    Parse::Text
+                },
+                (Parse::Text, '\x08') => {
+                    self.current().pop();
+                    Parse::Text
+                },
+                (Parse::Text, '\t') => {
+                    self.current().push('\t');
+                    Parse::Text
+                },
+                (Parse::Text, c) if c.is_control() => Parse::Text,
+                (Parse::Text, c) => {
+                    self.current().push(c);
+                    Parse::Text
+                },
+                (Parse::Escape, '[') => Parse::Csi,
+                (Parse::Escape, ']' | 'P' | 'X' | '^' | '_') => Parse::String,
+                (Parse::Escape, _) => Parse::Text,
+                (Parse::Csi, '\x40'..='\x7e') => Parse::Text,
+                (Parse::Csi, _) => Parse::Csi,
+                (Parse::String, '\x07') => Parse::Text,
+                (Parse::String, '\x1b') => Parse::StringEscape,
+                (Parse::String, _) => Parse::String,
+                (Parse::StringEscape, '\\') => Parse::Text,
+                (Parse::StringEscape, _) => Parse::String,
+            };
+        }
+    }
+    
+    /// Get the kept lines, oldest first, without a trailing empty line
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = self.lines.iter().cloned().collect();
+        if lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines
+    }
+    
+    fn current(&mut self) -> &mut String {
+        self.lines.back_mut().expect("scrollback always has a current line")
+    }
+}
+
+/// Full-screen pager over a snapshot of the scrollback
+///
+/// The cursor starts on the newest line. Lines between the mark and the
+/// cursor are selected for yanking.
+#[derive(Debug)]
+pub struct CopyMode {
+    lines: Vec<String>,
+    cursor: usize,
+    top: usize,
+    height: usize,
+    mark: Option<usize>,
+    /// The search being typed, if any
+    query: Option<String>,
+    last_search: Option<String>,
+    message: Option<String>,
+}
+
+impl CopyMode {
+    /// Open copy mode on `lines`, showing `height` lines at a time
+    pub fn new(lines: Vec<String>, height: usize) -> Self {
+        let height = height.max(1);
+        let cursor = lines.len().saturating_sub(1);
+        Self {
+            top: (cursor + 1).saturating_sub(height),
+            lines,
+            cursor,
+            height,
+            mark: None,
+            query: None,
+            last_search: None,
+            message: None,
+        }
+    }
+    
+    /// Get the line the cursor is on
+    #[allow(dead_code)]
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+    
+    /// Move the cursor by `delta` lines, scrolling to keep it visible
+    pub fn move_by(&mut self, delta: isize) {
+        let last = self.lines.len().saturating_sub(1) as isize;
+        self.cursor = (self.cursor as isize + delta).clamp(0, last.max(0)) as usize;
+        self.message = None;
+        if self.cursor < self.top {
+            self.top = self.cursor;
+        } else if self.cursor >= self.top + self.height {
+            self.top = self.cursor + 1 - self.height;
+        }
+    }
+    
+    /// Move the cursor a screen up (negative) or down
+    pub fn page(&mut self, pages: isize) {
+        self.move_by(pages * self.height as isize);
+    }
+    
+    /// Move the cursor to the oldest line
+    pub fn top(&mut self) {
+        self.move_by(-(self.lines.len() as isize));
+    }
+    
+    /// Move the cursor to the newest line
+    pub fn bottom(&mut self) {
+        self.move_by(self.lines.len() as isize);
+    }
+    
+    /// Start selecting at the cursor, or stop selecting
+    pub fn toggle_mark(&mut self) {
+        self.mark = match self.mark {
+            Some(_) => None,
+            None => Some(self.cursor),
+        };
+    }
+    
+    /// Get the selected lines, or the cursor's line if nothing is selected
+    pub fn selection(&self) -> String {
+        let start = self.mark.unwrap_or(self.cursor).min(self.cursor);
+        let end = self.mark.unwrap_or(self.cursor).max(self.cursor);
+        self.lines.get(start..=end).map(|lines| lines.join("\n")).unwrap_or_default()
+    }
+    
+    /// Whether a search is being typed
+    pub fn is_searching(&self) -> bool {
+        self.query.is_some()
+    }
+    
+    /// Start typing a search
+    pub fn start_search(&mut self) {
+        self.query = Some(String::new());
+    }
+    
+    /// Add a character to the search being typed
+    pub fn push_search(&mut self, c: char) {
+        if let Some(query) = &mut self.query {
+            query.push(c);
+        }
+    }
+    
+    /// Remove the last character of the search, cancelling it once it is empty
+    pub fn pop_search(&mut self) {
+        if let Some(query) = &mut self.query {
+            if query.pop().is_none() {
+                self.query = None;
+            }
+        }
+    }
+    
+    /// Stop typing the search without running it
+    pub fn cancel_search(&mut self) {
+        self.query = None;
+    }
+    
+    /// Run the typed search, looking towards older output
+    pub fn submit_search(&mut self) {
+        if let Some(query) = self.query.take().filter(|query| !query.is_empty()) {
+            self.last_search = Some(query);
+            self.search(false);
+        }
+    }
+    
+    /// Find the next match of the last search, towards older output unless `newer`
+    ///
+    /// Matching ignores case. Returns whether a match was found.
+    pub fn search(&mut self, newer: bool) -> bool {
+        let Some(query) = self.last_search.as_ref().map(|query| query.to_lowercase()) else {
+            return false;
+        };
+        let matches = |line: &String| line.to_lowercase().contains(&query);
+        let found = if newer {
+            self.lines.iter().enumerate().skip(self.cursor + 1).find(|(_, line)| matches(line)).map(|(i, _)| i)
+        } else {
+            self.lines[..self.cursor.min(self.lines.len())].iter().rposition(matches)
+        };
+        
+        match found {
+            Some(line) => {
+                self.move_by(line as isize - self.cursor as isize);
+                true
+            },
+            None => {
+                self.message = Some(format!("Pattern not found: {}", query));
+                false
+            },
+        }
+    }
+    
+    /// Draw the visible lines and the status line, `width` columns wide
+    pub fn render(&self, width: usize) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        queue!(stdout, MoveTo(0, 0), Clear(ClearType::All))?;
+        
+        let selected = |line: usize| match self.mark {
+            Some(mark) => line >= mark.min(self.cursor) && line <= mark.max(self.cursor),
+            None => line == self.cursor,
+        };
+        for (row, line) in self.lines.iter().enumerate().skip(self.top).take(self.height) {
+            queue!(stdout, MoveTo(0, (row - self.top) as u16))?;
+            if selected(row) {
+                queue!(stdout, SetAttribute(Attribute::Reverse))?;
+            }
+            let text: String = line.replace('\t', "    ").chars().take(width).collect();
+            write!(stdout, "{}", text)?;
+            queue!(stdout, SetAttribute(Attribute::Reset))?;
+        }
+        
+        let status = match (&self.query, &self.message) {
+            (Some(query), _) => format!("/{}", query),
+            (None, Some(message)) => message.clone(),
+            (None, None) => format!(
+                "-- copy mode -- line {}/{}  (q quit, / search, n/N next, v select, y copy)",
+                self.cursor + 1,
+                self.lines.len()
+            ),
+        };
+        queue!(stdout, MoveTo(0, self.height as u16), SetAttribute(Attribute::Reverse))?;
+        write!(stdout, "{}", status.chars().take(width).collect::<String>())?;
+        queue!(stdout, SetAttribute(Attribute::Reset))?;
+        stdout.flush()
+    }
+}
@@ -1,92 +1,707 @@
-use anyhow::Result;
-use std::sync::{Arc, Mutex};
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
 
 use crate::environment::Environment;
-use crate::process::ProcessManager;
+use crate::task::{format_last_used, Task, TaskContext};
+use crate::picker::PickerItem;
+use crate::transcript::{self, Exchange, Recorder};
+use crate::process::{ProcessManager, Traffic, WriteError};
 use crate::io::{IoHandler, Command};
-use crate::config::Config;
-use crate::cli_handler::{CliHandler, CliHandlerFactory};
+use crate::config::{ClearOnSwitch, CliProfile, Config, IdleAction, TaskConfig, TaskStatus, TriggerAction};
+use crate::cli_handler::{CliHandler, CliHandlerFactory, NativeCommands};
+use crate::control::{ControlHandles, ControlServer};
+use crate::hooks;
+use crate::context;
+use crate::events::{format_duration, Event, EventKind, EventLog};
+use crate::lock::{self, SessionLock};
+use crate::clipboard::{self, Copied};
+use crate::links::Linker;
+use crate::filters::OutputFilter;
+use crate::history::{self, LineTracker};
+use crate::macros::{self, MacroState};
+use crate::template;
+use crate::backchannel::Action;
+use crate::triggers::Triggers;
+use crate::idle::{self, IdleTracker};
+use crate::signal::{self, Signal};
+use crate::sandbox::Sandbox;
+use crate::remote;
+use crate::confirm;
+use crate::help::{self, Help};
+use crate::version::{self, VersionInfo};
+use crate::keys::KeyMap;
+use crate::startup::StartupPipeline;
+use crate::sidecar::Sidecars;
+use crate::watch::{Outcome, Watch, POLL_INTERVAL};
+use crate::heartbeat::{self, Heartbeat};
+use crate::metrics::{self, ResponseTimer};
+use crate::crash::{self, CrashLog};
+use crate::switch::{Launch, SwitchPlan};
+
+/// How long the CLI must stay quiet to count as started, when its prompt isn't recognized
+const STARTUP_QUIET: tokio::time::Duration = tokio::time::Duration::from_secs(2);
+
+/// How long the CLI is given to exit after being asked to, before it is stopped
+const QUIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(3);
+
+/// Most bytes of the CLI's startup output shown when it never gets to its prompt
+const STARTUP_OUTPUT_SHOWN: usize = 2000;
+
+/// Messages listed by `/history input` without a picker
+const INPUT_HISTORY_SHOWN: usize = 20;
+
+/// How long a question from grill waits for an answer before taking it as no
+const QUESTION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Where a yes or no question from grill waits for the user's answer
+type Question = Arc<Mutex<Option<oneshot::Sender<bool>>>>;
 
 /// Manages a grill session
 pub struct Session {
     environment: Environment,
     process_manager: Option<ProcessManager>,
-    current_task: Option<String>,
     running: Arc<Mutex<bool>>,
-    cli_handler: Option<CliHandler>,
+    control_servers: Vec<ControlServer>,
+    headless: bool,
+    events: EventLog,
+    state: Option<Arc<RwLock<SessionState>>>,
+    locks: Option<Arc<Mutex<SessionLocks>>>,
+    recorder: Arc<Mutex<Recorder>>,
+    keep_transcript: bool,
+    /// Set by `/quit`, after which input is no longer sent to the CLI
+    quitting: Arc<AtomicBool>,
+    /// Keeps the IO handler reading keys; cleared to hand the terminal back
+    io_running: Option<Arc<Mutex<bool>>>,
+    /// Commands running alongside the CLI for the active task
+    sidecars: Arc<Mutex<Sidecars>>,
+    /// Set by `/restart`, after which the session ends to be started again
+    restarting: Arc<AtomicBool>,
+    /// Bytes written to and read from the CLI
+    traffic: Option<Arc<Traffic>>,
+    /// Where metrics are written for Prometheus, if `[metrics] textfile` is set
+    metrics_textfile: Option<PathBuf>,
+    /// Recent output and commands, for a crash report
+    crash_log: Arc<Mutex<CrashLog>>,
+    /// Report written when the CLI crashed, shown once the terminal is restored
+    crash_report: Option<PathBuf>,
+}
+
+/// What a running session is working on
+///
+/// Shared by everything the session spawns, so a task switch is seen by all
+/// of them at once.
+#[derive(Clone)]
+pub struct SessionState {
+    /// The task the session is running, and where its output goes
+    pub task: TaskContext,
+    /// The handler for the task's CLI
+    pub cli_handler: CliHandler,
+}
+
+/// Locks that keep sessions in the same workspace from clobbering each other
+struct SessionLocks {
+    /// Held by the first session, which alone updates `current_task`
+    workspace: Option<SessionLock>,
+    /// Held for the task the session is running
+    task: SessionLock,
 }
 
 impl Session {
     /// Create a new session
     pub fn new(environment: Environment) -> Self {
+        let events = EventLog::new(environment.get_events_path());
+        
         Self {
             environment,
             process_manager: None,
-            current_task: None,
             running: Arc::new(Mutex::new(false)),
-            cli_handler: None,
+            control_servers: Vec::new(),
+            headless: false,
+            events,
+            state: None,
+            locks: None,
+            recorder: Arc::new(Mutex::new(Recorder::new())),
+            keep_transcript: false,
+            quitting: Arc::new(AtomicBool::new(false)),
+            io_running: None,
+            sidecars: Arc::new(Mutex::new(Sidecars::default())),
+            restarting: Arc::new(AtomicBool::new(false)),
+            traffic: None,
+            metrics_textfile: None,
+            crash_log: Arc::new(Mutex::new(CrashLog::default())),
+            crash_report: None,
         }
     }
     
+    /// Run without a terminal, serving output only to attached clients
+    pub fn set_headless(&mut self, headless: bool) {
+        self.headless = headless;
+    }
+    
     /// Start the session
     pub async fn start(&mut self, task_name: Option<String>) -> Result<()> {
-        // Set running state
-        let mut running = self.running.lock().unwrap();
-        *running = true;
-        drop(running);
-        
         // Get the current task
+        let explicit_task = task_name.is_some();
         let task_name = match task_name {
             Some(name) => name,
             None => self.environment.get_current_task()?,
         };
         
-        self.current_task = Some(task_name.clone());
+        // Another session may already be running in this workspace
+        let workspace_lock = SessionLock::acquire(&self.environment.get_lock_path(), &task_name)?;
+        if workspace_lock.is_none() && !explicit_task {
+            return Err(already_running(&self.environment.get_lock_path(), "A grill session is already running in this workspace"));
+        }
+        let task_lock = SessionLock::acquire(&self.environment.get_task_lock_path(&task_name)?, &task_name)?
+            .ok_or_else(|| already_running(
+                &self.environment.get_task_lock_path(&task_name).unwrap_or_default(),
+                &format!("Task '{}' is already running in another grill session", task_name),
+            ))?;
+        let locks = Arc::new(Mutex::new(SessionLocks { workspace: workspace_lock, task: task_lock }));
+        self.locks = Some(Arc::clone(&locks));
         
-        // Get the CLI command for the task
-        let cli_command = self.get_cli_command(&task_name)?;
+        // Set running state
+        *self.running.lock().unwrap() = true;
+        
+        // Get the CLI for the task
+        let cli = Self::get_cli_for_task(&self.environment, &task_name)?;
         
         // Create the appropriate CLI handler
-        let cli_handler = CliHandlerFactory::create_handler(cli_command.clone());
+        let cli_handler = CliHandlerFactory::create_handler(cli.command_line());
+        let cli_command = cli_handler.get_command().to_string();
+        
+        let config = Config::load(&self.environment.get_config_path())?;
+        self.metrics_textfile = config.metrics.textfile.as_ref()
+            .map(|path| self.environment.get_root_dir().join(path));
+        
+        // A CLI on another machine takes longer to start and to go quiet
+        let latency = if remote::is_ssh(&cli.command) {
+            config.remote.latency()
+        } else {
+            std::time::Duration::ZERO
+        };
+        let startup_quiet = STARTUP_QUIET + latency;
         
         // Create IO handler and channels
-        let (mut io_handler, input_tx, output_tx, command_tx) = IoHandler::new();
+        let (mut io_handler, input_tx, output_tx, command_tx) =
+            IoHandler::with_capacity(config.performance.output_channel_capacity);
+        io_handler.set_output_batching(config.performance.flush_interval(), config.performance.output_batch_size);
+        io_handler.set_mouse_capture(config.terminal.mouse);
+        io_handler.set_render_markdown(config.terminal.render_markdown);
+        io_handler.set_confirm_bytes(config.input.confirm_bytes);
+        let keys = KeyMap::new(&config.keys)?;
+        io_handler.set_keys(keys.clone());
+        io_handler.set_aliases(config.aliases.clone());
+        let native_commands = NativeCommands::for_cli(&cli_handler, &cli);
+        io_handler.set_native_commands(native_commands.clone());
+        io_handler.set_multiline_input(cli_handler.capabilities().multiline_input);
+        let link_root = self.environment.get_task_working_dir(&task_name)?
+            .unwrap_or_else(|| self.environment.get_root_dir());
+        io_handler.set_links(Arc::new(Mutex::new(Linker::new(link_root, config.terminal.hyperlinks))));
         
         // Subscribe to commands
         let mut command_rx = command_tx.subscribe();
         
         // Create process manager
-        let mut process_manager = ProcessManager::new(cli_handler.get_command());
+        let mut process_manager = ProcessManager::for_cli(&cli);
+        process_manager.set_working_dir(self.environment.get_task_working_dir(&task_name)?);
+        let task_dir = self.environment.get_task_dir(&task_name)?;
+        if !config.context.skip_context {
+            process_manager.add_args(cli_handler.context_args(&self.environment, &task_name, &task_dir));
+        }
+        let start_config = TaskConfig::load_for_task(&task_dir)?;
+        let launch = Launch::new(&start_config, &config);
+        process_manager.set_sandbox(Sandbox::for_task(&self.environment, &task_name, &start_config)?);
+        process_manager.set_limits(start_config.limits);
+        process_manager.set_remote(config.remote.clone());
+        process_manager.set_inherited_env(config.environment.clone());
+        process_manager.set_shell_wrap(config.shell_wrap);
+        process_manager.set_performance(config.performance.clone());
+        process_manager.set_input(config.input.clone());
+        process_manager.set_output_filter(OutputFilter::new(&config.filters)?);
+        let triggers = Arc::new(Mutex::new(Triggers::new(&config.triggers)?));
+        let (actions_tx, mut actions_rx) = mpsc::unbounded_channel::<Action>();
+        if config.terminal.backchannel {
+            process_manager.set_backchannel(actions_tx);
+        }
+        let (write_errors_tx, mut write_errors_rx) = mpsc::unbounded_channel::<WriteError>();
+        process_manager.set_write_errors(write_errors_tx);
+        let uses_worktrees = config.git.worktrees;
+        
+        // The recorder is fed every byte, however far behind the display falls
+        let (tap_tx, mut recorder_rx) = mpsc::unbounded_channel::<String>();
+        process_manager.set_output_tap(tap_tx);
         
         // Clone the handler for the process manager
         let cli_handler_clone = cli_handler.clone();
         
         // Start the process
-        let process_input_tx = process_manager.start(output_tx.clone(), cli_handler_clone)?;
+        let process_input_tx = process_manager.start(output_tx.clone(), cli_handler_clone)
+            .map_err(|e| crate::clis::explain_start_error(e, &self.environment, &task_name))?;
         
         // Clone the process input sender for the command processing task
         let process_input_tx_for_commands = process_input_tx.clone();
+        let child_pid = process_manager.process_id();
         
-        // Store the process manager and CLI handler
+        // Store the process manager, and share what the session is running
+        self.traffic = Some(process_manager.traffic());
         self.process_manager = Some(process_manager);
-        self.cli_handler = Some(cli_handler.clone());
+        let sidecar_dir = self.environment.get_task_working_dir(&task_name)?
+            .unwrap_or_else(|| self.environment.get_root_dir());
+        start_sidecars(&self.sidecars, &start_config.sidecars, &sidecar_dir, &output_tx).await;
+        let state = Arc::new(RwLock::new(SessionState { task: TaskContext::new(&self.environment, &task_name), cli_handler: cli_handler.clone() }));
+        self.state = Some(Arc::clone(&state));
+        
+        // Output is mirrored to attached clients, with recent output kept for replay
+        let (output_mirror, _) = broadcast::channel::<String>(100);
+        let replay = Arc::new(Mutex::new(String::new()));
+        
+        // Listen for control clients (`grill ctl`, `grill attach`)
+        self.events.record(&task_name, EventKind::SessionStart);
+        if let Err(e) = self.environment.mark_task_started(&task_name) {
+            eprintln!("Warning: Failed to update task status: {}", e);
+        }
+        let control_handles = ControlHandles {
+            session: Arc::clone(&state),
+            command: cli_command.clone(),
+            input_tx: input_tx.clone(),
+            command_tx: command_tx.clone(),
+            output_mirror: output_mirror.clone(),
+            replay: Arc::clone(&replay),
+        };
+        // The shared socket belongs to the first session; every session also
+        // listens on a per-task socket so concurrent sessions can be told apart
+        let socket_paths = [
+            self.environment.get_socket_path(),
+            self.environment.get_task_socket_path(&task_name)?,
+        ];
+        for socket_path in socket_paths {
+            match ControlServer::start(socket_path, control_handles.clone()) {
+                Ok(server) => self.control_servers.push(server),
+                Err(e) => eprintln!("Warning: Control socket unavailable: {}", e),
+            }
+        }
         
         // Send welcome message using the CLI handler
         cli_handler.on_start(&task_name, &output_tx)?;
         
+        // Context waits while the CLI asks the user to sign in
+        let (signing_in_tx, signing_in) = watch::channel(false);
+        let (activity_tx, _) = broadcast::channel::<bool>(100);
+        let paste_handler = cli_handler.clone();
+        let mut startup = StartupPipeline::new(startup_quiet, move |message| paste_handler.paste_message(message));
+        
+        // A conversation saved when the task was last left carries on where it
+        // was, in place of the task's context and transcript
+        let resume_conversations = config.context.resume_conversations;
+        let context_config = config.context.clone();
+        let saved_conversation = if resume_conversations && !config.context.skip_context {
+            let task = state.read().unwrap().task.clone();
+            task.saved_conversation().and_then(|path| cli_handler.conversation_commands(path))
+        } else {
+            None
+        };
+        
+        // The task's persona, instructions and state go first, read once the CLI is ready
+        if let Some(commands) = &saved_conversation {
+            let _ = output_tx.try_send("Resuming the task's saved conversation\n".to_string());
+            startup.message(commands.load.clone());
+        } else if !config.context.skip_context {
+            let cli_handler = cli_handler.clone();
+            let environment = self.environment.clone();
+            let task_dir = self.environment.get_task_dir(&task_name)?;
+            let task_name = task_name.clone();
+            startup.deferred(move || {
+                cli_handler.task_context_messages(&environment, &task_name, &task_dir).into_iter()
+                    .map(|(_, message)| message)
+            });
+        }
+        
+        // Optionally give the CLI the project structure once it has started
+        if config.context.repo_map_on_start {
+            let dir = self.environment.get_task_working_dir(&task_name)?
+                .unwrap_or_else(|| self.environment.get_root_dir());
+            let max_size = config.context.max_repo_map;
+            startup.deferred(move || Some(context::repo_map_message(&dir, max_size)));
+        }
+        
+        // Optionally keep a transcript, and remind the CLI of how it ended last time
+        let keep_transcript = config.context.resume_context;
+        self.keep_transcript = keep_transcript;
+        if keep_transcript && saved_conversation.is_none() {
+            let transcript_path = self.environment.get_transcript_path(&task_name);
+            match transcript::load_recent(&transcript_path, config.context.resume_exchanges) {
+                Ok(exchanges) => {
+                    if let Some(message) = transcript::resume_message(&task_name, &exchanges) {
+                        startup.message(message);
+                    }
+                },
+                Err(e) => eprintln!("Warning: Could not load transcript: {}", e),
+            }
+        }
+        
+        // Context is sent in order once the CLI is ready, rather than racing its banner
+        tokio::spawn(startup.run(activity_tx.subscribe(), signing_in, process_input_tx.clone()));
+        
+        // Programs running inside the CLI can ask grill to act through the backchannel
+        {
+            let state = Arc::clone(&state);
+            let command_tx = command_tx.clone();
+            let output_tx = output_tx.clone();
+            tokio::spawn(async move {
+                while let Some(action) = actions_rx.recv().await {
+                    let message = match action {
+                        Action::SwitchTask { task } => {
+                            let _ = command_tx.send(Command::SwitchTask(task));
+                            continue;
+                        },
+                        Action::AppendState { text } => {
+                            let task = state.read().unwrap().task.clone();
+                            match task.append_state(&text) {
+                                Ok(()) => format!("\n[grill] Updated the state of task '{}'\n", task.name()),
+                                Err(e) => format!("\n[grill] Error updating state: {:#}\n", e),
+                            }
+                        },
+                        Action::Notify { message, title } => match title {
+                            Some(title) => notification(&format!("{}: {}", title, message)),
+                            None => notification(&message),
+                        },
+                    };
+                    let _ = output_tx.send(message).await;
+                }
+            });
+        }
+        
+        // Input that was cut short is reported, so nobody thinks the CLI has it all
+        {
+            let output_tx = output_tx.clone();
+            tokio::spawn(async move {
+                while let Some(error) = write_errors_rx.recv().await {
+                    log::warn!("{}", error);
+                    let _ = output_tx.send(format!("\n\x1b[1;31m[grill] {}\x1b[0m\n", error)).await;
+                }
+            });
+        }
+        
+        // The conversation is always followed, for /copy, and so macros know when the CLI is ready
+        let recorder = Arc::clone(&self.recorder);
+        
+        // Tell the user when the CLI goes quiet in the middle of answering
+        let heartbeat = config.heartbeat.stuck_after()
+            .map(|threshold| Arc::new(Mutex::new(Heartbeat::new(threshold))));
+        if let Some(heartbeat) = &heartbeat {
+            let heartbeat = Arc::clone(heartbeat);
+            let output_tx = output_tx.clone();
+            let running = Arc::clone(&self.running);
+            tokio::spawn(async move {
+                loop {
+                    let next_check = heartbeat.lock().unwrap().next_check(std::time::Instant::now());
+                    tokio::time::sleep_until(next_check.into()).await;
+                    if !*running.lock().unwrap() {
+                        break;
+                    }
+                    let now = std::time::Instant::now();
+                    let quiet = {
+                        let mut heartbeat = heartbeat.lock().unwrap();
+                        heartbeat.check(now).then(|| heartbeat.quiet_for(now))
+                    };
+                    if let Some(quiet) = quiet {
+                        if output_tx.send(heartbeat::stuck_notice(quiet)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            });
+        }
+        
+        // Time each answer from the message that asked for it to the prompt
+        let response_timer = Arc::new(Mutex::new(ResponseTimer::default()));
+        
+        // Warn when the CLI never gets to its prompt, e.g. because it wants a login,
+        // showing what it printed instead of leaving the session silently hung
+        let startup_output = Arc::new(Mutex::new(Some(String::new())));
+        if let Some(timeout) = config.startup.timeout() {
+            let mut activity = activity_tx.subscribe();
+            let startup_output = Arc::clone(&startup_output);
+            let output_tx = output_tx.clone();
+            let timeout = timeout + latency;
+            tokio::spawn(async move {
+                if !wait_until_ready(&mut activity, timeout).await {
+                    let output = startup_output.lock().unwrap().take().unwrap_or_default();
+                    let _ = output_tx.send(startup_failure_message(timeout, &output)).await;
+                }
+            });
+        }
+        {
+            let startup_output = Arc::clone(&startup_output);
+            let recorder = Arc::clone(&recorder);
+            let environment = self.environment.clone();
+            let state = Arc::clone(&state);
+            let activity_tx = activity_tx.clone();
+            let output_tx = output_tx.clone();
+            let triggers = Arc::clone(&triggers);
+            let input_tx = input_tx.clone();
+            let heartbeat = heartbeat.clone();
+            let response_timer = Arc::clone(&response_timer);
+            let events = self.events.clone();
+            let metrics_textfile = self.metrics_textfile.clone();
+            let crash_log = Arc::clone(&self.crash_log);
+            tokio::spawn(async move {
+                while let Some(output) = recorder_rx.recv().await {
+                    crash_log.lock().unwrap().output(&output);
+                    // A plugin handler is asked outside the lock, so a slow one holds up only this
+                    let cli_handler = state.read().unwrap().cli_handler.clone();
+                    let at_prompt = cli_handler.is_prompt(&output);
+                    let _ = activity_tx.send(at_prompt);
+                    if let Some(heartbeat) = &heartbeat {
+                        heartbeat.lock().unwrap().output(std::time::Instant::now(), at_prompt);
+                    }
+                    let response = response_timer.lock().unwrap().output(std::time::Instant::now(), &output, at_prompt);
+                    if let Some(response) = response {
+                        let task = state.read().unwrap().task.name().to_string();
+                        events.record(&task, EventKind::Response {
+                            first_byte_ms: response.first_byte.as_millis() as u64,
+                            prompt_ms: response.to_prompt.as_millis() as u64,
+                        });
+                        if let Some(textfile) = metrics_textfile.clone() {
+                            let environment = environment.clone();
+                            tokio::task::spawn_blocking(move || export_metrics(&environment, &textfile));
+                        }
+                    }
+                    record_startup_output(&startup_output, &output, at_prompt);
+                    if at_prompt {
+                        // Back at the prompt, any sign-in is over
+                        signing_in_tx.send_if_modified(std::mem::take);
+                    } else if let Some(instructions) = cli_handler.auth_prompt(&output) {
+                        if !signing_in_tx.send_replace(true) {
+                            let _ = output_tx.try_send(sign_in_notice(&instructions));
+                        }
+                    }
+                    let fired = triggers.lock().unwrap().check(&output);
+                    for (action, matched) in fired {
+                        let task = state.read().unwrap().task.name().to_string();
+                        tokio::spawn(run_trigger(action, matched, environment.clone(), task, input_tx.clone(), output_tx.clone()));
+                    }
+                    let exchange = recorder.lock().unwrap().output(&output, at_prompt);
+                    if let Some(exchange) = exchange {
+                        let task = state.read().unwrap().task.clone();
+                        if keep_transcript {
+                            record_exchange(&task, &exchange);
+                        }
+                        if exchange.prompt == idle::SUMMARY_PROMPT {
+                            let _ = output_tx.send(save_summary(&task, &exchange.response)).await;
+                        }
+                        run_response_hooks(&environment, task.name(), &exchange, &output_tx);
+                    }
+                }
+            });
+        }
+        
+        // Act once nothing has been typed for a while
+        let idle = (config.idle.timeout_minutes > 0).then(|| {
+            let timeout = std::time::Duration::from_secs(config.idle.timeout_minutes * 60);
+            Arc::new(Mutex::new(IdleTracker::new(timeout, std::time::Instant::now())))
+        });
+        if let Some(idle) = &idle {
+            let idle = Arc::clone(idle);
+            let action = config.idle.action;
+            let minutes = config.idle.timeout_minutes;
+            let recorder = Arc::clone(&recorder);
+            let state = Arc::clone(&state);
+            let process_input_tx = process_input_tx.clone();
+            let output_tx = output_tx.clone();
+            tokio::spawn(async move {
+                loop {
+                    let next_check = idle.lock().unwrap().next_check(std::time::Instant::now());
+                    tokio::time::sleep_until(next_check.into()).await;
+                    if !idle.lock().unwrap().check(std::time::Instant::now()) {
+                        continue;
+                    }
+                    
+                    let task = state.read().unwrap().task.name().to_string();
+                    let message = match action {
+                        IdleAction::Notify => notification(&format!("Nothing typed for {} minutes in task '{}'", minutes, task)),
+                        IdleAction::Summarize => {
+                            // The recorder is told about the prompt, so the response can be picked out
+                            let prompt = format!("{}\r", idle::SUMMARY_PROMPT);
+                            recorder.lock().unwrap().input(&prompt);
+                            let _ = process_input_tx.send(prompt).await;
+                            format!("\n[grill] Nothing typed for {} minutes; asking the CLI to summarize task '{}'\n", minutes, task)
+                        },
+                        IdleAction::Pause => match child_pid.map(|pid| signal::send(pid, Signal::Pause)) {
+                            Some(Ok(())) => format!("\n[grill] Nothing typed for {} minutes; the CLI is paused until you type\n", minutes),
+                            Some(Err(e)) => format!("\n[grill] Could not pause the CLI: {}\n", e),
+                            None => continue,
+                        },
+                    };
+                    let _ = output_tx.send(message).await;
+                }
+            });
+        }
+        
         // Create a direct connection between IoHandler and ProcessManager
         let input_tx_clone = input_tx.clone();
         
-        // Clone the handler for the input processing task
-        let cli_handler_for_input = cli_handler.clone();
+        let events_for_input = self.events.clone();
+        let state_for_input = Arc::clone(&state);
+        let recorder_for_input = Arc::clone(&recorder);
+        let quitting_for_input = Arc::clone(&self.quitting);
+        let environment_for_input = self.environment.clone();
+        let output_tx_for_input = output_tx.clone();
+        let triggers_for_input = Arc::clone(&triggers);
+        let idle_for_input = idle.clone();
+        let heartbeat_for_input = heartbeat.clone();
+        let response_timer_for_input = Arc::clone(&response_timer);
+        let crash_log_for_input = Arc::clone(&self.crash_log);
+        let idle_action = config.idle.action;
+        let macro_quiet = macros::PLAYBACK_QUIET + latency;
+        let aliases = config.aliases.clone();
+        let mut input_lines = LineTracker::default();
+        let macros = Arc::new(Mutex::new(MacroState::default()));
+        io_handler.set_macros(Arc::clone(&macros));
+        let macros_for_input = Arc::clone(&macros);
+        if let Ok(path) = self.environment.get_input_history_path(&task_name) {
+            if let Err(e) = history::trim(&path, history::MAX_INPUT_HISTORY) {
+                log::warn!("Could not trim input history: {}", e);
+            }
+        }
+        
+        // The switch and the watch each ask their own questions, so neither
+        // can take the answer meant for the other
+        let headless = self.headless;
+        let switch_question = Question::default();
+        let watch_question = Question::default();
+        
+        // Rerun the active task's watch command as its files change, offering
+        // to send the output to the CLI when it fails
+        {
+            let environment = self.environment.clone();
+            let state = Arc::clone(&state);
+            let question = Arc::clone(&watch_question);
+            let process_input_tx = process_input_tx.clone();
+            let output_tx = output_tx.clone();
+            let max_output = config.context.max_output;
+            let running = Arc::clone(&self.running);
+            tokio::spawn(async move {
+                let mut watching: Option<Watch> = None;
+                loop {
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                    if output_tx.is_closed() || !*running.lock().unwrap() {
+                        break;
+                    }
+                    
+                    // The task may have been switched, or its config edited
+                    let task = state.read().unwrap().task.name().to_string();
+                    let command = environment.get_task_dir(&task).ok()
+                        .and_then(|dir| TaskConfig::load_for_task(&dir).ok())
+                        .and_then(|config| config.watch);
+                    let Some(command) = command else {
+                        watching = None;
+                        continue;
+                    };
+                    let dir = environment.get_task_working_dir(&task).ok().flatten()
+                        .unwrap_or_else(|| environment.get_root_dir());
+                    
+                    let current = watching.take().filter(|watch| watch.watches(&command, &dir));
+                    let checked = tokio::task::spawn_blocking(move || {
+                        let Some(mut watch) = current else {
+                            return (Watch::new(&command, dir), Ok(None));
+                        };
+                        let outcome = watch.check(max_output);
+                        (watch, outcome)
+                    }).await;
+                    let Ok((watch, outcome)) = checked else {
+                        continue;
+                    };
+                    match outcome {
+                        Ok(Some(Outcome::Failed(message))) => {
+                            let _ = output_tx.send(format!("\n[grill] `{}` failed after your changes\n", watch.command())).await;
+                            if ask(&question, headless, &output_tx, "Send its output to the CLI?").await {
+                                let cli_handler = state.read().unwrap().cli_handler.clone();
+                                let _ = process_input_tx.send(cli_handler.paste_message(&message)).await;
+                            }
+                        },
+                        Ok(Some(Outcome::Passed { recovered: true })) => {
+                            let _ = output_tx.send(format!("\n[grill] `{}` passes again\n", watch.command())).await;
+                        },
+                        Ok(_) => {},
+                        Err(e) => {
+                            let _ = output_tx.send(format!("\n[grill] Could not run `{}`: {}\n", watch.command(), e)).await;
+                        },
+                    }
+                    watching = Some(watch);
+                }
+            });
+        }
+        let questions_for_input = [Arc::clone(&switch_question), Arc::clone(&watch_question)];
         
         // Forward input from IoHandler to ProcessManager
         tokio::spawn(async move {
             let mut input_rx = input_tx_clone.subscribe();
             
             while let Ok(input) = input_rx.recv().await {
+                if quitting_for_input.load(Ordering::SeqCst) {
+                    break;
+                }
+                // A question from grill takes the next key as its answer, unless
+                // the user is in the middle of typing a message to the CLI
+                let pending = if input_lines.is_empty() {
+                    questions_for_input.iter()
+                        .find_map(|question| question.lock().unwrap().take().filter(|answer| !answer.is_closed()))
+                } else {
+                    None
+                };
+                if let Some(answer) = pending {
+                    let yes = input.trim().eq_ignore_ascii_case("y");
+                    let _ = output_tx_for_input.send(if yes { "y\n" } else { "n\n" }.to_string()).await;
+                    let _ = answer.send(yes);
+                    continue;
+                }
+                let was_idle = idle_for_input.as_ref().is_some_and(|idle| idle.lock().unwrap().input(std::time::Instant::now()));
+                if was_idle && idle_action == IdleAction::Pause {
+                    if let Some(Err(e)) = child_pid.map(|pid| signal::send(pid, Signal::Resume)) {
+                        log::warn!("Could not resume the CLI: {}", e);
+                    }
+                }
+                
+                // Each submitted line is a message to the CLI
+                if input.ends_with('\r') {
+                    let task = state_for_input.read().unwrap().task.name().to_string();
+                    events_for_input.record(&task, EventKind::Message);
+                    if let Some(heartbeat) = &heartbeat_for_input {
+                        heartbeat.lock().unwrap().input(std::time::Instant::now());
+                    }
+                    response_timer_for_input.lock().unwrap().input(std::time::Instant::now());
+                }
+                let submitted = input_lines.input(&input);
+                if !submitted.is_empty() {
+                    triggers_for_input.lock().unwrap().reset();
+                    let task = state_for_input.read().unwrap().task.clone();
+                    record_input_history(&task, &submitted);
+                    let mut macros = macros_for_input.lock().unwrap();
+                    let mut crash_log = crash_log_for_input.lock().unwrap();
+                    for line in &submitted {
+                        macros.record(line);
+                        crash_log.command(line);
+                    }
+                }
+                let exchanges = recorder_for_input.lock().unwrap().input(&input);
+                if !exchanges.is_empty() {
+                    let task = state_for_input.read().unwrap().task.clone();
+                    for exchange in exchanges {
+                        if keep_transcript {
+                            record_exchange(&task, &exchange);
+                        }
+                        run_response_hooks(&environment_for_input, task.name(), &exchange, &output_tx_for_input);
+                    }
+                }
+                
                 // Intercept input using CLI handler
-                match cli_handler_for_input.intercept_input(input.clone()) {
+                let cli_handler = state_for_input.read().unwrap().cli_handler.clone();
+                match cli_handler.intercept_input(input.clone()) {
                     Ok(Some(modified_input)) => {
                         // Send the processed input to the child process
                         if let Err(e) = process_input_tx.send(modified_input).await {
@@ -110,13 +725,27 @@ impl Session {
         
         // Set up command processing
         let environment = self.environment.clone();
-        let current_task = task_name.clone();
+        let events = self.events.clone();
+        let state_for_commands = Arc::clone(&state);
         let output_tx_clone = output_tx.clone();
+        let sidecars = Arc::clone(&self.sidecars);
         let running_clone = Arc::clone(&self.running);
+        let quitting = Arc::clone(&self.quitting);
+        let restarting = Arc::clone(&self.restarting);
         let process_input_tx_clone = process_input_tx_for_commands;
-        
-        // Clone the handler for the command processing task
-        let cli_handler_for_commands = cli_handler.clone();
+        let input_tx_for_commands = input_tx.clone();
+        let command_tx_for_commands = command_tx.clone();
+        let mut help = Help::new(&keys);
+        help.add_cli_topic(help::cli_topic(&help::cli_name(&cli_command), &cli_handler.get_help_text(), &native_commands));
+        *self.crash_log.lock().unwrap() = CrashLog::new(crash::summary(&self.environment.get_root_dir(), &task_name, &cli_command, &cli_handler.name()));
+        crash::install(self.environment.get_crash_dir(), Arc::clone(&self.crash_log));
+        let crash_log_for_commands = Arc::clone(&self.crash_log);
+        let version_info = VersionInfo {
+            workspace: Some(self.environment.get_root_dir()),
+            task: None,
+            cli: Some(cli_command.clone()),
+            handler: Some(cli_handler.name()),
+        };
         
         // Process commands
         tokio::spawn(async move {
@@ -128,10 +757,12 @@ impl Session {
             // Process commands
             while let Ok(command) = command_rx.recv().await {
                 eprintln!("Processing command: {:?}", command);
+                crash_log_for_commands.lock().unwrap().command(&format!("{:?}", command));
+                let SessionState { task: current_task, cli_handler } = state_for_commands.read().unwrap().clone();
                 
                 // First, try to handle the command with the CLI-specific handler
                 let mut handled = false;
-                match cli_handler_for_commands.process_command(command.clone(), &output_tx_clone, &current_task) {
+                match cli_handler.process_command(command.clone(), &output_tx_clone, current_task.name()) {
                     Ok(true) => {
                         handled = true;
                     },
@@ -148,22 +779,53 @@ impl Session {
                 if !handled {
                     match command {
                         Command::Quit => {
+                            // Stop taking input and ask the CLI to exit; the rest of
+                            // the shutdown happens once the session stops running
+                            quitting.store(true, Ordering::SeqCst);
                             let _ = output_tx_clone.send("\nExiting grill...\n".to_string()).await;
-                            // Set running to false
-                            let mut running = running_clone.lock().unwrap();
-                            *running = false;
+                            if resume_conversations {
+                                save_conversation(&cli_handler, &current_task, &process_input_tx_clone).await;
+                            }
+                            if let Err(e) = cli_handler.on_quit(&process_input_tx_clone).await {
+                                log::warn!("Could not ask the CLI to exit: {}", e);
+                            }
+                            *running_clone.lock().unwrap() = false;
                             break;
                         },
+                        Command::Restart => {
+                            // The session ends as it does on /quit, without asking the
+                            // CLI to exit or running on_quit hooks, and starts again
+                            restarting.store(true, Ordering::SeqCst);
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            events.record(&task, EventKind::Restart);
+                            let _ = output_tx_clone.send("\nRestarting the CLI...\n".to_string()).await;
+                            *running_clone.lock().unwrap() = false;
+                            break;
+                        },
+                        Command::Detach => {
+                            let _ = output_tx_clone.send("\nDetaching is only available in sessions started with 'grill start --detach'.\n\n".to_string()).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
                         Command::ListTasks => {
                             // List all tasks
-                            match environment.list_tasks() {
+                            let active_task = state_for_commands.read().unwrap().task.name().to_string();
+                            match environment.list_recent_tasks() {
                                 Ok(tasks) => {
                                     let mut output = String::from("\nAvailable tasks:\n");
-                                    for task in tasks {
-                                        if task == current_task {
-                                            output.push_str(&format!("* {} (current)\n", task));
+                                    for (task, last_used) in tasks {
+                                        let status = if environment.is_global_task(&task) {
+                                            " (global)".to_string()
                                         } else {
-                                            output.push_str(&format!("  {}\n", task));
+                                            match environment.get_task_config(&task) {
+                                                Ok(config) => format!(" [{}]", config.status),
+                                                Err(_) => String::new(),
+                                            }
+                                        };
+                                        let last_used = format_last_used(last_used);
+                                        if task == active_task {
+                                            output.push_str(&format!("* {} (current){} - last used {}\n", task, status, last_used));
+                                        } else {
+                                            output.push_str(&format!("  {}{} - last used {}\n", task, status, last_used));
                                         }
                                     }
                                     output.push('\n');
@@ -177,66 +839,459 @@ impl Session {
                             // Send a carriage return to the CLI to get the prompt back
                             send_prompt_restore(&process_input_tx_clone).await;
                         },
+                        Command::TaskDone { task, archive } => {
+                            let task = task.unwrap_or_else(|| state_for_commands.read().unwrap().task.name().to_string());
+                            match environment.set_task_status(&task, TaskStatus::Done, None) {
+                                Ok(_) => {
+                                    let _ = output_tx_clone.send(format!("\nMarked task '{}' as done\n", task)).await;
+                                    if archive {
+                                        match environment.archive_task(&task) {
+                                            Ok(path) => {
+                                                let _ = output_tx_clone.send(format!("Archived task '{}' to {}\n", task, path.display())).await;
+                                            },
+                                            Err(e) => {
+                                                let _ = output_tx_clone.send(format!("Error archiving task '{}': {}\n", task, e)).await;
+                                            }
+                                        }
+                                    }
+                                    let _ = output_tx_clone.send("\n".to_string()).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError updating task '{}': {}\n\n", task, e)).await;
+                                }
+                            }
+                            
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::BlockTask(reason) => {
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            let reason = if reason.is_empty() { None } else { Some(reason.as_str()) };
+                            match environment.set_task_status(&task, TaskStatus::Blocked, reason) {
+                                Ok(_) => {
+                                    let _ = output_tx_clone.send(format!("\nMarked task '{}' as blocked\n\n", task)).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError updating task '{}': {}\n\n", task, e)).await;
+                                }
+                            }
+                            
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::AddNote(text) => {
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            match environment.add_note(&task, &text) {
+                                Ok(_) => {
+                                    let _ = output_tx_clone.send(format!("\nAdded note to task '{}'\n\n", task)).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError adding note: {}\n\n", e)).await;
+                                }
+                            }
+                            
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::ShowNotes => {
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            match environment.get_notes(&task) {
+                                Ok(Some(notes)) => {
+                                    let _ = output_tx_clone.send(format!("\n{}\n", notes.trim_end())).await;
+                                    let _ = output_tx_clone.send("\n".to_string()).await;
+                                },
+                                Ok(None) => {
+                                    let _ = output_tx_clone.send(format!("\nNo notes for task '{}' yet. Add one with /note <text>\n\n", task)).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError reading notes: {}\n\n", e)).await;
+                                }
+                            }
+                            
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::Copy => {
+                            let response = recorder.lock().unwrap().latest_response();
+                            let message = match response {
+                                Some(response) => {
+                                    let clipboard = Config::load(&environment.get_config_path())
+                                        .map(|config| config.clipboard)
+                                        .unwrap_or_default();
+                                    match clipboard::copy_text(&clipboard, &response) {
+                                        Ok(Copied::Program(_)) => format!("\nCopied the last response ({} lines)\n\n", response.lines().count()),
+                                        Ok(Copied::Terminal(sequence)) => format!("{}\nCopied the last response ({} lines) through the terminal\n\n", sequence, response.lines().count()),
+                                        Err(e) => format!("\nError copying: {}\n\n", e),
+                                    }
+                                },
+                                None => "\nThere is no response to copy yet\n\n".to_string(),
+                            };
+                            let _ = output_tx_clone.send(message).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::Paste { force } => {
+                            let config = Config::load(&environment.get_config_path()).unwrap_or_default();
+                            let clipboard = config.clipboard;
+                            let pasted = tokio::task::spawn_blocking(move || clipboard::paste_text(&clipboard)).await
+                                .unwrap_or_else(|e| Err(anyhow!(e)));
+                            match pasted {
+                                Ok(text) if !force && confirm::needs_confirmation(&text, config.input.confirm_bytes) => {
+                                    let message = format!(
+                                        "\nThe clipboard holds {}Run '/paste --force' to send it anyway.\n\n",
+                                        confirm::summary(&text),
+                                    );
+                                    let _ = output_tx_clone.send(message).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                                Ok(text) if !text.trim().is_empty() => {
+                                    let _ = process_input_tx_clone.send(cli_handler.paste_message(&text)).await;
+                                },
+                                Ok(_) => {
+                                    let _ = output_tx_clone.send("\nThe clipboard is empty\n\n".to_string()).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError pasting: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                            }
+                        },
+                        Command::TaskTime => {
+                            let active_task = state_for_commands.read().unwrap().task.name().to_string();
+                            match crate::events::load(&environment.get_events_path()) {
+                                Ok(mut recorded) => {
+                                    // Count the running session up to now
+                                    recorded.push(Event {
+                                        time: chrono::Utc::now(),
+                                        session: events.session().to_string(),
+                                        task: active_task.clone(),
+                                        kind: EventKind::SessionEnd,
+                                    });
+                                    let times = crate::events::task_times(&recorded);
+                                    
+                                    let mut tasks: Vec<_> = times.into_iter().collect();
+                                    tasks.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+                                    
+                                    let mut output = String::from("\nTime spent per task:\n");
+                                    for (task, secs) in tasks {
+                                        let marker = if task == active_task { "*" } else { " " };
+                                        output.push_str(&format!("{} {:<20} {}\n", marker, task, format_duration(secs)));
+                                    }
+                                    output.push('\n');
+                                    let _ = output_tx_clone.send(output).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError reading session events: {}\n\n", e)).await;
+                                }
+                            }
+                            
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::Stop => {
+                            let cancel = cli_handler.cancel_sequence();
+                            if let Err(e) = process_input_tx_clone.send(cancel).await {
+                                let _ = output_tx_clone.send(format!("\nError stopping the response: {}\n\n", e)).await;
+                            }
+                        },
+                        Command::Retry { edit } => {
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            match last_message(&environment, &task) {
+                                // Through the input forwarder, so the retry is recorded like any message
+                                Some(message) if edit => {
+                                    let _ = input_tx_for_commands.send(message);
+                                },
+                                Some(message) => {
+                                    let _ = input_tx_for_commands.send(format!("{}\r", message));
+                                },
+                                None => {
+                                    let _ = output_tx_clone.send(format!("\nNo messages have been sent in task '{}' yet\n\n", task)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                            }
+                        },
+                        Command::InputHistory(number) => {
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            let messages = environment.get_input_history_path(&task)
+                                .and_then(|path| history::recent(&path, INPUT_HISTORY_SHOWN));
+                            match (messages, number) {
+                                (Ok(messages), Some(number)) => match messages.get(number.wrapping_sub(1)) {
+                                    Some(message) => {
+                                        let _ = process_input_tx_clone.send(cli_handler.paste_message(message)).await;
+                                    },
+                                    None => {
+                                        let _ = output_tx_clone.send(format!("\nNo message numbered {}; /history input lists them\n\n", number)).await;
+                                        send_prompt_restore(&process_input_tx_clone).await;
+                                    },
+                                },
+                                (Ok(messages), None) if messages.is_empty() => {
+                                    let _ = output_tx_clone.send(format!("\nNo messages have been sent in task '{}' yet\n\n", task)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                                (Ok(messages), None) => {
+                                    let mut list = String::from("\nMessages sent, most recent first:\n");
+                                    for (i, message) in messages.iter().enumerate() {
+                                        list.push_str(&format!("  {:>2}  {}\n", i + 1, message));
+                                    }
+                                    list.push_str("Send one again with /history input <n>\n\n");
+                                    let _ = output_tx_clone.send(list).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                                (Err(e), _) => {
+                                    let _ = output_tx_clone.send(format!("\nError reading input history: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                            }
+                        },
+                        Command::SendPrompt { name, args } => {
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            match environment.load_prompt(&name) {
+                                Ok(prompt) => {
+                                    let mut vars = template::task_vars(&environment, &task);
+                                    template::add_args(&mut vars, &args);
+                                    let message = template::expand(prompt.trim(), &vars);
+                                    let _ = process_input_tx_clone.send(cli_handler.paste_message(&message)).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                            }
+                        },
+                        Command::ListPrompts => {
+                            let message = match environment.list_prompts() {
+                                Ok(names) if names.is_empty() => "\nNo prompts yet. Add templates as .grill/prompts/<name>.md\n\n".to_string(),
+                                Ok(names) => format!("\nPrompts: {}\nSend one with /prompt <name> [args...]\n\n", names.join(", ")),
+                                Err(e) => format!("\nError listing prompts: {}\n\n", e),
+                            };
+                            let _ = output_tx_clone.send(message).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::MacroRecord(name) => {
+                            let recording = macros.lock().unwrap().recording().map(str::to_string);
+                            let message = match (macros::validate_name(&name), recording) {
+                                (_, Some(recording)) => format!("\nAlready recording macro '{}'; /macro stop saves it\n\n", recording),
+                                (Ok(()), None) => {
+                                    macros.lock().unwrap().start_recording(&name);
+                                    format!("\nRecording macro '{}'. Type /macro stop to save it\n\n", name)
+                                },
+                                (Err(e), None) => format!("\nError: {}\n\n", e),
+                            };
+                            let _ = output_tx_clone.send(message).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::MacroStop => {
+                            let stopped = macros.lock().unwrap().stop_playing();
+                            let recorded = macros.lock().unwrap().stop_recording();
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            let message = match (recorded, stopped) {
+                                (Some((name, steps)), _) => {
+                                    let saved = environment.get_macros_dir(&task)
+                                        .and_then(|dir| macros::save(&dir, &name, &steps));
+                                    match saved {
+                                        Ok(()) => format!("\nSaved macro '{}' ({} steps). Replay it with /macro play {}\n\n", name, steps.len(), name),
+                                        Err(e) => format!("\nError saving macro: {}\n\n", e),
+                                    }
+                                },
+                                (None, Some(name)) => format!("\nStopped playing macro '{}'\n\n", name),
+                                (None, None) => "\nNo macro is being recorded or played\n\n".to_string(),
+                            };
+                            let _ = output_tx_clone.send(message).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::MacroPlay(name) => {
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            let steps = environment.get_macros_dir(&task)
+                                .and_then(|dir| macros::load(&dir, &name));
+                            let playing = macros.lock().unwrap().playing().map(str::to_string);
+                            match (steps, playing) {
+                                (_, Some(playing)) => {
+                                    let _ = output_tx_clone.send(format!("\nMacro '{}' is already playing; /macro stop stops it\n\n", playing)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                                (Ok(steps), None) => {
+                                    macros.lock().unwrap().start_playing(&name);
+                                    tokio::spawn(play_macro(
+                                        name,
+                                        steps,
+                                        Arc::clone(&macros),
+                                        activity_tx.subscribe(),
+                                        input_tx_for_commands.clone(),
+                                        command_tx_for_commands.clone(),
+                                        macro_quiet,
+                                    ));
+                                },
+                                (Err(e), None) => {
+                                    let _ = output_tx_clone.send(format!("\nError: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                            }
+                        },
+                        Command::ListMacros => {
+                            let task = state_for_commands.read().unwrap().task.name().to_string();
+                            let names = environment.get_macros_dir(&task).and_then(|dir| macros::list(&dir));
+                            let message = match names {
+                                Ok(names) if names.is_empty() => format!("\nTask '{}' has no macros. Record one with /macro record <name>\n\n", task),
+                                Ok(names) => format!("\nMacros in task '{}': {}\nReplay one with /macro play <name>\n\n", task, names.join(", ")),
+                                Err(e) => format!("\nError listing macros: {}\n\n", e),
+                            };
+                            let _ = output_tx_clone.send(message).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
                         Command::CurrentTask => {
                             // Show current task
-                            let _ = output_tx_clone.send(format!("\nCurrent task: {}\n\n", current_task)).await;
+                            let _ = output_tx_clone.send(format!("\nCurrent task: {}\n\n", current_task.name())).await;
                             
                             // Send a carriage return to the CLI to get the prompt back
                             send_prompt_restore(&process_input_tx_clone).await;
                         },
+                        Command::PreviewSwitch(task_name) => {
+                            let message = match SwitchPlan::new(&environment, &cli_handler, &cli, &launch, uses_worktrees, &context_config, &task_name) {
+                                Ok(plan) => format!("{}Nothing has been changed. Run '/task switch {}' to switch.\n\n", plan.describe(&environment, &cli_handler, &cli), task_name),
+                                Err(e) => format!("\nError previewing switch to task '{}': {}\n\n", task_name, e),
+                            };
+                            let _ = output_tx_clone.send(message).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
                         Command::SwitchTask(task_name) => {
-                            // Check if the task exists first
-                            match environment.get_task_dir(&task_name) {
-                                Ok(task_dir) => {
-                                    // Get the CLI command for the new task
-                                    let new_cli_command = match Self::get_cli_command_for_task(&environment, &task_name) {
-                                        Ok(cmd) => cmd,
-                                        Err(e) => {
-                                            let _ = output_tx_clone.send(format!("\nError getting CLI command for task '{}': {}\n\n", task_name, e)).await;
-                                            send_prompt_restore(&process_input_tx_clone).await;
-                                            continue;
-                                        }
-                                    };
-                                    
-                                    // Check if the new task uses the same CLI as the current task
-                                    if cli_handler_for_commands.can_handle_command(&new_cli_command) {
-                                        // Same CLI - we can switch seamlessly
-                                        let _ = output_tx_clone.send(format!("\nSwitching to task: {} (seamless switch)\n", task_name)).await;
-                                        
-                                        // Clear context and switch task
-                                        match cli_handler_for_commands.clear_context_and_switch_task(
+                            // The session holds the task's lock itself, so it isn't "already running"
+                            if task_name == current_task.name() {
+                                let _ = output_tx_clone.send(format!("\nAlready on task '{}'\n\n", task_name)).await;
+                                send_prompt_restore(&process_input_tx_clone).await;
+                                continue;
+                            }
+                            
+                            // Check the task exists, and decide what switching to it does
+                            let plan = match SwitchPlan::new(&environment, &cli_handler, &cli, &launch, uses_worktrees, &context_config, &task_name) {
+                                Ok(plan) => plan,
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError switching to task '{}': {}\n\n", task_name, e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                    continue;
+                                }
+                            };
+                            if context_config.preview_switch {
+                                let _ = output_tx_clone.send(plan.describe(&environment, &cli_handler, &cli)).await;
+                                if !ask(&switch_question, headless, &output_tx_clone, &format!("Switch to task '{}'?", task_name)).await {
+                                    let _ = output_tx_clone.send(format!("Staying on task '{}'\n\n", current_task.name())).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                    continue;
+                                }
+                            }
+                            
+                            // Another session may be running the task already
+                            let new_task_lock = match environment.get_task_lock_path(&task_name)
+                                .and_then(|path| SessionLock::acquire(&path, &task_name))
+                            {
+                                Ok(Some(lock)) => lock,
+                                Ok(None) => {
+                                    let _ = output_tx_clone.send(format!("\nError: Task '{}' is already running in another grill session.\n\n", task_name)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                    continue;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError switching to task '{}': {}\n\n", task_name, e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                    continue;
+                                }
+                            };
+                            // Only the first session in the workspace updates current_task
+                            let owns_workspace = locks.lock().unwrap().workspace.is_some();
+                            
+                            for warning in &plan.warnings {
+                                let _ = output_tx_clone.send(format!("\nWarning: {}\n", warning)).await;
+                            }
+                            if !plan.unfinished.is_empty() {
+                                let _ = output_tx_clone.send(format!(
+                                    "\nWarning: task '{}' depends on tasks that aren't done yet: {}\n",
+                                    task_name,
+                                    plan.unfinished.join(", ")
+                                )).await;
+                            }
+                            
+                            let active_task = state_for_commands.read().unwrap().task.clone();
+                            finish_exchange(&recorder, keep_transcript, &active_task);
+                            if resume_conversations {
+                                save_conversation(&cli_handler, &active_task, &process_input_tx_clone).await;
+                            }
+                            let active_task = active_task.name().to_string();
+                            for failure in hooks::run_event(&environment, hooks::ON_SWITCH, &active_task, Some(&task_name), None) {
+                                let _ = output_tx_clone.send(format!("\nWarning: {}\n", failure)).await;
+                            }
+                            
+                            if plan.is_seamless() {
+                                // Same CLI - we can switch seamlessly
+                                let _ = output_tx_clone.send(format!("\nSwitching to task: {} (seamless switch)\n", task_name)).await;
+                                
+                                // Pick up the task's saved conversation, or clear context and switch task
+                                let switched = match &plan.conversation {
+                                    Some(commands) => {
+                                        let _ = output_tx_clone.send("Resuming the task's saved conversation\n".to_string()).await;
+                                        process_input_tx_clone.send(format!("{}\r", commands.load)).await
+                                            .map_err(|_| anyhow!("Failed to send load command to CLI"))
+                                    },
+                                    None => {
+                                        let clear = match plan.clear {
+                                            Some(ClearOnSwitch::Always) => true,
+                                            Some(ClearOnSwitch::Ask) => ask(&switch_question, headless, &output_tx_clone, "Clear the CLI's conversation first?").await,
+                                            Some(ClearOnSwitch::Never) | None => false,
+                                        };
+                                        cli_handler.clear_context_and_switch_task(
+                                            &environment,
                                             &task_name,
-                                            &task_dir,
+                                            &plan.task_dir,
+                                            clear,
                                             &process_input_tx_clone,
                                             &output_tx_clone,
-                                        ).await {
-                                            Ok(_) => {
-                                                // Update the current task in the environment
-                                                if let Err(e) = environment.set_current_task(&task_name) {
-                                                    let _ = output_tx_clone.send(format!("Warning: Failed to update current task file: {}\n", e)).await;
-                                                }
-                                                // Note: We don't update current_task variable here since it's used for display only
-                                                // The actual task switching is handled by the CLI context clearing
-                                            },
-                                            Err(e) => {
-                                                let _ = output_tx_clone.send(format!("Error switching task context: {}\n\n", e)).await;
+                                        ).await
+                                    },
+                                };
+                                match switched {
+                                    Ok(_) => {
+                                        // Update the current task in the environment
+                                        if owns_workspace {
+                                            if let Err(e) = environment.set_current_task(&task_name) {
+                                                let _ = output_tx_clone.send(format!("Warning: Failed to update current task file: {}\n", e)).await;
                                             }
                                         }
-                                    } else {
-                                        // Different CLI - requires restart
-                                        match environment.set_current_task(&task_name) {
-                                            Ok(_) => {
-                                                let _ = output_tx_clone.send(format!("\nSwitched to task: {}\n", task_name)).await;
-                                                let _ = output_tx_clone.send("Task uses a different CLI. Please restart grill to apply the change.\n\n".to_string()).await;
-                                            },
-                                            Err(e) => {
-                                                let _ = output_tx_clone.send(format!("\nError switching to task '{}': {}\n\n", task_name, e)).await;
+                                        {
+                                            let mut locks = locks.lock().unwrap();
+                                            locks.task = new_task_lock;
+                                            if let Some(workspace) = locks.workspace.as_mut() {
+                                                if let Err(e) = workspace.set_task(&task_name) {
+                                                    log::warn!("Failed to update session lock: {}", e);
+                                                }
                                             }
                                         }
+                                        state_for_commands.write().unwrap().task = TaskContext::new(&environment, &task_name);
+                                        events.record(&active_task, EventKind::TaskSwitch { to: task_name.clone() });
+                                        let sidecar_dir = environment.get_task_working_dir(&task_name).ok().flatten()
+                                            .unwrap_or_else(|| environment.get_root_dir());
+                                        start_sidecars(&sidecars, &plan.config.sidecars, &sidecar_dir, &output_tx_clone).await;
+                                        if let Err(e) = environment.mark_task_started(&task_name) {
+                                            let _ = output_tx_clone.send(format!("Warning: Failed to update task status: {}\n", e)).await;
+                                        }
+                                    },
+                                    Err(e) => {
+                                        let _ = output_tx_clone.send(format!("Error switching task context: {}\n\n", e)).await;
+                                    }
+                                }
+                            } else {
+                                // Different CLI or profile - requires restart
+                                let reason = plan.restart.as_deref().unwrap_or_default();
+                                if !owns_workspace {
+                                    let _ = output_tx_clone.send(format!(
+                                        "\nTask '{}' uses {}. Start it with 'grill start {}'.\n\n",
+                                        task_name,
+                                        reason,
+                                        task_name
+                                    )).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                    continue;
+                                }
+                                match environment.set_current_task(&task_name) {
+                                    Ok(_) => {
+                                        let _ = output_tx_clone.send(format!("\nSwitched to task: {}\n", task_name)).await;
+                                        let _ = output_tx_clone.send(format!("Task uses {}. Please restart grill to apply the change.\n\n", reason)).await;
+                                    },
+                                    Err(e) => {
+                                        let _ = output_tx_clone.send(format!("\nError switching to task '{}': {}\n\n", task_name, e)).await;
                                     }
-                                },
-                                Err(e) => {
-                                    let _ = output_tx_clone.send(format!("\nError switching to task '{}': {}\n\n", task_name, e)).await;
                                 }
                             }
                             
@@ -261,7 +1316,7 @@ impl Session {
                             // Delete a task
                             match environment.delete_task(&task_name) {
                                 Ok(_) => {
-                                    let _ = output_tx_clone.send(format!("\nDeleted task: {}\n\n", task_name)).await;
+                                    let _ = output_tx_clone.send(format!("\nDeleted task: {} (undo with /task restore {})\n\n", task_name, task_name)).await;
                                 },
                                 Err(e) => {
                                     let _ = output_tx_clone.send(format!("\nError deleting task '{}': {}\n\n", task_name, e)).await;
@@ -271,17 +1326,164 @@ impl Session {
                             // Send a carriage return to the CLI to get the prompt back
                             send_prompt_restore(&process_input_tx_clone).await;
                         },
-                        Command::Help => {
-                            // Show grill help first
-                            let mut help_text = get_help_text();
+                        Command::ContextDiff { staged } => {
+                            let active_task = state_for_commands.read().unwrap().task.name().to_string();
+                            let dir = match environment.get_task_working_dir(&active_task) {
+                                Ok(dir) => dir.unwrap_or_else(|| environment.get_root_dir()),
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError finding working directory: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                    continue;
+                                }
+                            };
+                            
+                            match context::diff_message(&dir, staged) {
+                                Ok(Some(message)) => {
+                                    let _ = output_tx_clone.send("\nSending git diff to the CLI...\n".to_string()).await;
+                                    let _ = process_input_tx_clone.send(cli_handler.paste_message(&message)).await;
+                                },
+                                Ok(None) => {
+                                    let _ = output_tx_clone.send("\nNo changes to send.\n\n".to_string()).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError getting git diff: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                }
+                            }
+                        },
+                        Command::ContextRun(shell_command) => {
+                            let active_task = state_for_commands.read().unwrap().task.name().to_string();
+                            let dir = match environment.get_task_working_dir(&active_task) {
+                                Ok(dir) => dir.unwrap_or_else(|| environment.get_root_dir()),
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError finding working directory: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                    continue;
+                                }
+                            };
+                            let max_output = Config::load(&environment.get_config_path())
+                                .map(|config| config.context.max_output)
+                                .unwrap_or(context::DEFAULT_MAX_OUTPUT);
+                            
+                            // A sidecar's name sends what it has printed instead
+                            let sidecar_log = sidecars.lock().unwrap()
+                                .get_mut(&shell_command)
+                                .map(|sidecar| sidecar.log_message(max_output));
+                            if let Some(message) = sidecar_log {
+                                let _ = output_tx_clone.send(format!("\nSending the output of the `{}` sidecar to the CLI...\n", shell_command)).await;
+                                let _ = process_input_tx_clone.send(cli_handler.paste_message(&message)).await;
+                                continue;
+                            }
                             
-                            // Add CLI-specific help placeholder
-                            help_text.push_str(&cli_handler_for_commands.get_help_text());
+                            let _ = output_tx_clone.send(format!("\nRunning `{}`...\n", shell_command)).await;
+                            let result = tokio::task::spawn_blocking(move || {
+                                context::run_message(&dir, &shell_command, max_output)
+                            }).await;
                             
-                            let _ = output_tx_clone.send(help_text).await;
+                            match result {
+                                Ok(Ok(message)) => {
+                                    let _ = output_tx_clone.send("Sending output to the CLI...\n".to_string()).await;
+                                    let _ = process_input_tx_clone.send(cli_handler.paste_message(&message)).await;
+                                },
+                                Ok(Err(e)) => {
+                                    let _ = output_tx_clone.send(format!("Error running command: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("Error running command: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                }
+                            }
+                        },
+                        Command::ContextMap => {
+                            let active_task = state_for_commands.read().unwrap().task.name().to_string();
+                            let dir = match environment.get_task_working_dir(&active_task) {
+                                Ok(dir) => dir.unwrap_or_else(|| environment.get_root_dir()),
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError finding working directory: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                    continue;
+                                }
+                            };
+                            let max_size = Config::load(&environment.get_config_path())
+                                .map(|config| config.context.max_repo_map)
+                                .unwrap_or(context::DEFAULT_MAX_REPO_MAP);
+                            
+                            let _ = output_tx_clone.send("\nSending repository map to the CLI...\n".to_string()).await;
+                            match tokio::task::spawn_blocking(move || context::repo_map_message(&dir, max_size)).await {
+                                Ok(message) => {
+                                    let _ = process_input_tx_clone.send(cli_handler.paste_message(&message)).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("Error building repository map: {}\n\n", e)).await;
+                                    send_prompt_restore(&process_input_tx_clone).await;
+                                }
+                            }
+                        },
+                        Command::ConfirmDeleteTask(task_name) => {
+                            let message = match environment.get_task_dir(&task_name) {
+                                Ok(_) => format!("\nThis moves task '{}' and its files to .grill/trash. Run '/task delete {} --force' to confirm.\n\n", task_name, task_name),
+                                Err(e) => format!("\nError deleting task '{}': {}\n\n", task_name, e),
+                            };
+                            let _ = output_tx_clone.send(message).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::RestoreTask(task_name) => {
+                            match environment.restore_task(&task_name) {
+                                Ok(_) => {
+                                    let _ = output_tx_clone.send(format!("\nRestored task: {}\n\n", task_name)).await;
+                                },
+                                Err(e) => {
+                                    let _ = output_tx_clone.send(format!("\nError restoring task '{}': {}\n\n", task_name, e)).await;
+                                }
+                            }
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::Help => {
+                            let page = help_with_custom(&help, &environment, state_for_commands.read().unwrap().task.name(), &aliases)
+                                .page(None, 1, help_page_lines());
+                            let _ = output_tx_clone.send(format!("\n{}\n", page)).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::HelpTopic { topic, page } => {
+                            let help = help_with_custom(&help, &environment, state_for_commands.read().unwrap().task.name(), &aliases);
+                            let text = help.page(topic.as_deref(), page, help_page_lines());
+                            let _ = output_tx_clone.send(format!("\n{}\n", text)).await;
                             
-                            // Now send /help to the Q CLI to show its native help
-                            let _ = process_input_tx_clone.send("/help\r".to_string()).await;
+                            // The CLI's topic ends with the CLI's own help, if it has one
+                            if topic.as_deref().is_some_and(|topic| help.is_cli_topic(topic))
+                                && cli_handler.capabilities().native_help
+                            {
+                                let _ = process_input_tx_clone.send("/help\r".to_string()).await;
+                            } else {
+                                send_prompt_restore(&process_input_tx_clone).await;
+                            }
+                        },
+                        Command::Version => {
+                            let info = VersionInfo {
+                                task: Some(state_for_commands.read().unwrap().task.name().to_string()),
+                                ..version_info.clone()
+                            };
+                            // Asking the CLI for its version can take a moment
+                            let report = tokio::task::spawn_blocking(move || version::report(&info)).await
+                                .unwrap_or_else(|e| format!("Error finding versions: {}\n", e));
+                            let _ = output_tx_clone.send(format!("\n{}\n", report)).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::ListAliases => {
+                            let _ = output_tx_clone.send(aliases_message(&aliases)).await;
+                            send_prompt_restore(&process_input_tx_clone).await;
+                        },
+                        Command::Unknown(command) => {
+                            if native_commands.may_include(&command) {
+                                if let Err(e) = input_tx_for_commands.send(format!("{}\r", command)) {
+                                    log::warn!("Failed to send command to CLI: {}", e);
+                                }
+                            } else {
+                                let _ = output_tx_clone.send(format!("\n{}\n\n", crate::io::unknown_command_message(&command))).await;
+                                send_prompt_restore(&process_input_tx_clone).await;
+                            }
                         },
                     }
                 }
@@ -289,8 +1491,25 @@ impl Session {
         });
         
         // Start IO handler
+        self.io_running = Some(io_handler.running());
+        io_handler.set_output_mirror(output_mirror, replay);
+        let picker_environment = self.environment.clone();
+        io_handler.set_task_source(Arc::new(move || task_picker_items(&picker_environment)));
+        let history_environment = self.environment.clone();
+        let history_state = Arc::clone(&state);
+        io_handler.set_history_source(Arc::new(move || {
+            let task = history_state.read().unwrap().task.name().to_string();
+            history_environment.get_input_history_path(&task)
+                .and_then(|path| history::recent(&path, history::MAX_INPUT_HISTORY))
+                .unwrap_or_default()
+        }));
         tokio::spawn(async move {
-            if let Err(e) = io_handler.start().await {
+            let result = if headless {
+                io_handler.bridge().await
+            } else {
+                io_handler.start().await
+            };
+            if let Err(e) = result {
                 eprintln!("Error in IO handler: {}", e);
             }
         });
@@ -298,59 +1517,467 @@ impl Session {
         Ok(())
     }
     
-    /// Get the CLI command for a task
-    fn get_cli_command(&self, task_name: &str) -> Result<String> {
-        // Try to load task-specific config
-        let task_dir = self.environment.get_task_dir(task_name)?;
-        let config_path = task_dir.join("config.toml");
+    /// Get the CLI for a task (static so it can be used in async contexts)
+    ///
+    /// The task's `cli` setting wins over `default_cli`; either may name a
+    /// profile under `[clis]`.
+    pub fn get_cli_for_task(environment: &Environment, task_name: &str) -> Result<CliProfile> {
+        let config = Config::load(&environment.get_config_path())?;
         
-        if config_path.exists() {
-            let task_config = crate::config::TaskConfig::load(&config_path)?;
-            if let Some(cli) = task_config.get_cli() {
-                return Ok(cli.to_string());
-            }
+        // Try to load task-specific config
+        let task = Task::load(environment, task_name)?;
+        if let Some(cli) = task.config().get_cli() {
+            return Ok(config.resolve_cli(cli));
         }
         
         // Fall back to global config
-        let config_path = self.environment.get_config_path();
-        let config = Config::load(&config_path)?;
-        Ok(config.get_default_cli().to_string())
+        Ok(config.resolve_cli(config.get_default_cli()))
     }
     
-    /// Get the CLI command for a task (static version for use in async contexts)
-    fn get_cli_command_for_task(environment: &Environment, task_name: &str) -> Result<String> {
-        // Try to load task-specific config
-        let task_dir = environment.get_task_dir(task_name)?;
-        let config_path = task_dir.join("config.toml");
+    /// End the session if the CLI has exited on its own
+    ///
+    /// A failure exit status is recorded as a crash.
+    pub fn check_process(&mut self) -> Result<()> {
+        let status = match self.process_manager.as_mut() {
+            Some(process_manager) => process_manager.try_wait()?,
+            None => None,
+        };
         
-        if config_path.exists() {
-            let task_config = crate::config::TaskConfig::load(&config_path)?;
-            if let Some(cli) = task_config.get_cli() {
-                return Ok(cli.to_string());
+        if let Some(status) = status {
+            if !*self.running.lock().unwrap() {
+                return Ok(());
             }
+            
+            let task = match &self.state {
+                Some(state) => state.read().unwrap().task.name().to_string(),
+                None => return Ok(()),
+            };
+            if !status.success() {
+                eprintln!("\r\nThe CLI exited with status {}.\r", status.exit_code());
+                self.events.record(&task, EventKind::Crash { exit_code: status.exit_code() });
+                let reason = format!("The CLI exited with status {}.", status.exit_code());
+                let report = self.crash_log.lock().unwrap().report(&reason, None);
+                match crash::write_report(&self.environment.get_crash_dir(), &report) {
+                    Ok(path) => self.crash_report = Some(path),
+                    Err(e) => log::warn!("{:#}", e),
+                }
+            }
+            *self.running.lock().unwrap() = false;
         }
         
-        // Fall back to global config
-        let config_path = environment.get_config_path();
-        let config = Config::load(&config_path)?;
-        Ok(config.get_default_cli().to_string())
+        Ok(())
     }
     
     /// Check if the session is running
     pub fn is_running(&self) -> bool {
         *self.running.lock().unwrap()
     }
+    
+    /// Get the task to start a new session for, if `/restart` ended this one
+    pub fn restart_task(&self) -> Option<String> {
+        if !self.restarting.load(Ordering::SeqCst) {
+            return None;
+        }
+        let state = self.state.as_ref()?;
+        let task = state.read().unwrap().task.name().to_string();
+        Some(task)
+    }
+    
+    /// Finish a session that has stopped running
+    ///
+    /// The CLI gets `QUIT_TIMEOUT` to exit after `/quit` asked it to, and is
+    /// stopped if it hasn't. Then the last exchange is written to the
+    /// transcript, the `on_quit` hooks run and the terminal is restored.
+    pub async fn shutdown(&mut self) {
+        if let Some(process_manager) = self.process_manager.as_mut() {
+            // A CLI being restarted wasn't asked to exit, so isn't waited for
+            let deadline = if self.restarting.load(Ordering::SeqCst) {
+                std::time::Instant::now()
+            } else {
+                std::time::Instant::now() + QUIT_TIMEOUT
+            };
+            while matches!(process_manager.try_wait(), Ok(None)) && std::time::Instant::now() < deadline {
+                tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
+            }
+            if let Err(e) = process_manager.stop() {
+                eprintln!("\r\nWarning: Failed to stop the CLI: {}\r", e);
+            }
+        }
+        self.sidecars.lock().unwrap().stop();
+        
+        if let Some(state) = &self.state {
+            let task = state.read().unwrap().task.clone();
+            finish_exchange(&self.recorder, self.keep_transcript, &task);
+            if let Some(traffic) = &self.traffic {
+                self.events.record(task.name(), EventKind::Traffic {
+                    bytes_in: traffic.bytes_in(),
+                    bytes_out: traffic.bytes_out(),
+                });
+            }
+            self.events.record(task.name(), EventKind::SessionEnd);
+            if let Some(textfile) = &self.metrics_textfile {
+                export_metrics(&self.environment, textfile);
+            }
+            if self.quitting.load(Ordering::SeqCst) {
+                for failure in hooks::run_event(&self.environment, hooks::ON_QUIT, task.name(), None, None) {
+                    eprintln!("\r\nWarning: {}\r", failure);
+                }
+            }
+        }
+        
+        // Stop reading keys, and hand the terminal back
+        if let Some(io_running) = &self.io_running {
+            *io_running.lock().unwrap() = false;
+        }
+        if !self.headless {
+            crate::io::restore_terminal();
+        }
+        if let Some(path) = &self.crash_report {
+            eprintln!("Crash report written to {}", path.display());
+        }
+    }
 }
 
-/// Get help text
-fn get_help_text() -> String {
-    let mut help = String::from("\nGrill Commands:\n");
-    help.push_str("  /task                 Show the current task\n");
-    help.push_str("  /task list            List all available tasks\n");
-    help.push_str("  /task <n>          Switch to the specified task\n");
-    help.push_str("  /task init <n>     Create a new task\n");
-    help.push_str("  /task delete <n>   Delete a task\n");
-    help.push_str("  /help                 Show this help message\n");
-    help.push_str("  /quit                 Exit grill\n\n");
+/// Rewrite the metrics textfile, logging rather than reporting failures
+fn export_metrics(environment: &Environment, textfile: &Path) {
+    let workspace = environment.get_root_dir().display().to_string();
+    if let Err(e) = metrics::write_textfile(textfile, &environment.get_events_path(), &workspace) {
+        log::warn!("{:#}", e);
+    }
+}
+
+/// Stop the sidecars that are running and start `commands` in `dir` instead
+///
+/// Stopping can take a moment, so it is done off the async runtime.
+async fn start_sidecars(
+    sidecars: &Arc<Mutex<Sidecars>>,
+    commands: &std::collections::BTreeMap<String, String>,
+    dir: &Path,
+    output_tx: &mpsc::Sender<String>,
+) {
+    let running = Arc::clone(sidecars);
+    let _ = tokio::task::spawn_blocking(move || running.lock().unwrap().stop()).await;
+    if commands.is_empty() {
+        return;
+    }
+    
+    let (started, failures) = Sidecars::start(commands, dir, output_tx);
+    if !started.names().is_empty() {
+        let _ = output_tx.send(format!("Started sidecars: {}\n", started.names().join(", "))).await;
+    }
+    for failure in failures {
+        let _ = output_tx.send(format!("Warning: {}\n", failure)).await;
+    }
+    *sidecars.lock().unwrap() = started;
+}
+
+/// Ask the user a yes or no question in grill, answered by the next key they
+/// press at an empty prompt
+///
+/// The answer is no if nobody answers within `QUESTION_TIMEOUT`, or at once
+/// in a headless session, which has no terminal to answer from.
+async fn ask(question: &Mutex<Option<oneshot::Sender<bool>>>, headless: bool, output_tx: &mpsc::Sender<String>, prompt: &str) -> bool {
+    if headless {
+        let _ = output_tx.send(format!("[grill] {} [y/N] n (no terminal to answer from)\n", prompt)).await;
+        return false;
+    }
+    let (answer_tx, answer_rx) = oneshot::channel();
+    *question.lock().unwrap() = Some(answer_tx);
+    let _ = output_tx.send(format!("[grill] {} [y/N] ", prompt)).await;
+    match tokio::time::timeout(QUESTION_TIMEOUT, answer_rx).await {
+        Ok(answer) => answer.unwrap_or(false),
+        Err(_) => {
+            question.lock().unwrap().take();
+            let _ = output_tx.send("n (no answer)\n".to_string()).await;
+            false
+        },
+    }
+}
+
+/// Have the CLI save a task's conversation, if it can, so the task can carry it on later
+async fn save_conversation(cli_handler: &CliHandler, task: &TaskContext, process_input_tx: &mpsc::Sender<String>) {
+    if let Some(commands) = task.conversation_path().and_then(|path| cli_handler.conversation_commands(path)) {
+        let _ = process_input_tx.send(format!("{}\r", commands.save)).await;
+    }
+}
+
+/// Point out the CLI's sign-in instructions, which are easily lost among its startup output
+fn sign_in_notice(instructions: &str) -> String {
+    format!(
+        "\n\x1b[1;33m[grill] The CLI needs you to sign in:\x1b[0m {}\n[grill] The task's context is sent once you're back at the CLI's prompt.\n",
+        instructions
+    )
+}
+
+/// Wait for the CLI to show its first prompt, returning whether it did within `timeout`
+async fn wait_until_ready(activity: &mut broadcast::Receiver<bool>, timeout: std::time::Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        match tokio::time::timeout_at(deadline, activity.recv()).await {
+            Ok(Ok(true)) => return true,
+            Ok(Ok(false)) | Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+            // The session ended first, which is reported elsewhere
+            Ok(Err(broadcast::error::RecvError::Closed)) => return true,
+            Err(_) => return false,
+        }
+    }
+}
+
+/// Keep what the CLI prints until its first prompt, for when it never shows one
+///
+/// Only the last `STARTUP_OUTPUT_SHOWN` bytes are kept, and nothing once the
+/// prompt has been seen.
+fn record_startup_output(startup_output: &Mutex<Option<String>>, output: &str, at_prompt: bool) {
+    let mut startup_output = startup_output.lock().unwrap();
+    if at_prompt {
+        *startup_output = None;
+    } else if let Some(kept) = startup_output.as_mut() {
+        kept.push_str(output);
+        if kept.len() > STARTUP_OUTPUT_SHOWN * 2 {
+            let mut start = kept.len() - STARTUP_OUTPUT_SHOWN;
+            while !kept.is_char_boundary(start) {
+                start += 1;
+            }
+            kept.drain(..start);
+        }
+    }
+}
+
+/// Explain that the CLI never showed its prompt, with the end of what it printed
+fn startup_failure_message(timeout: std::time::Duration, output: &str) -> String {
+    let output = crate::ansi::strip_ansi(output);
+    let output = output.trim();
+    let mut start = output.len().saturating_sub(STARTUP_OUTPUT_SHOWN);
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    let mut message = format!(
+        "\n[grill] The CLI hasn't shown its prompt after {}s. It may be waiting for you to log in, or its credentials may be wrong.\n",
+        timeout.as_secs()
+    );
+    if output.is_empty() {
+        message.push_str("[grill] It hasn't printed anything.\n\n");
+    } else {
+        message.push_str(&format!("[grill] It printed:\n{}\n\n", &output[start..]));
+    }
+    message
+}
+
+/// Explain that a lock is held by another session, and what to do instead
+fn already_running(lock_path: &Path, message: &str) -> anyhow::Error {
+    let holder = lock::holder(lock_path)
+        .map(|holder| format!(" (task '{}', pid {})", holder.task, holder.pid))
+        .unwrap_or_default();
+    anyhow!(
+        "{}{}. Attach to it with 'grill attach', watch it read-only with 'grill attach --observe', or start a different task with 'grill start <task>'.",
+        message,
+        holder
+    )
+}
+
+/// Append an exchange to a task's transcript
+///
+/// Failures are only logged: a missing transcript entry isn't worth
+/// interrupting the session for.
+fn record_exchange(task: &TaskContext, exchange: &Exchange) {
+    if let Err(e) = task.record_exchange(exchange) {
+        log::warn!("Failed to record transcript: {}", e);
+    }
+}
+
+/// Format a notification for the display
+///
+/// Terminals that support OSC 9 show a desktop notification; the bell and the
+/// message itself reach the rest.
+fn notification(text: &str) -> String {
+    format!("\x1b]9;{}\x07\x07\n[grill] {}\n", text.replace(['\x07', '\x1b'], ""), text)
+}
+
+/// Carry out a trigger's action after its pattern matched the CLI's output
+async fn run_trigger(
+    action: TriggerAction,
+    matched: String,
+    environment: Environment,
+    task_name: String,
+    input_tx: broadcast::Sender<String>,
+    output_tx: mpsc::Sender<String>,
+) {
+    let message = match action {
+        TriggerAction::Run { command } => {
+            let dir = environment.get_task_working_dir(&task_name)
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| environment.get_root_dir());
+            let result = tokio::task::spawn_blocking(move || {
+                hooks::run_hook(&command, &dir, &[("GRILL_TASK", task_name.as_str())], Some(&matched), Some(hooks::HOOK_TIMEOUT))
+                    .map(|output| (command, output))
+            }).await;
+            match result {
+                Ok(Ok((_, output))) if output.success => return,
+                Ok(Ok((command, output))) => format!("\nWarning: Trigger command '{}' failed: {}\n", command, output.stderr.trim()),
+                Ok(Err(e)) => format!("\nWarning: {}\n", e),
+                Err(_) => return,
+            }
+        },
+        TriggerAction::Send { input, delay } => {
+            tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+            let _ = input_tx.send(format!("{}\r", input));
+            return;
+        },
+        TriggerAction::Notify { message } => notification(message.as_deref().unwrap_or(&matched)),
+        TriggerAction::Block { reason } => {
+            let reason = reason.unwrap_or(matched);
+            match environment.set_task_status(&task_name, TaskStatus::Blocked, Some(&reason)) {
+                Ok(()) => format!("\n[grill] Marked task '{}' as blocked: {}\n", task_name, reason),
+                Err(e) => format!("\n[grill] Error marking task as blocked: {}\n", e),
+            }
+        },
+        TriggerAction::Retry { delay } => {
+            let Some(last) = last_message(&environment, &task_name) else {
+                return;
+            };
+            let _ = output_tx.send(format!("\n[grill] Retrying the last message in {}s\n", delay)).await;
+            tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+            let _ = input_tx.send(format!("{}\r", last));
+            return;
+        },
+    };
+    let _ = output_tx.send(message).await;
+}
+
+/// Save the CLI's answer to the idle summary prompt as a task's state, returning a message to show
+fn save_summary(task: &TaskContext, summary: &str) -> String {
+    let state = format!("# Task State\n\n{}\n", summary.trim());
+    match task.save_state(&state) {
+        Ok(()) => format!("\n[grill] Saved the summary as the state of task '{}'\n", task.name()),
+        Err(e) => format!("\n[grill] Error saving the summary: {:#}\n", e),
+    }
+}
+
+/// Run the `on_response` hooks for a completed exchange in the background,
+/// showing any failures
+fn run_response_hooks(environment: &Environment, task_name: &str, exchange: &Exchange, output_tx: &mpsc::Sender<String>) {
+    if exchange.response.trim().is_empty() {
+        return;
+    }
+    
+    let environment = environment.clone();
+    let task_name = task_name.to_string();
+    let response = exchange.response.clone();
+    let output_tx = output_tx.clone();
+    tokio::spawn(async move {
+        let failures = tokio::task::spawn_blocking(move || {
+            hooks::run_event(&environment, hooks::ON_RESPONSE, &task_name, None, Some(&response))
+        }).await.unwrap_or_default();
+        for failure in failures {
+            let _ = output_tx.send(format!("\nWarning: {}\n", failure)).await;
+        }
+    });
+}
+
+/// Complete the exchange in progress before the session ends or leaves a task,
+/// recording it if the session keeps a transcript
+fn finish_exchange(recorder: &Mutex<Recorder>, keep_transcript: bool, task: &TaskContext) {
+    let exchange = recorder.lock().unwrap().finish();
+    if let Some(exchange) = exchange.filter(|_| keep_transcript) {
+        record_exchange(task, &exchange);
+    }
+}
+
+/// Replay a macro's steps, waiting for the CLI to be ready before each one
+///
+/// Slash commands go through the same handling as when they are typed, and
+/// the CLI is ready once it has been quiet for `quiet`.
+async fn play_macro(
+    name: String,
+    steps: Vec<String>,
+    macros: Arc<Mutex<MacroState>>,
+    mut activity: broadcast::Receiver<bool>,
+    input_tx: broadcast::Sender<String>,
+    command_tx: broadcast::Sender<Command>,
+    quiet: std::time::Duration,
+) {
+    for step in steps {
+        if macros.lock().unwrap().playing() != Some(name.as_str()) {
+            return;
+        }
+        if macros::is_macro_command(&step) {
+            continue;
+        }
+        
+        // Only output caused by this step counts
+        activity = activity.resubscribe();
+        if step.starts_with('/') {
+            IoHandler::process_command_buffer(&step, &command_tx);
+        } else if let Err(e) = input_tx.send(format!("{}\r", step)) {
+            log::warn!("Failed to send macro step: {}", e);
+            break;
+        }
+        macros::wait_for_prompt(&mut activity, quiet, macros::PLAYBACK_TIMEOUT).await;
+    }
+    
+    let mut macros = macros.lock().unwrap();
+    if macros.playing() == Some(name.as_str()) {
+        macros.stop_playing();
+    }
+}
+
+/// Add the lines submitted to the CLI to the task's input history
+fn record_input_history(task: &TaskContext, lines: &[String]) {
+    if let Err(e) = task.record_input(lines) {
+        log::warn!("Could not record input history: {}", e);
+    }
+}
+
+/// Get the last message sent to a task's CLI
+fn last_message(environment: &Environment, task_name: &str) -> Option<String> {
+    let path = environment.get_input_history_path(task_name).ok()?;
+    history::recent(&path, 1).ok()?.into_iter().next()
+}
+
+/// List tasks for the `/task switch` picker, most recently used first
+fn task_picker_items(environment: &Environment) -> Vec<PickerItem> {
+    let tasks = environment.list_recent_tasks().unwrap_or_default();
+    tasks.into_iter()
+        .map(|(name, _)| {
+            let description = environment.get_task_config(&name)
+                .ok()
+                .and_then(|config| config.description);
+            PickerItem { name, description }
+        })
+        .collect()
+}
+
+/// List command aliases, sorted, for `/alias`
+fn aliases_message(aliases: &std::collections::HashMap<String, String>) -> String {
+    if aliases.is_empty() {
+        return "\nNo aliases yet. Add them under [aliases] in .grill/config.toml, e.g. \"/sw\" = \"/task switch\"\n\n".to_string();
+    }
+    let mut sorted: Vec<_> = aliases.iter().collect();
+    sorted.sort();
+    let mut message = String::from("\nAliases:\n");
+    for (alias, command) in sorted {
+        let alias = format!("/{}", alias.trim_start_matches('/'));
+        message.push_str(&format!("  {:<12} {}\n", alias, command));
+    }
+    message.push('\n');
+    message
+}
+
+/// Add the user's own commands to the help: their aliases, prompts and the task's macros
+fn help_with_custom(help: &Help, environment: &Environment, task: &str, aliases: &std::collections::HashMap<String, String>) -> Help {
+    let prompts = environment.list_prompts().unwrap_or_default();
+    let macros = environment.get_macros_dir(task).and_then(|dir| macros::list(&dir)).unwrap_or_default();
+    let mut help = help.clone();
+    help.add_topic(help::custom_topic(aliases, &prompts, &macros));
     help
 }
+
+/// How many lines of help fit on the terminal
+fn help_page_lines() -> usize {
+    // Pseudo-terminals that were never sized report no rows
+    crossterm::terminal::size()
+        .ok()
+        .filter(|(_, rows)| *rows > 0)
+        .map_or(help::DEFAULT_PAGE_LINES, |(_, rows)| rows as usize)
+}
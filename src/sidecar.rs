@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::context;
+use crate::hooks;
+use crate::signal::{self, Signal};
+
+/// Most recent output kept from each sidecar, in bytes
+pub const MAX_LOG: usize = 100_000;
+
+/// How long a sidecar gets to exit when asked before it is killed
+const STOP_GRACE: Duration = Duration::from_secs(2);
+
+/// A command that runs alongside the CLI, such as a dev server or a test watcher
+///
+/// Each line it prints is shown prefixed with its name, and its recent
+/// output is kept so it can be sent to the CLI.
+pub struct Sidecar {
+    name: String,
+    command: String,
+    child: Child,
+    log: Arc<Mutex<String>>,
+}
+
+impl Sidecar {
+    /// Start `command` in a shell in `dir`, sending its output to `output_tx`
+    pub fn start(name: &str, command: &str, dir: &Path, output_tx: mpsc::Sender<String>) -> Result<Self> {
+        let mut process = hooks::shell_command(command);
+        process
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            // Lead a process group, so stopping it also stops what it started
+            process.process_group(0);
+        }
+        
+        let mut child = process.spawn()
+            .context(format!("Failed to start sidecar '{}'", name))?;
+        let log = Arc::new(Mutex::new(String::new()));
+        if let Some(stdout) = child.stdout.take() {
+            forward(name, stdout, Arc::clone(&log), output_tx.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward(name, stderr, Arc::clone(&log), output_tx);
+        }
+        
+        Ok(Self {
+            name: name.to_string(),
+            command: command.to_string(),
+            child,
+            log,
+        })
+    }
+    
+    /// Get the sidecar's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
+    /// Get the sidecar's recent output
+    pub fn log(&self) -> String {
+        self.log.lock().unwrap().clone()
+    }
+    
+    /// Build a context message with the sidecar's recent output, keeping its last `max_output` bytes
+    pub fn log_message(&mut self, max_output: usize) -> String {
+        let status = match self.child.try_wait() {
+            Ok(Some(status)) => format!("exited with {}", status),
+            _ => "still running".to_string(),
+        };
+        format!(
+            "Here is the recent output of the `{}` sidecar, `{}` ({}):\n\n```\n{}\n```\n",
+            self.name,
+            self.command,
+            status,
+            context::truncate_start(self.log().trim_end(), max_output)
+        )
+    }
+    
+    /// Ask the sidecar and everything it started to exit, killing them if they don't in time
+    pub fn stop(&mut self) {
+        if matches!(self.child.try_wait(), Ok(Some(_))) {
+            return;
+        }
+        
+        if signal::send(self.child.id(), Signal::Terminate).is_ok() {
+            let started = Instant::now();
+            while started.elapsed() < STOP_GRACE {
+                if matches!(self.child.try_wait(), Ok(Some(_))) {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+        }
+        if signal::send(self.child.id(), Signal::Kill).is_err() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for Sidecar {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The sidecars running for a session's task
+#[derive(Default)]
+pub struct Sidecars {
+    running: Vec<Sidecar>,
+}
+
+impl Sidecars {
+    /// Start a task's sidecars, given by name, in `dir`
+    ///
+    /// Returns the sidecars that did start, and why any others didn't.
+    pub fn start(commands: &BTreeMap<String, String>, dir: &Path, output_tx: &mpsc::Sender<String>) -> (Self, Vec<String>) {
+        let mut running = Vec::new();
+        let mut failures = Vec::new();
+        for (name, command) in commands {
+            match Sidecar::start(name, command, dir, output_tx.clone()) {
+                Ok(sidecar) => running.push(sidecar),
+                Err(e) => failures.push(format!("{:#}", e)),
+            }
+        }
+        (Self { running }, failures)
+    }
+    
+    /// Find a running sidecar by name
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Sidecar> {
+        self.running.iter_mut().find(|sidecar| sidecar.name == name)
+    }
+    
+    /// Get the names of the sidecars
+    pub fn names(&self) -> Vec<&str> {
+        self.running.iter().map(Sidecar::name).collect()
+    }
+    
+    /// Stop every sidecar
+    pub fn stop(&mut self) {
+        self.running.clear();
+    }
+}
+
+/// Show each line of a sidecar's output prefixed with its name, and keep it in its log
+fn forward(name: &str, stream: impl Read + Send + 'static, log: Arc<Mutex<String>>, output_tx: mpsc::Sender<String>) {
+    let prefix = format!("[{}] ", name);
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stream);
+        let mut line = Vec::new();
+        while matches!(reader.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+            let text = String::from_utf8_lossy(&line);
+            let text = text.trim_end_matches(['\n', '\r']);
+            {
+                let mut log = log.lock().unwrap();
+                log.push_str(text);
+                log.push('\n');
+                if log.len() > MAX_LOG {
+                    let mut cut = log.len() - MAX_LOG;
+                    while !log.is_char_boundary(cut) {
+                        cut += 1;
+                    }
+                    log.drain(..cut);
+                }
+            }
+            // The log is kept even once the display has gone
+            let _ = output_tx.blocking_send(format!("{}{}\n", prefix, text));
+            line.clear();
+        }
+    });
+}
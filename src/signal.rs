@@ -0,0 +1,123 @@
+use anyhow::{Result, anyhow};
+
+/// Signal numbers, which are the same on every Unix
+const SIGTERM: i32 = 15;
+const SIGKILL: i32 = 9;
+
+/// Signal numbers that differ between Unixes
+#[cfg(unix)]
+const SIGSTOP: i32 = libc::SIGSTOP;
+#[cfg(unix)]
+const SIGCONT: i32 = libc::SIGCONT;
+#[cfg(not(unix))]
+const SIGSTOP: i32 = 19;
+#[cfg(not(unix))]
+const SIGCONT: i32 = 18;
+
+/// A request to stop, pause or resume a CLI and everything it started
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Ask the process tree to exit
+    Terminate,
+    /// End the process tree immediately
+    Kill,
+    /// Freeze the process tree until it is resumed
+    Pause,
+    /// Let a paused process tree run again
+    Resume,
+}
+
+/// Platforms that stop process trees differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    Unix,
+    Windows,
+}
+
+impl Platform {
+    /// The platform grill was built for
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::Unix
+        }
+    }
+}
+
+/// How a signal reaches a process tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delivery {
+    /// Signal the process group the child leads (the PTY makes it a session leader)
+    ProcessGroup { group: i32, signal: i32 },
+    /// Run a program that ends the tree, as Windows has no process groups to signal
+    Command(Vec<String>),
+    /// The platform can't deliver the signal
+    Unsupported,
+}
+
+/// Work out how to send `signal` to the tree rooted at `pid` on `platform`
+///
+/// Kept separate from `send` so the Windows behavior can be tested anywhere.
+pub fn delivery(signal: Signal, pid: u32, platform: Platform) -> Delivery {
+    match platform {
+        Platform::Unix => Delivery::ProcessGroup {
+            group: pid as i32,
+            signal: match signal {
+                Signal::Terminate => SIGTERM,
+                Signal::Kill => SIGKILL,
+                Signal::Pause => SIGSTOP,
+                Signal::Resume => SIGCONT,
+            },
+        },
+        Platform::Windows if matches!(signal, Signal::Pause | Signal::Resume) => Delivery::Unsupported,
+        Platform::Windows => {
+            let mut command = vec![
+                "taskkill".to_string(),
+                "/PID".to_string(),
+                pid.to_string(),
+                "/T".to_string(),
+            ];
+            if signal == Signal::Kill {
+                command.push("/F".to_string());
+            }
+            Delivery::Command(command)
+        },
+    }
+}
+
+/// Send `signal` to the process tree rooted at `pid`
+pub fn send(pid: u32, signal: Signal) -> Result<()> {
+    match delivery(signal, pid, Platform::current()) {
+        Delivery::ProcessGroup { group, signal } => signal_group(group, signal),
+        Delivery::Command(command) => {
+            let status = std::process::Command::new(&command[0])
+                .args(&command[1..])
+                .stdin(std::process::Stdio::null())
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(anyhow!("{} exited with {}", command[0], status))
+            }
+        },
+        Delivery::Unsupported => Err(anyhow!("Processes can only be paused on Unix platforms")),
+    }
+}
+
+#[cfg(unix)]
+fn signal_group(group: i32, signal: i32) -> Result<()> {
+    // A negative pid addresses the whole process group
+    if unsafe { libc::kill(-group, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error().into())
+    }
+}
+
+#[cfg(not(unix))]
+fn signal_group(_group: i32, _signal: i32) -> Result<()> {
+    Err(anyhow!("Process groups can only be signalled on Unix platforms"))
+}
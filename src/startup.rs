@@ -0,0 +1,109 @@
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+
+use crate::macros;
+
+/// Longest grill waits for the CLI's first prompt before sending context anyway
+pub const READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How far a session's CLI has got in starting up
+///
+/// A session moves through these in order, and only once: the CLI is
+/// spawned, shows its first prompt, is sent the task's context, and has
+/// answered it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StartupState {
+    /// The CLI is running but hasn't shown its prompt yet
+    Spawned,
+    /// The CLI is at its prompt, and done with any sign-in
+    Ready,
+    /// The task's context has all been sent
+    ContextLoaded,
+    /// The CLI has answered the context and is the user's
+    Interactive,
+}
+
+/// Messages for the CLI, worked out when they are about to be sent
+type Step = Box<dyn FnOnce() -> Vec<String> + Send>;
+
+/// Turns a message into the input that sends it to the CLI as one message
+type Paste = Box<dyn Fn(&str) -> String + Send>;
+
+/// Sends the CLI a task's context once it has started, one message at a time
+///
+/// Messages go in the order they were added, each once the CLI has answered
+/// the one before, so none of them races with the CLI's banner or with each
+/// other. Each is sent as `paste` has it, so a message spanning several
+/// lines isn't submitted a line at a time.
+pub struct StartupPipeline {
+    steps: Vec<Step>,
+    paste: Paste,
+    quiet: Duration,
+    state: StartupState,
+}
+
+impl StartupPipeline {
+    /// Create a pipeline for a CLI that counts as ready once quiet for `quiet`,
+    /// when its prompt isn't recognized, and takes a message as `paste` wraps it
+    pub fn new(quiet: Duration, paste: impl Fn(&str) -> String + Send + 'static) -> Self {
+        Self { steps: Vec::new(), paste: Box::new(paste), quiet, state: StartupState::Spawned }
+    }
+    
+    /// Add a message, sent after those added before it
+    pub fn message(&mut self, message: String) {
+        self.steps.push(Box::new(move || vec![message]));
+    }
+    
+    /// Add messages that are only worked out when it's their turn, off the
+    /// async runtime, such as a task's instructions or a map of the project
+    pub fn deferred<I>(&mut self, step: impl FnOnce() -> I + Send + 'static)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.steps.push(Box::new(move || step().into_iter().collect()));
+    }
+    
+    /// Wait for the CLI to be ready, then send it each message in turn
+    ///
+    /// `activity` carries, for each chunk of output, whether it ended at the
+    /// CLI's prompt, and `signing_in` whether the CLI is asking the user to
+    /// sign in. Running the pipeline uses it up, so context is only sent once.
+    pub async fn run(
+        mut self,
+        mut activity: broadcast::Receiver<bool>,
+        mut signing_in: watch::Receiver<bool>,
+        process_input_tx: mpsc::Sender<String>,
+    ) {
+        let quiet = self.quiet;
+        macros::wait_for_prompt(&mut activity, quiet, READY_TIMEOUT).await;
+        let _ = signing_in.wait_for(|signing_in| !signing_in).await;
+        self.advance(StartupState::Ready);
+        
+        let mut sent = false;
+        for step in std::mem::take(&mut self.steps) {
+            let messages = tokio::task::spawn_blocking(step).await.unwrap_or_default();
+            for message in messages {
+                if sent {
+                    macros::wait_for_prompt(&mut activity, quiet, macros::PLAYBACK_TIMEOUT).await;
+                }
+                // Only what the CLI prints in answer to this message counts
+                activity = activity.resubscribe();
+                if process_input_tx.send((self.paste)(&message)).await.is_err() {
+                    return;
+                }
+                sent = true;
+            }
+        }
+        self.advance(StartupState::ContextLoaded);
+        
+        if sent {
+            macros::wait_for_prompt(&mut activity, quiet, macros::PLAYBACK_TIMEOUT).await;
+        }
+        self.advance(StartupState::Interactive);
+    }
+    
+    fn advance(&mut self, state: StartupState) {
+        log::debug!("CLI startup: {:?} -> {:?}", self.state, state);
+        self.state = state;
+    }
+}
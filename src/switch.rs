@@ -0,0 +1,204 @@
+use anyhow::Result;
+use std::path::PathBuf;
+
+use crate::cli_handler::{CliHandler, ConversationCommands};
+use crate::config::{ClearOnSwitch, CliProfile, Config, ContextConfig, EnvironmentConfig, ResourceLimits, SandboxEngine, TaskConfig};
+use crate::confirm;
+use crate::environment::Environment;
+use crate::session::Session;
+use crate::task::TaskContext;
+
+/// How the CLI is started for a task, besides its command line
+///
+/// A running CLI was started one way for good, so it can only take a task
+/// that would start it the same way.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Launch {
+    pub sandbox: Option<SandboxEngine>,
+    pub sandbox_image: Option<String>,
+    pub sandbox_args: Vec<String>,
+    pub limits: ResourceLimits,
+    pub environment: EnvironmentConfig,
+    pub shell_wrap: bool,
+}
+
+impl Launch {
+    /// Get how the CLI is started for a task with `task_config`, under `config`
+    pub fn new(task_config: &TaskConfig, config: &Config) -> Self {
+        Self {
+            sandbox: task_config.sandbox,
+            sandbox_image: task_config.sandbox_image.clone(),
+            sandbox_args: task_config.sandbox_args.clone(),
+            limits: task_config.limits.clone(),
+            environment: config.environment.clone(),
+            shell_wrap: config.shell_wrap,
+        }
+    }
+    
+    /// Say what makes another launch differ from this one, if anything does
+    pub fn difference(&self, other: &Self) -> Option<&'static str> {
+        if (&self.sandbox, &self.sandbox_image, &self.sandbox_args) != (&other.sandbox, &other.sandbox_image, &other.sandbox_args) {
+            Some("a different sandbox")
+        } else if self.limits != other.limits {
+            Some("different resource limits")
+        } else if self.environment != other.environment {
+            Some("a different [environment] policy")
+        } else if self.shell_wrap != other.shell_wrap {
+            Some("a different shell_wrap setting")
+        } else {
+            None
+        }
+    }
+}
+
+/// What switching the running session to a task does
+///
+/// Decided once and followed by both `/task switch` and its `--dry-run`
+/// preview, so the preview can't promise something the switch won't do.
+#[derive(Debug)]
+pub struct SwitchPlan {
+    pub task: String,
+    pub task_dir: PathBuf,
+    /// The CLI the task runs
+    pub cli: CliProfile,
+    /// The task's config, or the defaults if it can't be read
+    pub config: TaskConfig,
+    /// Problems found while planning, which don't stop the switch
+    pub warnings: Vec<String>,
+    /// Tasks the task depends on that aren't done yet
+    pub unfinished: Vec<String>,
+    /// Why grill has to be restarted to run the task, if the running CLI can't take it
+    pub restart: Option<String>,
+    /// Commands that load the task's saved conversation, in place of its context
+    pub conversation: Option<ConversationCommands>,
+    /// What happens to the CLI's conversation, or `None` if the CLI can't clear it
+    pub clear: Option<ClearOnSwitch>,
+}
+
+impl SwitchPlan {
+    /// Plan a switch to `task_name` from a session running `cli` through
+    /// `cli_handler`, started as `launch` says
+    ///
+    /// Fails only if the task doesn't exist or its CLI can't be found.
+    pub fn new(
+        environment: &Environment,
+        cli_handler: &CliHandler,
+        cli: &CliProfile,
+        launch: &Launch,
+        uses_worktrees: bool,
+        context: &ContextConfig,
+        task_name: &str,
+    ) -> Result<Self> {
+        let task_dir = environment.get_task_dir(task_name)?;
+        let new_cli = Session::get_cli_for_task(environment, task_name)?;
+        
+        let mut warnings = Vec::new();
+        let config = TaskConfig::load_for_task(&task_dir).unwrap_or_else(|e| {
+            warnings.push(format!("Could not load the config of task '{}': {:#}", task_name, e));
+            TaskConfig::default()
+        });
+        let unfinished = environment.unfinished_dependencies(task_name).unwrap_or_else(|e| {
+            warnings.push(format!("Could not check dependencies of task '{}': {}", task_name, e));
+            Vec::new()
+        });
+        let new_launch = Launch::new(&config, &Config::load(&environment.get_config_path())?);
+        
+        // With worktrees the CLI also has to move to the task's directory
+        let restart = if uses_worktrees {
+            Some("its own worktree".to_string())
+        } else if !cli_handler.can_handle_command(&new_cli.command_line())
+            || new_cli.args != cli.args
+            || new_cli.env != cli.env
+        {
+            Some("a different CLI".to_string())
+        } else {
+            launch.difference(&new_launch).map(str::to_string)
+        };
+        
+        let conversation = TaskContext::new(environment, task_name).saved_conversation()
+            .filter(|_| context.resume_conversations)
+            .and_then(|path| cli_handler.conversation_commands(path));
+        let clear = cli_handler.capabilities().supports_clear
+            .then(|| config.clear_on_switch.unwrap_or(context.clear_on_switch));
+        
+        Ok(Self {
+            task: task_name.to_string(),
+            task_dir,
+            cli: new_cli,
+            config,
+            warnings,
+            unfinished,
+            restart,
+            conversation,
+            clear,
+        })
+    }
+    
+    /// Check whether the running CLI takes the task without a restart
+    pub fn is_seamless(&self) -> bool {
+        self.restart.is_none()
+    }
+    
+    /// Describe what the switch would do, without doing any of it
+    pub fn describe(&self, environment: &Environment, cli_handler: &CliHandler, cli: &CliProfile) -> String {
+        let mut preview = format!("\nSwitching to task '{}' would:\n", self.task);
+        for warning in &self.warnings {
+            preview.push_str(&format!("  - warn: {}\n", warning));
+        }
+        if !self.unfinished.is_empty() {
+            preview.push_str(&format!("  - warn that it depends on unfinished tasks: {}\n", self.unfinished.join(", ")));
+        }
+        
+        if let Some(reason) = &self.restart {
+            preview.push_str(&format!(
+                "  - make it the current task; it uses {}, so grill has to be restarted to run `{}`\n",
+                reason,
+                self.cli.command_line(),
+            ));
+            return preview;
+        }
+        preview.push_str(&format!("  - keep running `{}`\n", cli.command_line()));
+        
+        if let Some(commands) = &self.conversation {
+            preview.push_str(&format!("  - load the task's saved conversation with `{}`\n", commands.load));
+            return preview;
+        }
+        
+        match self.clear {
+            None => preview.push_str("  - keep the conversation, which the CLI can't clear\n"),
+            Some(ClearOnSwitch::Always) => match cli_handler.clear_input() {
+                Some(input) => {
+                    let input: Vec<String> = input.iter().map(|input| format!("`{}`", input.trim_end())).collect();
+                    preview.push_str(&format!("  - clear the conversation by sending {}\n", input.join(", ")));
+                },
+                None => preview.push_str("  - have the handler plugin clear the conversation\n"),
+            },
+            Some(ClearOnSwitch::Ask) => preview.push_str("  - ask whether to clear the conversation\n"),
+            Some(ClearOnSwitch::Never) => preview.push_str("  - keep the conversation\n"),
+        }
+        
+        match cli_handler.switch_context_messages(environment, &self.task, &self.task_dir) {
+            Some(messages) if messages.is_empty() => preview.push_str("  - send no context\n"),
+            Some(messages) => {
+                let bytes: usize = messages.iter().map(|(_, message)| message.len()).sum();
+                let tokens: usize = messages.iter().map(|(_, message)| confirm::estimate_tokens(message)).sum();
+                preview.push_str(&format!(
+                    "  - send the task's context, {} (about {} tokens):\n",
+                    confirm::format_size(bytes),
+                    tokens,
+                ));
+                for (label, message) in &messages {
+                    preview.push_str(&format!(
+                        "      {}: {}, about {} tokens\n",
+                        label,
+                        confirm::format_size(message.len()),
+                        confirm::estimate_tokens(message),
+                    ));
+                }
+            },
+            None => preview.push_str("  - send the context the handler plugin chooses\n"),
+        }
+        
+        preview
+    }
+}
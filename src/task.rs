@@ -1,14 +1,310 @@
-// This file contains the Task struct and related functionality.
-// It's not currently used in the main application flow but is kept
-// for future expansion of the task management capabilities.
+use anyhow::{Result, Context, anyhow};
+use chrono::{DateTime, Local, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
 
-use std::path::PathBuf;
-use crate::config::TaskConfig;
+use crate::config::{self, TaskConfig};
+use crate::environment::Environment;
+use crate::history;
+use crate::transcript::{self, Exchange};
 
-/// Represents a task in the grill environment
+/// File holding a task's instructions for the CLI
+pub const INSTRUCTIONS_FILE: &str = "instructions.md";
+
+/// File holding the current state of a task
+pub const STATE_FILE: &str = "state.md";
+
+/// File holding the user's notes on a task
+pub const NOTES_FILE: &str = "notes.md";
+
+/// File the CLI saves a task's conversation to, for CLIs that can
+pub const CONVERSATION_FILE: &str = "conversation.json";
+
+/// Contents of `instructions.md` in a new task
+pub const DEFAULT_INSTRUCTIONS: &str = "# Task Instructions\n\nAdd your instructions here.\n";
+
+/// Contents of `state.md` in a new task
+pub const DEFAULT_STATE: &str = "# Task State\n\nTask state will be tracked here.\n";
+
+/// Contents of the config file of a new task
+pub const DEFAULT_CONFIG: &str = "# Task Configuration\ncli = \"q chat\"\n";
+
+/// Filesystem details of a task
 #[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct TaskMetadata {
+    /// When the task directory was created, where the platform records it
+    pub created: Option<DateTime<Local>>,
+    /// When any of the task's files last changed
+    pub modified: Option<DateTime<Local>>,
+}
+
+/// Represents a task in the grill environment
+#[derive(Debug)]
 pub struct Task {
     name: String,
     path: PathBuf,
     config: TaskConfig,
+    instructions: Option<String>,
+    state: Option<String>,
+}
+
+impl Task {
+    /// Load a task from the environment, including global tasks
+    pub fn load(environment: &Environment, name: &str) -> Result<Self> {
+        Self::from_dir(name, &environment.get_task_dir(name)?)
+    }
+    
+    /// Load the task stored in a directory
+    pub fn from_dir(name: &str, path: &Path) -> Result<Self> {
+        Ok(Self {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            config: TaskConfig::load_for_task(path)?,
+            instructions: read_optional(&path.join(INSTRUCTIONS_FILE))?,
+            state: read_optional(&path.join(STATE_FILE))?,
+        })
+    }
+    
+    /// Get the task's name
+    #[allow(dead_code)]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
+    /// Get the task's directory
+    #[allow(dead_code)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Get the task's configuration, including frontmatter from its instructions
+    pub fn config(&self) -> &TaskConfig {
+        &self.config
+    }
+    
+    /// Take the task's configuration
+    pub fn into_config(self) -> TaskConfig {
+        self.config
+    }
+    
+    /// Get the path of the task's config file, in whichever format it is written
+    pub fn config_path(&self) -> PathBuf {
+        config::find_config_file(&self.path)
+    }
+    
+    /// Get the path of the task's instructions
+    pub fn instructions_path(&self) -> PathBuf {
+        self.path.join(INSTRUCTIONS_FILE)
+    }
+    
+    /// Get the path of the task's state
+    #[allow(dead_code)]
+    pub fn state_path(&self) -> PathBuf {
+        self.path.join(STATE_FILE)
+    }
+    
+    /// Get the path of the task's notes
+    pub fn notes_path(&self) -> PathBuf {
+        self.path.join(NOTES_FILE)
+    }
+    
+    /// Get the task's instructions without any frontmatter
+    pub fn instructions(&self) -> Option<&str> {
+        self.instructions.as_deref().map(crate::frontmatter::strip)
+    }
+    
+    /// Get the task's state, unless it is missing or still the template of a new task
+    pub fn state(&self) -> Option<&str> {
+        self.state.as_deref()
+            .filter(|state| !state.trim().starts_with(DEFAULT_STATE.trim()))
+    }
+    
+    /// Replace the task's state
+    pub fn save_state(&mut self, state: &str) -> Result<()> {
+        crate::environment::write_atomic(&self.state_path(), state)
+            .context(format!("Failed to write state for task '{}'", self.name))?;
+        self.state = Some(state.to_string());
+        Ok(())
+    }
+    
+    /// Add text to the end of the task's state, replacing the template of a new task
+    pub fn append_state(&mut self, text: &str) -> Result<()> {
+        let mut state = match self.state.as_deref().filter(|state| state.trim() != DEFAULT_STATE.trim()) {
+            Some(state) => format!("{}\n\n", state.trim_end()),
+            None => "# Task State\n\n".to_string(),
+        };
+        state.push_str(text.trim());
+        state.push('\n');
+        self.save_state(&state)
+    }
+    
+    /// Get when the task was created and last changed
+    #[allow(dead_code)]
+    pub fn metadata(&self) -> Result<TaskMetadata> {
+        let dir = fs::metadata(&self.path)
+            .context(format!("Failed to read metadata of task '{}'", self.name))?;
+        
+        let mut modified = dir.modified().ok();
+        for entry in fs::read_dir(&self.path)? {
+            let file_modified = entry?.metadata()?.modified().ok();
+            modified = modified.max(file_modified);
+        }
+        
+        Ok(TaskMetadata {
+            created: dir.created().ok().map(DateTime::from),
+            modified: modified.map(DateTime::from),
+        })
+    }
+}
+
+/// Read a file, or `None` if it doesn't exist
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path)
+        .map(Some)
+        .context(format!("Failed to read {}", path.display()))
+}
+
+/// Where a running task's output goes: its transcript, input history and state
+///
+/// A session holds one for the task it is running and replaces it whole on a
+/// switch, so output is never written half to one task and half to another.
+#[derive(Clone)]
+pub struct TaskContext {
+    environment: Environment,
+    name: String,
+    transcript_path: PathBuf,
+    history_path: Option<PathBuf>,
+    conversation_path: Option<PathBuf>,
+}
+
+impl TaskContext {
+    /// Resolve where a task's output goes
+    pub fn new(environment: &Environment, name: &str) -> Self {
+        Self {
+            environment: environment.clone(),
+            name: name.to_string(),
+            transcript_path: environment.get_transcript_path(name),
+            history_path: environment.get_input_history_path(name).ok(),
+            conversation_path: environment.get_task_dir(name).ok().map(|dir| dir.join(CONVERSATION_FILE)),
+        }
+    }
+    
+    /// Get the task's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    
+    /// Get where the CLI saves the task's conversation
+    pub fn conversation_path(&self) -> Option<&Path> {
+        self.conversation_path.as_deref()
+    }
+    
+    /// Get where the task's conversation was saved, if it has been
+    pub fn saved_conversation(&self) -> Option<&Path> {
+        self.conversation_path().filter(|path| path.exists())
+    }
+    
+    /// Add an exchange with the CLI to the task's transcript
+    pub fn record_exchange(&self, exchange: &Exchange) -> Result<()> {
+        transcript::append(&self.transcript_path, exchange)
+    }
+    
+    /// Add messages sent to the CLI to the task's input history
+    pub fn record_input(&self, lines: &[String]) -> Result<()> {
+        let path = self.history_path.as_deref()
+            .ok_or_else(|| anyhow!("Task '{}' has no directory for its input history", self.name))?;
+        lines.iter().try_for_each(|line| history::append(path, line))
+    }
+    
+    /// Replace the task's state
+    pub fn save_state(&self, state: &str) -> Result<()> {
+        Task::load(&self.environment, &self.name)?.save_state(state)
+    }
+    
+    /// Add text to the end of the task's state
+    pub fn append_state(&self, text: &str) -> Result<()> {
+        Task::load(&self.environment, &self.name)?.append_state(text)
+    }
+}
+
+/// Format when a task was last used for task listings
+pub fn format_last_used(last_used: Option<DateTime<Utc>>) -> String {
+    match last_used {
+        Some(time) => time.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+        None => "never".to_string(),
+    }
+}
+
+/// Render the tasks' dependencies as a tree
+///
+/// Tasks that no other task depends on are the roots, with the tasks they
+/// depend on nested below them. Tasks in a dependency cycle that no root
+/// reaches are shown as roots too.
+pub fn dependency_graph(environment: &Environment) -> Result<String> {
+    let mut tasks = environment.list_tasks()?;
+    tasks.sort();
+    
+    let mut configs = HashMap::new();
+    for task in &tasks {
+        configs.insert(task.clone(), environment.get_task_config(task)?);
+    }
+    let is_dependency = |task: &String| {
+        configs.values().any(|config| config.depends_on.contains(task))
+    };
+    
+    let mut output = String::new();
+    let mut shown = HashSet::new();
+    for task in tasks.iter().filter(|task| !is_dependency(task)) {
+        render_node(&configs, task, "", "", &mut Vec::new(), &mut shown, &mut output);
+    }
+    for task in &tasks {
+        if !shown.contains(task) {
+            render_node(&configs, task, "", "", &mut Vec::new(), &mut shown, &mut output);
+        }
+    }
+    
+    Ok(output)
+}
+
+fn render_node(
+    configs: &HashMap<String, TaskConfig>,
+    task: &str,
+    prefix: &str,
+    child_prefix: &str,
+    path: &mut Vec<String>,
+    shown: &mut HashSet<String>,
+    output: &mut String,
+) {
+    let Some(config) = configs.get(task) else {
+        output.push_str(&format!("{}{} (missing)\n", prefix, task));
+        return;
+    };
+    if path.iter().any(|ancestor| ancestor == task) {
+        output.push_str(&format!("{}{} (cycle)\n", prefix, task));
+        return;
+    }
+    
+    output.push_str(&format!("{}{} [{}]\n", prefix, task, config.status));
+    shown.insert(task.to_string());
+    
+    path.push(task.to_string());
+    for (i, dependency) in config.depends_on.iter().enumerate() {
+        let last = i + 1 == config.depends_on.len();
+        let (branch, indent) = if last { ("└── ", "    ") } else { ("├── ", "│   ") };
+        render_node(
+            configs,
+            dependency,
+            &format!("{}{}", child_prefix, branch),
+            &format!("{}{}", child_prefix, indent),
+            path,
+            shown,
+            output,
+        );
+    }
+    path.pop();
 }
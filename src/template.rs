@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::environment::Environment;
+use crate::git;
+
+/// Values for the `{{name}}` placeholders in instructions and templates
+pub type TemplateVars = HashMap<&'static str, String>;
+
+/// Build the placeholder values for a task
+///
+/// - `task_name`: the task's name
+/// - `date`: today's date, as `YYYY-MM-DD`
+/// - `workspace`: the workspace's root directory
+/// - `git_branch`: the branch checked out where the task's CLI runs, or empty
+pub fn task_vars(environment: &Environment, task_name: &str) -> TemplateVars {
+    let workspace = environment.get_root_dir();
+    let working_dir = environment.get_task_working_dir(task_name)
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| workspace.clone());
+    
+    let mut vars = TemplateVars::new();
+    vars.insert("task_name", task_name.to_string());
+    vars.insert("date", chrono::Local::now().format("%Y-%m-%d").to_string());
+    vars.insert("workspace", workspace.display().to_string());
+    vars.insert("git_branch", git::current_branch(&working_dir).unwrap_or_default());
+    vars
+}
+
+/// Placeholders for the positional arguments of a prompt template
+const ARG_NAMES: [&str; 9] = ["1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+/// Add the placeholders for arguments given to a prompt template
+///
+/// - `args`: all of the arguments, as typed
+/// - `1` to `9`: each whitespace-separated argument, or empty if not given
+pub fn add_args(vars: &mut TemplateVars, args: &str) {
+    let mut words = args.split_whitespace();
+    for name in ARG_NAMES {
+        vars.insert(name, words.next().unwrap_or_default().to_string());
+    }
+    vars.insert("args", args.trim().to_string());
+}
+
+/// Replace `{{name}}` placeholders with their values
+///
+/// Whitespace inside the braces is ignored. Unknown placeholders are left as
+/// they are, so text that happens to use braces isn't mangled.
+pub fn expand(text: &str, vars: &TemplateVars) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    
+    while let Some(start) = rest.find("{{") {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        
+        match after.find("}}") {
+            Some(end) => match vars.get(after[..end].trim()) {
+                Some(value) => {
+                    result.push_str(value);
+                    rest = &after[end + 2..];
+                },
+                None => {
+                    result.push_str("{{");
+                    rest = after;
+                },
+            },
+            None => {
+                result.push_str(&rest[start..]);
+                rest = "";
+            },
+        }
+    }
+    
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,72 @@
+use std::io::IsTerminal;
+
+/// How grill reads the user's input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalMode {
+    /// Key by key in raw mode, with the task picker and live command editing
+    Raw,
+    /// A line at a time, for terminals that can't do raw mode
+    Line,
+}
+
+/// The input mode to use and, for line mode, why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminalSupport {
+    pub mode: TerminalMode,
+    pub reason: Option<String>,
+}
+
+/// What grill knows about the terminal it runs in
+#[derive(Debug, Clone, Default)]
+pub struct TerminalInfo {
+    /// `GRILL_TERMINAL`, which forces `raw` or `line` mode
+    pub forced: Option<String>,
+    /// `TERM`
+    pub term: Option<String>,
+    /// The first of `LC_ALL`, `LC_CTYPE` and `LANG` that is set
+    pub locale: Option<String>,
+    /// Whether stdin and stdout are terminals
+    pub is_tty: bool,
+}
+
+impl TerminalInfo {
+    /// Read the terminal's details from the environment
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        Self {
+            forced: var("GRILL_TERMINAL"),
+            term: var("TERM"),
+            locale: var("LC_ALL").or_else(|| var("LC_CTYPE")).or_else(|| var("LANG")),
+            is_tty: std::io::stdin().is_terminal() && std::io::stdout().is_terminal(),
+        }
+    }
+    
+    /// Decide whether raw mode can be used
+    ///
+    /// An unset locale is assumed to be UTF-8, as it is on Windows and in
+    /// most containers.
+    pub fn support(&self) -> TerminalSupport {
+        let line = |reason: String| TerminalSupport { mode: TerminalMode::Line, reason: Some(reason) };
+        
+        match self.forced.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            Some("line") => return line("GRILL_TERMINAL=line".to_string()),
+            Some("raw") => return TerminalSupport { mode: TerminalMode::Raw, reason: None },
+            _ => {},
+        }
+        
+        if !self.is_tty {
+            return line("input or output is not a terminal".to_string());
+        }
+        if self.term.as_deref() == Some("dumb") {
+            return line("TERM is dumb".to_string());
+        }
+        if let Some(locale) = &self.locale {
+            let normalized = locale.to_ascii_lowercase().replace('-', "");
+            if !normalized.contains("utf8") {
+                return line(format!("locale {} is not UTF-8", locale));
+            }
+        }
+        
+        TerminalSupport { mode: TerminalMode::Raw, reason: None }
+    }
+}
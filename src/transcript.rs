@@ -0,0 +1,167 @@
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+/// Default number of exchanges replayed when a session resumes
+pub const DEFAULT_RESUME_EXCHANGES: usize = 5;
+
+/// Longest response replayed, in bytes; the end of longer ones is kept
+const MAX_REPLAYED_RESPONSE: usize = 4000;
+
+/// A message sent to the CLI and its answer, as recorded in `.grill/transcripts/<task>.jsonl`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Exchange {
+    pub time: DateTime<Utc>,
+    pub prompt: String,
+    pub response: String,
+}
+
+/// Append an exchange to a transcript
+pub fn append(path: &Path, exchange: &Exchange) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .context("Failed to create transcripts directory")?;
+    }
+    
+    let line = serde_json::to_string(exchange)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("Failed to open transcript")?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+/// Read the last `count` exchanges of a transcript, skipping lines that can't be parsed
+pub fn load_recent(path: &Path, count: usize) -> Result<Vec<Exchange>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let content = fs::read_to_string(path)
+        .context("Failed to read transcript")?;
+    let exchanges: Vec<Exchange> = content.lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    
+    let skip = exchanges.len().saturating_sub(count);
+    Ok(exchanges.into_iter().skip(skip).collect())
+}
+
+/// Build the message that reminds a freshly started CLI of earlier exchanges
+pub fn resume_message(task_name: &str, exchanges: &[Exchange]) -> Option<String> {
+    if exchanges.is_empty() {
+        return None;
+    }
+    
+    let mut message = format!(
+        "Here is the end of our previous conversation on task '{}', for context. There is no need to answer it.\n",
+        task_name
+    );
+    for exchange in exchanges {
+        message.push_str(&format!(
+            "\nUser: {}\nAssistant: {}\n",
+            exchange.prompt,
+            crate::context::truncate_start(&exchange.response, MAX_REPLAYED_RESPONSE)
+        ));
+    }
+    
+    Some(message)
+}
+
+/// Pieces a session's raw keystrokes and output together into exchanges
+#[derive(Debug, Default)]
+pub struct Recorder {
+    /// Line being typed
+    line: String,
+    /// Prompt that was sent, with the output received since
+    pending: Option<(String, String)>,
+    /// Response of the last completed exchange
+    last_response: Option<String>,
+}
+
+impl Recorder {
+    /// Create a recorder with nothing recorded yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Record keystrokes sent to the CLI
+    ///
+    /// Submitting a line completes the exchange before it, if the CLI's
+    /// prompt wasn't recognized.
+    pub fn input(&mut self, input: &str) -> Vec<Exchange> {
+        let mut completed = Vec::new();
+        
+        for c in input.chars() {
+            match c {
+                '\r' | '\n' => {
+                    let line = std::mem::take(&mut self.line);
+                    let line = line.trim();
+                    if !line.is_empty() {
+                        completed.extend(self.take_exchange());
+                        self.pending = Some((line.to_string(), String::new()));
+                    }
+                },
+                '\x08' | '\x7f' => {
+                    self.line.pop();
+                },
+                c if !c.is_control() => self.line.push(c),
+                _ => {},
+            }
+        }
+        
+        completed
+    }
+    
+    /// Record output from the CLI, completing the exchange when the CLI shows its prompt again
+    pub fn output(&mut self, output: &str, at_prompt: bool) -> Option<Exchange> {
+        let (_, response) = self.pending.as_mut()?;
+        response.push_str(&crate::ansi::strip_ansi(output));
+        
+        if !at_prompt {
+            return None;
+        }
+        // Drop the prompt itself from the end of the response
+        let end = response.trim_end().rfind('\n').unwrap_or(0);
+        response.truncate(end);
+        self.take_exchange()
+    }
+    
+    /// Complete the exchange in progress, if any
+    pub fn finish(&mut self) -> Option<Exchange> {
+        self.take_exchange()
+    }
+    
+    /// Get the CLI's latest response, including one still arriving
+    pub fn latest_response(&self) -> Option<String> {
+        let pending = self.pending.as_ref()
+            .map(|(prompt, response)| clean_response(prompt, response))
+            .filter(|response| !response.is_empty());
+        pending.or_else(|| self.last_response.clone())
+    }
+    
+    fn take_exchange(&mut self) -> Option<Exchange> {
+        let (prompt, response) = self.pending.take()?;
+        let response = clean_response(&prompt, &response);
+        self.last_response = Some(response.clone());
+        
+        Some(Exchange {
+            time: Utc::now(),
+            prompt,
+            response,
+        })
+    }
+}
+
+/// Normalize line endings and drop the echoed prompt from a response
+fn clean_response(prompt: &str, response: &str) -> String {
+    let response = response.replace("\r\n", "\n").replace('\r', "");
+    // The terminal echoes the prompt back before the answer
+    let response = response.trim_start().strip_prefix(prompt).unwrap_or(&response);
+    response.trim().to_string()
+}
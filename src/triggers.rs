@@ -0,0 +1,69 @@
+use anyhow::{Result, Context};
+use regex::{Regex, RegexBuilder};
+
+use crate::config::{TriggerAction, TriggersConfig};
+
+/// Most output kept for matching, in bytes
+const MAX_RECENT_OUTPUT: usize = 8192;
+
+/// The `[triggers]` rules, watching the CLI's output for their patterns
+///
+/// Output is matched without escape sequences, and across chunks, since the
+/// response so far is kept. Each rule fires at most once per response: the
+/// rules are rearmed when the next message is sent.
+#[derive(Debug, Default)]
+pub struct Triggers {
+    rules: Vec<(Regex, TriggerAction)>,
+    fired: Vec<bool>,
+    recent: String,
+}
+
+impl Triggers {
+    /// Compile the configured rules
+    pub fn new(config: &TriggersConfig) -> Result<Self> {
+        let rules = config.rules.iter()
+            .map(|rule| {
+                let pattern = RegexBuilder::new(&rule.pattern)
+                    .multi_line(true)
+                    .build()
+                    .context(format!("Invalid pattern '{}' under [triggers]", rule.pattern))?;
+                Ok((pattern, rule.action.clone()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { fired: vec![false; rules.len()], rules, recent: String::new() })
+    }
+    
+    /// Check a chunk of output, returning the actions of rules that now match, with the matched text
+    pub fn check(&mut self, output: &str) -> Vec<(TriggerAction, String)> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        
+        self.recent.push_str(&crate::ansi::strip_ansi(output));
+        if self.recent.len() > MAX_RECENT_OUTPUT {
+            let mut start = self.recent.len() - MAX_RECENT_OUTPUT;
+            while !self.recent.is_char_boundary(start) {
+                start += 1;
+            }
+            self.recent.drain(..start);
+        }
+        
+        let mut actions = Vec::new();
+        for ((pattern, action), fired) in self.rules.iter().zip(self.fired.iter_mut()) {
+            if *fired {
+                continue;
+            }
+            if let Some(found) = pattern.find(&self.recent) {
+                *fired = true;
+                actions.push((action.clone(), found.as_str().to_string()));
+            }
+        }
+        actions
+    }
+    
+    /// Forget the output so far and rearm every rule, when a message is sent
+    pub fn reset(&mut self) {
+        self.recent.clear();
+        self.fired.iter_mut().for_each(|fired| *fired = false);
+    }
+}
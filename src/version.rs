@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use crate::clis;
+use crate::remote;
+
+/// What a session is running, for `/version` and `grill version --verbose`
+#[derive(Debug, Clone, Default)]
+pub struct VersionInfo {
+    pub workspace: Option<PathBuf>,
+    pub task: Option<String>,
+    /// The wrapped CLI's command line
+    pub cli: Option<String>,
+    pub handler: Option<String>,
+}
+
+/// Grill's version, as in `grill 0.1.0`
+pub fn grill_version() -> String {
+    format!("grill {}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Describe how this grill was built, as in `release build for linux x86_64`
+pub fn build_info() -> String {
+    let profile = if cfg!(debug_assertions) { "debug" } else { "release" };
+    format!("{} build for {} {}", profile, std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Find the wrapped CLI's version by running it with `--version`
+///
+/// A CLI on another machine isn't asked, since `--version` would only reach ssh.
+pub fn cli_version(command: &str) -> String {
+    if remote::is_ssh(command) {
+        return "runs over ssh".to_string();
+    }
+    match clis::program(command).and_then(|program| clis::find_binary(&program)) {
+        Some(binary) => clis::version(&binary).unwrap_or_else(|| "unknown version".to_string()),
+        None => "not installed".to_string(),
+    }
+}
+
+/// Report everything a bug report needs, one item per line
+///
+/// Asks the CLI for its version, so this can take a few seconds.
+pub fn report(info: &VersionInfo) -> String {
+    let mut report = format!("{:<10} {} ({})\n", "grill", env!("CARGO_PKG_VERSION"), build_info());
+    if let Some(workspace) = &info.workspace {
+        report.push_str(&format!("{:<10} {}\n", "workspace", workspace.display()));
+    }
+    if let Some(task) = &info.task {
+        report.push_str(&format!("{:<10} {}\n", "task", task));
+    }
+    if let Some(cli) = &info.cli {
+        report.push_str(&format!("{:<10} {} ({})\n", "cli", cli, cli_version(cli)));
+    }
+    if let Some(handler) = &info.handler {
+        report.push_str(&format!("{:<10} {}\n", "handler", handler));
+    }
+    report
+}
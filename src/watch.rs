@@ -0,0 +1,103 @@
+use anyhow::Result;
+use ignore::WalkBuilder;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::context;
+use crate::hooks;
+
+/// How often a watched task's files are checked for changes
+pub const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Summarize the files under `dir` as a number that changes when any of them does
+///
+/// Covers each file's path, size and modification time. Files ignored by
+/// `.gitignore` or `.grillignore`, and hidden ones such as `.git` and
+/// `.grill`, are left out, so build output and grill's own files don't count.
+pub fn snapshot(dir: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let walker = WalkBuilder::new(dir)
+        .require_git(false)
+        .add_custom_ignore_filename(".grillignore")
+        .sort_by_file_path(|a, b| a.cmp(b))
+        .build();
+    for entry in walker.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if metadata.is_file() {
+            entry.path().hash(&mut hasher);
+            metadata.len().hash(&mut hasher);
+            metadata.modified().ok().hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// What happened when a watch command was rerun
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The command succeeded; `recovered` if it had been failing
+    Passed { recovered: bool },
+    /// The command failed, with a context message of its output
+    Failed(String),
+}
+
+/// Reruns a task's `watch` command whenever its files change
+///
+/// Changes are waited out until the files stop changing for a poll, so a
+/// burst of edits runs the command once.
+#[derive(Debug)]
+pub struct Watch {
+    command: String,
+    dir: PathBuf,
+    last: u64,
+    changed: bool,
+    failing: bool,
+}
+
+impl Watch {
+    /// Start watching `dir`, taking its files as they are now as unchanged
+    pub fn new(command: &str, dir: PathBuf) -> Self {
+        let last = snapshot(&dir);
+        Self { command: command.to_string(), dir, last, changed: false, failing: false }
+    }
+    
+    /// Get the command being rerun
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+    
+    /// Check whether this watches `command` in `dir`
+    pub fn watches(&self, command: &str, dir: &Path) -> bool {
+        self.command == command && self.dir == dir
+    }
+    
+    /// Look for changes, and rerun the command once they have settled
+    ///
+    /// Returns `None` while there is nothing to run. Whatever the command
+    /// changes itself is taken as the new starting point.
+    pub fn check(&mut self, max_output: usize) -> Result<Option<Outcome>> {
+        let current = snapshot(&self.dir);
+        if current != self.last {
+            self.last = current;
+            self.changed = true;
+            return Ok(None);
+        }
+        if !std::mem::take(&mut self.changed) {
+            return Ok(None);
+        }
+        
+        let output = hooks::run_hook(&self.command, &self.dir, &[], None, Some(context::RUN_TIMEOUT))?;
+        self.last = snapshot(&self.dir);
+        let outcome = if output.success {
+            Outcome::Passed { recovered: self.failing }
+        } else {
+            Outcome::Failed(context::output_message(&self.command, output, max_output))
+        };
+        self.failing = matches!(outcome, Outcome::Failed(_));
+        Ok(Some(outcome))
+    }
+}
@@ -0,0 +1,199 @@
+use anyhow::{Result, Context, anyhow};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::environment::Environment;
+use crate::hooks;
+use crate::runner::{Exchange, OutputFormat, Runner};
+
+/// An ordered list of prompts to run against one or more tasks
+#[derive(Debug, Deserialize)]
+pub struct Workflow {
+    /// What the workflow does
+    #[serde(default)]
+    pub description: Option<String>,
+    
+    /// Seconds to wait for each response
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    
+    /// Steps, run in order
+    #[serde(default)]
+    pub steps: Vec<WorkflowStep>,
+}
+
+fn default_timeout() -> u64 {
+    300
+}
+
+/// A single workflow step
+#[derive(Debug, Deserialize)]
+pub struct WorkflowStep {
+    /// Name shown in the report
+    #[serde(default)]
+    pub name: Option<String>,
+    
+    /// Task to run the step in (defaults to the previous step's task, then the current task)
+    #[serde(default)]
+    pub task: Option<String>,
+    
+    /// Prompt to send
+    #[serde(default)]
+    pub prompt: Option<String>,
+    
+    /// File whose contents are sent as the prompt, relative to `.grill/`
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    
+    /// Text the response must contain for the step to pass
+    #[serde(default)]
+    pub expect: Option<String>,
+    
+    /// Shell command run after the step; the response is passed on stdin
+    #[serde(default)]
+    pub hook: Option<String>,
+}
+
+/// Outcome of a workflow step
+#[derive(Debug)]
+pub struct StepResult {
+    pub name: String,
+    pub exchange: Exchange,
+    /// Why the step failed, if it did
+    pub failure: Option<String>,
+}
+
+impl Workflow {
+    /// Load a workflow file
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .context(format!("Failed to read workflow file {}", path.display()))?;
+        
+        let workflow: Workflow = toml::from_str(&content)
+            .context("Failed to parse workflow file")?;
+        
+        for (i, step) in workflow.steps.iter().enumerate() {
+            if step.prompt.is_none() == step.file.is_none() {
+                return Err(anyhow!("Workflow step {} must have exactly one of 'prompt' or 'file'", i + 1));
+            }
+        }
+        
+        Ok(workflow)
+    }
+    
+    /// Run every step, stopping at the first failure
+    ///
+    /// Consecutive steps for the same task share one CLI session; switching task
+    /// starts a fresh session with that task's context loaded.
+    pub async fn run(&self, environment: &Environment, format: OutputFormat) -> Result<Vec<StepResult>> {
+        let limit = Duration::from_secs(self.timeout);
+        let mut results = Vec::new();
+        let mut task = environment.get_current_task()?;
+        let mut runner: Option<Runner> = None;
+        
+        for (i, step) in self.steps.iter().enumerate() {
+            let name = step.name.clone().unwrap_or_else(|| format!("step {}", i + 1));
+            
+            if let Some(step_task) = &step.task {
+                if *step_task != task {
+                    if let Some(mut previous) = runner.take() {
+                        previous.stop()?;
+                    }
+                    task = step_task.clone();
+                }
+            }
+            
+            if runner.is_none() {
+                let mut new_runner = Runner::start_for_task(environment, &task)?;
+                new_runner.wait_until_ready(limit).await?;
+                new_runner.load_task_context(environment, &task, limit).await?;
+                runner = Some(new_runner);
+            }
+            
+            let prompt = match (&step.prompt, &step.file) {
+                (Some(prompt), _) => prompt.clone(),
+                (None, Some(file)) => {
+                    let path = environment.get_grill_dir().join(file);
+                    fs::read_to_string(&path)
+                        .context(format!("Failed to read prompt file {}", path.display()))?
+                },
+                (None, None) => unreachable!("validated on load"),
+            };
+            
+            let exchange = runner.as_mut().unwrap().exchange(&task, prompt.trim(), limit).await;
+            exchange.print(format);
+            
+            let failure = self.check_step(step, &exchange, environment)?;
+            let failed = failure.is_some();
+            results.push(StepResult { name, exchange, failure });
+            
+            if failed {
+                break;
+            }
+        }
+        
+        if let Some(mut runner) = runner {
+            runner.stop()?;
+        }
+        
+        Ok(results)
+    }
+    
+    /// Apply a step's expected-output check and hook
+    fn check_step(&self, step: &WorkflowStep, exchange: &Exchange, environment: &Environment) -> Result<Option<String>> {
+        let response = match (&exchange.response, &exchange.error) {
+            (Some(response), _) => response,
+            (None, error) => return Ok(Some(error.clone().unwrap_or_else(|| "No response".to_string()))),
+        };
+        
+        if let Some(expect) = &step.expect {
+            if !response.contains(expect.as_str()) {
+                return Ok(Some(format!("Response did not contain '{}'", expect)));
+            }
+        }
+        
+        if let Some(hook) = &step.hook {
+            let output = hooks::run_hook(
+                hook,
+                &environment.get_root_dir(),
+                &[("GRILL_TASK", exchange.task.as_str())],
+                Some(response),
+                Some(hooks::HOOK_TIMEOUT),
+            )?;
+            if !output.success {
+                let detail = if output.stderr.trim().is_empty() { &output.stdout } else { &output.stderr };
+                return Ok(Some(format!("Hook '{}' failed: {}", hook, detail.trim())));
+            }
+        }
+        
+        Ok(None)
+    }
+}
+
+/// Format a summary table of step results
+pub fn format_report(results: &[StepResult], total_steps: usize) -> String {
+    let mut report = String::from("\nWorkflow summary:\n");
+    
+    for result in results {
+        let status = if result.failure.is_none() { "ok" } else { "FAILED" };
+        report.push_str(&format!(
+            "  {:<6} {} [{}] ({:.1}s)\n",
+            status,
+            result.name,
+            result.exchange.task,
+            result.exchange.duration_ms as f64 / 1000.0,
+        ));
+        if let Some(failure) = &result.failure {
+            report.push_str(&format!("         {}\n", failure));
+        }
+    }
+    
+    let skipped = total_steps.saturating_sub(results.len());
+    if skipped > 0 {
+        report.push_str(&format!("  {} step(s) skipped\n", skipped));
+    }
+    
+    report
+}
@@ -0,0 +1,67 @@
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A grill environment this user has initialized or started a session in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workspace {
+    /// Root directory of the project (the one containing `.grill`)
+    pub path: PathBuf,
+    pub last_used: DateTime<Utc>,
+}
+
+/// Get the path of the user-level workspace registry
+///
+/// `GRILL_WORKSPACES_FILE` overrides the default location in the user's data
+/// directory.
+pub fn registry_path() -> Option<PathBuf> {
+    std::env::var_os("GRILL_WORKSPACES_FILE")
+        .map(PathBuf::from)
+        .or_else(|| dirs::data_dir().map(|dir| dir.join("grill").join("workspaces.json")))
+}
+
+/// Load the known workspaces, most recently used first
+pub fn load(registry: &Path) -> Result<Vec<Workspace>> {
+    if !registry.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let content = fs::read_to_string(registry)
+        .context("Failed to read workspace registry")?;
+    let mut workspaces: Vec<Workspace> = serde_json::from_str(&content)
+        .context("Failed to parse workspace registry")?;
+    workspaces.sort_by_key(|workspace| std::cmp::Reverse(workspace.last_used));
+    
+    Ok(workspaces)
+}
+
+/// Add a workspace to the registry, or mark it as just used
+pub fn register(registry: &Path, root: &Path) -> Result<()> {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let mut workspaces = load(registry)?;
+    workspaces.retain(|workspace| workspace.path != root);
+    workspaces.insert(0, Workspace { path: root, last_used: Utc::now() });
+    
+    if let Some(parent) = registry.parent() {
+        fs::create_dir_all(parent)
+            .context("Failed to create workspace registry directory")?;
+    }
+    crate::environment::write_atomic(registry, serde_json::to_string_pretty(&workspaces)?)
+        .context("Failed to write workspace registry")?;
+    
+    Ok(())
+}
+
+/// Record use of a workspace in the default registry
+///
+/// Failures are only logged: the registry is a convenience, not something
+/// to stop a session over.
+pub fn record(root: &Path) {
+    if let Some(registry) = registry_path() {
+        if let Err(e) = register(&registry, root) {
+            log::warn!("Failed to update workspace registry: {}", e);
+        }
+    }
+}
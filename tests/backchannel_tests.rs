@@ -0,0 +1,54 @@
+use grill::backchannel::{self, Action, Backchannel};
+
+#[test]
+fn test_backchannel_requests() {
+    let mut backchannel = Backchannel::default();
+    
+    let (shown, actions) = backchannel.process("before\x1b]7777;grill:{\"action\":\"switch_task\",\"task\":\"api\"}\x07after");
+    assert_eq!(shown, "beforeafter");
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0].as_ref().unwrap(), &Action::SwitchTask { task: "api".to_string() });
+    
+    // ST ends a sequence too
+    let (shown, actions) = backchannel.process("\x1b]7777;grill:{\"action\":\"notify\",\"message\":\"Tests passed\"}\x1b\\done\r\n");
+    assert_eq!(shown, "done\r\n");
+    assert_eq!(actions[0].as_ref().unwrap(), &Action::Notify { message: "Tests passed".to_string(), title: None });
+    
+    // Invalid requests are reported and still hidden
+    let (shown, actions) = backchannel.process("\x1b]7777;grill:{\"action\":\"format_disk\"}\x07");
+    assert_eq!(shown, "");
+    assert!(actions[0].is_err());
+    
+    // Other escape sequences pass through
+    let (shown, actions) = backchannel.process("\x1b[1mbold\x1b[0m \x1b]0;title\x07");
+    assert_eq!(shown, "\x1b[1mbold\x1b[0m \x1b]0;title\x07");
+    assert!(actions.is_empty());
+}
+
+#[test]
+fn test_backchannel_split_across_chunks() {
+    let mut backchannel = Backchannel::default();
+    
+    let (shown, actions) = backchannel.process("text\x1b]77");
+    assert_eq!(shown, "text");
+    assert!(actions.is_empty());
+    
+    let (shown, actions) = backchannel.process("77;grill:{\"action\":\"append_state\",");
+    assert_eq!(shown, "");
+    assert!(actions.is_empty());
+    
+    let (shown, actions) = backchannel.process("\"text\":\"Done: parser\"}\x07more");
+    assert_eq!(shown, "more");
+    assert_eq!(actions[0].as_ref().unwrap(), &Action::AppendState { text: "Done: parser".to_string() });
+    
+    // An escape that turns out not to be a request is shown once the rest arrives
+    assert_eq!(backchannel.process("\x1b").0, "");
+    assert_eq!(backchannel.process("[2J").0, "\x1b[2J");
+}
+
+#[test]
+fn test_backchannel_parse() {
+    let action = backchannel::parse("{\"action\":\"notify\",\"message\":\"Build done\",\"title\":\"CI\"}").unwrap();
+    assert_eq!(action, Action::Notify { message: "Build done".to_string(), title: Some("CI".to_string()) });
+    assert!(backchannel::parse("not json").is_err());
+}
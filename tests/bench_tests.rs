@@ -0,0 +1,66 @@
+use anyhow::Result;
+use std::time::Duration;
+
+use grill::bench::{self, BenchOptions, BenchResult};
+use grill::clipboard;
+use grill::config::{CliProfile, Config};
+use grill::events::Latency;
+
+#[test]
+fn test_context_text() {
+    let text = bench::context_text(10_000);
+    assert_eq!(text.len(), 10_000);
+    assert!(text.starts_with(bench::DEFAULT_PROMPT));
+    assert!(text.contains("\n00001 The quick brown fox"));
+    
+    // Never shorter than the request to acknowledge it
+    assert!(bench::context_text(0).starts_with(bench::DEFAULT_PROMPT));
+}
+
+#[test]
+fn test_bench_report() {
+    let result = BenchResult {
+        cli: "claude".to_string(),
+        handler: "claude".to_string(),
+        prompt_recognized: true,
+        spawn_to_prompt_ms: 2100,
+        round_trip: Latency::from_samples(vec![1500, 1800, 2600]),
+        context_bytes: 65536,
+        context_write_ms: 500,
+        context_answer_ms: 5100,
+    };
+    let report = result.report();
+    assert!(report.contains("Spawn to prompt:  2.1s\n"), "{}", report);
+    assert!(report.contains("Round trip:       1.8s median, 2.6s 95th percentile, 2.6s max over 3 rounds\n"), "{}", report);
+    assert!(report.contains("Context:          65536 bytes written in 500ms (128.0 KiB/s), answered 5.1s later\n"), "{}", report);
+    assert!(!report.contains("wasn't recognized"), "{}", report);
+}
+
+#[tokio::test]
+async fn test_bench_cat() -> Result<()> {
+    let options = BenchOptions {
+        rounds: 1,
+        context_bytes: 2000,
+        timeout: Duration::from_secs(20),
+        ..Default::default()
+    };
+    let result = bench::run("cat", &CliProfile::from_command("cat"), None, &Config::default(), &options).await?;
+    
+    assert_eq!(result.cli, "cat");
+    assert_eq!(result.round_trip.count, 1);
+    // cat isn't known to take a multi-line paste, so the context goes on one line
+    assert_eq!(result.context_bytes, clipboard::join_lines(&bench::context_text(2000)).len() + 1, "{:?}", result);
+    assert!(!result.prompt_recognized);
+    assert!(result.report().contains("wasn't recognized"));
+    
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_bench_cli_exits() {
+    let options = BenchOptions { rounds: 1, timeout: Duration::from_secs(10), ..Default::default() };
+    let cli = CliProfile::from_command("sh -c 'exit 3'");
+    let error = bench::run("broken", &cli, None, &Config::default(), &options).await.unwrap_err();
+    assert_eq!(error.to_string(), "The CLI exited with status 3 before its first prompt");
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+
+use grill::clipboard::{self, Copied};
+use grill::config::{ClipboardConfig, ClipboardMethod};
+
+#[test]
+fn test_clipboard_commands() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let file = dir.path().join("clipboard.txt");
+    let config = ClipboardConfig {
+        method: ClipboardMethod::Command,
+        copy_command: Some(format!("tee {}", file.display())),
+        paste_command: Some(format!("cat {}", file.display())),
+    };
+    
+    let copied = clipboard::copy_text(&config, "first line\nsecond line")?;
+    assert!(matches!(copied, Copied::Program(_)));
+    assert_eq!(clipboard::paste_text(&config)?, "first line\nsecond line");
+    
+    Ok(())
+}
+
+#[test]
+fn test_clipboard_methods() {
+    let osc52 = ClipboardConfig { method: ClipboardMethod::Osc52, ..ClipboardConfig::default() };
+    assert_eq!(clipboard::copy_text(&osc52, "hi").unwrap(), Copied::Terminal("\x1b]52;c;aGk=\x07".to_string()));
+    
+    let off = ClipboardConfig { method: ClipboardMethod::Off, ..ClipboardConfig::default() };
+    assert!(clipboard::copy_text(&off, "hi").is_err());
+    assert!(clipboard::paste_text(&off).is_err());
+}
+
+#[test]
+fn test_paste_message() {
+    assert_eq!(clipboard::paste_message("one line\n"), "one line\r");
+    assert_eq!(clipboard::paste_message("two\nlines"), "\x1b[200~two\nlines\x1b[201~\r");
+    
+    // CLIs without multi-line input get it on one line
+    assert_eq!(clipboard::join_lines("two\n\n  lines \n"), "two lines");
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+
+use grill::clis;
+use grill::config::Config;
+
+#[test]
+fn test_find_binary_and_version() {
+    let git = clis::find_binary("git").expect("git is installed");
+    assert!(clis::version(&git).expect("git has a version").starts_with("git version"));
+    
+    assert!(clis::find_binary("grill-no-such-cli").is_none());
+    assert!(clis::find_binary("/no/such/dir/cli").is_none());
+    assert_eq!(clis::program("q chat --verbose").as_deref(), Some("q"));
+    assert_eq!(clis::program("'/opt/my tools/q' chat").as_deref(), Some("/opt/my tools/q"));
+}
+
+#[test]
+fn test_split_command() {
+    assert_eq!(clis::split_command("q chat --profile 'my prof'"), ["q", "chat", "--profile", "my prof"]);
+    assert_eq!(clis::split_command(r#"aider --message "fix \"it\"""#), ["aider", "--message", "fix \"it\""]);
+    assert_eq!(clis::split_command("/path/with\\ space/q chat"), ["/path/with space/q", "chat"]);
+    
+    // Unbalanced quotes fall back to splitting on whitespace
+    assert_eq!(clis::split_command("q chat 'oops"), ["q", "chat", "'oops"]);
+    assert!(clis::split_command("").is_empty());
+}
+
+#[test]
+fn test_list_and_check_installed() -> Result<()> {
+    let config: Config = toml::from_str(r#"
+default_cli = "grill-no-such-cli chat"
+
+[clis]
+missing = "grill-no-such-cli chat"
+cat = "cat"
+"#)?;
+    
+    let clis = clis::list(&config);
+    assert_eq!(clis.iter().map(|cli| cli.name.as_str()).collect::<Vec<_>>(), ["cat", "missing"]);
+    assert!(clis[1].is_default);
+    assert!(clis[1].binary.is_none());
+    assert!(clis[0].binary.is_some());
+    
+    // The error suggests configured CLIs that are installed
+    let error = clis::check_installed(&config, config.get_default_cli()).unwrap_err().to_string();
+    assert!(error.contains("'grill-no-such-cli chat' is not installed"));
+    assert!(error.contains("\n  cat"));
+    
+    assert!(clis::check_installed(&config, "cat -u").is_ok());
+    
+    // A default that isn't under [clis] is still listed
+    let config: Config = toml::from_str("default_cli = \"cat\"\n")?;
+    let clis = clis::list(&config);
+    assert_eq!(clis.len(), 1);
+    assert_eq!(clis[0].name, "default");
+    
+    Ok(())
+}
@@ -2,7 +2,9 @@ use anyhow::{Result, anyhow};
 use tokio::sync::{mpsc, broadcast};
 use tokio::time::{Duration, timeout};
 
-use grill::io::{Command, IoHandler};
+use grill::cli_handler::{self, Capabilities, CliHandlerFactory, CommandLine, ContextStrategy, NativeCommands};
+use grill::config::CliProfile;
+use grill::io::{self, Command, IoHandler};
 
 /// Test that commands are properly sent and received
 #[tokio::test]
@@ -91,6 +93,9 @@ async fn test_io_handler_commands() -> Result<()> {
                     let _ = test_tx.send("Quit command received\n".to_string()).await;
                     break;
                 },
+                _ => {
+                    // Other commands aren't exercised by this test
+                },
             }
         }
     });
@@ -133,3 +138,335 @@ async fn test_io_handler_commands() -> Result<()> {
     
     Ok(())
 }
+
+/// Test that output arriving in quick succession is written as one batch
+#[tokio::test]
+async fn test_output_batching() -> Result<()> {
+    let (output_tx, mut output_rx) = mpsc::channel::<String>(100);
+    for token in ["Hel", "lo", ", ", "world"] {
+        output_tx.send(token.to_string()).await?;
+    }
+    
+    let batch = io::next_batch(&mut output_rx, Duration::from_millis(50), 1024).await;
+    assert_eq!(batch.as_deref(), Some("Hello, world"));
+    
+    // A full batch is returned without waiting for more
+    for token in ["aaaa", "bbbb", "cccc"] {
+        output_tx.send(token.to_string()).await?;
+    }
+    let batch = io::next_batch(&mut output_rx, Duration::from_secs(60), 6).await;
+    assert_eq!(batch.as_deref(), Some("aaaabbbb"));
+    let batch = io::next_batch(&mut output_rx, Duration::from_millis(10), 6).await;
+    assert_eq!(batch.as_deref(), Some("cccc"));
+    
+    drop(output_tx);
+    assert_eq!(io::next_batch(&mut output_rx, Duration::from_millis(10), 6).await, None);
+    
+    Ok(())
+}
+
+/// Test that only bare newlines are turned into CRLF
+#[test]
+fn test_newline_translator() {
+    let to_crlf = |output: &str| io::NewlineTranslator::default().translate(output);
+    assert_eq!(to_crlf("one\ntwo\n"), "one\r\ntwo\r\n");
+    assert_eq!(to_crlf("one\r\ntwo"), "one\r\ntwo");
+    assert_eq!(to_crlf("\n\n"), "\r\n\r\n");
+}
+
+/// Test that newlines inside escape sequences are left alone
+#[test]
+fn test_newline_translator_escapes() {
+    let mut newlines = io::NewlineTranslator::default();
+    
+    // Alternate screen and cursor movement pass through
+    assert_eq!(newlines.translate("\x1b[?1049h\x1b[2;5Hhi\n"), "\x1b[?1049h\x1b[2;5Hhi\r\n");
+    
+    // A title containing a newline is not altered, even split across chunks
+    assert_eq!(newlines.translate("\x1b]0;two\n"), "\x1b]0;two\n");
+    assert_eq!(newlines.translate("lines\x07\n"), "lines\x07\r\n");
+    assert_eq!(newlines.translate("\x1bPq\n\x1b\\done\n"), "\x1bPq\n\x1b\\done\r\n");
+    
+    // CRLF split across chunks is not doubled
+    assert_eq!(newlines.translate("end\r"), "end\r");
+    assert_eq!(newlines.translate("\nnext"), "\nnext");
+}
+
+/// Test that /stop and /retry are parsed, with or without the edit step
+#[test]
+fn test_stop_and_retry_commands() -> Result<()> {
+    let (command_tx, mut command_rx) = broadcast::channel::<Command>(10);
+    
+    IoHandler::process_command_buffer("/stop", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Stop));
+    IoHandler::process_command_buffer("/retry", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Retry { edit: false }));
+    IoHandler::process_command_buffer("/retry edit", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Retry { edit: true }));
+    
+    Ok(())
+}
+
+/// Test that a task switch with --dry-run only previews it
+#[test]
+fn test_dry_run_switch_command() -> Result<()> {
+    let (command_tx, mut command_rx) = broadcast::channel::<Command>(10);
+    
+    IoHandler::process_command_buffer("/task switch api --dry-run", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::PreviewSwitch(name) if name == "api"));
+    IoHandler::process_command_buffer("/task api --dry-run", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::PreviewSwitch(name) if name == "api"));
+    IoHandler::process_command_buffer("/task switch --dry-run api", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::PreviewSwitch(name) if name == "api"));
+    IoHandler::process_command_buffer("/task switch api", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::SwitchTask(name) if name == "api"));
+    
+    Ok(())
+}
+
+/// Test that each CLI is asked to exit with its own quit command
+#[tokio::test]
+async fn test_quit_command() -> Result<()> {
+    assert_eq!(cli_handler::quit_input("q chat"), "/quit\r");
+    assert_eq!(cli_handler::quit_input("/usr/local/bin/aider --model x"), "/exit\r");
+    assert_eq!(cli_handler::quit_input("python3"), "\x04");
+    
+    let (process_input_tx, mut process_input_rx) = mpsc::channel::<String>(10);
+    CliHandlerFactory::create_handler("q chat".to_string()).on_quit(&process_input_tx).await?;
+    assert_eq!(process_input_rx.recv().await.as_deref(), Some("/quit\r"));
+    
+    Ok(())
+}
+
+/// Test that CLIs with their own context system are given files through it
+#[test]
+fn test_context_strategy() -> Result<()> {
+    assert_eq!(ContextStrategy::for_command("q chat"), ContextStrategy::Command("/context add".to_string()));
+    assert_eq!(ContextStrategy::for_command("/usr/bin/aider --model x"), ContextStrategy::Command("/read".to_string()));
+    assert_eq!(ContextStrategy::for_command("claude"), ContextStrategy::Flag("--append-system-prompt".to_string()));
+    assert_eq!(ContextStrategy::for_command("python3"), ContextStrategy::Paste);
+    assert_eq!(CliHandlerFactory::create_handler("q chat".to_string()).context_strategy(), ContextStrategy::for_command("q chat"));
+    
+    // Plugins name theirs the same way
+    let strategy: ContextStrategy = serde_json::from_value(serde_json::json!({ "command": "/add" }))?;
+    assert_eq!(strategy, ContextStrategy::Command("/add".to_string()));
+    let strategy: ContextStrategy = serde_json::from_value(serde_json::json!("paste"))?;
+    assert_eq!(strategy, ContextStrategy::Paste);
+    
+    Ok(())
+}
+
+/// Test that handlers are chosen, and switches kept seamless, by the program a command runs
+#[test]
+fn test_can_handle_command() {
+    let command = CommandLine::parse("/usr/local/bin/q --verbose chat --profile work");
+    assert_eq!(command.program_name(), "q");
+    assert_eq!(command.subcommand(), Some("chat"));
+    assert!(command.runs("q", Some("chat")));
+    assert!(!CommandLine::parse("q translate").runs("q", Some("chat")));
+    
+    let q = CliHandlerFactory::create_handler("q chat".to_string());
+    assert_eq!(q.name(), "q");
+    assert!(q.can_handle_command("/opt/q/bin/q chat --profile work"));
+    assert!(!q.can_handle_command("sqlite3 chat.db"));
+    assert!(!q.can_handle_command("aider --chat-mode ask"));
+    
+    // Standing in for another CLI, only that CLI's program is handled
+    let stand_in = CliHandlerFactory::create_handler("sqlite3 chat.db".to_string());
+    assert_eq!(stand_in.name(), "default");
+    assert!(stand_in.can_handle_command("/usr/bin/sqlite3 other.db"));
+    assert!(!stand_in.can_handle_command("q chat"));
+}
+
+/// Only Q's own prompt, alone on the last line, counts as being back at the prompt
+#[test]
+fn test_is_prompt() {
+    let q = CliHandlerFactory::create_handler("q chat".to_string());
+    assert!(q.is_prompt("Hello! How can I help?\n\n> "));
+    assert!(q.is_prompt("\x1b[35m> \x1b[0m"));
+    assert!(q.is_prompt("done\n[work] > "));
+    assert!(q.is_prompt("done\n!> "));
+    
+    // Short output that happens to end in '>' is still part of the response
+    assert!(!q.is_prompt("a -> b"));
+    assert!(!q.is_prompt("<div>"));
+    assert!(!q.is_prompt("Use Vec<String>"));
+    assert!(!q.is_prompt("  > quoted"));
+    assert!(!q.is_prompt("> \nmore output"));
+}
+
+/// Test that handlers report what their CLI can do, and only ask known CLIs to clear
+#[test]
+fn test_capabilities() -> Result<()> {
+    let q = CliHandlerFactory::create_handler("q chat".to_string());
+    assert!(q.capabilities().supports_resume && q.capabilities().needs_confirmation);
+    assert_eq!(q.clear_input(), Some(vec!["/clear\r".to_string(), "y\r".to_string()]));
+    
+    let aider = CliHandlerFactory::create_handler("aider".to_string());
+    assert!(aider.capabilities().supports_clear && !aider.capabilities().supports_resume);
+    assert_eq!(aider.clear_input(), Some(vec!["/clear\r".to_string()]));
+    
+    // Nothing is assumed of an unknown CLI, so what it is sent goes on one line
+    let other = CliHandlerFactory::create_handler("python3".to_string());
+    assert_eq!(other.capabilities(), Capabilities::default());
+    assert_eq!(other.paste_message("two\nlines\n"), "two lines\r");
+    assert_eq!(q.paste_message("two\nlines\n"), "\x1b[200~two\nlines\x1b[201~\r");
+    assert_eq!(other.clear_input(), Some(Vec::new()));
+    
+    // Plugins report theirs, anything left out being false
+    let reported: Capabilities = serde_json::from_value(serde_json::json!({ "supports_clear": true }))?;
+    assert!(reported.supports_clear && !reported.multiline_input);
+    
+    Ok(())
+}
+
+/// Test that Q saves and loads a task's conversation, and CLIs it stands in for don't
+#[test]
+fn test_conversation_commands() {
+    let path = std::path::Path::new("/work/.grill/tasks/api/conversation.json");
+    let commands = CliHandlerFactory::create_handler("q chat".to_string()).conversation_commands(path).unwrap();
+    assert_eq!(commands.save, "/save /work/.grill/tasks/api/conversation.json --force");
+    assert_eq!(commands.load, "/load /work/.grill/tasks/api/conversation.json");
+    assert!(CliHandlerFactory::create_handler("python3".to_string()).conversation_commands(path).is_none());
+}
+
+/// Test that sign-in prompts are picked out of the CLI's output
+#[test]
+fn test_auth_prompt() {
+    let output = "Welcome\r\n\x1b[1mOpen this URL to sign in: https://view.awsapps.com/start/#/device?user_code=ABCD\x1b[0m\r\n";
+    assert_eq!(
+        cli_handler::find_auth_prompt(output).as_deref(),
+        Some("Open this URL to sign in: https://view.awsapps.com/start/#/device?user_code=ABCD")
+    );
+    assert!(cli_handler::find_auth_prompt("Please enter your OpenAI API key: ").is_some());
+    assert!(cli_handler::find_auth_prompt("You are not logged in, please run q login\n").is_some());
+    assert_eq!(cli_handler::find_auth_prompt("Added 3 files to the chat\n> "), None);
+}
+
+#[test]
+fn test_help_command() -> Result<()> {
+    let (command_tx, mut command_rx) = broadcast::channel::<Command>(10);
+    
+    IoHandler::process_command_buffer("/help", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Help));
+    IoHandler::process_command_buffer("/help 2", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::HelpTopic { topic: None, page: 2 }));
+    IoHandler::process_command_buffer("/help tasks", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::HelpTopic { topic: Some(topic), page: 1 } if topic == "tasks"));
+    IoHandler::process_command_buffer("/version", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Version));
+    IoHandler::process_command_buffer("/restart", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Restart));
+    IoHandler::process_command_buffer("/help keys 3", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::HelpTopic { topic: Some(topic), page: 3 } if topic == "keys"));
+    
+    // A mistyped /context shows its help
+    IoHandler::process_command_buffer("/context dif", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::HelpTopic { topic: Some(topic), .. } if topic == "context"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_paste_command() -> Result<()> {
+    let (command_tx, mut command_rx) = broadcast::channel::<Command>(10);
+    
+    IoHandler::process_command_buffer("/paste", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Paste { force: false }));
+    IoHandler::process_command_buffer("/paste --force", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Paste { force: true }));
+    
+    Ok(())
+}
+
+/// Command mode edits in the middle of the line, redrawing what follows the cursor
+#[test]
+fn test_line_editor() {
+    let mut line = io::LineEditor::default();
+    assert_eq!(line.insert("/tsk"), "/tsk");
+    assert_eq!(line.left(), "\x1b[4D/tsk\x1b[1D");
+    line.left();
+    assert_eq!(line.insert("a"), "\x1b[2D/task\x1b[2D");
+    assert_eq!(line.text(), "/task");
+    
+    line.end();
+    line.insert(" switch  api");
+    assert_eq!(line.delete_word(), "\x1b[17D/task switch     \x1b[3D");
+    assert_eq!(line.text(), "/task switch  ");
+    line.delete_word();
+    assert_eq!(line.text(), "/task ");
+    
+    line.home();
+    line.right();
+    assert_eq!(line.backspace(), "\x1b[1Dtask  \x1b[6D");
+    line.insert("/");
+    line.right();
+    line.right();
+    line.kill_line();
+    assert_eq!(line.text(), "sk ");
+    line.clear();
+    assert!(line.is_empty());
+}
+
+#[test]
+fn test_unknown_command() -> Result<()> {
+    let (command_tx, mut command_rx) = broadcast::channel::<Command>(10);
+    
+    IoHandler::process_command_buffer("/model claude-sonnet ", &command_tx);
+    assert!(matches!(command_rx.try_recv()?, Command::Unknown(command) if command == "/model claude-sonnet"));
+    
+    assert_eq!(io::unknown_command_message("/taks list"), "Unknown command: /taks (type /help). Did you mean /task?");
+    assert_eq!(io::unknown_command_message("/ntoes"), "Unknown command: /ntoes (type /help). Did you mean /notes?");
+    assert_eq!(io::unknown_command_message("/frobnicate"), "Unknown command: /frobnicate (type /help)");
+    
+    // Q's own commands go to Q; other CLIs' commands aren't known, so any might be theirs
+    let native = |command: &str| {
+        let handler = CliHandlerFactory::create_handler(command.to_string());
+        NativeCommands::for_cli(&handler, &CliProfile::from_command(command))
+    };
+    assert!(native("q chat").may_include("/model claude-sonnet"));
+    assert!(!native("q chat").may_include("/frobnicate"));
+    assert!(native("claude").may_include("/frobnicate"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_expand_alias() {
+    let aliases = [("/t", "/task"), ("sw", "/task switch"), ("/q", "/quit")]
+        .into_iter()
+        .map(|(alias, command)| (alias.to_string(), command.to_string()))
+        .collect();
+    
+    assert_eq!(io::expand_alias("/t list", &aliases), "/task list");
+    assert_eq!(io::expand_alias("/sw api", &aliases), "/task switch api");
+    assert_eq!(io::expand_alias("/q", &aliases), "/quit");
+    // Only whole first words that are aliases are expanded
+    assert_eq!(io::expand_alias("/task list", &aliases), "/task list");
+    assert_eq!(io::expand_alias("/notes /t", &aliases), "/notes /t");
+    assert_eq!(io::expand_alias("sw", &aliases), "sw");
+}
+
+/// The CLI's own commands go to it as typed, even where grill has one by the same name
+#[test]
+fn test_route_command() {
+    let aliases = [("/c".to_string(), "/compact".to_string()), ("/d".to_string(), "/context diff".to_string())]
+        .into_iter()
+        .collect();
+    let q = CliHandlerFactory::create_handler("q chat".to_string());
+    let native = NativeCommands::for_cli(&q, &CliProfile::from_command("q chat"));
+    
+    assert_eq!(io::route_command("/context add src/main.rs", &aliases, &native), io::Route::Cli("/context add src/main.rs".to_string()));
+    assert_eq!(io::route_command("/c", &aliases, &native), io::Route::Cli("/compact".to_string()));
+    assert_eq!(io::route_command("/task list", &aliases, &native), io::Route::Grill("/task list".to_string()));
+    assert_eq!(io::route_command("/grill context diff", &aliases, &native), io::Route::Grill("/context diff".to_string()));
+    assert_eq!(io::route_command("/grill /d", &aliases, &native), io::Route::Grill("/context diff".to_string()));
+    
+    // A profile can declare the commands of a CLI grill doesn't know
+    let mut aider = CliProfile::from_command("aider");
+    aider.native_commands = vec!["add".to_string(), "/drop".to_string()];
+    let native = NativeCommands::for_cli(&CliHandlerFactory::create_handler("aider".to_string()), &aider);
+    assert_eq!(io::route_command("/drop notes.md", &aliases, &native), io::Route::Cli("/drop notes.md".to_string()));
+    assert!(native.declares("/add"));
+    assert!(!native.may_include("/frobnicate"));
+}
@@ -0,0 +1,264 @@
+use anyhow::Result;
+use std::fs;
+use std::time::Duration;
+
+use grill::config::{self, ClearOnSwitch, Config, OverflowPolicy, TaskConfig};
+use grill::environment::Environment;
+
+#[test]
+fn test_config_formats() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let dir = temp_dir.path();
+    
+    let yaml = dir.join("config.yaml");
+    fs::write(&yaml, "default_cli: claude\nclis:\n  claude: claude\ngit:\n  worktrees: true\n")?;
+    let config = Config::load(&yaml)?;
+    assert_eq!(config.get_default_cli(), "claude");
+    assert!(config.git.worktrees);
+    
+    let json = dir.join("config.json");
+    fs::write(&json, r#"{"cli": "aider", "tags": ["json"]}"#)?;
+    let task_config = TaskConfig::load(&json)?;
+    assert_eq!(task_config.get_cli(), Some("aider"));
+    assert_eq!(task_config.tags, ["json"]);
+    
+    // An empty YAML file is an empty config
+    fs::write(&yaml, "")?;
+    assert_eq!(Config::load(&yaml)?.get_default_cli(), "q chat");
+    
+    // Errors name the file
+    fs::write(&json, "{")?;
+    let error = format!("{:#}", TaskConfig::load(&json).unwrap_err());
+    assert!(error.contains("config.json"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_find_config_file() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path();
+    fs::create_dir_all(root.join(".grill"))?;
+    fs::write(root.join(".grill").join("config.yml"), "default_cli: cat\n")?;
+    
+    // A workspace configured in YAML is found and used as-is
+    let env = Environment::new(root.to_path_buf());
+    assert!(env.exists());
+    assert_eq!(env.get_config_path(), root.join(".grill").join("config.yml"));
+    env.init()?;
+    assert!(!root.join(".grill").join("config.toml").exists());
+    
+    // TOML wins when there are several
+    fs::write(root.join(".grill").join("config.toml"), "default_cli = \"q chat\"\n")?;
+    assert_eq!(config::find_config_file(&root.join(".grill")), root.join(".grill").join("config.toml"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_task_config_round_trip() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let path = temp_dir.path().join("config.toml");
+    fs::write(&path, "cli = \"q chat\"\nowner = \"sam\"\n\n[plugin]\nlevel = 3\n")?;
+    
+    let mut task_config = TaskConfig::load(&path)?;
+    task_config.set_cli(Some("claude".to_string()));
+    task_config.add_tag("review");
+    task_config.add_tag("review");
+    task_config.set_status(grill::config::TaskStatus::Blocked, Some("waiting"));
+    task_config.save()?;
+    
+    // Unknown keys survive, known ones are updated
+    let saved: toml::Table = fs::read_to_string(&path)?.parse()?;
+    assert_eq!(saved["cli"].as_str(), Some("claude"));
+    assert_eq!(saved["owner"].as_str(), Some("sam"));
+    assert_eq!(saved["plugin"]["level"].as_integer(), Some(3));
+    assert_eq!(saved["status"].as_str(), Some("blocked"));
+    assert_eq!(saved["blocked_reason"].as_str(), Some("waiting"));
+    assert_eq!(TaskConfig::load(&path)?.tags, ["review"]);
+    
+    // The reason only applies while blocked
+    let mut task_config = TaskConfig::load(&path)?;
+    task_config.set_status(grill::config::TaskStatus::Done, Some("ignored"));
+    assert!(task_config.remove_tag("review"));
+    assert!(!task_config.remove_tag("review"));
+    task_config.save()?;
+    let saved: toml::Table = fs::read_to_string(&path)?.parse()?;
+    assert!(!saved.contains_key("blocked_reason"));
+    assert!(!saved.contains_key("tags"));
+    
+    // JSON configs stay JSON
+    let json = temp_dir.path().join("config.json");
+    fs::write(&json, r#"{"cli": "aider", "extra": {"a": [1, 2]}}"#)?;
+    let mut task_config = TaskConfig::load(&json)?;
+    task_config.set_status(grill::config::TaskStatus::Done, None);
+    task_config.save()?;
+    let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json)?)?;
+    assert_eq!(saved["status"], "done");
+    assert_eq!(saved["extra"]["a"][1], 2);
+    
+    Ok(())
+}
+
+#[test]
+fn test_frontmatter_config_is_not_saved() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    fs::write(temp_dir.path().join("instructions.md"), "---\ncli: claude\n---\nBody\n")?;
+    
+    let task_config = TaskConfig::load_for_task(temp_dir.path())?;
+    assert_eq!(task_config.get_cli(), Some("claude"));
+    assert!(task_config.save().is_err());
+    assert!(!temp_dir.path().join("config.toml").exists());
+    
+    Ok(())
+}
+
+#[test]
+fn test_cli_profiles() -> Result<()> {
+    let config: Config = toml::from_str(r#"
+default_cli = "q-sonnet"
+
+[clis]
+q = "q chat"
+
+[clis.q-sonnet]
+command = "q chat"
+args = ["--model", "claude-sonnet"]
+env = { AWS_REGION = "us-west-2" }
+"#)?;
+    
+    let profile = config.resolve_cli(config.get_default_cli());
+    assert_eq!(profile.command, "q chat");
+    assert_eq!(profile.command_line(), "q chat --model claude-sonnet");
+    assert_eq!(profile.env.get("AWS_REGION").map(String::as_str), Some("us-west-2"));
+    
+    // Plain entries and commands that aren't profile names have no extras
+    assert_eq!(config.resolve_cli("q"), config::CliProfile::from_command("q chat"));
+    assert_eq!(config.resolve_cli("aider --yes").command_line(), "aider --yes");
+    
+    Ok(())
+}
+
+#[test]
+fn test_cli_command_arrays() -> Result<()> {
+    let config: Config = toml::from_str(r#"
+default_cli = ["q", "chat", "--profile", "my prof"]
+
+[clis]
+spaced = ["/opt/my tools/q", "chat"]
+
+[clis.q-prof]
+command = ["q", "chat"]
+args = ["--profile", "my prof"]
+"#)?;
+    
+    assert_eq!(config.get_default_cli(), "q chat --profile 'my prof'");
+    assert_eq!(grill::clis::split_command(config.get_default_cli()), ["q", "chat", "--profile", "my prof"]);
+    assert_eq!(config.resolve_cli("spaced").command, "'/opt/my tools/q' chat");
+    assert_eq!(config.resolve_cli("q-prof").command_line(), "q chat --profile 'my prof'");
+    
+    let task: TaskConfig = toml::from_str(r#"cli = ["aider", "--model", "gpt 4"]"#)?;
+    assert_eq!(task.cli.as_deref(), Some("aider --model 'gpt 4'"));
+    let task: TaskConfig = toml::from_str("")?;
+    assert_eq!(task.cli, None);
+    
+    Ok(())
+}
+
+#[test]
+fn test_input_pacing() -> Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert_eq!(config.input.chunk_bytes, 1024);
+    assert_eq!(config.input.chunk_delay(1024), Duration::from_millis(10));
+    
+    // A rate limit stretches the pause to fit the chunk
+    let config: Config = toml::from_str("[input]\nchunk_bytes = 512\nchunk_delay_ms = 0\nbytes_per_second = 1024\n")?;
+    assert_eq!(config.input.chunk_delay(512), Duration::from_millis(500));
+    assert_eq!(config.input.confirm_bytes, 100 * 1024);
+    
+    Ok(())
+}
+
+#[test]
+fn test_shell_wrap() -> Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert!(!config.shell_wrap);
+    let config: Config = toml::from_str("shell_wrap = true")?;
+    assert!(config.shell_wrap);
+    
+    Ok(())
+}
+
+#[test]
+fn test_backchannel_is_opt_in() -> Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert!(!config.terminal.backchannel);
+    assert!(!Config::default().terminal.backchannel);
+    let config: Config = toml::from_str("[terminal]\nbackchannel = true")?;
+    assert!(config.terminal.backchannel);
+    
+    Ok(())
+}
+
+#[test]
+fn test_performance_config() -> Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert_eq!(config.performance.output_channel_capacity, 100);
+    assert_eq!(config.performance.display_overflow, OverflowPolicy::Block);
+    assert_eq!(config.performance.read_buffer_size, 1024);
+    assert_eq!(config.performance.flush_interval_ms, 8);
+    
+    let config: Config = toml::from_str("[performance]\noutput_channel_capacity = 1000\ndisplay_overflow = \"coalesce\"\nread_buffer_size = 16384\nflush_interval_ms = 0\n")?;
+    assert_eq!(config.performance.read_buffer_size, 16384);
+    assert!(config.performance.flush_interval().is_zero());
+    assert_eq!(config.performance.output_channel_capacity, 1000);
+    assert_eq!(config.performance.input_channel_capacity, 100);
+    assert_eq!(config.performance.display_overflow, OverflowPolicy::Coalesce);
+    
+    Ok(())
+}
+
+#[test]
+fn test_environment_config() -> Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert!(config.environment.passes("GITHUB_TOKEN"));
+    
+    let config: Config = toml::from_str("[environment]\nallow = [\"PATH\", \"LC_*\", \"AWS_*\"]\ndeny = [\"*_SECRET*\"]\n")?;
+    assert!(config.environment.passes("PATH"));
+    assert!(config.environment.passes("LC_ALL"));
+    assert!(config.environment.passes("AWS_REGION"));
+    assert!(!config.environment.passes("AWS_SECRET_ACCESS_KEY"));
+    assert!(!config.environment.passes("GITHUB_TOKEN"));
+    assert!(!config.environment.passes("PATHS"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_clear_on_switch() -> Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert_eq!(config.context.clear_on_switch, ClearOnSwitch::Always);
+    let config: Config = toml::from_str("[context]\nclear_on_switch = \"ask\"\n")?;
+    assert_eq!(config.context.clear_on_switch, ClearOnSwitch::Ask);
+    
+    // A task's own setting is left out until it has one
+    let task_config: TaskConfig = toml::from_str("clear_on_switch = \"never\"\n")?;
+    assert_eq!(task_config.clear_on_switch, Some(ClearOnSwitch::Never));
+    assert!(!toml::to_string(&TaskConfig::default())?.contains("clear_on_switch"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_startup_config() -> Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert_eq!(config.startup.timeout(), Some(std::time::Duration::from_secs(30)));
+    
+    assert!(!config.context.skip_context);
+    
+    let config: Config = toml::from_str("[startup]\ntimeout_seconds = 0\n\n[context]\nskip_context = true\n")?;
+    assert_eq!(config.startup.timeout(), None);
+    assert!(config.context.skip_context);
+    
+    Ok(())
+}
@@ -0,0 +1,25 @@
+use grill::confirm;
+
+#[test]
+fn test_needs_confirmation() {
+    let text = "x".repeat(2000);
+    assert!(confirm::needs_confirmation(&text, 1024));
+    assert!(!confirm::needs_confirmation(&text, 4096));
+    assert!(!confirm::needs_confirmation(&text, 0));
+}
+
+#[test]
+fn test_summary() {
+    assert_eq!(confirm::estimate_tokens("abcdefghi"), 3);
+    
+    let text = format!("first line\nsecond line\n{}\nfourth\nfifth\n", "y".repeat(100));
+    let summary = confirm::summary(&text);
+    assert!(summary.starts_with("137 bytes (5 lines, about 35 tokens):\n"), "{}", summary);
+    assert!(summary.contains("  | first line\n  | second line\n"));
+    assert!(summary.contains(&format!("  | {}...\n", "y".repeat(72))));
+    assert!(summary.ends_with("  | ... 2 more lines\n"));
+    assert!(!summary.contains("fourth"));
+    
+    let large = "z\n".repeat(1024 * 1024);
+    assert!(confirm::summary(&large).starts_with("2.0 MB (1048576 lines"));
+}
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use grill::context;
+
+fn git(dir: &Path, args: &[&str]) -> Result<()> {
+    let status = Command::new("git")
+        .args(["-c", "user.name=grill", "-c", "user.email=grill@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()?;
+    assert!(status.success(), "git {:?} failed", args);
+    Ok(())
+}
+
+#[test]
+fn test_diff_message() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path();
+    git(root, &["init", "-q"])?;
+    fs::write(root.join("lib.rs"), "fn one() {}\n")?;
+    git(root, &["add", "lib.rs"])?;
+    git(root, &["commit", "-q", "-m", "initial"])?;
+    
+    // A clean tree has nothing to send
+    assert_eq!(context::diff_message(root, false)?, None);
+    
+    fs::write(root.join("lib.rs"), "fn two() {}\n")?;
+    let message = context::diff_message(root, false)?.expect("diff");
+    assert!(message.contains("```diff"));
+    assert!(message.contains("+fn two() {}"));
+    
+    // Nothing is staged yet
+    assert_eq!(context::diff_message(root, true)?, None);
+    git(root, &["add", "lib.rs"])?;
+    assert!(context::diff_message(root, true)?.expect("staged diff").contains("staged changes"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_run_message() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    
+    let message = context::run_message(temp_dir.path(), "echo built; echo 'warning: unused' >&2", 1000)?;
+    assert!(message.starts_with("Here is the output of `echo built; echo 'warning: unused' >&2` (succeeded)"));
+    assert!(message.contains("built\nwarning: unused\n```"));
+    
+    let message = context::run_message(temp_dir.path(), "exit 2", 1000)?;
+    assert!(message.contains("(failed)"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_truncate_start() {
+    assert_eq!(context::truncate_start("short", 10), "short");
+    assert_eq!(context::truncate_start("0123456789", 4), "[... 6 bytes truncated ...]\n6789");
+}
+
+#[test]
+fn test_repo_map() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path();
+    
+    fs::create_dir_all(root.join("src"))?;
+    fs::write(root.join("src/lib.rs"), "pub mod api;\n\npub struct Client {\n    url: String,\n}\n\nimpl Client {\n    pub fn new() -> Self { todo!() }\n}\n")?;
+    fs::write(root.join("tool.py"), "import os\n\ndef main():\n    pass\n")?;
+    fs::create_dir_all(root.join("target"))?;
+    fs::write(root.join("target/out.rs"), "fn built() {}\n")?;
+    fs::write(root.join("secrets.txt"), "hunter2\n")?;
+    fs::write(root.join(".gitignore"), "target/\n")?;
+    fs::write(root.join(".grillignore"), "secrets.txt\n")?;
+    
+    let map = context::repo_map(root, 10_000);
+    assert!(map.contains("src/lib.rs\n    pub mod api\n    pub struct Client\n    impl Client\n"));
+    assert!(map.contains("tool.py\n    def main()\n"));
+    assert!(!map.contains("target"));
+    assert!(!map.contains("secrets.txt"));
+    
+    // Large maps are cut off
+    let map = context::repo_map(root, 20);
+    assert!(map.ends_with("more files\n"));
+    
+    Ok(())
+}
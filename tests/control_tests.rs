@@ -0,0 +1,187 @@
+use anyhow::Result;
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::broadcast;
+use tokio::time::{Duration, timeout};
+
+use grill::cli_handler::{CliHandler, QCliHandler};
+use grill::control::{self, ControlHandles, ControlRequest, ControlResponse, ControlServer};
+use grill::environment::Environment;
+use grill::io::Command;
+use grill::session::SessionState;
+use grill::task::TaskContext;
+
+#[tokio::test]
+async fn test_control_socket_requests() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    let (input_tx, _) = broadcast::channel::<String>(100);
+    let (command_tx, _) = broadcast::channel::<Command>(100);
+    let mut input_rx = input_tx.subscribe();
+    let mut command_rx = command_tx.subscribe();
+    
+    let socket_path = env.get_socket_path();
+    let _server = ControlServer::start(socket_path.clone(), ControlHandles {
+        session: Arc::new(RwLock::new(SessionState {
+            task: TaskContext::new(&env, "default"),
+            cli_handler: CliHandler::Q(QCliHandler::new("cat".to_string())),
+        })),
+        command: "cat".to_string(),
+        input_tx,
+        command_tx,
+        output_mirror: broadcast::channel::<String>(100).0,
+        replay: Arc::new(Mutex::new(String::new())),
+    })?;
+    
+    // Status reports the current task
+    let path = socket_path.clone();
+    let response = tokio::task::spawn_blocking(move || {
+        control::send_request(&path, &ControlRequest::Status)
+    }).await??;
+    match response {
+        ControlResponse::Status { task, command, .. } => {
+            assert_eq!(task, "default");
+            assert_eq!(command, "cat");
+        },
+        other => panic!("Unexpected response: {:?}", other),
+    }
+    
+    // Send forwards text to the child input with a carriage return
+    let path = socket_path.clone();
+    tokio::task::spawn_blocking(move || {
+        control::send_request(&path, &ControlRequest::Send { text: "hello".to_string(), task: None })
+    }).await??;
+    let input = timeout(Duration::from_secs(1), input_rx.recv()).await??;
+    assert_eq!(input, "hello\r");
+    
+    // Sending to a task the session isn't running is rejected
+    let path = socket_path.clone();
+    let response = tokio::task::spawn_blocking(move || {
+        control::send_request(&path, &ControlRequest::Send {
+            text: "hello".to_string(),
+            task: Some("other".to_string()),
+        })
+    }).await??;
+    assert!(matches!(response, ControlResponse::Error { .. }));
+    assert!(input_rx.try_recv().is_err());
+    
+    // Switch task is delivered as a session command
+    let path = socket_path.clone();
+    tokio::task::spawn_blocking(move || {
+        control::send_request(&path, &ControlRequest::SwitchTask { task: "other".to_string() })
+    }).await??;
+    match timeout(Duration::from_secs(1), command_rx.recv()).await?? {
+        Command::SwitchTask(name) => assert_eq!(name, "other"),
+        other => panic!("Unexpected command: {:?}", other),
+    }
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_observer_cannot_send_input() -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    let (input_tx, _) = broadcast::channel::<String>(100);
+    let (command_tx, _) = broadcast::channel::<Command>(100);
+    let (output_mirror, _) = broadcast::channel::<String>(100);
+    let mut input_rx = input_tx.subscribe();
+    
+    let socket_path = env.get_socket_path();
+    let _server = ControlServer::start(socket_path.clone(), ControlHandles {
+        session: Arc::new(RwLock::new(SessionState {
+            task: TaskContext::new(&env, "default"),
+            cli_handler: CliHandler::Q(QCliHandler::new("cat".to_string())),
+        })),
+        command: "cat".to_string(),
+        input_tx,
+        command_tx,
+        output_mirror: output_mirror.clone(),
+        replay: Arc::new(Mutex::new("earlier output".to_string())),
+    })?;
+    
+    let stream = UnixStream::connect(&socket_path).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    
+    writer.write_all(b"{\"type\":\"attach\",\"observe\":true}\n").await?;
+    
+    // Replay arrives first
+    let line = timeout(Duration::from_secs(1), lines.next_line()).await??.unwrap();
+    match serde_json::from_str::<ControlResponse>(&line)? {
+        ControlResponse::Output { data } => assert_eq!(data, "earlier output"),
+        other => panic!("Unexpected response: {:?}", other),
+    }
+    
+    // Input from an observer is refused
+    writer.write_all(b"{\"type\":\"input\",\"data\":\"x\"}\n").await?;
+    let line = timeout(Duration::from_secs(1), lines.next_line()).await??.unwrap();
+    assert!(matches!(serde_json::from_str::<ControlResponse>(&line)?, ControlResponse::Error { .. }));
+    assert!(input_rx.try_recv().is_err());
+    
+    // Live output is streamed
+    output_mirror.send("live".to_string())?;
+    let line = timeout(Duration::from_secs(1), lines.next_line()).await??.unwrap();
+    assert!(matches!(serde_json::from_str::<ControlResponse>(&line)?, ControlResponse::Output { data } if data == "live"));
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_observer_cannot_reattach_with_input() -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    let (input_tx, _) = broadcast::channel::<String>(100);
+    let (command_tx, _) = broadcast::channel::<Command>(100);
+    let (output_mirror, _) = broadcast::channel::<String>(100);
+    let mut input_rx = input_tx.subscribe();
+    
+    let socket_path = env.get_socket_path();
+    let _server = ControlServer::start(socket_path.clone(), ControlHandles {
+        session: Arc::new(RwLock::new(SessionState {
+            task: TaskContext::new(&env, "default"),
+            cli_handler: CliHandler::Q(QCliHandler::new("cat".to_string())),
+        })),
+        command: "cat".to_string(),
+        input_tx,
+        command_tx,
+        output_mirror: output_mirror.clone(),
+        replay: Arc::new(Mutex::new(String::new())),
+    })?;
+    
+    let stream = UnixStream::connect(&socket_path).await?;
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    
+    writer.write_all(b"{\"type\":\"attach\",\"observe\":true}\n").await?;
+    
+    // A second attach asking for input is refused
+    writer.write_all(b"{\"type\":\"attach\",\"observe\":false}\n").await?;
+    let line = timeout(Duration::from_secs(1), lines.next_line()).await??.unwrap();
+    assert!(matches!(serde_json::from_str::<ControlResponse>(&line)?, ControlResponse::Error { .. }));
+    
+    // The client is still an observer
+    writer.write_all(b"{\"type\":\"input\",\"data\":\"x\"}\n").await?;
+    let line = timeout(Duration::from_secs(1), lines.next_line()).await??.unwrap();
+    assert!(matches!(serde_json::from_str::<ControlResponse>(&line)?, ControlResponse::Error { .. }));
+    assert!(input_rx.try_recv().is_err());
+    
+    // Output is streamed once, not once per attach
+    output_mirror.send("live".to_string())?;
+    let line = timeout(Duration::from_secs(1), lines.next_line()).await??.unwrap();
+    assert!(matches!(serde_json::from_str::<ControlResponse>(&line)?, ControlResponse::Output { data } if data == "live"));
+    assert!(timeout(Duration::from_millis(200), lines.next_line()).await.is_err());
+    
+    Ok(())
+}
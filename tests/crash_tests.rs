@@ -0,0 +1,55 @@
+use anyhow::Result;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use grill::crash::{self, CrashLog, MAX_COMMANDS, MAX_OUTPUT};
+
+#[test]
+fn test_crash_report() {
+    let summary = crash::summary(Path::new("/work/app"), "api", "q chat", "q");
+    let mut log = CrashLog::new(summary);
+    log.output("\x1b[31mfirst\x1b[0m\r\n");
+    log.output(&"x".repeat(MAX_OUTPUT));
+    log.output("\x1b[1mlast line\x1b[0m\r\n");
+    for i in 0..MAX_COMMANDS + 5 {
+        log.command(&format!("message {}", i));
+    }
+    
+    let report = log.report("The CLI exited with status 101.", None);
+    assert!(report.starts_with("# grill crash report\n\nThe CLI exited with status 101.\n"), "{}", report);
+    assert!(report.contains("Task: api\n"), "{}", report);
+    assert!(report.contains("CLI: q chat\n"), "{}", report);
+    assert!(report.contains(&format!("Version: {}\n", grill::version::grill_version())), "{}", report);
+    
+    // Only the last commands and output are kept, without escape sequences
+    assert!(!report.contains("- message 4\n"), "{}", report);
+    assert!(report.contains("- message 5\n"), "{}", report);
+    assert!(report.contains(&format!("- message {}\n", MAX_COMMANDS + 4)), "{}", report);
+    assert!(!report.contains("first"), "{}", report);
+    assert!(report.contains("last line\n```\n"), "{}", report);
+    assert!(!report.contains('\x1b'), "{}", report);
+    assert!(!report.contains("## Backtrace"), "{}", report);
+    
+    assert!(CrashLog::default().report("boom", Some("0: main")).contains("## Backtrace\n\n```\n0: main\n```\n"));
+}
+
+#[test]
+fn test_panic_writes_crash_report() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let crash_dir = dir.path().join("crash");
+    let log = Arc::new(Mutex::new(CrashLog::new(Vec::new())));
+    log.lock().unwrap().command("/task switch api");
+    crash::install(crash_dir.clone(), Arc::clone(&log));
+    
+    assert!(std::thread::spawn(|| panic!("something broke")).join().is_err());
+    
+    let reports: Vec<_> = std::fs::read_dir(&crash_dir)?.collect::<Result<_, _>>()?;
+    assert_eq!(reports.len(), 1);
+    let report = std::fs::read_to_string(reports[0].path())?;
+    assert!(report.contains("grill panicked: "), "{}", report);
+    assert!(report.contains("something broke"), "{}", report);
+    assert!(report.contains("- /task switch api\n"), "{}", report);
+    assert!(report.contains("## Backtrace"), "{}", report);
+    
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+
+use grill::events::{self, Event, EventKind, EventLog, Stats};
+
+#[test]
+fn test_event_log_round_trip() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let path = temp_dir.path().join("events.jsonl");
+    
+    let log = EventLog::new(path.clone());
+    log.record("default", EventKind::SessionStart);
+    log.record("default", EventKind::Message);
+    log.record("default", EventKind::TaskSwitch { to: "api".to_string() });
+    log.record("api", EventKind::Crash { exit_code: 1 });
+    
+    let events = events::load(&path)?;
+    assert_eq!(events.len(), 4);
+    assert_eq!(events[2].kind, EventKind::TaskSwitch { to: "api".to_string() });
+    assert_eq!(events[3].task, "api");
+    
+    Ok(())
+}
+
+#[test]
+fn test_stats_from_events() {
+    let start = Utc::now();
+    let event = |session: &str, minutes: i64, task: &str, kind: EventKind| Event {
+        time: start + Duration::minutes(minutes),
+        session: session.to_string(),
+        task: task.to_string(),
+        kind,
+    };
+    
+    let events = vec![
+        event("a", 0, "default", EventKind::SessionStart),
+        event("a", 1, "default", EventKind::Message),
+        event("a", 2, "default", EventKind::TaskSwitch { to: "api".to_string() }),
+        event("a", 3, "api", EventKind::Message),
+        event("a", 4, "api", EventKind::Message),
+        event("a", 10, "api", EventKind::SessionEnd),
+        // Killed session without an end event
+        event("b", 20, "api", EventKind::SessionStart),
+        event("b", 25, "api", EventKind::Crash { exit_code: 101 }),
+    ];
+    
+    let stats = Stats::from_events(&events);
+    assert_eq!(stats.sessions, 2);
+    assert_eq!(stats.duration_secs, 15 * 60);
+    assert_eq!(stats.messages, 3);
+    assert_eq!(stats.crashes, 1);
+    assert_eq!(stats.tasks["api"].messages, 2);
+    assert_eq!(stats.tasks["api"].sessions, 2);
+    
+    let report = stats.report();
+    assert!(report.contains("Total time:     15m 00s"));
+    assert!(report.find("api").unwrap() < report.find("default").unwrap());
+}
+
+#[test]
+fn test_task_times() {
+    let start = Utc::now();
+    let event = |session: &str, minutes: i64, task: &str, kind: EventKind| Event {
+        time: start + Duration::minutes(minutes),
+        session: session.to_string(),
+        task: task.to_string(),
+        kind,
+    };
+    
+    let events = vec![
+        event("a", 0, "default", EventKind::SessionStart),
+        event("a", 5, "default", EventKind::Message),
+        event("a", 10, "default", EventKind::TaskSwitch { to: "api".to_string() }),
+        // A second session running in parallel
+        event("b", 12, "docs", EventKind::SessionStart),
+        event("a", 40, "api", EventKind::SessionEnd),
+        // Killed without an end event: counts until its last event
+        event("b", 15, "docs", EventKind::Message),
+    ];
+    
+    let times = events::task_times(&events);
+    assert_eq!(times["default"], 10 * 60);
+    assert_eq!(times["api"], 30 * 60);
+    assert_eq!(times["docs"], 3 * 60);
+}
+
+#[test]
+fn test_session_metrics() {
+    let start = Utc::now();
+    let event = |session: &str, kind: EventKind| Event {
+        time: start,
+        session: session.to_string(),
+        task: "default".to_string(),
+        kind,
+    };
+    
+    let mut events = vec![
+        event("a", EventKind::SessionStart),
+        event("a", EventKind::Restart),
+        event("a", EventKind::Traffic { bytes_in: 10, bytes_out: 2000 }),
+        event("b", EventKind::SessionStart),
+        event("b", EventKind::Traffic { bytes_in: 5, bytes_out: 500 }),
+    ];
+    for ms in 1..=100 {
+        events.push(event("b", EventKind::Response { first_byte_ms: ms, prompt_ms: ms * 10 }));
+    }
+    
+    let stats = Stats::from_events(&events);
+    assert_eq!(stats.restarts, 1);
+    assert_eq!((stats.bytes_in, stats.bytes_out), (15, 2500));
+    assert_eq!(stats.time_to_first_byte.count, 100);
+    assert_eq!(stats.time_to_first_byte.median_ms, 51);
+    assert_eq!(stats.time_to_first_byte.p95_ms, 95);
+    assert_eq!(stats.first_byte_to_prompt.max_ms, 1000);
+    assert_eq!(stats.first_byte_to_prompt.total_ms, 50500);
+    
+    let report = stats.report();
+    assert!(report.contains("CLI restarts:   1"), "{}", report);
+    assert!(report.contains("First output:   51ms median, 95ms 95th percentile"), "{}", report);
+    assert!(report.contains("Answer time:    510ms median, 950ms 95th percentile"), "{}", report);
+    
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["bytes_out"], 2500);
+    assert_eq!(json["time_to_first_byte"]["p95_ms"], 95);
+    
+    // Without any answers there is nothing to report on them
+    assert!(!Stats::from_events(&events[..3]).report().contains("First output"));
+}
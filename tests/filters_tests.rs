@@ -0,0 +1,68 @@
+use anyhow::Result;
+
+use grill::config::{Config, FilterAction};
+use grill::filters::OutputFilter;
+
+fn filter(config: &str) -> Result<OutputFilter> {
+    let config: Config = toml::from_str(config)?;
+    OutputFilter::new(&config.filters)
+}
+
+#[test]
+fn test_filters_config() -> Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert!(config.filters.rules.is_empty());
+    
+    let config: Config = toml::from_str("[[filters.rules]]\npattern = \"^Telemetry.*$\"\naction = \"drop\"\n\n[[filters.rules]]\npattern = \"a+\"\nreplacement = \"a\"\n")?;
+    assert_eq!(config.filters.rules.len(), 2);
+    assert_eq!(config.filters.rules[0].action, FilterAction::Drop);
+    assert_eq!(config.filters.rules[1].action, FilterAction::Replace);
+    assert_eq!(config.filters.rules[1].replacement, "a");
+    
+    Ok(())
+}
+
+#[test]
+fn test_filters_apply_in_order() -> Result<()> {
+    let filter = filter(r#"
+        [[filters.rules]]
+        pattern = "^Telemetry is enabled.*$"
+        action = "drop"
+        
+        [[filters.rules]]
+        pattern = "(\r- Thinking)+"
+        replacement = "$1"
+        
+        [[filters.rules]]
+        pattern = "secret-\\w+"
+    "#)?;
+    
+    assert_eq!(
+        filter.apply("Welcome\r\nTelemetry is enabled, see docs\r\nReady\r\n".to_string()),
+        Some("Welcome\r\nReady\r\n".to_string())
+    );
+    assert_eq!(filter.apply("\r- Thinking\r- Thinking\r- Thinking".to_string()), Some("\r- Thinking".to_string()));
+    assert_eq!(filter.apply("key secret-abc123 used".to_string()), Some("key  used".to_string()));
+    
+    // Output the rules remove entirely isn't passed on
+    assert_eq!(filter.apply("Telemetry is enabled\r\n".to_string()), None);
+    
+    Ok(())
+}
+
+#[test]
+fn test_filters_without_rules() -> Result<()> {
+    let filter = OutputFilter::default();
+    assert_eq!(filter.apply("unchanged\n".to_string()), Some("unchanged\n".to_string()));
+    assert_eq!(filter.apply(String::new()), Some(String::new()));
+    
+    Ok(())
+}
+
+#[test]
+fn test_filters_invalid_pattern() -> Result<()> {
+    let error = filter("[[filters.rules]]\npattern = \"(unclosed\"\n").unwrap_err();
+    assert!(error.to_string().contains("(unclosed"));
+    
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use grill::environment::Environment;
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(["-c", "user.name=grill", "-c", "user.email=grill@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()?;
+    assert!(output.status.success(), "git {:?} failed", args);
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[test]
+fn test_task_worktrees() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path();
+    git(root, &["init", "-q"])?;
+    fs::write(root.join("README.md"), "hello\n")?;
+    git(root, &["add", "README.md"])?;
+    git(root, &["commit", "-q", "-m", "initial"])?;
+    
+    let env = Environment::new(root.to_path_buf());
+    env.init()?;
+    
+    // Without the setting tasks run in the current directory
+    assert_eq!(env.get_task_working_dir("default")?, None);
+    
+    let config = fs::read_to_string(env.get_config_path())?;
+    fs::write(env.get_config_path(), format!("{}\n[git]\nworktrees = true\n", config))?;
+    
+    let worktree = env.get_task_working_dir("default")?.expect("worktree");
+    assert_eq!(worktree, env.get_worktree_path("default"));
+    assert!(worktree.join("README.md").exists());
+    assert_eq!(git(&worktree, &["rev-parse", "--abbrev-ref", "HEAD"])?, "grill/default");
+    
+    // Reusing the worktree doesn't recreate it, and it stays out of the main status
+    assert_eq!(env.get_task_working_dir("default")?, Some(worktree));
+    assert!(!git(root, &["status", "--porcelain"])?.contains("worktrees"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_branch_task_mapping() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path();
+    git(root, &["init", "-q"])?;
+    git(root, &["commit", "-q", "--allow-empty", "-m", "initial"])?;
+    
+    let env = Environment::new(root.to_path_buf());
+    env.init()?;
+    env.create_task("login")?;
+    env.create_task("payments")?;
+    
+    let config = fs::read_to_string(env.get_config_path())?;
+    fs::write(env.get_config_path(), format!(
+        "{}\n[git]\nbranch_prefix = \"feature/\"\n\n[git.branch_task_map]\nhotfix-42 = \"payments\"\n",
+        config
+    ))?;
+    
+    // Explicit mapping
+    git(root, &["checkout", "-q", "-b", "hotfix-42"])?;
+    assert_eq!(env.get_branch_task()?, Some(("hotfix-42".to_string(), "payments".to_string())));
+    
+    // Naming convention
+    git(root, &["checkout", "-q", "-b", "feature/login"])?;
+    assert_eq!(env.get_branch_task()?, Some(("feature/login".to_string(), "login".to_string())));
+    
+    // Branches for tasks that don't exist are ignored
+    git(root, &["checkout", "-q", "-b", "feature/unknown"])?;
+    assert_eq!(env.get_branch_task()?, None);
+    
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+
+use grill::config::Config;
+use grill::heartbeat::{self, Heartbeat};
+
+#[test]
+fn test_heartbeat_config() -> anyhow::Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert_eq!(config.heartbeat.stuck_after(), Some(Duration::from_secs(120)));
+    
+    let config: Config = toml::from_str("[heartbeat]\nstuck_seconds = 0\n")?;
+    assert_eq!(config.heartbeat.stuck_after(), None);
+    
+    Ok(())
+}
+
+#[test]
+fn test_heartbeat() {
+    let start = Instant::now();
+    let second = Duration::from_secs(1);
+    let mut heartbeat = Heartbeat::new(60 * second);
+    
+    // Nothing is waited on before a message is sent
+    assert!(!heartbeat.check(start + 600 * second));
+    assert_eq!(heartbeat.next_check(start), start + second);
+    
+    // Output while answering restarts the clock
+    heartbeat.input(start);
+    assert_eq!(heartbeat.next_check(start), start + 60 * second);
+    heartbeat.output(start + 50 * second, false);
+    assert!(!heartbeat.check(start + 100 * second));
+    assert_eq!(heartbeat.quiet_for(start + 100 * second), 50 * second);
+    
+    // Reported once per message
+    assert!(heartbeat.check(start + 110 * second));
+    assert!(!heartbeat.check(start + 500 * second));
+    assert!(heartbeat::stuck_notice(heartbeat.quiet_for(start + 110 * second)).contains("no output for 1m 00s"));
+    
+    // Getting back to the prompt means the CLI answered
+    heartbeat.input(start + 600 * second);
+    heartbeat.output(start + 601 * second, true);
+    assert!(!heartbeat.check(start + 900 * second));
+    
+    // Output with nothing asked doesn't start the clock
+    heartbeat.output(start + 901 * second, false);
+    assert!(!heartbeat.check(start + 1000 * second));
+}
@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use grill::cli_handler::{CliHandlerFactory, NativeCommands};
+use grill::config::CliProfile;
+use grill::help::{self, Help};
+
+fn help_for(command: &str) -> Help {
+    let handler = CliHandlerFactory::create_handler(command.to_string());
+    let native = NativeCommands::for_cli(&handler, &CliProfile::from_command(command));
+    let mut help = Help::default();
+    help.add_cli_topic(help::cli_topic(&help::cli_name(command), &handler.get_help_text(), &native));
+    help
+}
+
+#[test]
+fn test_find_topic() {
+    let help = help_for("/usr/local/bin/q chat");
+    assert_eq!(help.find("tasks").unwrap().name, "tasks");
+    assert_eq!(help.find("task").unwrap().name, "tasks");
+    assert_eq!(help.find("KEYS").unwrap().name, "keys");
+    assert!(help.find("m").is_none(), "messages and macros both start with m");
+    assert!(help.find("nothing").is_none());
+    
+    // The CLI's topic is named after its program, and also found as `cli`
+    assert_eq!(help.find("cli").unwrap().name, "q");
+    assert!(help.is_cli_topic("q"));
+    assert!(help.is_cli_topic("cli"));
+    assert!(!help.is_cli_topic("tasks"));
+    assert!(help.find("q").unwrap().body.contains("/model"));
+    
+    // Other CLIs may have any command
+    let help = help_for("claude --verbose");
+    assert!(help.find("claude").unwrap().body.contains("Slash commands grill doesn't know go to claude"));
+    
+    assert!(help.page(Some("nothing"), 1, 24).starts_with("No help on 'nothing'. Topics: tasks, context"));
+}
+
+#[test]
+fn test_paginate() {
+    let text: String = (1..=12).map(|n| format!("line {}\n", n)).collect();
+    
+    // Ten lines leave seven for help
+    let first = help::paginate(&text, "/help tasks", 1, 10);
+    assert!(first.starts_with("line 1\n"));
+    assert!(first.contains("line 7\n"));
+    assert!(!first.contains("line 8\n"));
+    assert!(first.ends_with("-- Page 1 of 2; /help tasks 2 for more --\n"));
+    
+    let last = help::paginate(&text, "/help tasks", 5, 10);
+    assert!(last.starts_with("line 8\n"));
+    assert!(last.ends_with("line 12\n-- Page 2 of 2 --\n"));
+    
+    // Help that fits has no footer
+    assert_eq!(help::paginate("one\ntwo\n", "/help", 1, 24), "one\ntwo\n");
+}
+
+#[test]
+fn test_custom_topic() {
+    let mut aliases = HashMap::new();
+    aliases.insert("ts".to_string(), "/task switch".to_string());
+    let topic = help::custom_topic(&aliases, &["review".to_string()], &[]);
+    assert!(topic.body.contains("/ts"));
+    assert!(topic.body.contains("/prompt review"));
+    assert!(topic.body.contains("No macros in this task"));
+    
+    let mut help = Help::default();
+    help.add_topic(topic);
+    assert!(help.overview().contains("/help custom"));
+}
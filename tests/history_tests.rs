@@ -0,0 +1,43 @@
+use anyhow::Result;
+
+use grill::history::{self, LineTracker};
+
+#[test]
+fn test_input_history_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join(history::INPUT_HISTORY_FILE);
+    assert!(history::load(&path)?.is_empty());
+    
+    for message in ["first", "second", "first", "with \"quotes\"\nand a newline"] {
+        history::append(&path, message)?;
+    }
+    assert_eq!(history::load(&path)?.len(), 4);
+    
+    // Newest first, without repeats
+    assert_eq!(history::recent(&path, 10)?, vec!["with \"quotes\"\nand a newline", "first", "second"]);
+    assert_eq!(history::recent(&path, 1)?, vec!["with \"quotes\"\nand a newline"]);
+    
+    history::trim(&path, 2)?;
+    assert_eq!(history::load(&path)?, vec!["first", "with \"quotes\"\nand a newline"]);
+    
+    Ok(())
+}
+
+#[test]
+fn test_line_tracker() {
+    let mut tracker = LineTracker::default();
+    
+    assert!(tracker.input("hel").is_empty());
+    assert!(!tracker.is_empty());
+    assert_eq!(tracker.input("lo\r"), vec!["hello"]);
+    assert!(tracker.is_empty());
+    
+    // Editing keys apply, and blank lines aren't messages
+    assert_eq!(tracker.input("fixx\x7f it\r\r"), vec!["fix it"]);
+    
+    // Arrow keys recalling the CLI's own history are skipped
+    assert_eq!(tracker.input("\x1b[A\x1b[Dnew\r"), vec!["new"]);
+    
+    // Ctrl+C abandons a line
+    assert_eq!(tracker.input("never mind\x03again\r"), vec!["again"]);
+}
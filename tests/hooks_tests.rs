@@ -0,0 +1,76 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use grill::environment::Environment;
+use grill::hooks::{self, ON_SWITCH};
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()?;
+    assert!(output.status.success(), "git {:?} failed", args);
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[test]
+fn test_git_checkpoint_hook() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path();
+    git(root, &["init", "-q"])?;
+    git(root, &["config", "user.name", "grill"])?;
+    git(root, &["config", "user.email", "grill@example.com"])?;
+    
+    let env = Environment::new(root.to_path_buf());
+    env.init()?;
+    env.create_task("api")?;
+    
+    let config = fs::read_to_string(env.get_config_path())?;
+    fs::write(env.get_config_path(), format!("{}\n[hooks]\non_switch = \"builtin:git-checkpoint\"\n", config))?;
+    
+    fs::write(root.join("main.rs"), "fn main() {}\n")?;
+    let failures = hooks::run_event(&env, ON_SWITCH, "default", Some("api"), None);
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(git(root, &["log", "-1", "--format=%s"])?, "grill: checkpoint before switching to api");
+    
+    // Nothing to commit is not a failure
+    let failures = hooks::run_event(&env, ON_SWITCH, "default", Some("api"), None);
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(git(root, &["rev-list", "--count", "HEAD"])?, "1");
+    
+    Ok(())
+}
+
+#[test]
+fn test_failing_hook_is_reported() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    let config = fs::read_to_string(env.get_config_path())?;
+    fs::write(env.get_config_path(), format!("{}\n[hooks]\non_quit = \"echo nope >&2; exit 1\"\n", config))?;
+    
+    let failures = hooks::run_event(&env, hooks::ON_QUIT, "default", None, None);
+    assert_eq!(failures.len(), 1);
+    assert!(failures[0].contains("nope"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_response_hook_reads_stdin() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    let config = fs::read_to_string(env.get_config_path())?;
+    fs::write(env.get_config_path(), format!("{}\n[hooks]\non_response = \"cat > response.txt\"\n", config))?;
+    
+    let failures = hooks::run_event(&env, hooks::ON_RESPONSE, "default", None, Some("Here is the fix.\n"));
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(fs::read_to_string(temp_dir.path().join("response.txt"))?, "Here is the fix.\n");
+    
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+use grill::config::{Config, IdleAction};
+use grill::idle::IdleTracker;
+
+#[test]
+fn test_idle_config() -> anyhow::Result<()> {
+    let config: Config = toml::from_str("")?;
+    assert_eq!(config.idle.timeout_minutes, 0);
+    assert_eq!(config.idle.action, IdleAction::Notify);
+    
+    let config: Config = toml::from_str("[idle]\ntimeout_minutes = 30\naction = \"pause\"\n")?;
+    assert_eq!(config.idle.timeout_minutes, 30);
+    assert_eq!(config.idle.action, IdleAction::Pause);
+    
+    Ok(())
+}
+
+#[test]
+fn test_idle_tracker() {
+    let start = Instant::now();
+    let minute = Duration::from_secs(60);
+    let mut idle = IdleTracker::new(10 * minute, start);
+    
+    assert!(!idle.check(start + 9 * minute));
+    assert_eq!(idle.next_check(start + 9 * minute), start + 10 * minute);
+    
+    // Reported once per idle spell
+    assert!(idle.check(start + 10 * minute));
+    assert!(!idle.check(start + 30 * minute));
+    assert_eq!(idle.next_check(start + 30 * minute), start + 40 * minute);
+    
+    // Typing ends the spell and restarts the clock
+    assert!(idle.input(start + 31 * minute));
+    assert!(!idle.input(start + 32 * minute));
+    assert!(!idle.check(start + 41 * minute));
+    assert!(idle.check(start + 42 * minute));
+}
@@ -93,6 +93,9 @@ async fn test_command_processing() -> Result<()> {
                     *r = false;
                     break;
                 },
+                _ => {
+                    // Other commands aren't exercised by this test
+                },
             }
         }
     });
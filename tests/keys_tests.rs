@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use grill::config::KeysConfig;
+use grill::keys::{Action, KeyBinding, KeyChord, KeyMap, KeyMatch, DOUBLE_PRESS};
+
+fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+    KeyEvent::new(code, modifiers)
+}
+
+fn ctrl(c: char) -> KeyEvent {
+    key(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+fn plain(c: char) -> KeyEvent {
+    key(KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+#[test]
+fn test_parse_keys() {
+    let chord = KeyChord::parse("Ctrl+G").unwrap();
+    assert_eq!(chord, KeyChord { code: KeyCode::Char('g'), modifiers: KeyModifiers::CONTROL });
+    assert_eq!(chord.to_string(), "Ctrl+G");
+    assert_eq!(KeyChord::parse("alt+shift+f5").unwrap().to_string(), "Alt+Shift+F5");
+    assert_eq!(KeyChord::parse("ctrl++").unwrap().code, KeyCode::Char('+'));
+    assert_eq!(KeyChord::parse("esc").unwrap().code, KeyCode::Esc);
+    assert!(KeyChord::parse("hyper+x").is_err());
+    assert!(KeyChord::parse("f13").is_err());
+    
+    assert_eq!(KeyBinding::parse("ctrl+g [").unwrap().unwrap().to_string(), "Ctrl+G [");
+    assert!(KeyBinding::parse("").unwrap().is_none());
+    assert!(KeyBinding::parse("a b c").is_err());
+    
+    let config = KeysConfig { quit: "ctrl+q x y".to_string(), ..KeysConfig::default() };
+    let error = KeyMap::new(&config).unwrap_err();
+    assert!(format!("{:#}", error).contains("quit = \"ctrl+q x y\" under [keys]"));
+}
+
+#[test]
+fn test_key_matches() {
+    let chord = KeyChord::parse("ctrl+]").unwrap();
+    assert!(chord.matches(&ctrl(']')));
+    assert!(chord.matches(&ctrl('5')), "some terminals report Ctrl+] as Ctrl+5");
+    assert!(!chord.matches(&plain(']')));
+    
+    // Shift comes with the character typed
+    assert!(KeyChord::parse("?").unwrap().matches(&key(KeyCode::Char('?'), KeyModifiers::SHIFT)));
+}
+
+#[test]
+fn test_default_bindings() {
+    let mut keys = KeyMap::default();
+    let now = Instant::now();
+    
+    assert_eq!(keys.press(&ctrl('c'), false, now), KeyMatch::Action(Action::Quit));
+    assert_eq!(keys.press(&plain('/'), false, now), KeyMatch::Action(Action::CommandMode));
+    assert_eq!(keys.press(&ctrl('t'), false, now), KeyMatch::Action(Action::SwitchTaskPicker));
+    
+    // While a command is typed, its keys are the command's
+    assert_eq!(keys.press(&plain('/'), true, now), KeyMatch::Unbound { held: None });
+    assert_eq!(keys.press(&ctrl('c'), true, now), KeyMatch::Action(Action::Quit));
+    
+    // A prefix waits for its key, and pressed twice goes to the CLI
+    assert_eq!(keys.press(&ctrl('g'), false, now), KeyMatch::Pending);
+    assert_eq!(keys.press(&plain('['), false, now), KeyMatch::Action(Action::CopyMode));
+    assert_eq!(keys.press(&ctrl('g'), false, now), KeyMatch::Pending);
+    assert_eq!(keys.press(&ctrl('g'), false, now), KeyMatch::Literal("\x07".to_string()));
+    assert_eq!(keys.press(&ctrl('g'), false, now), KeyMatch::Pending);
+    assert_eq!(keys.press(&plain('x'), false, now), KeyMatch::Unbound { held: Some("\x07".to_string()) });
+}
+
+#[test]
+fn test_double_press() {
+    let mut keys = KeyMap::default();
+    let esc = key(KeyCode::Esc, KeyModifiers::NONE);
+    let now = Instant::now();
+    
+    assert_eq!(keys.press(&esc, false, now), KeyMatch::Pending);
+    assert_eq!(keys.press(&esc, false, now + Duration::from_millis(100)), KeyMatch::Action(Action::StopGeneration));
+    
+    // Too slow, so the second press starts over
+    assert_eq!(keys.press(&esc, false, now), KeyMatch::Pending);
+    let later = now + DOUBLE_PRESS + Duration::from_millis(100);
+    assert_eq!(keys.press(&esc, false, later), KeyMatch::Pending);
+    assert_eq!(keys.press(&esc, false, later), KeyMatch::Action(Action::StopGeneration));
+}
+
+#[test]
+fn test_rebound_keys() {
+    let config = KeysConfig {
+        quit: "ctrl+q".to_string(),
+        command_mode: "ctrl+k".to_string(),
+        stop_generation: String::new(),
+        ..KeysConfig::default()
+    };
+    let mut keys = KeyMap::new(&config).unwrap();
+    let now = Instant::now();
+    
+    assert_eq!(keys.press(&ctrl('q'), false, now), KeyMatch::Action(Action::Quit));
+    assert_eq!(keys.press(&ctrl('c'), false, now), KeyMatch::Unbound { held: None });
+    assert_eq!(keys.press(&ctrl('k'), false, now), KeyMatch::Action(Action::CommandMode));
+    assert_eq!(keys.press(&plain('/'), false, now), KeyMatch::Unbound { held: None });
+    assert_eq!(keys.press(&key(KeyCode::Esc, KeyModifiers::NONE), false, now), KeyMatch::Unbound { held: None });
+    assert!(keys.binding(Action::StopGeneration).is_none());
+}
+
+#[test]
+fn test_leader_keys() {
+    let config = KeysConfig { command_mode: String::new(), ..KeysConfig::default() };
+    let mut keys = KeyMap::new(&config).unwrap();
+    let now = Instant::now();
+    
+    assert_eq!(keys.press(&ctrl('g'), false, now), KeyMatch::Pending);
+    assert_eq!(keys.press(&plain('t'), false, now), KeyMatch::Command("/task".to_string()));
+    assert_eq!(keys.press(&ctrl('g'), false, now), KeyMatch::Pending);
+    assert_eq!(keys.press(&plain('n'), false, now), KeyMatch::Command("/note ".to_string()));
+    
+    // The leader's other bindings still apply, and an unknown letter goes to the CLI
+    assert_eq!(keys.press(&ctrl('g'), false, now), KeyMatch::Pending);
+    assert_eq!(keys.press(&plain('['), false, now), KeyMatch::Action(Action::CopyMode));
+    assert_eq!(keys.press(&ctrl('g'), false, now), KeyMatch::Pending);
+    assert!(matches!(keys.press(&plain('z'), false, now), KeyMatch::Unbound { held: Some(_) }));
+    
+    // With command mode off, `/` is the CLI's
+    assert_eq!(keys.press(&plain('/'), false, now), KeyMatch::Unbound { held: None });
+    
+    let (leader, commands) = keys.leader().unwrap();
+    assert_eq!(leader.to_string(), "Ctrl+G");
+    assert_eq!(commands.len(), 4);
+    
+    let config = KeysConfig {
+        leader_keys: [("tt".to_string(), "/task".to_string())].into_iter().collect(),
+        ..KeysConfig::default()
+    };
+    assert!(KeyMap::new(&config).is_err());
+    
+    let config = KeysConfig { leader: String::new(), ..KeysConfig::default() };
+    assert!(KeyMap::new(&config).unwrap().leader().is_none());
+}
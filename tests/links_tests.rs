@@ -0,0 +1,71 @@
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+use grill::links::{self, FileRef, Linker};
+
+fn workspace() -> Result<tempfile::TempDir> {
+    let dir = tempfile::tempdir()?;
+    fs::create_dir_all(dir.path().join("src"))?;
+    fs::write(dir.path().join("src/main.rs"), "fn main() {}\n")?;
+    fs::write(dir.path().join("README.md"), "# Readme\n")?;
+    Ok(dir)
+}
+
+#[test]
+fn test_find_file_refs() -> Result<()> {
+    let dir = workspace()?;
+    let text = "Edit src/main.rs:12:5 and README.md, not version 1.2.3 or e.g. src/missing.rs";
+    
+    let found = links::find(text, dir.path());
+    assert_eq!(found.len(), 2);
+    assert_eq!(&text[found[0].0.clone()], "src/main.rs:12:5");
+    assert_eq!(found[0].1.text, "src/main.rs");
+    assert_eq!(found[0].1.line, Some(12));
+    assert_eq!(found[0].1.column, Some(5));
+    assert_eq!(found[1].1.text, "README.md");
+    assert_eq!(found[1].1.line, None);
+    
+    // Paths inside URLs are not files in the workspace
+    assert!(links::find("see https://example.com/src/main.rs", dir.path()).is_empty());
+    
+    Ok(())
+}
+
+#[test]
+fn test_linker() -> Result<()> {
+    let dir = workspace()?;
+    let mut linker = Linker::new(dir.path().to_path_buf(), true);
+    
+    let output = linker.process("\x1b[1msrc/main.rs\x1b[0m:3 changed\r\n");
+    assert!(output.starts_with("\x1b[1m\x1b]8;;file://"));
+    assert!(output.contains("src/main.rs\x1b\\src/main.rs\x1b]8;;\x1b\\\x1b[0m"));
+    
+    linker.process("Also README.md and src/main.rs:7");
+    let refs: Vec<String> = linker.refs().iter().map(FileRef::display).collect();
+    assert_eq!(refs, vec!["src/main.rs:7", "README.md", "src/main.rs"]);
+    
+    // Without hyperlinks, output is unchanged but files are still remembered
+    let mut plain = Linker::new(dir.path().to_path_buf(), false);
+    assert_eq!(plain.process("README.md"), "README.md");
+    assert_eq!(plain.refs().len(), 1);
+    
+    Ok(())
+}
+
+#[test]
+fn test_editor_command() {
+    let file_ref = FileRef {
+        text: "src/main.rs".to_string(),
+        path: PathBuf::from("/work/src/main.rs"),
+        line: Some(12),
+        column: None,
+    };
+    
+    assert_eq!(links::editor_command("vim", &file_ref), vec!["vim", "+12", "/work/src/main.rs"]);
+    assert_eq!(links::editor_command("code --wait", &file_ref), vec!["code", "--wait", "-g", "/work/src/main.rs:12:1"]);
+    assert_eq!(links::editor_command("/usr/bin/subl", &file_ref), vec!["/usr/bin/subl", "/work/src/main.rs:12"]);
+    
+    let no_line = FileRef { line: None, ..file_ref };
+    assert_eq!(links::editor_command("nano", &no_line), vec!["nano", "/work/src/main.rs"]);
+}
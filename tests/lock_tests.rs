@@ -0,0 +1,34 @@
+use anyhow::Result;
+
+use grill::environment::Environment;
+use grill::lock::{self, SessionLock};
+
+#[test]
+fn test_session_lock() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    let path = env.get_lock_path();
+    
+    let mut first = SessionLock::acquire(&path, "default")?.expect("lock is free");
+    let holder = lock::holder(&path).expect("holder is recorded");
+    assert_eq!(holder.pid, std::process::id());
+    assert_eq!(holder.task, "default");
+    
+    // A second session is turned away without disturbing the holder
+    assert!(SessionLock::acquire(&path, "other")?.is_none());
+    assert_eq!(lock::holder(&path).map(|holder| holder.task).as_deref(), Some("default"));
+    
+    first.set_task("api")?;
+    assert_eq!(lock::holder(&path).map(|holder| holder.task).as_deref(), Some("api"));
+    
+    // Dropping the lock releases it
+    drop(first);
+    assert!(SessionLock::acquire(&path, "other")?.is_some());
+    
+    // Task locks live next to the session sockets, one per task
+    assert!(env.get_task_lock_path("default")?.ends_with("sessions/default.lock"));
+    assert!(env.get_task_lock_path("../escape").is_err());
+    
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use grill::macros::{self, MacroState};
+
+#[test]
+fn test_macro_files() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let macros_dir = dir.path().join(macros::MACROS_DIR);
+    assert!(macros::list(&macros_dir)?.is_empty());
+    assert!(macros::load(&macros_dir, "missing").is_err());
+    
+    let steps = vec!["/clear".to_string(), "/context diff".to_string(), "fix the failing tests".to_string()];
+    macros::save(&macros_dir, "fix-tests", &steps)?;
+    macros::save(&macros_dir, "review", &["review this".to_string()])?;
+    assert_eq!(macros::load(&macros_dir, "fix-tests")?, steps);
+    assert_eq!(macros::list(&macros_dir)?, vec!["fix-tests", "review"]);
+    
+    // Names become file names, so paths are rejected
+    assert!(macros::validate_name("../escape").is_err());
+    assert!(macros::validate_name("").is_err());
+    assert!(macros::save(&macros_dir, "a b", &steps).is_err());
+    
+    Ok(())
+}
+
+#[test]
+fn test_macro_recording() {
+    let mut state = MacroState::default();
+    state.record("not recorded");
+    assert_eq!(state.recording(), None);
+    
+    state.start_recording("daily");
+    assert_eq!(state.recording(), Some("daily"));
+    state.record("/clear");
+    state.record("  ");
+    state.record("/macro play other");
+    state.record("run the tests");
+    
+    assert_eq!(state.stop_recording(), Some(("daily".to_string(), vec!["/clear".to_string(), "run the tests".to_string()])));
+    assert_eq!(state.stop_recording(), None);
+    
+    state.start_playing("daily");
+    assert_eq!(state.playing(), Some("daily"));
+    assert_eq!(state.stop_playing(), Some("daily".to_string()));
+    assert_eq!(state.playing(), None);
+}
+
+#[tokio::test]
+async fn test_wait_for_prompt() {
+    let (activity_tx, mut activity) = broadcast::channel::<bool>(10);
+    
+    // The CLI's prompt ends the wait at once
+    activity_tx.send(false).unwrap();
+    activity_tx.send(true).unwrap();
+    let started = std::time::Instant::now();
+    macros::wait_for_prompt(&mut activity, Duration::from_secs(30), Duration::from_secs(60)).await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    
+    // Without a prompt, output followed by quiet does
+    activity_tx.send(false).unwrap();
+    let started = std::time::Instant::now();
+    macros::wait_for_prompt(&mut activity, Duration::from_millis(50), Duration::from_secs(60)).await;
+    assert!(started.elapsed() < Duration::from_secs(5));
+    
+    // With no output at all, the timeout does
+    macros::wait_for_prompt(&mut activity, Duration::from_millis(10), Duration::from_millis(100)).await;
+}
@@ -0,0 +1,43 @@
+use grill::markdown::MarkdownRenderer;
+
+#[test]
+fn test_render_markdown() {
+    let mut renderer = MarkdownRenderer::default();
+    
+    assert_eq!(renderer.process("# Title\n"), "\x1b[1m\x1b[4mTitle\x1b[0m\n");
+    assert_eq!(renderer.process("## Usage\r\n"), "\x1b[1mUsage\x1b[0m\r\n");
+    assert_eq!(renderer.process("Use **cargo** and `grill`\n"), "Use \x1b[1mcargo\x1b[0m and \x1b[36mgrill\x1b[0m\n");
+    assert_eq!(renderer.process("- an *item*\n"), "\u{2022} an \x1b[3mitem\x1b[0m\n");
+    
+    // Plain text, snake_case names and lone asterisks pass through unchanged
+    assert_eq!(renderer.process("call my_function_name with 2 * 3\n"), "call my_function_name with 2 * 3\n");
+    
+    // Lines the CLI styled itself are left alone
+    assert_eq!(renderer.process("\x1b[1m# Styled\x1b[0m\n"), "\x1b[1m# Styled\x1b[0m\n");
+}
+
+#[test]
+fn test_render_code_blocks() {
+    let mut renderer = MarkdownRenderer::default();
+    
+    assert!(renderer.process("```rust\n").contains("rust"));
+    assert_eq!(
+        renderer.process("let x = \"# not a heading\"; // note\n"),
+        "\x1b[35mlet\x1b[0m x = \x1b[32m\"# not a heading\"\x1b[0m; \x1b[2m// note\x1b[0m\n"
+    );
+    // Markdown isn't rendered inside code
+    assert_eq!(renderer.process("**kept**\n"), "**kept**\n");
+    renderer.process("```\n");
+    assert_eq!(renderer.process("**bold**\n"), "\x1b[1mbold\x1b[0m\n");
+}
+
+#[test]
+fn test_render_streamed_lines() {
+    let mut renderer = MarkdownRenderer::default();
+    
+    // Partial lines are shown as they arrive, then redrawn once complete
+    assert_eq!(renderer.process("# Ti"), "# Ti");
+    assert_eq!(renderer.process("tle\nmore "), "\r\x1b[2K\x1b[1m\x1b[4mTitle\x1b[0m\nmore ");
+    // A line rendering doesn't change only needs its remainder
+    assert_eq!(renderer.process("text\n"), "text\n");
+}
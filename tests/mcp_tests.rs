@@ -0,0 +1,89 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use grill::environment::Environment;
+use grill::mcp::McpServer;
+use grill::task::Task;
+
+/// Run a session of requests through the server and parse the responses
+fn exchange(server: &McpServer, requests: &[Value]) -> Result<Vec<Value>> {
+    let input: String = requests.iter().map(|request| format!("{}\n", request)).collect();
+    let mut output = Vec::new();
+    server.serve(input.as_bytes(), &mut output)?;
+    Ok(String::from_utf8(output)?
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect())
+}
+
+/// Get the text of a tool call's result
+fn tool_text(response: &Value) -> &str {
+    response["result"]["content"][0]["text"].as_str().unwrap()
+}
+
+#[test]
+fn test_mcp_handshake() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    let server = McpServer::new(env);
+    
+    let responses = exchange(&server, &[
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+        // Notifications get no response
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+        json!({ "jsonrpc": "2.0", "id": 3, "method": "resources/list" }),
+    ])?;
+    
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["result"]["serverInfo"]["name"], "grill");
+    let tools: Vec<&str> = responses[1]["result"]["tools"].as_array().unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap())
+        .collect();
+    assert!(tools.contains(&"list_tasks"));
+    assert!(tools.contains(&"write_state"));
+    assert_eq!(responses[2]["id"], 3);
+    assert_eq!(responses[2]["error"]["code"], -32601);
+    
+    Ok(())
+}
+
+#[test]
+fn test_mcp_tools() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    let server = McpServer::new(Environment::new(temp_dir.path().to_path_buf()));
+    let call = |id: u64, name: &str, arguments: Value| {
+        json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": name, "arguments": arguments } })
+    };
+    
+    let responses = exchange(&server, &[
+        call(1, "create_task", json!({ "name": "api", "instructions": "Build the API\n" })),
+        call(2, "write_state", json!({ "task": "api", "state": "# Task State\n\nRoutes done\n" })),
+        call(3, "append_state", json!({ "task": "api", "text": "Auth next" })),
+        call(4, "read_state", json!({ "task": "api" })),
+        call(5, "add_note", json!({ "task": "api", "text": "Check rate limits" })),
+        call(6, "list_tasks", json!({})),
+        call(7, "create_task", json!({ "name": "api" })),
+        call(8, "read_state", json!({})),
+    ])?;
+    
+    assert_eq!(tool_text(&responses[0]), "Created task 'api'");
+    assert_eq!(Task::load(&env, "api")?.instructions(), Some("Build the API\n"));
+    assert_eq!(tool_text(&responses[3]), "# Task State\n\nRoutes done\n\nAuth next\n");
+    assert!(env.get_notes("api")?.unwrap().contains("Check rate limits"));
+    assert!(tool_text(&responses[5]).contains("api: todo"));
+    assert!(tool_text(&responses[5]).contains("default: todo (current)"));
+    
+    // Failures are reported to the model as tool errors
+    assert_eq!(responses[6]["result"]["isError"], true);
+    assert!(tool_text(&responses[6]).contains("already exists"));
+    
+    // Without a task, the current task is used
+    assert_eq!(tool_text(&responses[7]), "The task has no state yet.");
+    
+    Ok(())
+}
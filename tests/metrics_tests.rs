@@ -0,0 +1,67 @@
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use grill::events::{EventKind, EventLog, Stats};
+use grill::metrics::{self, ResponseTime, ResponseTimer};
+
+#[test]
+fn test_response_timer() {
+    let start = Instant::now();
+    let at = |ms: u64| start + Duration::from_millis(ms);
+    let mut timer = ResponseTimer::default();
+    
+    // Output before any message isn't an answer
+    assert_eq!(timer.output(at(0), "> ", true), None);
+    
+    timer.input(at(100));
+    // The echoed Enter isn't the start of the answer
+    assert_eq!(timer.output(at(110), "\r\n", false), None);
+    assert_eq!(timer.output(at(600), "Thinking", false), None);
+    assert_eq!(timer.output(at(900), "Done", false), None);
+    assert_eq!(timer.output(at(2600), "\n> ", true), Some(ResponseTime {
+        first_byte: Duration::from_millis(500),
+        to_prompt: Duration::from_millis(2000),
+    }));
+    
+    // Each message is timed once
+    assert_eq!(timer.output(at(2700), "> ", true), None);
+    
+    // A prompt straight away has no wait after the first byte
+    timer.input(at(3000));
+    assert_eq!(timer.output(at(3200), "> ", true), Some(ResponseTime {
+        first_byte: Duration::from_millis(200),
+        to_prompt: Duration::ZERO,
+    }));
+}
+
+#[test]
+fn test_prometheus_textfile() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let events_path = dir.path().join("events.jsonl");
+    let log = EventLog::new(events_path.clone());
+    log.record("api", EventKind::SessionStart);
+    log.record("api", EventKind::Message);
+    log.record("api", EventKind::Response { first_byte_ms: 250, prompt_ms: 1500 });
+    log.record("api", EventKind::Traffic { bytes_in: 12, bytes_out: 3400 });
+    log.record("api", EventKind::SessionEnd);
+    
+    let textfile = dir.path().join("grill.prom");
+    metrics::write_textfile(&textfile, &events_path, "/work/\"app\"")?;
+    let text = std::fs::read_to_string(&textfile)?;
+    
+    let label = "workspace=\"/work/\\\"app\\\"\"";
+    assert!(text.contains("# TYPE grill_sessions_total counter\n"), "{}", text);
+    assert!(text.contains(&format!("grill_sessions_total{{{}}} 1\n", label)), "{}", text);
+    assert!(text.contains(&format!("grill_output_bytes_total{{{}}} 3400\n", label)), "{}", text);
+    assert!(text.contains(&format!("grill_first_byte_seconds{{{},quantile=\"0.5\"}} 0.25\n", label)), "{}", text);
+    assert!(text.contains(&format!("grill_answer_seconds_count{{{}}} 1\n", label)), "{}", text);
+    assert!(text.contains(&format!("grill_task_messages_total{{{},task=\"api\"}} 1\n", label)), "{}", text);
+    assert!(!dir.path().join("grill.prom.tmp").exists());
+    
+    // Without answers the summaries have no quantiles
+    let empty = metrics::prometheus(&Stats::default(), "w");
+    assert!(!empty.contains("quantile"), "{}", empty);
+    assert!(empty.contains("grill_answer_seconds_count{workspace=\"w\"} 0\n"), "{}", empty);
+    
+    Ok(())
+}
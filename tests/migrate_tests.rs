@@ -0,0 +1,60 @@
+use anyhow::Result;
+use std::fs;
+
+use grill::config::Config;
+use grill::environment::Environment;
+use grill::migrate;
+
+#[test]
+fn test_migrate_legacy_workspace() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let grill_dir = temp_dir.path().join(".grill");
+    fs::create_dir_all(grill_dir.join("tasks"))?;
+    fs::write(grill_dir.join("config.toml"), "default_cli = \"cat\"\n")?;
+    fs::write(grill_dir.join("tasks").join("web.toml"), "cli = \"cat -u\"\n")?;
+    fs::write(grill_dir.join("current_task"), "web")?;
+    
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    let upgrade = migrate::migrate(&env)?.expect("legacy workspace is upgraded");
+    assert_eq!((upgrade.from, upgrade.to), (0, migrate::CURRENT_VERSION));
+    
+    // Old files are backed up
+    assert!(upgrade.backup.join("config.toml").exists());
+    assert!(upgrade.backup.join("tasks").join("web.toml").exists());
+    
+    // Task files become task directories and settings survive
+    assert!(!grill_dir.join("tasks").join("web.toml").exists());
+    assert_eq!(env.get_task_config("web")?.get_cli(), Some("cat -u"));
+    let config = Config::load(&env.get_config_path())?;
+    assert_eq!(config.version, migrate::CURRENT_VERSION);
+    assert_eq!(config.get_default_cli(), "cat");
+    
+    // Nothing to do the second time
+    assert!(migrate::migrate(&env)?.is_none());
+    
+    Ok(())
+}
+
+#[test]
+fn test_migrate_versions() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    
+    // New workspaces are current
+    env.init()?;
+    assert!(migrate::migrate(&env)?.is_none());
+    
+    // YAML configs keep their format
+    fs::remove_file(env.get_config_path())?;
+    fs::write(env.get_grill_dir().join("config.yaml"), "default_cli: cat\n")?;
+    migrate::migrate(&env)?;
+    let yaml = fs::read_to_string(env.get_grill_dir().join("config.yaml"))?;
+    assert!(yaml.contains("version: 1"));
+    assert!(yaml.contains("default_cli: cat"));
+    
+    // Workspaces from a newer grill are refused
+    fs::write(env.get_grill_dir().join("config.yaml"), "version: 99\n")?;
+    assert!(migrate::migrate(&env).unwrap_err().to_string().contains("newer version of grill"));
+    
+    Ok(())
+}
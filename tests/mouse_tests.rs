@@ -0,0 +1,29 @@
+use grill::mouse::MouseState;
+
+#[test]
+fn test_mouse_reporting_tracking() {
+    let mut mouse = MouseState::default();
+    assert!(!mouse.is_reporting());
+    
+    mouse.observe("hello \x1b[1mbold\x1b[0m");
+    assert!(!mouse.is_reporting());
+    
+    // Split across chunks, with SGR encoding in the same sequence
+    mouse.observe("\x1b[?10");
+    mouse.observe("00;1006h");
+    assert!(mouse.is_reporting());
+    assert_eq!(mouse.wheel(true, 4, 9), "\x1b[<64;5;10M");
+    
+    mouse.observe("\x1b[?1000l");
+    assert!(!mouse.is_reporting());
+}
+
+#[test]
+fn test_legacy_wheel_encoding() {
+    let mut mouse = MouseState::default();
+    mouse.observe("\x1b[?1002h");
+    
+    assert!(mouse.is_reporting());
+    assert_eq!(mouse.wheel(false, 0, 0), "\x1b[Ma!!");
+    assert_eq!(mouse.wheel(true, 500, 2), "\x1b[M`~#");
+}
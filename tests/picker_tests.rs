@@ -0,0 +1,59 @@
+use grill::picker::{self, Picker, PickerItem};
+
+fn item(name: &str, description: Option<&str>) -> PickerItem {
+    PickerItem {
+        name: name.to_string(),
+        description: description.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_fuzzy_score() {
+    assert!(picker::score("api", "api-refactor").is_some());
+    assert!(picker::score("arf", "api-refactor").is_some());
+    assert!(picker::score("API", "api-refactor").is_some());
+    assert_eq!(picker::score("xyz", "api-refactor"), None);
+    assert_eq!(picker::score("rotp", "api-refactor"), None);
+    
+    // Consecutive characters beat scattered ones
+    assert!(picker::score("web", "web-redesign") > picker::score("web", "w-e-b"));
+}
+
+#[test]
+fn test_filter_tasks() {
+    let items = vec![
+        item("default", None),
+        item("web-redesign", Some("New landing page")),
+        item("api-refactor", Some("Split the monolith")),
+    ];
+    
+    let names = |query: &str| -> Vec<String> {
+        picker::filter(query, &items).iter().map(|item| item.name.clone()).collect()
+    };
+    assert_eq!(names(""), vec!["default", "web-redesign", "api-refactor"]);
+    assert_eq!(names("api"), vec!["api-refactor"]);
+    // Descriptions are searched too
+    assert_eq!(names("landing"), vec!["web-redesign"]);
+    assert!(names("zzz").is_empty());
+}
+
+#[test]
+fn test_picker_selection() {
+    let mut picker = Picker::new(vec![item("alpha", None), item("beta", None), item("gamma", None)]);
+    assert_eq!(picker.selected().as_deref(), Some("alpha"));
+    
+    picker.down();
+    picker.down();
+    picker.down();
+    assert_eq!(picker.selected().as_deref(), Some("gamma"));
+    picker.up();
+    assert_eq!(picker.selected().as_deref(), Some("beta"));
+    
+    // Typing resets the highlight to the best match
+    picker.push('g');
+    assert_eq!(picker.selected().as_deref(), Some("gamma"));
+    picker.push('x');
+    assert_eq!(picker.selected(), None);
+    picker.pop();
+    assert_eq!(picker.matches().len(), 1);
+}
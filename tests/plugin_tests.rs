@@ -0,0 +1,126 @@
+use anyhow::Result;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use grill::io::Command;
+use grill::plugin::{self, PluginCliHandler, PluginInfo};
+use tokio::sync::mpsc;
+
+/// Write a minimal plugin script that answers every hook with a canned result
+fn write_plugin(dir: &Path, name: &str) -> Result<PluginInfo> {
+    let path = dir.join(format!("grill-handler-{}", name));
+    let script = r#"#!/bin/sh
+while IFS= read -r line; do
+  case "$line" in
+    *can_handle*) echo '{"result": true}' ;;
+    *intercept_output*) echo '{"result": "from plugin"}' ;;
+    *help_text*) echo '{"result": "plugin help"}' ;;
+    *native_commands*) echo '{"result": ["/add", "drop"]}' ;;
+    *process_command*)
+      escaped=$(printf '%s' "$line" | sed 's/\\/\\\\/g; s/"/\\"/g')
+      printf '{"result": {"output": ["%s"], "handled": true}}\n' "$escaped" ;;
+    *) echo '{"result": null}' ;;
+  esac
+done
+"#;
+    fs::write(&path, script)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    
+    Ok(PluginInfo {
+        name: name.to_string(),
+        path,
+    })
+}
+
+#[test]
+fn test_plugin_hooks() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let info = write_plugin(temp_dir.path(), "echo")?;
+    
+    let handler = PluginCliHandler::spawn(&info, "echo-cli".to_string())?;
+    
+    assert_eq!(handler.name(), "echo");
+    assert_eq!(handler.get_command(), "echo-cli");
+    assert!(handler.can_handle_command("echo-cli"));
+    assert_eq!(handler.get_help_text(), "plugin help");
+    assert_eq!(handler.intercept_output("raw".to_string())?, Some("from plugin".to_string()));
+    
+    // A null result drops the input
+    assert_eq!(handler.intercept_input("x".to_string())?, None);
+    
+    // Without its own cancel sequence, the CLI gets Ctrl+C
+    assert_eq!(handler.cancel_sequence(), "\x03");
+    
+    assert_eq!(handler.native_commands(), Some(vec!["add".to_string(), "drop".to_string()]));
+    
+    Ok(())
+}
+
+#[test]
+fn test_plugin_discovery() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    write_plugin(temp_dir.path(), "demo")?;
+    
+    // Non-executable files with the prefix are ignored
+    fs::write(temp_dir.path().join("grill-handler-broken"), "")?;
+    
+    std::env::set_var("PATH", temp_dir.path());
+    let plugins = plugin::discover_plugins();
+    
+    assert_eq!(plugins.len(), 1);
+    assert_eq!(plugins[0].name, "demo");
+    
+    Ok(())
+}
+
+#[test]
+fn test_plugin_command_protocol() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let info = write_plugin(temp_dir.path(), "echo")?;
+    let handler = PluginCliHandler::spawn(&info, "echo-cli".to_string())?;
+    
+    // The plugin sees the command by name, with its arguments as typed
+    let (output_tx, mut output_rx) = mpsc::channel(10);
+    assert!(handler.process_command(Command::TaskDone { task: Some("api".to_string()), archive: true }, &output_tx, "default")?);
+    let request: serde_json::Value = serde_json::from_str(&output_rx.try_recv()?)?;
+    assert_eq!(request["command"], serde_json::json!({"name": "task_done", "args": ["api", "--archive"]}));
+    assert_eq!(request["task"], "default");
+    
+    Ok(())
+}
+
+#[test]
+fn test_plugin_that_stops_answering() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let path = temp_dir.path().join("grill-handler-stuck");
+    let script = r#"#!/bin/sh
+while IFS= read -r line; do
+  case "$line" in
+    # Never answers
+    *intercept_input*) ;;
+    *) echo '{"result": null}' ;;
+  esac
+done
+"#;
+    fs::write(&path, script)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    let handler = PluginCliHandler::spawn(&PluginInfo { name: "stuck".to_string(), path }, "stuck-cli".to_string())?;
+    assert!(handler.is_alive());
+    
+    // A hook that isn't answered in time gives up on the plugin and lets the input through
+    let started = std::time::Instant::now();
+    assert_eq!(handler.intercept_input("x".to_string())?, Some("x".to_string()));
+    assert!(started.elapsed() >= plugin::PLUGIN_TIMEOUT);
+    assert!(!handler.is_alive());
+    
+    // From then on grill carries on without it, at once
+    let started = std::time::Instant::now();
+    assert_eq!(handler.intercept_output("y".to_string())?, Some("y".to_string()));
+    assert!(!handler.is_prompt("> "));
+    assert_eq!(handler.auth_prompt("Open this URL to sign in: https://example.com/device"), grill::cli_handler::find_auth_prompt("Open this URL to sign in: https://example.com/device"));
+    assert_eq!(handler.cancel_sequence(), "\x03");
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    
+    Ok(())
+}
@@ -2,7 +2,8 @@ use anyhow::Result;
 use tokio::sync::mpsc;
 use std::time::Duration;
 use std::thread;
-use grill::process::ProcessManager;
+use grill::config::{CliProfile, EnvironmentConfig, InputConfig, OverflowPolicy, PerformanceConfig, ResourceLimits};
+use grill::process::{self, ProcessManager};
 use grill::cli_handler::CliHandlerFactory;
 
 #[test]
@@ -45,13 +46,15 @@ fn test_process_cat() -> Result<()> {
     // Send some input to the process
     input_tx.blocking_send("Hello, world!".to_string())?;
     
-    // Wait for output
-    thread::sleep(Duration::from_millis(500));
-    
-    // Check that we got the expected output
+    // Wait for output; the reader thread gives the process 500ms to start
+    // before its first read, so a fixed wait of that long races it
+    let deadline = std::time::Instant::now() + Duration::from_secs(5);
     let mut output = String::new();
-    while let Ok(Some(line)) = output_rx.try_recv().map(Some) {
-        output.push_str(&line);
+    while output.is_empty() && std::time::Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(50));
+        while let Ok(line) = output_rx.try_recv() {
+            output.push_str(&line);
+        }
     }
     
     // The test might be flaky due to timing issues, so let's just check if we got any output
@@ -103,3 +106,257 @@ fn test_process_drop() -> Result<()> {
     // If we got here, the test passed
     Ok(())
 }
+
+/// Run `seq 1 20000` without ever reading the display, and collect what the tap saw
+fn run_chatty_process(policy: OverflowPolicy) -> Result<(String, ProcessManager)> {
+    let (output_tx, _output_rx) = mpsc::channel(1);
+    let (tap_tx, mut tap_rx) = mpsc::unbounded_channel();
+    
+    let mut process = ProcessManager::new("seq 1 20000");
+    process.set_performance(PerformanceConfig {
+        output_channel_capacity: 1,
+        display_overflow: policy,
+        max_pending_output: 1024,
+        ..PerformanceConfig::default()
+    });
+    process.set_output_tap(tap_tx);
+    let cli_handler = CliHandlerFactory::create_handler("seq".to_string());
+    let _input_tx = process.start(output_tx, cli_handler)?;
+    
+    // The reader only finishes if the full display channel doesn't stall it
+    let mut output = String::new();
+    let deadline = std::time::Instant::now() + Duration::from_secs(20);
+    while !output.contains("\n20000") && std::time::Instant::now() < deadline {
+        match tap_rx.try_recv() {
+            Ok(chunk) => output.push_str(&chunk),
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    
+    Ok((output, process))
+}
+
+#[test]
+fn test_display_overflow_drop() -> Result<()> {
+    let (output, process) = run_chatty_process(OverflowPolicy::Drop)?;
+    
+    assert!(output.contains("\n20000"), "The tap should see all output");
+    assert!(output.contains("\n10000\r\n10001\r\n"));
+    let stats = process.output_stats();
+    assert!(stats.dropped_chunks() > 0);
+    assert!(stats.dropped_bytes() > 0);
+    
+    Ok(())
+}
+
+#[test]
+fn test_display_overflow_coalesce() -> Result<()> {
+    let (output, process) = run_chatty_process(OverflowPolicy::Coalesce)?;
+    
+    assert!(output.contains("\n20000"), "The tap should see all output");
+    let stats = process.output_stats();
+    assert!(stats.coalesced_chunks() > 0);
+    assert!(stats.dropped_bytes() > 0, "Output beyond max_pending_output should be trimmed");
+    
+    Ok(())
+}
+
+#[test]
+fn test_input_chunks() {
+    let text = "a".repeat(10);
+    let chunks = process::input_chunks(text.as_bytes(), 4);
+    assert_eq!(chunks, [b"aaaa".as_slice(), b"aaaa", b"aa"]);
+    
+    // Short input and a chunk size of 0 keep the input whole
+    assert_eq!(process::input_chunks(b"q", 4), [b"q".as_slice()]);
+    assert_eq!(process::input_chunks(text.as_bytes(), 0).len(), 1);
+    
+    // Characters and escape sequences aren't split
+    let chunks = process::input_chunks("aaé".as_bytes(), 3);
+    assert_eq!(chunks, ["aa".as_bytes(), "é".as_bytes()]);
+    let paste = "ab\x1b[200~text\x1b[201~";
+    let chunks = process::input_chunks(paste.as_bytes(), 8);
+    assert_eq!(chunks, [b"ab\x1b[200~".as_slice(), b"text", b"\x1b[201~"]);
+}
+
+/// A writer that takes a few bytes at a time, failing along the way
+struct FlakyWriter {
+    written: Vec<u8>,
+    errors: Vec<std::io::ErrorKind>,
+}
+
+impl std::io::Write for FlakyWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if let Some(kind) = self.errors.pop() {
+            return Err(kind.into());
+        }
+        let n = buf.len().min(3);
+        self.written.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+    
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.errors.pop() {
+            Some(kind) => Err(kind.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_write_fully() {
+    use std::io::ErrorKind;
+    
+    // Short writes are finished, and interrupted or blocked writes retried
+    let mut writer = FlakyWriter { written: Vec::new(), errors: vec![ErrorKind::WouldBlock, ErrorKind::Interrupted] };
+    let mut written = 0;
+    process::write_fully(&mut writer, b"hello world", &mut written).unwrap();
+    assert_eq!(writer.written, b"hello world");
+    assert_eq!(written, 11);
+    writer.errors.push(ErrorKind::Interrupted);
+    process::flush_fully(&mut writer).unwrap();
+    
+    // Other errors are reported with how much got through
+    let mut writer = FlakyWriter { written: Vec::new(), errors: Vec::new() };
+    let mut written = 0;
+    process::write_fully(&mut writer, b"abcdef", &mut written).unwrap();
+    writer.errors.push(ErrorKind::BrokenPipe);
+    let error = process::write_fully(&mut writer, b"ghi", &mut written).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::BrokenPipe);
+    assert_eq!(written, 6);
+    
+    let error = process::WriteError { written, total: 9, error };
+    assert!(error.to_string().starts_with("Only 6 of 9 bytes of input reached the CLI"), "{}", error);
+}
+
+/// Long input arrives whole when it is written in paced chunks
+#[test]
+fn test_process_paced_input() -> Result<()> {
+    let (output_tx, mut output_rx) = mpsc::channel(1000);
+    let mut process = ProcessManager::new("cat");
+    process.set_input(InputConfig { chunk_bytes: 100, chunk_delay_ms: 1, ..Default::default() });
+    let input_tx = process.start(output_tx, CliHandlerFactory::create_handler("cat".to_string()))?;
+    
+    let lines: Vec<String> = (0..40).map(|i| format!("line {:02} {}", i, "x".repeat(40))).collect();
+    input_tx.blocking_send(format!("{}\n", lines.join("\n")))?;
+    
+    let started = std::time::Instant::now();
+    let mut output = String::new();
+    while !output.contains("line 39") && started.elapsed() < Duration::from_secs(10) {
+        match output_rx.try_recv() {
+            Ok(chunk) => output.push_str(&chunk),
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    for line in &lines {
+        assert!(output.contains(line.as_str()), "{} missing from {:?}", line, output);
+    }
+    
+    // Every byte written is counted, and the echo at least as many read
+    let traffic = process.traffic();
+    let total = lines.join("\n").len() as u64 + 1;
+    assert_eq!(traffic.bytes_in(), total);
+    assert!(traffic.bytes_out() >= total, "{} bytes read", traffic.bytes_out());
+    
+    Ok(())
+}
+
+#[test]
+fn test_limited_command() {
+    let limits = ResourceLimits { max_memory_mb: Some(2), max_cpu_seconds: None, max_open_files: Some(64) };
+    let (program, args) = process::limited_command("q", &["chat".to_string()], &limits).unwrap();
+    assert_eq!(program, "sh");
+    assert_eq!(args, ["-c", "ulimit -v 2048 && ulimit -n 64 && exec \"$@\"", "sh", "q", "chat"]);
+    
+    // Without limits the command runs as it is
+    assert!(process::limited_command("q", &[], &ResourceLimits::default()).is_none());
+}
+
+/// With shell_wrap the CLI runs as a command line in the user's login shell
+#[cfg(unix)]
+#[test]
+fn test_process_shell_wrap() -> Result<()> {
+    std::env::set_var("SHELL", "/bin/sh");
+    let (program, args) = process::login_shell_command("q", &["chat".to_string(), "my prof".to_string()]).unwrap();
+    assert_eq!(program, "/bin/sh");
+    assert_eq!(args, ["-lc", "q chat 'my prof'"]);
+    
+    let (output_tx, mut output_rx) = mpsc::channel(100);
+    let mut process = ProcessManager::new("echo 'wrapped  cli'");
+    process.set_shell_wrap(true);
+    let _input_tx = process.start(output_tx, CliHandlerFactory::create_handler("echo".to_string()))?;
+    
+    let mut output = String::new();
+    while let Some(chunk) = output_rx.blocking_recv() {
+        output.push_str(&chunk);
+        if output.contains('\n') {
+            break;
+        }
+    }
+    assert!(output.contains("wrapped  cli"), "{:?}", output);
+    
+    Ok(())
+}
+
+/// The CLI starts with the limits applied
+#[cfg(unix)]
+#[test]
+fn test_process_limits() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let dir = tempfile::tempdir()?;
+    let script = dir.path().join("cli.sh");
+    std::fs::write(&script, "#!/bin/sh\necho \"files=$(ulimit -n)\"\nsleep 1\n")?;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+    
+    let (output_tx, mut output_rx) = mpsc::channel(100);
+    let mut process = ProcessManager::new(script.to_str().unwrap());
+    process.set_limits(ResourceLimits { max_open_files: Some(77), ..Default::default() });
+    let _input_tx = process.start(output_tx, CliHandlerFactory::create_handler("cli.sh".to_string()))?;
+    
+    let mut output = String::new();
+    while let Some(chunk) = output_rx.blocking_recv() {
+        output.push_str(&chunk);
+        if output.contains('\n') {
+            break;
+        }
+    }
+    assert!(output.contains("files=77"), "{:?}", output);
+    
+    Ok(())
+}
+
+/// Denied variables are kept from the CLI, unless its profile sets them
+#[cfg(unix)]
+#[test]
+fn test_process_inherited_env() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let dir = tempfile::tempdir()?;
+    let script = dir.path().join("cli.sh");
+    std::fs::write(&script, "#!/bin/sh\necho \"secret=$GRILL_TEST_SECRET kept=$GRILL_TEST_KEPT set=$GRILL_TEST_SET.\"\nsleep 1\n")?;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+    std::env::set_var("GRILL_TEST_SECRET", "hunter2");
+    std::env::set_var("GRILL_TEST_KEPT", "yes");
+    
+    let (output_tx, mut output_rx) = mpsc::channel(100);
+    let mut cli = CliProfile::from_command(script.to_str().unwrap());
+    cli.env.insert("GRILL_TEST_SET".to_string(), "profile".to_string());
+    let mut process = ProcessManager::for_cli(&cli);
+    process.set_inherited_env(EnvironmentConfig {
+        allow: Vec::new(),
+        deny: vec!["GRILL_TEST_SE*".to_string()],
+    });
+    let _input_tx = process.start(output_tx, CliHandlerFactory::create_handler("cli.sh".to_string()))?;
+    
+    let mut output = String::new();
+    while let Some(chunk) = output_rx.blocking_recv() {
+        output.push_str(&chunk);
+        if output.contains('\n') {
+            break;
+        }
+    }
+    assert!(output.contains("secret= kept=yes set=profile."), "{:?}", output);
+    
+    Ok(())
+}
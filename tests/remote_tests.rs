@@ -0,0 +1,58 @@
+use std::process::Command;
+
+use grill::config::RemoteConfig;
+use grill::remote;
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn test_is_ssh() {
+    assert!(remote::is_ssh("ssh devbox -t q chat"));
+    assert!(remote::is_ssh("/usr/bin/ssh devbox"));
+    assert!(!remote::is_ssh("q chat"));
+    assert!(!remote::is_ssh("sshpass devbox"));
+    assert!(!remote::is_ssh(""));
+}
+
+#[test]
+fn test_ssh_args() {
+    let config = RemoteConfig::default();
+    assert_eq!(
+        remote::ssh_args(&args(&["devbox", "q", "chat"]), &config),
+        args(&["-t", "-o", "ServerAliveInterval=15", "-o", "ServerAliveCountMax=3", "devbox", "q", "chat"]),
+    );
+    
+    // Options the command already has are kept as they are
+    let given = args(&["-tt", "-o", "ServerAliveInterval=60", "devbox", "q", "chat"]);
+    assert_eq!(remote::ssh_args(&given, &config), given);
+    
+    let no_keepalive = RemoteConfig { keepalive_seconds: 0, ..RemoteConfig::default() };
+    assert_eq!(remote::ssh_args(&args(&["devbox"]), &no_keepalive), args(&["-t", "devbox"]));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_reconnecting_command() {
+    let config = RemoteConfig { reconnect_attempts: 1, ..RemoteConfig::default() };
+    let run = |script: &str| {
+        let (program, wrapped) = remote::reconnecting_command("sh", &args(&["-c", script]), &config).unwrap();
+        Command::new(program).args(wrapped).output().unwrap()
+    };
+    
+    // Exits of the remote CLI end the loop
+    let output = run("exit 3");
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output.stdout.is_empty());
+    
+    // A lost connection is retried, then given up on
+    let output = run("echo connected; exit 255");
+    assert_eq!(output.status.code(), Some(remote::SSH_ERROR_STATUS));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.matches("connected").count(), 2);
+    assert!(stdout.contains("[grill] Connection lost; reconnecting (1 of 1)"));
+    
+    let off = RemoteConfig { reconnect_attempts: 0, ..RemoteConfig::default() };
+    assert!(remote::reconnecting_command("ssh", &args(&["devbox"]), &off).is_none());
+}
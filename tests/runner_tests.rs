@@ -0,0 +1,90 @@
+use anyhow::Result;
+use std::time::Duration;
+
+use grill::ansi::strip_ansi;
+use grill::runner::{clean_response, Runner};
+
+#[test]
+fn test_strip_ansi() {
+    assert_eq!(strip_ansi("\x1b[1;32mgreen\x1b[0m text"), "green text");
+    assert_eq!(strip_ansi("\x1b]0;title\x07after"), "after");
+    assert_eq!(strip_ansi("line\r\n"), "line\n");
+}
+
+#[test]
+fn test_clean_response() {
+    let output = "> what is 2+2\r\n\x1b[32m4\x1b[0m\r\n\r\n> ";
+    assert_eq!(clean_response(output, "what is 2+2"), "4");
+}
+
+#[tokio::test]
+async fn test_runner_round_trip() -> Result<()> {
+    let mut runner = Runner::start("cat")?;
+    
+    runner.wait_until_ready(Duration::from_secs(10)).await?;
+    let response = runner.send("ping", Duration::from_secs(10)).await?;
+    
+    // cat repeats the line after the PTY echo
+    assert_eq!(response, "ping");
+    
+    // Exchanges record the outcome for --output json
+    let exchange = runner.exchange("default", "pong", Duration::from_secs(10)).await;
+    assert!(exchange.is_ok());
+    let record = serde_json::to_value(&exchange)?;
+    assert_eq!(record["response"], "pong");
+    assert_eq!(record["status"], "ok");
+    
+    runner.stop()?;
+    Ok(())
+}
+
+#[test]
+fn test_missing_cli_error() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = grill::environment::Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    // A task's own CLI points at the task's config
+    let config_path = env.get_task_dir("default")?.join("config.toml");
+    std::fs::write(&config_path, "cli = \"grill-no-such-cli --flag\"\n")?;
+    let message = Runner::start_for_task(&env, "default").err().expect("missing CLI").to_string();
+    assert_eq!(
+        message,
+        format!("Command 'grill-no-such-cli' not found \u{2014} install 'grill-no-such-cli' or set cli in {}", config_path.display())
+    );
+    
+    // Otherwise the default comes from the workspace config
+    std::fs::write(&config_path, "")?;
+    std::fs::write(env.get_config_path(), "default_cli = \"q chat\"\n")?;
+    if grill::clis::find_binary("q").is_none() {
+        let message = Runner::start_for_task(&env, "default").err().expect("missing CLI").to_string();
+        assert_eq!(
+            message,
+            format!("Command 'q' not found \u{2014} install Amazon Q CLI or set default_cli in {}", env.get_config_path().display())
+        );
+    }
+    
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_runner_cli_profile() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = grill::environment::Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    std::fs::write(env.get_config_path(), r#"
+[clis.echo-env]
+command = "sh"
+args = ["-c", "echo profile=$GRILL_PROFILE; cat"]
+env = { GRILL_PROFILE = "reviewer" }
+"#)?;
+    std::fs::write(env.get_task_dir("default")?.join("config.toml"), "cli = \"echo-env\"\n")?;
+    
+    // Arguments keep their spaces and the environment reaches the CLI
+    let mut runner = Runner::start_for_task(&env, "default")?;
+    let output = runner.wait_until_ready(Duration::from_secs(10)).await?;
+    assert!(strip_ansi(&output).contains("profile=reviewer"));
+    
+    runner.stop()?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::fs;
+
+use grill::config::SandboxEngine;
+use grill::environment::Environment;
+use grill::sandbox::Sandbox;
+
+#[test]
+fn test_sandbox_for_task() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    env.create_task("api")?;
+    
+    assert_eq!(Sandbox::for_task(&env, "api", &env.get_task_config("api")?)?, None);
+    
+    // An engine without an image can't run anything
+    let config_path = env.get_task_dir("api")?.join("config.toml");
+    let config = fs::read_to_string(&config_path)?;
+    fs::write(&config_path, format!("sandbox = \"podman\"\n{}", config))?;
+    assert!(Sandbox::for_task(&env, "api", &env.get_task_config("api")?).is_err());
+    
+    fs::write(&config_path, format!("sandbox = \"podman\"\nsandbox_image = \"agent:latest\"\nsandbox_args = [\"--network\", \"none\"]\n{}", config))?;
+    let sandbox = Sandbox::for_task(&env, "api", &env.get_task_config("api")?)?.unwrap();
+    assert_eq!(sandbox.engine, SandboxEngine::Podman);
+    assert_eq!(sandbox.image, "agent:latest");
+    assert_eq!(sandbox.name, format!("grill-api-{}", std::process::id()));
+    assert_eq!(sandbox.mount, env.get_root_dir());
+    
+    Ok(())
+}
+
+#[test]
+fn test_sandbox_command() {
+    let sandbox = Sandbox {
+        engine: SandboxEngine::Docker,
+        image: "agent".to_string(),
+        name: "grill-api-1".to_string(),
+        mount: "/work/project".into(),
+        args: vec!["--network".to_string(), "none".to_string()],
+    };
+    let env = HashMap::from([("AWS_PROFILE".to_string(), "dev".to_string())]);
+    
+    let (program, args) = sandbox.command("q", &["chat".to_string()], &env, None);
+    assert_eq!(program, "docker");
+    assert_eq!(args, [
+        "run", "--rm", "-it", "--name", "grill-api-1",
+        "-v", "/work/project:/work/project", "-w", "/work/project",
+        "-e", "TERM", "-e", "AWS_PROFILE",
+        "--network", "none", "agent", "q", "chat",
+    ]);
+    // Values stay out of the engine's arguments, where anyone could read them
+    assert!(!args.iter().any(|arg| arg.contains("dev")));
+    
+    // The CLI starts in the task's worktree, inside the mounted workspace
+    let worktree = "/work/project/.grill/worktrees/api".into();
+    let (_, args) = sandbox.command("q", &[], &HashMap::new(), Some(&worktree));
+    assert_eq!(args[8], "/work/project/.grill/worktrees/api");
+}
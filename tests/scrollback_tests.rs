@@ -0,0 +1,67 @@
+use grill::clipboard;
+use grill::scrollback::{CopyMode, Scrollback};
+
+#[test]
+fn test_scrollback_strips_escapes() {
+    let mut scrollback = Scrollback::default();
+    scrollback.push("\x1b[1;32mgreen\x1b[0m text\r\n\x1b]0;ti");
+    scrollback.push("tle\x07second\x08D line\n");
+    
+    assert_eq!(scrollback.lines(), vec!["green text", "seconD line"]);
+}
+
+#[test]
+fn test_scrollback_limit() {
+    let mut scrollback = Scrollback::new(3);
+    scrollback.push("one\ntwo\nthree\nfour\nfive");
+    
+    assert_eq!(scrollback.lines(), vec!["three", "four", "five"]);
+}
+
+#[test]
+fn test_copy_mode_navigation() {
+    let lines: Vec<String> = (1..=50).map(|i| format!("line {}", i)).collect();
+    let mut copy_mode = CopyMode::new(lines, 10);
+    assert_eq!(copy_mode.cursor(), 49);
+    
+    copy_mode.page(-1);
+    assert_eq!(copy_mode.cursor(), 39);
+    copy_mode.top();
+    assert_eq!(copy_mode.cursor(), 0);
+    copy_mode.move_by(-5);
+    assert_eq!(copy_mode.cursor(), 0);
+    copy_mode.bottom();
+    assert_eq!(copy_mode.cursor(), 49);
+}
+
+#[test]
+fn test_copy_mode_search_and_select() {
+    let lines = vec!["error: one".to_string(), "ok".to_string(), "Error: two".to_string(), "done".to_string()];
+    let mut copy_mode = CopyMode::new(lines, 10);
+    
+    copy_mode.start_search();
+    for c in "error".chars() {
+        copy_mode.push_search(c);
+    }
+    copy_mode.submit_search();
+    assert_eq!(copy_mode.cursor(), 2);
+    assert!(copy_mode.search(false));
+    assert_eq!(copy_mode.cursor(), 0);
+    assert!(!copy_mode.search(false));
+    assert!(copy_mode.search(true));
+    assert_eq!(copy_mode.cursor(), 2);
+    
+    assert_eq!(copy_mode.selection(), "Error: two");
+    copy_mode.toggle_mark();
+    copy_mode.move_by(-1);
+    assert_eq!(copy_mode.selection(), "ok\nError: two");
+}
+
+#[test]
+fn test_osc52() {
+    assert_eq!(clipboard::base64(b""), "");
+    assert_eq!(clipboard::base64(b"f"), "Zg==");
+    assert_eq!(clipboard::base64(b"fo"), "Zm8=");
+    assert_eq!(clipboard::base64(b"foobar"), "Zm9vYmFy");
+    assert_eq!(clipboard::osc52("hi"), "\x1b]52;c;aGk=\x07");
+}
@@ -0,0 +1,72 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use grill::config::TaskConfig;
+use grill::sidecar::{Sidecar, Sidecars};
+
+/// Collect output until it contains `expected`, or a few seconds have passed
+fn wait_for_output(output_rx: &mut mpsc::Receiver<String>, expected: &str) -> String {
+    let started = Instant::now();
+    let mut output = String::new();
+    while !output.contains(expected) && started.elapsed() < Duration::from_secs(5) {
+        match output_rx.try_recv() {
+            Ok(chunk) => output.push_str(&chunk),
+            Err(_) => std::thread::sleep(Duration::from_millis(10)),
+        }
+    }
+    output
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sidecar_output_and_log() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let (output_tx, mut output_rx) = mpsc::channel(100);
+    let mut sidecar = Sidecar::start("server", "echo listening; echo oops >&2; sleep 30", dir.path(), output_tx)?;
+    
+    // Each line is shown with the sidecar's name; stdout and stderr may come in either order
+    let mut output = wait_for_output(&mut output_rx, "[server] listening\n");
+    if !output.contains("[server] oops\n") {
+        output.push_str(&wait_for_output(&mut output_rx, "[server] oops\n"));
+    }
+    assert!(output.contains("[server] listening\n"), "{:?}", output);
+    assert!(output.contains("[server] oops\n"), "{:?}", output);
+    
+    let message = sidecar.log_message(1000);
+    assert!(message.contains("`server` sidecar"), "{}", message);
+    assert!(message.contains("still running"), "{}", message);
+    assert!(message.contains("listening\n"), "{}", message);
+    
+    // Stopping doesn't wait out the sleep
+    let started = Instant::now();
+    sidecar.stop();
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(sidecar.log_message(1000).contains("exited"));
+    
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_sidecars_from_task_config() -> Result<()> {
+    let config: TaskConfig = toml::from_str("[sidecars]\nwatch = \"echo watching; sleep 30\"\n")?;
+    let dir = tempfile::tempdir()?;
+    let (output_tx, mut output_rx) = mpsc::channel(100);
+    
+    let mut commands = config.sidecars.clone();
+    commands.insert("broken".to_string(), "exit 1".to_string());
+    let (mut sidecars, failures) = Sidecars::start(&commands, dir.path(), &output_tx);
+    assert!(failures.is_empty(), "{:?}", failures);
+    assert_eq!(sidecars.names(), ["broken", "watch"]);
+    assert!(wait_for_output(&mut output_rx, "[watch] watching").contains("[watch] watching"));
+    assert!(sidecars.get_mut("watch").is_some());
+    assert!(sidecars.get_mut("cargo test").is_none());
+    
+    sidecars.stop();
+    assert!(sidecars.names().is_empty());
+    assert!(Sidecars::start(&BTreeMap::new(), dir.path(), &output_tx).0.names().is_empty());
+    
+    Ok(())
+}
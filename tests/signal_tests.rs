@@ -0,0 +1,96 @@
+use anyhow::Result;
+use grill::signal::{self, Delivery, Platform, Signal};
+
+#[test]
+fn test_unix_delivery() {
+    assert_eq!(
+        signal::delivery(Signal::Terminate, 1234, Platform::Unix),
+        Delivery::ProcessGroup { group: 1234, signal: 15 }
+    );
+    assert_eq!(
+        signal::delivery(Signal::Kill, 1234, Platform::Unix),
+        Delivery::ProcessGroup { group: 1234, signal: 9 }
+    );
+}
+
+#[test]
+fn test_windows_delivery() {
+    assert_eq!(
+        signal::delivery(Signal::Terminate, 1234, Platform::Windows),
+        Delivery::Command(vec!["taskkill".into(), "/PID".into(), "1234".into(), "/T".into()])
+    );
+    assert_eq!(
+        signal::delivery(Signal::Kill, 1234, Platform::Windows),
+        Delivery::Command(vec!["taskkill".into(), "/PID".into(), "1234".into(), "/T".into(), "/F".into()])
+    );
+    assert_eq!(signal::delivery(Signal::Pause, 1234, Platform::Windows), Delivery::Unsupported);
+}
+
+/// Pausing freezes a process group until it is resumed
+#[cfg(unix)]
+#[test]
+fn test_pause_and_resume() -> Result<()> {
+    use std::os::unix::process::CommandExt;
+    
+    let mut child = std::process::Command::new("sleep").arg("30").process_group(0).spawn()?;
+    let pid = child.id();
+    let state = || -> Result<String> {
+        let output = std::process::Command::new("ps").args(["-o", "stat=", "-p", &pid.to_string()]).output()?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    
+    signal::send(pid, Signal::Pause)?;
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(state()?.starts_with('T'), "state {}", state()?);
+    
+    signal::send(pid, Signal::Resume)?;
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    assert!(!state()?.starts_with('T'), "state {}", state()?);
+    
+    child.kill()?;
+    child.wait()?;
+    Ok(())
+}
+
+/// Stopping a CLI also stops the processes it started
+#[cfg(unix)]
+#[test]
+fn test_stop_kills_process_tree() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+    use grill::cli_handler::CliHandlerFactory;
+    use grill::process::ProcessManager;
+    use tokio::sync::mpsc;
+    
+    let dir = tempfile::tempdir()?;
+    let pid_file = dir.path().join("sleep.pid");
+    let script = dir.path().join("cli.sh");
+    std::fs::write(&script, format!("#!/bin/sh\nsleep 300 &\necho $! > {}\nwait\n", pid_file.display()))?;
+    std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755))?;
+    
+    let (output_tx, _output_rx) = mpsc::channel(100);
+    let mut process = ProcessManager::new(script.to_str().unwrap());
+    let _input_tx = process.start(output_tx, CliHandlerFactory::create_handler("cli.sh".to_string()))?;
+    
+    let started = Instant::now();
+    while !pid_file.exists() && started.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    let pid = std::fs::read_to_string(&pid_file)?.trim().to_string();
+    
+    process.stop()?;
+    
+    let alive = || std::process::Command::new("kill")
+        .args(["-0", &pid])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    let stopped = Instant::now();
+    while alive() && stopped.elapsed() < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert!(!alive(), "The CLI's background process should have been stopped");
+    
+    Ok(())
+}
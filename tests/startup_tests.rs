@@ -0,0 +1,118 @@
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, watch};
+
+use grill::cli_handler::CliHandlerFactory;
+use grill::clipboard;
+use grill::environment::Environment;
+use grill::startup::{StartupPipeline, StartupState};
+
+#[test]
+fn test_startup_states_in_order() {
+    assert!(StartupState::Spawned < StartupState::Ready);
+    assert!(StartupState::Ready < StartupState::ContextLoaded);
+    assert!(StartupState::ContextLoaded < StartupState::Interactive);
+}
+
+#[tokio::test]
+async fn test_startup_pipeline() {
+    let (activity_tx, activity) = broadcast::channel::<bool>(10);
+    let (signing_in_tx, signing_in) = watch::channel(true);
+    let (process_input_tx, mut process_input_rx) = mpsc::channel::<String>(10);
+    
+    let mut pipeline = StartupPipeline::new(Duration::from_secs(30), clipboard::paste_message);
+    pipeline.message("You are a reviewer".to_string());
+    pipeline.deferred(|| None);
+    pipeline.deferred(|| vec!["Instructions".to_string(), "State".to_string()]);
+    let run = tokio::spawn(pipeline.run(activity, signing_in, process_input_tx));
+    
+    // Nothing is sent while the CLI is starting or asking the user to sign in
+    activity_tx.send(false).unwrap();
+    activity_tx.send(true).unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(process_input_rx.try_recv().is_err());
+    
+    // Once signed in, messages go one at a time, each after the CLI answers
+    signing_in_tx.send(false).unwrap();
+    let first = tokio::time::timeout(Duration::from_secs(5), process_input_rx.recv()).await.unwrap();
+    assert_eq!(first.as_deref(), Some("You are a reviewer\r"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(process_input_rx.try_recv().is_err());
+    
+    activity_tx.send(true).unwrap();
+    let second = tokio::time::timeout(Duration::from_secs(5), process_input_rx.recv()).await.unwrap();
+    assert_eq!(second.as_deref(), Some("Instructions\r"));
+    
+    activity_tx.send(true).unwrap();
+    let third = tokio::time::timeout(Duration::from_secs(5), process_input_rx.recv()).await.unwrap();
+    assert_eq!(third.as_deref(), Some("State\r"));
+    
+    activity_tx.send(true).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), run).await.unwrap().unwrap();
+    assert!(process_input_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_startup_pipeline_pastes_multiline_messages() {
+    let (activity_tx, activity) = broadcast::channel::<bool>(10);
+    let (_signing_in_tx, signing_in) = watch::channel(false);
+    let (process_input_tx, mut process_input_rx) = mpsc::channel::<String>(10);
+    
+    let mut pipeline = StartupPipeline::new(Duration::from_secs(30), clipboard::paste_message);
+    pipeline.message("Task: review\nCheck the error handling\n".to_string());
+    tokio::spawn(pipeline.run(activity, signing_in, process_input_tx));
+    activity_tx.send(true).unwrap();
+    
+    // The message is one bracketed paste, not a prompt per line
+    let first = tokio::time::timeout(Duration::from_secs(5), process_input_rx.recv()).await.unwrap();
+    assert_eq!(first.as_deref(), Some("\x1b[200~Task: review\nCheck the error handling\x1b[201~\r"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(process_input_rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_startup_task_context_is_one_message_each() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init().unwrap();
+    env.create_task("review").unwrap();
+    let task_dir = env.get_task_dir("review").unwrap();
+    std::fs::write(task_dir.join("instructions.md"), "Review the code\nCheck the error handling\n").unwrap();
+    
+    let (activity_tx, activity) = broadcast::channel::<bool>(10);
+    let (_signing_in_tx, signing_in) = watch::channel(false);
+    let (process_input_tx, mut process_input_rx) = mpsc::channel::<String>(10);
+    
+    // Started as the session starts Q, with the task's instructions worked out in turn
+    let handler = CliHandlerFactory::create_handler("q chat".to_string());
+    let paste_handler = handler.clone();
+    let mut pipeline = StartupPipeline::new(Duration::from_secs(30), move |message| paste_handler.paste_message(message));
+    pipeline.deferred(move || {
+        handler.task_context_messages(&env, "review", &task_dir).into_iter()
+            .map(|(_, message)| message)
+    });
+    tokio::spawn(pipeline.run(activity, signing_in, process_input_tx));
+    activity_tx.send(true).unwrap();
+    
+    // The instructions arrive as one bracketed paste, not a prompt per line
+    let first = tokio::time::timeout(Duration::from_secs(5), process_input_rx.recv()).await.unwrap().unwrap();
+    assert!(first.starts_with("\x1b[200~"), "{:?}", first);
+    assert!(first.ends_with("\x1b[201~\r"), "{:?}", first);
+    assert!(first.contains("Review the code\nCheck the error handling"), "{:?}", first);
+}
+
+#[tokio::test]
+async fn test_startup_context_is_one_line_for_unknown_clis() {
+    let (activity_tx, activity) = broadcast::channel::<bool>(10);
+    let (_signing_in_tx, signing_in) = watch::channel(false);
+    let (process_input_tx, mut process_input_rx) = mpsc::channel::<String>(10);
+    
+    // A CLI grill doesn't know might take each line of a paste as a message
+    let handler = CliHandlerFactory::create_handler("python3".to_string());
+    let mut pipeline = StartupPipeline::new(Duration::from_secs(30), move |message| handler.paste_message(message));
+    pipeline.message("Task: review\nCheck the error handling\n".to_string());
+    tokio::spawn(pipeline.run(activity, signing_in, process_input_tx));
+    activity_tx.send(true).unwrap();
+    
+    let first = tokio::time::timeout(Duration::from_secs(5), process_input_rx.recv()).await.unwrap();
+    assert_eq!(first.as_deref(), Some("Task: review Check the error handling\r"));
+}
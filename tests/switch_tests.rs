@@ -0,0 +1,44 @@
+use anyhow::Result;
+use std::fs;
+use tokio::sync::mpsc;
+
+use grill::cli_handler::CliHandlerFactory;
+use grill::environment::Environment;
+
+/// Switching tasks seamlessly sends the same context as starting one: the
+/// task's instructions with placeholders expanded, and its context files
+#[tokio::test]
+async fn test_switch_sends_context_files() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path().canonicalize()?;
+    let env = Environment::new(root.clone());
+    env.init()?;
+    env.create_task("review")?;
+    let task_dir = env.get_task_dir("review")?;
+    fs::write(task_dir.join("instructions.md"), "Review the code for task {{task_name}}\n")?;
+    fs::write(task_dir.join("config.toml"), "context_files = [\"notes.md\"]\n")?;
+    fs::write(root.join("notes.md"), "Prefer small functions\nName things well\n")?;
+    
+    // Q takes the file with its own command
+    let (input_tx, mut input_rx) = mpsc::channel(100);
+    let (output_tx, _output_rx) = mpsc::channel(100);
+    let q = CliHandlerFactory::create_handler("q chat".to_string());
+    q.clear_context_and_switch_task(&env, "review", &task_dir, false, &input_tx, &output_tx).await?;
+    let mut sent = Vec::new();
+    while let Ok(input) = input_rx.try_recv() {
+        sent.push(input);
+    }
+    assert!(sent.iter().any(|input| input.contains("Review the code for task review")), "{:?}", sent);
+    assert!(sent.contains(&format!("/context add {}\r", root.join("notes.md").display())), "{:?}", sent);
+    
+    // Any other CLI is pasted the file's contents, on one line
+    let other = CliHandlerFactory::create_handler("python3".to_string());
+    other.clear_context_and_switch_task(&env, "review", &task_dir, false, &input_tx, &output_tx).await?;
+    let mut sent = Vec::new();
+    while let Ok(input) = input_rx.try_recv() {
+        sent.push(input);
+    }
+    assert!(sent.iter().any(|input| input.contains("Prefer small functions Name things well")), "{:?}", sent);
+    
+    Ok(())
+}
@@ -0,0 +1,481 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::fs;
+
+use grill::cli_handler::CliHandlerFactory;
+use grill::config::{ClearOnSwitch, CliProfile, Config, ContextConfig, TaskConfig, TaskStatus};
+use grill::environment::Environment;
+use grill::history;
+use grill::switch::{Launch, SwitchPlan};
+use grill::task::{Task, TaskContext};
+use grill::transcript::{self, Exchange};
+
+#[test]
+fn test_task_status_lifecycle() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    env.create_task("api")?;
+    
+    // New tasks start as todo and move to in-progress when first used
+    assert_eq!(env.get_task_config("api")?.status, TaskStatus::Todo);
+    env.mark_task_started("api")?;
+    assert_eq!(env.get_task_config("api")?.status, TaskStatus::InProgress);
+    
+    env.set_task_status("api", TaskStatus::Blocked, Some("waiting on review"))?;
+    let config = env.get_task_config("api")?;
+    assert_eq!(config.status, TaskStatus::Blocked);
+    assert_eq!(config.blocked_reason.as_deref(), Some("waiting on review"));
+    // Other settings survive the update
+    assert_eq!(config.cli.as_deref(), Some("q chat"));
+    
+    // Starting a blocked task doesn't unblock it
+    env.mark_task_started("api")?;
+    assert_eq!(env.get_task_config("api")?.status, TaskStatus::Blocked);
+    
+    env.set_task_status("api", TaskStatus::Done, None)?;
+    let config = env.get_task_config("api")?;
+    assert_eq!(config.status, TaskStatus::Done);
+    assert_eq!(config.blocked_reason, None);
+    
+    Ok(())
+}
+
+#[test]
+fn test_archive_task() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    env.create_task("api")?;
+    
+    // The current task can't be archived
+    assert!(env.archive_task("default").is_err());
+    
+    let archived = env.archive_task("api")?;
+    assert!(archived.join("instructions.md").exists());
+    assert!(!env.list_tasks()?.contains(&"api".to_string()));
+    
+    // Archiving a task with the same name again keeps both
+    env.create_task("api")?;
+    fs::write(env.get_task_dir("api")?.join("state.md"), "second")?;
+    let second = env.archive_task("api")?;
+    assert_ne!(archived, second);
+    
+    Ok(())
+}
+
+#[test]
+fn test_task_notes() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    assert_eq!(env.get_notes("default")?, None);
+    
+    env.add_note("default", "Asked about  the retry logic")?;
+    env.add_note("default", "Tests pass on CI")?;
+    
+    let notes = env.get_notes("default")?.expect("notes");
+    assert!(notes.starts_with("# Notes for default\n\n- "));
+    let lines: Vec<&str> = notes.lines().skip(2).collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].ends_with(" Asked about  the retry logic"));
+    assert!(lines[1].ends_with(" Tests pass on CI"));
+    
+    // Notes are kept apart from the state the CLI sees
+    let state = fs::read_to_string(env.get_task_dir("default")?.join("state.md"))?;
+    assert!(!state.contains("retry"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_delete_and_restore_task() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    env.create_task("my-task")?;
+    fs::write(env.get_task_dir("my-task")?.join("state.md"), "first")?;
+    
+    // Deleting moves the task to the trash
+    let trashed = env.delete_task("my-task")?;
+    assert!(trashed.starts_with(env.get_grill_dir().join("trash")));
+    assert!(trashed.join("state.md").exists());
+    assert!(env.get_task_dir("my-task").is_err());
+    
+    // A newer deletion of the same name wins on restore
+    env.create_task("my-task")?;
+    fs::write(env.get_task_dir("my-task")?.join("state.md"), "second")?;
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    env.delete_task("my-task")?;
+    assert_eq!(env.list_trash()?.len(), 2);
+    
+    env.restore_task("my-task")?;
+    assert_eq!(fs::read_to_string(env.get_task_dir("my-task")?.join("state.md"))?, "second");
+    
+    // Restoring over an existing task is refused
+    assert!(env.restore_task("my-task").is_err());
+    assert!(env.restore_task("missing").is_err());
+    
+    Ok(())
+}
+
+#[test]
+fn test_task_name_validation() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    for name in ["", "../escape", "a/b", "a\\b", ".hidden", "..", "tab\tname", "bell\u{7}", "list", "delete"] {
+        assert!(env.create_task(name).is_err(), "'{}' should be rejected", name.escape_default());
+    }
+    assert!(!temp_dir.path().join(".grill/escape").exists());
+    
+    // Lookups are checked too, so they can't reach outside the tasks directory
+    assert!(env.get_task_dir("..").is_err());
+    assert!(env.set_current_task("../tasks/default").is_err());
+    
+    for name in ["bug-fix", "v2.0", "feature_login", "ünïcode"] {
+        env.create_task(name)?;
+    }
+    
+    let error = env.create_task("a/b").unwrap_err().to_string();
+    assert!(error.contains("cannot contain '/'"), "{}", error);
+    
+    Ok(())
+}
+
+#[test]
+fn test_global_tasks() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let global_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf())
+        .with_global_tasks_dir(Some(global_dir.path().to_path_buf()));
+    env.init()?;
+    
+    fs::create_dir_all(global_dir.path().join("code-review"))?;
+    fs::write(global_dir.path().join("code-review").join("context.md"), "global")?;
+    fs::create_dir_all(global_dir.path().join("shared"))?;
+    env.create_task("shared")?;
+    
+    // Global tasks are listed after project ones, without duplicates
+    let tasks = env.list_tasks()?;
+    assert_eq!(tasks.iter().filter(|task| *task == "shared").count(), 1);
+    assert_eq!(tasks.last().map(String::as_str), Some("code-review"));
+    
+    assert!(env.is_global_task("code-review"));
+    assert!(!env.is_global_task("shared"));
+    assert_eq!(env.get_task_dir("code-review")?, global_dir.path().join("code-review"));
+    assert_eq!(env.get_task_dir("shared")?, temp_dir.path().join(".grill/tasks/shared"));
+    
+    // Global tasks can be used but not modified from the project
+    env.set_current_task("code-review")?;
+    assert_eq!(env.get_current_task()?, "code-review");
+    env.mark_task_started("code-review")?;
+    env.set_current_task("default")?;
+    assert!(env.delete_task("code-review").is_err());
+    assert!(env.archive_task("code-review").is_err());
+    assert!(env.set_task_status("code-review", TaskStatus::Done, None).is_err());
+    assert!(global_dir.path().join("code-review").exists());
+    
+    Ok(())
+}
+
+#[test]
+fn test_discover_environment() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path();
+    let nested = root.join("src").join("deep");
+    fs::create_dir_all(&nested)?;
+    
+    // Without a .grill anywhere the start directory is the root
+    assert_eq!(Environment::discover(&nested).get_root_dir(), nested);
+    
+    Environment::new(root.to_path_buf()).init()?;
+    let env = Environment::discover(&nested);
+    assert_eq!(env.get_root_dir(), root);
+    assert!(env.exists());
+    assert_eq!(env.get_current_task()?, "default");
+    
+    Ok(())
+}
+
+#[test]
+fn test_instructions_frontmatter() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    let task_dir = env.get_task_dir("default")?;
+    fs::write(task_dir.join("config.toml"), "cli = \"q chat\"\ntags = [\"backend\"]\n")?;
+    
+    // YAML frontmatter overrides the CLI and adds tags and context files
+    let instructions = "---\ncli: claude\ntags: [backend, review]\ncontext_files:\n  - docs/style.md\n---\n\n# Review\n";
+    fs::write(task_dir.join("instructions.md"), instructions)?;
+    let config = env.get_task_config("default")?;
+    assert_eq!(config.get_cli(), Some("claude"));
+    assert_eq!(config.tags, ["backend", "review"]);
+    assert_eq!(config.context_files, [std::path::PathBuf::from("docs/style.md")]);
+    assert_eq!(grill::frontmatter::strip(instructions), "# Review\n");
+    
+    // TOML frontmatter uses +++ fences
+    fs::write(task_dir.join("instructions.md"), "+++\ncli = \"aider\"\n+++\nBody\n")?;
+    assert_eq!(env.get_task_config("default")?.get_cli(), Some("aider"));
+    
+    // Without frontmatter the file is left alone
+    assert_eq!(grill::frontmatter::strip("---\nno closing fence\n"), "---\nno closing fence\n");
+    fs::write(task_dir.join("instructions.md"), "# Plain\n")?;
+    assert_eq!(env.get_task_config("default")?.get_cli(), Some("q chat"));
+    
+    // Invalid frontmatter is reported
+    fs::write(task_dir.join("instructions.md"), "---\ncli: [unclosed\n---\n")?;
+    assert!(env.get_task_config("default").is_err());
+    
+    Ok(())
+}
+
+#[test]
+fn test_load_task() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    env.create_task("feature")?;
+    
+    let task_dir = temp_dir.path().join(".grill/tasks/feature");
+    fs::write(task_dir.join("instructions.md"), "---\ntags: [api]\n---\nBuild the API\n")?;
+    
+    let mut task = Task::load(&env, "feature")?;
+    assert_eq!(task.name(), "feature");
+    assert_eq!(task.path(), task_dir);
+    assert_eq!(task.config_path(), task_dir.join("config.toml"));
+    assert_eq!(task.notes_path(), task_dir.join("notes.md"));
+    assert_eq!(task.config().get_cli(), Some("q chat"));
+    assert_eq!(task.config().tags, vec!["api".to_string()]);
+    assert_eq!(task.instructions(), Some("Build the API\n"));
+    
+    // The template state of a new task doesn't count as state
+    assert_eq!(task.state(), None);
+    task.save_state("Endpoints done")?;
+    assert_eq!(task.state(), Some("Endpoints done"));
+    assert_eq!(fs::read_to_string(task.state_path())?, "Endpoints done");
+    assert!(task.metadata()?.modified.is_some());
+    
+    assert!(Task::load(&env, "missing").is_err());
+    
+    Ok(())
+}
+
+#[test]
+fn test_recently_used_tasks() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    env.create_task("api")?;
+    env.create_task("docs")?;
+    
+    env.mark_task_started("docs")?;
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    env.mark_task_started("api")?;
+    
+    let tasks = env.list_recent_tasks()?;
+    let names: Vec<&str> = tasks.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["api", "docs", "default"]);
+    assert!(tasks[0].1 > tasks[1].1);
+    assert_eq!(tasks[2].1, None);
+    
+    // Without a current task, the most recently used one is picked
+    fs::remove_file(temp_dir.path().join(".grill/current_task"))?;
+    assert_eq!(env.get_current_task()?, "api");
+    
+    Ok(())
+}
+
+#[test]
+fn test_task_dependencies() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    for task in ["design", "build", "launch"] {
+        env.create_task(task)?;
+    }
+    let tasks_dir = temp_dir.path().join(".grill/tasks");
+    fs::write(tasks_dir.join("build/config.toml"), "depends_on = [\"design\"]\n")?;
+    fs::write(tasks_dir.join("launch/config.toml"), "depends_on = [\"build\", \"docs\"]\n")?;
+    
+    assert_eq!(env.unfinished_dependencies("build")?, vec!["design"]);
+    env.set_task_status("design", TaskStatus::Done, None)?;
+    assert!(env.unfinished_dependencies("build")?.is_empty());
+    // Missing tasks can't be done
+    assert_eq!(env.unfinished_dependencies("launch")?, vec!["build", "docs"]);
+    
+    let graph = grill::task::dependency_graph(&env)?;
+    assert_eq!(graph, "\
+default [todo]
+launch [todo]
+├── build [todo]
+│   └── design [done]
+└── docs (missing)
+");
+    
+    // Cycles are cut short rather than followed forever
+    fs::write(tasks_dir.join("design/config.toml"), "depends_on = [\"launch\"]\n")?;
+    let graph = grill::task::dependency_graph(&env)?;
+    assert!(graph.starts_with("default [todo]\nbuild [todo]\n"));
+    assert!(graph.contains("├── build (cycle)"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_write_atomic() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let path = temp_dir.path().join("current_task");
+    
+    grill::environment::write_atomic(&path, "first")?;
+    grill::environment::write_atomic(&path, "second")?;
+    assert_eq!(fs::read_to_string(&path)?, "second");
+    
+    // No temporary files are left behind
+    assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+    
+    // A failed write leaves nothing behind either
+    assert!(grill::environment::write_atomic(&temp_dir.path().join("missing/file"), "x").is_err());
+    assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+    
+    Ok(())
+}
+
+#[test]
+fn test_task_persona() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    let task_dir = env.get_task_dir("default")?;
+    
+    // Tasks without a persona send no persona message
+    let config = env.get_task_config("default")?;
+    assert_eq!(config.persona, None);
+    assert_eq!(grill::cli_handler::persona_message(&env, "default", &config), None);
+    
+    fs::create_dir_all(env.get_grill_dir().join("personas"))?;
+    fs::write(env.get_persona_path("reviewer"), "---\ndescription: Strict\n---\nYou are a strict code reviewer.\n")?;
+    fs::write(task_dir.join("config.toml"), "persona = \"reviewer\"\n")?;
+    let config = env.get_task_config("default")?;
+    let (label, message) = grill::cli_handler::persona_message(&env, "default", &config).unwrap();
+    assert_eq!(label, "persona reviewer");
+    assert!(message.contains("You are a strict code reviewer."));
+    assert!(!message.contains("Strict"));
+    
+    // Frontmatter can choose the persona too
+    fs::write(task_dir.join("instructions.md"), "---\npersona: writer\n---\nWrite docs\n")?;
+    assert_eq!(env.get_task_config("default")?.persona.as_deref(), Some("writer"));
+    
+    assert!(env.load_persona("writer").is_err());
+    assert!(env.load_persona("../config").is_err());
+    
+    Ok(())
+}
+
+#[test]
+fn test_append_state() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    
+    // The template of a new task is replaced
+    let mut task = Task::load(&env, "default")?;
+    assert_eq!(task.state(), None);
+    task.append_state("Parser done\n")?;
+    assert_eq!(Task::load(&env, "default")?.state(), Some("# Task State\n\nParser done\n"));
+    
+    let mut task = Task::load(&env, "default")?;
+    task.append_state("Tests next")?;
+    assert_eq!(Task::load(&env, "default")?.state(), Some("# Task State\n\nParser done\n\nTests next\n"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_task_context() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    env.create_task("api")?;
+    
+    let exchange = Exchange { time: Utc::now(), prompt: "hi".to_string(), response: "hello".to_string() };
+    let default = TaskContext::new(&env, "default");
+    let api = TaskContext::new(&env, "api");
+    default.record_exchange(&exchange)?;
+    api.record_input(&["add tests".to_string()])?;
+    api.append_state("Tests next")?;
+    
+    // Each task's output stays in its own files
+    assert_eq!(transcript::load_recent(&env.get_transcript_path("default"), 10)?.len(), 1);
+    assert!(transcript::load_recent(&env.get_transcript_path("api"), 10)?.is_empty());
+    assert_eq!(history::load(&env.get_input_history_path("api")?)?, vec!["add tests".to_string()]);
+    assert!(history::load(&env.get_input_history_path("default")?)?.is_empty());
+    assert_eq!(Task::load(&env, "api")?.state(), Some("# Task State\n\nTests next\n"));
+    assert_eq!(Task::load(&env, "default")?.state(), None);
+    
+    // A conversation only counts as saved once the CLI has written it
+    assert_eq!(api.conversation_path(), Some(env.get_task_dir("api")?.join("conversation.json").as_path()));
+    assert!(api.saved_conversation().is_none());
+    std::fs::write(env.get_task_dir("api")?.join("conversation.json"), "{}")?;
+    assert!(api.saved_conversation().is_some());
+    assert!(default.saved_conversation().is_none());
+    
+    Ok(())
+}
+
+#[test]
+fn test_preview_switch() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    env.create_task("api")?;
+    fs::write(env.get_task_dir("api")?.join("instructions.md"), "Add the orders endpoint\n")?;
+    
+    let handler = CliHandlerFactory::create_handler("q chat".to_string());
+    let cli = CliProfile::from_command("q chat");
+    let mut context = ContextConfig::default();
+    let launch = Launch::new(&TaskConfig::default(), &Config::load(&env.get_config_path())?);
+    let preview = |uses_worktrees: bool, context: &ContextConfig| -> Result<String> {
+        let plan = SwitchPlan::new(&env, &handler, &cli, &launch, uses_worktrees, context, "api")?;
+        Ok(plan.describe(&env, &handler, &cli))
+    };
+    
+    // The same CLI is cleared and sent the task's context
+    let plan = SwitchPlan::new(&env, &handler, &cli, &launch, false, &context, "api")?;
+    assert!(plan.is_seamless());
+    assert_eq!(plan.clear, Some(ClearOnSwitch::Always));
+    let text = preview(false, &context)?;
+    assert!(text.contains("keep running `q chat`"));
+    assert!(text.contains("`/clear`, `y`"));
+    assert!(text.contains("instructions: "));
+    
+    context.clear_on_switch = ClearOnSwitch::Never;
+    let text = preview(false, &context)?;
+    assert!(text.contains("keep the conversation"));
+    assert!(!text.contains("/clear"));
+    
+    // A task in its own worktree needs a restart
+    let text = preview(true, &context)?;
+    assert!(text.contains("restarted to run `q chat`"));
+    assert!(!text.contains("context"));
+    
+    // The running CLI can't take on different limits
+    fs::write(env.get_task_dir("api")?.join("config.toml"), "[limits]\nmax_open_files = 256\n")?;
+    let plan = SwitchPlan::new(&env, &handler, &cli, &launch, false, &context, "api")?;
+    assert_eq!(plan.restart.as_deref(), Some("different resource limits"));
+    let mut limited = TaskConfig::default();
+    limited.limits.max_open_files = Some(256);
+    let limited = Launch::new(&limited, &Config::load(&env.get_config_path())?);
+    assert!(SwitchPlan::new(&env, &handler, &cli, &limited, false, &context, "api")?.is_seamless());
+    
+    // Without its config the task's CLI isn't known, so neither the preview nor the switch goes ahead
+    fs::write(env.get_task_dir("api")?.join("config.toml"), "clear_on_switch = 3\n")?;
+    assert!(preview(false, &context).is_err());
+    
+    assert!(SwitchPlan::new(&env, &handler, &cli, &launch, false, &context, "missing").is_err());
+    
+    Ok(())
+}
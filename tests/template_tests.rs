@@ -0,0 +1,65 @@
+use anyhow::Result;
+use std::process::Command;
+
+use grill::environment::Environment;
+use grill::template::{self, TemplateVars};
+
+#[test]
+fn test_expand() {
+    let mut vars = TemplateVars::new();
+    vars.insert("task_name", "auth".to_string());
+    vars.insert("git_branch", "main".to_string());
+    
+    assert_eq!(template::expand("Work on {{task_name}} on {{ git_branch }}.", &vars), "Work on auth on main.");
+    
+    // Unknown and unterminated placeholders are kept
+    assert_eq!(template::expand("{{unknown}} {{task_name}}", &vars), "{{unknown}} auth");
+    assert_eq!(template::expand("fn f() {{ x }} {{task_name", &vars), "fn f() {{ x }} {{task_name");
+}
+
+#[test]
+fn test_task_vars() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let root = temp_dir.path();
+    let status = Command::new("git").args(["init", "-q", "-b", "feature"]).current_dir(root).status()?;
+    assert!(status.success());
+    
+    let env = Environment::new(root.to_path_buf());
+    env.init()?;
+    
+    let vars = template::task_vars(&env, "default");
+    assert_eq!(vars["task_name"], "default");
+    assert_eq!(vars["workspace"], root.display().to_string());
+    assert_eq!(vars["git_branch"], "feature");
+    assert_eq!(vars["date"], chrono::Local::now().format("%Y-%m-%d").to_string());
+    
+    Ok(())
+}
+
+#[test]
+fn test_prompt_templates() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let env = Environment::new(temp_dir.path().to_path_buf());
+    env.init()?;
+    assert!(env.list_prompts()?.is_empty());
+    
+    let prompts_dir = env.get_grill_dir().join("prompts");
+    std::fs::create_dir_all(&prompts_dir)?;
+    std::fs::write(prompts_dir.join("review.md"), "Review {{1}} for {{2}} in {{task_name}}. {{ args }}\n")?;
+    std::fs::write(prompts_dir.join("notes.txt"), "not a prompt")?;
+    assert_eq!(env.list_prompts()?, vec!["review"]);
+    
+    let mut vars = template::task_vars(&env, "default");
+    template::add_args(&mut vars, "src/lib.rs  bugs ");
+    let prompt = env.load_prompt("review")?;
+    assert_eq!(template::expand(prompt.trim(), &vars), "Review src/lib.rs for bugs in default. src/lib.rs  bugs");
+    
+    // Missing arguments are left empty
+    template::add_args(&mut vars, "");
+    assert_eq!(template::expand("[{{1}}][{{args}}]", &vars), "[][]");
+    
+    assert!(env.load_prompt("missing").is_err());
+    assert!(env.load_prompt("../config").is_err());
+    
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use grill::terminal::{TerminalInfo, TerminalMode};
+
+fn terminal(term: &str, locale: Option<&str>) -> TerminalInfo {
+    TerminalInfo {
+        forced: None,
+        term: Some(term.to_string()),
+        locale: locale.map(str::to_string),
+        is_tty: true,
+    }
+}
+
+#[test]
+fn test_raw_mode_when_supported() {
+    assert_eq!(terminal("xterm-256color", Some("en_US.UTF-8")).support().mode, TerminalMode::Raw);
+    assert_eq!(terminal("xterm", Some("C.utf8")).support().mode, TerminalMode::Raw);
+    assert_eq!(terminal("xterm", None).support().mode, TerminalMode::Raw);
+}
+
+#[test]
+fn test_line_mode_fallbacks() {
+    let dumb = terminal("dumb", Some("en_US.UTF-8")).support();
+    assert_eq!(dumb.mode, TerminalMode::Line);
+    assert_eq!(dumb.reason.as_deref(), Some("TERM is dumb"));
+    
+    let latin1 = terminal("xterm", Some("de_DE.ISO-8859-1")).support();
+    assert_eq!(latin1.mode, TerminalMode::Line);
+    assert!(latin1.reason.unwrap().contains("de_DE.ISO-8859-1"));
+    
+    assert_eq!(terminal("xterm", Some("C")).support().mode, TerminalMode::Line);
+    
+    let piped = TerminalInfo { is_tty: false, ..terminal("xterm", None) };
+    assert_eq!(piped.support().mode, TerminalMode::Line);
+}
+
+#[test]
+fn test_forced_mode() {
+    let forced = TerminalInfo { forced: Some("line".to_string()), ..terminal("xterm", None) };
+    assert_eq!(forced.support().mode, TerminalMode::Line);
+    
+    let forced = TerminalInfo { forced: Some("RAW".to_string()), ..terminal("dumb", Some("C")) };
+    assert_eq!(forced.support().mode, TerminalMode::Raw);
+}
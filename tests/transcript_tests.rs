@@ -0,0 +1,82 @@
+use anyhow::Result;
+use chrono::Utc;
+
+use grill::transcript::{self, Exchange, Recorder};
+
+fn exchange(prompt: &str, response: &str) -> Exchange {
+    Exchange {
+        time: Utc::now(),
+        prompt: prompt.to_string(),
+        response: response.to_string(),
+    }
+}
+
+#[test]
+fn test_record_exchanges() {
+    let mut recorder = Recorder::new();
+    
+    // Output before anything was asked isn't part of an exchange
+    assert_eq!(recorder.output("Welcome\r\n> ", true), None);
+    
+    // Keystrokes arrive one at a time, with corrections
+    for key in ["h", "i", "x", "\x08", "\r"] {
+        assert!(recorder.input(key).is_empty());
+    }
+    assert_eq!(recorder.output("hi\r\n\x1b[32mHello there\x1b[0m\r\n", false), None);
+    let completed = recorder.output("How can I help?\r\n> ", true).unwrap();
+    assert_eq!(completed.prompt, "hi");
+    assert_eq!(completed.response, "Hello there\nHow can I help?");
+    
+    // Without a recognized prompt, the next message completes the exchange
+    recorder.input("first\r");
+    recorder.output("one\r\n", false);
+    let completed = recorder.input("second\r");
+    assert_eq!(completed.len(), 1);
+    assert_eq!(completed[0].prompt, "first");
+    assert_eq!(completed[0].response, "one");
+    
+    recorder.output("two\r\n", false);
+    assert_eq!(recorder.finish().map(|exchange| exchange.response), Some("two".to_string()));
+    assert_eq!(recorder.finish(), None);
+}
+
+#[test]
+fn test_resume_from_transcript() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let path = temp_dir.path().join("transcripts").join("default.jsonl");
+    
+    assert!(transcript::load_recent(&path, 5)?.is_empty());
+    assert_eq!(transcript::resume_message("default", &[]), None);
+    
+    for i in 1..=4 {
+        transcript::append(&path, &exchange(&format!("question {}", i), &format!("answer {}", i)))?;
+    }
+    
+    let recent = transcript::load_recent(&path, 2)?;
+    let prompts: Vec<&str> = recent.iter().map(|exchange| exchange.prompt.as_str()).collect();
+    assert_eq!(prompts, vec!["question 3", "question 4"]);
+    
+    let message = transcript::resume_message("default", &recent).unwrap();
+    assert!(message.contains("task 'default'"));
+    assert!(message.contains("User: question 3\nAssistant: answer 3\n"));
+    assert!(!message.contains("question 2"));
+    
+    Ok(())
+}
+
+#[test]
+fn test_latest_response() {
+    let mut recorder = Recorder::new();
+    assert_eq!(recorder.latest_response(), None);
+    
+    recorder.input("hello\r");
+    recorder.output("hello\r\nHi there\r\n", false);
+    assert_eq!(recorder.latest_response().as_deref(), Some("Hi there"));
+    recorder.output("> ", true);
+    
+    // Still the last answer until the next one starts arriving
+    recorder.input("again\r");
+    assert_eq!(recorder.latest_response().as_deref(), Some("Hi there"));
+    recorder.output("again\r\nSure\r\n", false);
+    assert_eq!(recorder.latest_response().as_deref(), Some("Sure"));
+}
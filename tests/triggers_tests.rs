@@ -0,0 +1,67 @@
+use anyhow::Result;
+
+use grill::config::{Config, TriggerAction};
+use grill::triggers::Triggers;
+
+fn triggers(config: &str) -> Result<Triggers> {
+    let config: Config = toml::from_str(config)?;
+    Triggers::new(&config.triggers)
+}
+
+#[test]
+fn test_triggers_config() -> Result<()> {
+    let config: Config = toml::from_str(r#"
+        [[triggers.rules]]
+        pattern = "(?i)rate limit"
+        action = "retry"
+        delay = 60
+        
+        [[triggers.rules]]
+        pattern = "Allow this action\\?"
+        action = "send"
+        input = "y"
+        
+        [[triggers.rules]]
+        pattern = "quota exceeded"
+        action = "block"
+    "#)?;
+    assert_eq!(config.triggers.rules.len(), 3);
+    assert_eq!(config.triggers.rules[0].action, TriggerAction::Retry { delay: 60 });
+    assert_eq!(config.triggers.rules[1].action, TriggerAction::Send { input: "y".to_string(), delay: 0 });
+    assert_eq!(config.triggers.rules[2].action, TriggerAction::Block { reason: None });
+    
+    // Every action needs its own settings
+    assert!(toml::from_str::<Config>("[[triggers.rules]]\npattern = \"x\"\naction = \"run\"\n").is_err());
+    assert!(triggers("[[triggers.rules]]\npattern = \"(\"\naction = \"notify\"\n").is_err());
+    
+    Ok(())
+}
+
+#[test]
+fn test_triggers_fire_once_per_response() -> Result<()> {
+    let mut triggers = triggers(r#"
+        [[triggers.rules]]
+        pattern = "rate limit exceeded"
+        action = "retry"
+        delay = 60
+        
+        [[triggers.rules]]
+        pattern = "Done"
+        action = "notify"
+    "#)?;
+    
+    // Escape sequences and chunk boundaries don't hide a match
+    assert!(triggers.check("Error: \x1b[31mrate lim").is_empty());
+    let fired = triggers.check("it exceeded\x1b[0m\r\n");
+    assert_eq!(fired, vec![(TriggerAction::Retry { delay: 60 }, "rate limit exceeded".to_string())]);
+    assert!(triggers.check("rate limit exceeded\r\n").is_empty());
+    
+    let fired = triggers.check("Done\r\n");
+    assert_eq!(fired, vec![(TriggerAction::Notify { message: None }, "Done".to_string())]);
+    
+    // Sending a message rearms the rules
+    triggers.reset();
+    assert_eq!(triggers.check("rate limit exceeded").len(), 1);
+    
+    Ok(())
+}
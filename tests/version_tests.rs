@@ -0,0 +1,29 @@
+use std::path::PathBuf;
+
+use grill::version::{self, VersionInfo};
+
+#[test]
+fn test_report() {
+    let info = VersionInfo {
+        workspace: Some(PathBuf::from("/work/project")),
+        task: Some("api".to_string()),
+        cli: Some("grill-no-such-cli --model fast".to_string()),
+        handler: Some("default".to_string()),
+    };
+    let report = version::report(&info);
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines[0], format!("grill      {} ({})", env!("CARGO_PKG_VERSION"), version::build_info()));
+    assert_eq!(lines[1], "workspace  /work/project");
+    assert_eq!(lines[2], "task       api");
+    assert_eq!(lines[3], "cli        grill-no-such-cli --model fast (not installed)");
+    assert_eq!(lines[4], "handler    default");
+    
+    // Outside a workspace there's only grill itself
+    assert_eq!(version::report(&VersionInfo::default()).lines().count(), 1);
+}
+
+#[test]
+fn test_cli_version() {
+    assert_eq!(version::cli_version("ssh devbox q chat"), "runs over ssh");
+    assert_eq!(version::cli_version("grill-no-such-cli"), "not installed");
+}
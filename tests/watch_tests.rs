@@ -0,0 +1,53 @@
+use anyhow::Result;
+use std::fs;
+
+use grill::config::TaskConfig;
+use grill::watch::{self, Outcome, Watch};
+
+#[test]
+fn test_snapshot() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join(".gitignore"), "target/\n")?;
+    fs::write(dir.path().join("main.rs"), "fn main() {}\n")?;
+    let before = watch::snapshot(dir.path());
+    assert_eq!(watch::snapshot(dir.path()), before);
+    
+    // Ignored and hidden files don't count
+    fs::create_dir(dir.path().join("target"))?;
+    fs::write(dir.path().join("target/out"), "built")?;
+    fs::create_dir(dir.path().join(".grill"))?;
+    fs::write(dir.path().join(".grill/transcript.md"), "log")?;
+    assert_eq!(watch::snapshot(dir.path()), before);
+    
+    fs::write(dir.path().join("main.rs"), "fn main() { todo!() }\n")?;
+    assert_ne!(watch::snapshot(dir.path()), before);
+    
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_watch_reruns_on_change() -> Result<()> {
+    let config: TaskConfig = toml::from_str("watch = \"test -f fixed\"")?;
+    let command = config.watch.expect("watch is set");
+    let dir = tempfile::tempdir()?;
+    fs::write(dir.path().join("lib.rs"), "")?;
+    let mut watch = Watch::new(&command, dir.path().to_path_buf());
+    assert!(watch.watches("test -f fixed", dir.path()));
+    assert_eq!(watch.check(1000)?, None);
+    
+    // A change is run once the files stop changing
+    fs::write(dir.path().join("lib.rs"), "broken")?;
+    assert_eq!(watch.check(1000)?, None);
+    match watch.check(1000)? {
+        Some(Outcome::Failed(message)) => assert!(message.contains("`test -f fixed` (failed)"), "{}", message),
+        outcome => panic!("expected a failure, got {:?}", outcome),
+    }
+    assert_eq!(watch.check(1000)?, None);
+    
+    fs::write(dir.path().join("fixed"), "")?;
+    assert_eq!(watch.check(1000)?, None);
+    assert_eq!(watch.check(1000)?, Some(Outcome::Passed { recovered: true }));
+    
+    Ok(())
+}
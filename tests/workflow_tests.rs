@@ -0,0 +1,80 @@
+use anyhow::Result;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use grill::hooks::run_hook;
+use grill::workflow::Workflow;
+
+#[test]
+fn test_workflow_load() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let path = temp_dir.path().join("review.toml");
+    
+    fs::write(&path, r#"
+description = "Review and test"
+
+[[steps]]
+name = "review"
+task = "review"
+file = "prompts/review.md"
+
+[[steps]]
+prompt = "Run the tests"
+expect = "passed"
+hook = "cargo test"
+"#)?;
+    
+    let workflow = Workflow::load(&path)?;
+    assert_eq!(workflow.description.as_deref(), Some("Review and test"));
+    assert_eq!(workflow.timeout, 300);
+    assert_eq!(workflow.steps.len(), 2);
+    assert_eq!(workflow.steps[0].task.as_deref(), Some("review"));
+    assert_eq!(workflow.steps[1].expect.as_deref(), Some("passed"));
+    
+    // A step needs exactly one of prompt or file
+    fs::write(&path, "[[steps]]\nname = \"empty\"\n")?;
+    assert!(Workflow::load(&path).is_err());
+    
+    Ok(())
+}
+
+#[test]
+fn test_run_hook() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    
+    let output = run_hook("cat; echo \" $GRILL_TASK\"", temp_dir.path(), &[("GRILL_TASK", "default")], Some("response"), None)?;
+    assert!(output.success);
+    assert_eq!(output.stdout.trim(), "response default");
+    
+    let output = run_hook("exit 3", temp_dir.path(), &[], None, None)?;
+    assert!(!output.success);
+    
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_hook_large_input() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    
+    // The hook fills its output pipe before reading any of its input
+    let input = "x".repeat(1024 * 1024);
+    let output = run_hook("head -c 1000000 /dev/zero; wc -c", temp_dir.path(), &[], Some(&input), Some(Duration::from_secs(20)))?;
+    assert!(output.success);
+    assert!(output.stdout.ends_with("1048576\n"), "{}", &output.stdout[output.stdout.len() - 20..]);
+    
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_run_hook_timeout() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    
+    let started = Instant::now();
+    let error = run_hook("sleep 30", temp_dir.path(), &[], None, Some(Duration::from_millis(200))).unwrap_err();
+    assert!(error.to_string().contains("stopped after running"), "{}", error);
+    assert!(started.elapsed() < Duration::from_secs(10));
+    
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use anyhow::Result;
+use std::fs;
+
+use grill::workspaces;
+
+#[test]
+fn test_workspace_registry() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let registry = temp_dir.path().join("data").join("workspaces.json");
+    let first = temp_dir.path().join("first");
+    let second = temp_dir.path().join("second");
+    fs::create_dir_all(&first)?;
+    fs::create_dir_all(&second)?;
+    
+    assert!(workspaces::load(&registry)?.is_empty());
+    
+    workspaces::register(&registry, &first)?;
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    workspaces::register(&registry, &second)?;
+    
+    let known = workspaces::load(&registry)?;
+    assert_eq!(known.len(), 2);
+    assert_eq!(known[0].path, second.canonicalize()?);
+    
+    // Using a workspace again moves it to the front without duplicating it
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    workspaces::register(&registry, &first)?;
+    let known = workspaces::load(&registry)?;
+    assert_eq!(known.len(), 2);
+    assert_eq!(known[0].path, first.canonicalize()?);
+    
+    Ok(())
+}